@@ -8,6 +8,12 @@ fn main() {
         "GOOGLE_CLIENT_SECRET",
         "MICROSOFT_CLIENT_ID",
         "MICROSOFT_CLIENT_SECRET",
+        "FASTMAIL_CLIENT_ID",
+        "FASTMAIL_CLIENT_SECRET",
+        "YAHOO_CLIENT_ID",
+        "YAHOO_CLIENT_SECRET",
+        "ZOHO_CLIENT_ID",
+        "ZOHO_CLIENT_SECRET",
     ];
 
     for var in vars {
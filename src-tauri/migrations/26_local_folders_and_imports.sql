@@ -0,0 +1,16 @@
+-- Migration 26: Local folders and resumable imports
+-- Local folders live only in this database and are never synced with a server.
+ALTER TABLE folders ADD COLUMN is_local BOOLEAN NOT NULL DEFAULT 0;
+
+-- How far an import of a source into a folder got, so an interrupted import resumes there
+CREATE TABLE IF NOT EXISTS imports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_path TEXT NOT NULL,
+    folder_id INTEGER NOT NULL,
+    processed INTEGER NOT NULL DEFAULT 0,
+    imported INTEGER NOT NULL DEFAULT 0,
+    completed_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (folder_id) REFERENCES folders (id) ON DELETE CASCADE,
+    UNIQUE(source_path, folder_id)
+);
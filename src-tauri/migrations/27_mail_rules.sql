@@ -0,0 +1,17 @@
+-- Migration 27: Client-side mail rules
+-- Conditions and actions are JSON arrays, rules run in ascending position.
+CREATE TABLE IF NOT EXISTS rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER, -- NULL applies to every account
+    name TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    match_all BOOLEAN NOT NULL DEFAULT 1,
+    conditions TEXT NOT NULL,
+    actions TEXT NOT NULL,
+    stop_processing BOOLEAN NOT NULL DEFAULT 0,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_rules_position ON rules(position);
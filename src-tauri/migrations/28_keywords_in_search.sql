@@ -0,0 +1,50 @@
+-- Index custom keywords (labels) with the text so searching for a label finds its mail.
+-- System flags like seen are left out, they would match nearly every message.
+DROP TRIGGER IF EXISTS emails_ai;
+DROP TRIGGER IF EXISTS emails_ad;
+DROP TRIGGER IF EXISTS emails_au;
+DROP TABLE IF EXISTS emails_fts;
+
+CREATE VIRTUAL TABLE emails_fts USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    flags,
+    content='emails',
+    content_rowid='id'
+);
+
+CREATE TRIGGER emails_ai AFTER INSERT ON emails BEGIN
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, flags)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text,
+    (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(new.flags) THEN new.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent')));
+END;
+
+CREATE TRIGGER emails_ad AFTER DELETE ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text, flags)
+  VALUES ('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text,
+    (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(old.flags) THEN old.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent')));
+END;
+
+-- Only re-index when the text or the labels changed, system flags share the column and change all the time
+CREATE TRIGGER emails_au AFTER UPDATE OF subject, sender_name, sender_address, body_text, flags ON emails
+WHEN old.subject IS NOT new.subject
+  OR old.sender_name IS NOT new.sender_name
+  OR old.sender_address IS NOT new.sender_address
+  OR old.body_text IS NOT new.body_text
+  OR (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(old.flags) THEN old.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent'))
+     IS NOT (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(new.flags) THEN new.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent'))
+BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text, flags)
+  VALUES ('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text,
+    (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(old.flags) THEN old.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent')));
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, flags)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text,
+    (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(new.flags) THEN new.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent')));
+END;
+
+INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, flags)
+SELECT id, subject, sender_name, sender_address, body_text,
+  (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(emails.flags) THEN emails.flags ELSE '[]' END) WHERE value NOT IN ('seen', 'answered', 'flagged', 'deleted', 'draft', 'recent'))
+FROM emails;
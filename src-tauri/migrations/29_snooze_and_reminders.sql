@@ -0,0 +1,18 @@
+-- Snoozed threads and reminders for sent mail that gets no reply.
+-- Timers point at a thread rather than a row, rows come and go as folders are re-synced.
+CREATE TABLE IF NOT EXISTS timers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL, -- 'snooze' or 'follow_up'
+    account_id INTEGER NOT NULL,
+    thread_id TEXT NOT NULL,
+    message_id TEXT, -- the sent message a follow-up waits on a reply to
+    due_at TEXT NOT NULL, -- RFC 3339, UTC
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    UNIQUE (kind, account_id, thread_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_timers_due_at ON timers(due_at);
+
+-- When a snoozed thread came back, so it sorts at the top of the inbox
+ALTER TABLE emails ADD COLUMN resurfaced_at TEXT;
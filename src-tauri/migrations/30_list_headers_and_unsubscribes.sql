@@ -0,0 +1,21 @@
+-- Mailing list headers, captured when a message is indexed
+ALTER TABLE emails ADD COLUMN list_id TEXT;
+ALTER TABLE emails ADD COLUMN list_unsubscribe TEXT;
+ALTER TABLE emails ADD COLUMN list_unsubscribe_post TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_emails_list_id ON emails(list_id);
+
+-- Lists the user unsubscribed from, so mail that still arrives from them can be archived
+CREATE TABLE IF NOT EXISTS unsubscribes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    list_id TEXT,
+    sender_address TEXT NOT NULL,
+    method TEXT NOT NULL, -- 'one_click', 'mailto' or 'link'
+    status TEXT NOT NULL, -- 'done', 'failed' or 'link_opened'
+    detail TEXT,
+    auto_archive BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    UNIQUE (account_id, sender_address)
+);
@@ -0,0 +1,10 @@
+-- How new mail from a mailing list or automated sender is handled. A list is keyed by its
+-- List-Id, or by the sender address when it has none.
+CREATE TABLE IF NOT EXISTS subscription_modes (
+    account_id INTEGER NOT NULL,
+    list_key TEXT NOT NULL,
+    mode TEXT NOT NULL, -- 'muted' (marked read, no notification) or 'digest' (archived for the digest)
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    PRIMARY KEY (account_id, list_key)
+);
@@ -0,0 +1,16 @@
+-- Daily digest of newsletters and notifications, stored as local messages in a 'digest' folder
+INSERT OR IGNORE INTO settings (key, value) VALUES ('digestEnabled', 'false');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('digestHour', '8');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('digestAutoArchive', 'false');
+
+CREATE TABLE IF NOT EXISTS digests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    email_id INTEGER,
+    covered INTEGER NOT NULL,
+    created_at TEXT NOT NULL, -- RFC 3339, like message dates
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_digests_account ON digests(account_id, created_at);
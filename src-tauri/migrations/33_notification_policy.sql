@@ -0,0 +1,18 @@
+-- Notification policy: VIPs, quiet hours, muted threads and per-account and per-folder switches
+INSERT OR IGNORE INTO settings (key, value) VALUES ('notifyVipsOnly', 'false');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('quietHoursEnabled', 'false');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('quietHoursStart', '22');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('quietHoursEnd', '7');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('quietHoursAllowVips', 'true');
+
+ALTER TABLE accounts ADD COLUMN notifications_enabled BOOLEAN NOT NULL DEFAULT 1;
+ALTER TABLE folders ADD COLUMN notifications_enabled BOOLEAN NOT NULL DEFAULT 1;
+
+-- Threads are keyed like timers key them
+CREATE TABLE IF NOT EXISTS muted_threads (
+    account_id INTEGER NOT NULL,
+    thread_id TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    PRIMARY KEY (account_id, thread_id)
+);
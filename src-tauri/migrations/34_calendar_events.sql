@@ -0,0 +1,30 @@
+-- Calendar invitations (iTIP) found in messages, one row per event per message
+CREATE TABLE IF NOT EXISTS calendar_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email_id INTEGER NOT NULL,
+    account_id INTEGER NOT NULL,
+    uid TEXT NOT NULL,
+    recurrence_id TEXT NOT NULL DEFAULT '',
+    sequence INTEGER NOT NULL DEFAULT 0,
+    method TEXT NOT NULL, -- 'REQUEST', 'CANCEL', 'REPLY' or 'PUBLISH'
+    status TEXT,
+    summary TEXT,
+    location TEXT,
+    description TEXT,
+    organizer TEXT,
+    organizer_name TEXT,
+    starts_at TEXT,
+    ends_at TEXT,
+    all_day BOOLEAN NOT NULL DEFAULT 0,
+    timezone TEXT,
+    attendees TEXT NOT NULL DEFAULT '[]', -- JSON array of {address, name, partstat}
+    change TEXT NOT NULL, -- 'new', 'updated', 'outdated', 'cancelled' or 'reply'
+    response TEXT, -- 'accepted', 'tentative' or 'declined' once the user answered
+    ics TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE CASCADE,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    UNIQUE (email_id, uid, recurrence_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendar_events_uid ON calendar_events(account_id, uid);
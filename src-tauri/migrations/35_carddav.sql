@@ -0,0 +1,20 @@
+-- CardDAV address books synced into the contacts, one server per account
+CREATE TABLE IF NOT EXISTS carddav_accounts (
+    account_id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL,
+    username TEXT, -- the IMAP username when empty
+    last_synced_at DATETIME,
+    last_error TEXT,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE
+);
+
+-- The cards seen at the last sync, so only changed ETags are fetched again
+CREATE TABLE IF NOT EXISTS carddav_cards (
+    account_id INTEGER NOT NULL,
+    href TEXT NOT NULL,
+    address_book TEXT NOT NULL,
+    etag TEXT NOT NULL,
+    addresses TEXT NOT NULL DEFAULT '[]', -- JSON array of the card's email addresses
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    PRIMARY KEY (account_id, href)
+);
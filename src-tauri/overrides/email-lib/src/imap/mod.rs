@@ -766,6 +766,27 @@ pub async fn fetch_messages_with_items(&mut self, uids: SequenceSet, items: Macr
         Ok(Messages::from(fetches))
     }
 
+    /// Fetches arbitrary data items, keyed by UID.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_items(
+        &mut self,
+        uids: SequenceSet,
+        items: MacroOrMessageDataItemNames<'static>,
+    ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
+        loop {
+            let res = self
+                .retry
+                .timeout(self.inner.uid_fetch(uids.clone(), items.clone()))
+                .await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+            }
+        }
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn peek_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         self.fetch_messages_with_items(uids, PEEK_MESSAGES.clone()).await
@@ -0,0 +1,3 @@
+fn main() {
+    dueam_lib::cli::run()
+}
@@ -0,0 +1,237 @@
+//! `dueam-cli`: the backend without a window, for terminals and cron jobs. It opens the same
+//! database, account registry and attachment cache as the app.
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use clap::{Parser, Subcommand};
+use mail_builder::MessageBuilder;
+use mail_parser::MessageParser;
+use crate::context::{AppContext, NoEvents};
+use crate::db::setup::open_database;
+use crate::email_backend::emails::commands::{find_emails, save_to_sent, Email};
+use crate::email_backend::emails::transport::Transport;
+use crate::email_backend::export::commands::{raw_source, write_mbox, ExportSelection};
+use crate::email_backend::sync::SyncEngine;
+
+/// Must match `identifier` in tauri.conf.json, the app keeps its data in a directory named after it.
+const APP_IDENTIFIER: &str = "com.shashtech.dueam";
+
+#[derive(Parser)]
+#[command(name = "dueam-cli", about = "Use your Dueam mail from the command line")]
+struct Cli {
+    /// Data directory of the app, defaults to the one the desktop app uses
+    #[arg(long, env = "DUEAM_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the configured accounts
+    Accounts,
+    /// Sync all accounts, or one
+    Sync {
+        #[arg(long)]
+        account: Option<i64>,
+    },
+    /// Search messages, newest first
+    Search {
+        query: String,
+        #[arg(long)]
+        account: Option<i64>,
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Print a message
+    Read {
+        id: i64,
+        /// Print the full RFC 5322 source instead of the text body
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Send a message, reading the plain text body from stdin or a file
+    Send {
+        #[arg(long)]
+        account: i64,
+        /// Comma separated recipients
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        cc: Option<String>,
+        #[arg(long)]
+        subject: String,
+        #[arg(long)]
+        body_file: Option<PathBuf>,
+    },
+    /// Export a folder, a search or a date range to an mbox file
+    Export {
+        output: PathBuf,
+        #[arg(long, conflicts_with_all = ["query", "since"])]
+        folder: Option<i64>,
+        #[arg(long, conflicts_with = "since")]
+        query: Option<String>,
+        /// ISO 8601 date, needs --until
+        #[arg(long, requires = "until")]
+        since: Option<String>,
+        /// ISO 8601 date, exclusive
+        #[arg(long, requires = "since")]
+        until: Option<String>,
+        #[arg(long)]
+        account: Option<i64>,
+    },
+}
+
+/// Entry point of the `dueam-cli` binary.
+pub fn run() {
+    let cli = Cli::parse();
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => exit_with(e.to_string()),
+    };
+    // The engine spawns its background work on Tauri's runtime
+    tauri::async_runtime::set(runtime.handle().clone());
+
+    if let Err(e) = runtime.block_on(execute(cli)) {
+        exit_with(e);
+    }
+}
+
+fn exit_with(message: String) -> ! {
+    eprintln!("dueam-cli: {}", message);
+    std::process::exit(1);
+}
+
+fn default_data_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Cannot find the data directory, pass --data-dir".to_string())
+}
+
+async fn execute(cli: Cli) -> Result<(), String> {
+    let data_dir = match cli.data_dir {
+        Some(dir) => dir,
+        None => default_data_dir()?,
+    };
+    let pool = open_database(&data_dir).await?;
+    let engine = SyncEngine::new(AppContext::new(pool.clone(), data_dir, Arc::new(NoEvents)));
+
+    match cli.command {
+        Command::Accounts => {
+            for account in engine.app().accounts().await?.load().await?.accounts {
+                let id = account.id().map(|id| id.to_string()).unwrap_or_else(|| "-".to_string());
+                println!("{}\t{}\t{:?}\t{}", id, account.email(), account.backend_kind(), account.name().unwrap_or(""));
+            }
+        }
+        Command::Sync { account: None } => engine.sync_all_accounts().await?,
+        Command::Sync { account: Some(account_id) } => {
+            let account = engine.app().accounts().await?.get_account_by_id(account_id).await?;
+            engine.sync_account(&account).await?;
+        }
+        Command::Search { query, account, limit } => {
+            for email in find_emails(&pool, &query, account, None, Some(limit), None, None).await? {
+                print_summary(&email);
+            }
+        }
+        Command::Read { id, raw: true } => {
+            let source = raw_source(&engine, id).await?;
+            print!("{}", String::from_utf8_lossy(&source));
+        }
+        Command::Read { id, raw: false } => read(&engine, id).await?,
+        Command::Send { account, to, cc, subject, body_file } => {
+            let body = match body_file {
+                Some(path) => std::fs::read_to_string(path).map_err(|e| e.to_string())?,
+                None => {
+                    let mut body = String::new();
+                    std::io::stdin().read_to_string(&mut body).map_err(|e| e.to_string())?;
+                    body
+                }
+            };
+            send(&engine, account, to, cc, subject, body).await?;
+        }
+        Command::Export { output, folder, query, since, until, account } => {
+            let selection = match (folder, query, since, until) {
+                (Some(folder_id), _, _, _) => ExportSelection::Folder { folder_id },
+                (None, Some(query_text), _, _) => ExportSelection::Search { query_text, account_id: account },
+                (None, None, Some(since), Some(until)) => ExportSelection::DateRange { since, until, account_id: account },
+                _ => return Err("Choose what to export with --folder, --query or --since/--until".to_string()),
+            };
+            let path = output.to_string_lossy().to_string();
+            let written = write_mbox(&engine, &selection, &path, |progress| {
+                eprintln!("{}/{}", progress.done, progress.total);
+            }).await?;
+            println!("Exported {} messages to {}", written, path);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_summary(email: &Email) {
+    println!(
+        "{}\t{}\t{}\t{}",
+        email.id,
+        email.date.get(..10).unwrap_or(&email.date),
+        email.sender_name.as_deref().unwrap_or(&email.sender_address),
+        email.subject.as_deref().unwrap_or("(no subject)"),
+    );
+}
+
+/// Prints the headers and text body of a message, fetching it when the body is not cached yet.
+async fn read(engine: &SyncEngine, email_id: i64) -> Result<(), String> {
+    let pool = engine.app().pool();
+    let (sender_name, sender_address, recipient_to, subject, date, body_text): (Option<String>, String, Option<String>, Option<String>, String, Option<String>) = sqlx::query_as(
+        "SELECT sender_name, sender_address, recipient_to, subject, date, body_text FROM emails WHERE id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("No message with id {}", email_id))?;
+
+    let body = match body_text {
+        Some(text) => text,
+        None => {
+            let source = raw_source(engine, email_id).await?;
+            MessageParser::default()
+                .parse(&source)
+                .and_then(|message| message.body_text(0).map(|text| text.into_owned()))
+                .unwrap_or_default()
+        }
+    };
+
+    match sender_name {
+        Some(name) => println!("From: {} <{}>", name, sender_address),
+        None => println!("From: {}", sender_address),
+    }
+    if let Some(to) = recipient_to {
+        println!("To: {}", to);
+    }
+    println!("Date: {}", date);
+    println!("Subject: {}", subject.unwrap_or_default());
+    println!();
+    println!("{}", body);
+    Ok(())
+}
+
+async fn send(engine: &SyncEngine, account_id: i64, to: String, cc: Option<String>, subject: String, body: String) -> Result<(), String> {
+    let account = engine.app().accounts().await?.get_authenticated_account(account_id).await?;
+    let transport = Transport::for_account(&account)?;
+
+    let split = |list: &str| list.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect::<Vec<_>>();
+    let mut builder = MessageBuilder::new()
+        .from(account.email())
+        .to(split(&to))
+        .subject(subject)
+        .text_body(body);
+    if let Some(cc) = cc.as_deref().map(split).filter(|cc| !cc.is_empty()) {
+        builder = builder.cc(cc);
+    }
+
+    let message = builder.write_to_vec().map_err(|e| e.to_string())?;
+    transport.send(&message).await?;
+    save_to_sent(engine, account_id, &message).await
+}
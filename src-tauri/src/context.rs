@@ -0,0 +1,198 @@
+//! What the backend needs from its host: the database, the data directory and somewhere to
+//! send events. The app backs events with Tauri, the command line drops them and tests
+//! collect them in memory.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tauri::Emitter;
+use tauri_plugin_notification::NotificationExt;
+use crate::db::secrets::SecretStore;
+use crate::email_backend::accounts::manager::AccountManager;
+
+/// Receives the events the backend reports to its host.
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &str, payload: Value);
+
+    /// Whether `notify` reaches the user, so work that only feeds notifications can be skipped.
+    fn can_notify(&self) -> bool {
+        false
+    }
+
+    fn notify(&self, _title: &str, _body: &str) {}
+}
+
+impl<R: tauri::Runtime> EventSink for tauri::AppHandle<R> {
+    fn emit(&self, event: &str, payload: Value) {
+        let _ = Emitter::emit(self, event, payload);
+    }
+
+    fn can_notify(&self) -> bool {
+        true
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        let _ = self.notification().builder().title(title).body(body).show();
+    }
+}
+
+/// Drops every event, for hosts nobody is watching.
+pub struct NoEvents;
+
+impl EventSink for NoEvents {
+    fn emit(&self, _event: &str, _payload: Value) {}
+}
+
+/// Keeps events and notifications in memory so tests can assert on them.
+#[cfg(test)]
+#[derive(Default)]
+pub struct EventCollector {
+    events: std::sync::Mutex<Vec<(String, Value)>>,
+    notifications: std::sync::Mutex<Vec<(String, String)>>,
+}
+
+#[cfg(test)]
+impl EventCollector {
+    /// Payloads of the events named `event`, oldest first.
+    pub fn named(&self, event: &str) -> Vec<Value> {
+        self.events.lock().unwrap().iter()
+            .filter(|(name, _)| name == event)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    pub fn notifications(&self) -> Vec<(String, String)> {
+        self.notifications.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl EventSink for EventCollector {
+    fn emit(&self, event: &str, payload: Value) {
+        self.events.lock().unwrap().push((event.to_string(), payload));
+    }
+
+    fn can_notify(&self) -> bool {
+        true
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        self.notifications.lock().unwrap().push((title.to_string(), body.to_string()));
+    }
+}
+
+/// Shared state of the backend, cheap to clone.
+#[derive(Clone)]
+pub struct AppContext {
+    pool: SqlitePool,
+    data_dir: PathBuf,
+    events: Arc<dyn EventSink>,
+}
+
+impl AppContext {
+    pub fn new(pool: SqlitePool, data_dir: PathBuf, events: Arc<dyn EventSink>) -> Self {
+        Self { pool, data_dir, events }
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub async fn accounts(&self) -> Result<AccountManager, String> {
+        AccountManager::open(self.pool.clone(), &self.data_dir).await
+    }
+
+    pub async fn secrets(&self) -> Result<SecretStore, String> {
+        SecretStore::open(&self.data_dir).await
+    }
+
+    pub fn settings(&self) -> Settings<'_> {
+        Settings { pool: &self.pool }
+    }
+
+    pub fn emit<S: Serialize>(&self, event: &str, payload: S) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => self.events.emit(event, payload),
+            Err(e) => log::error!("Failed to serialize {} event: {}", event, e),
+        }
+    }
+
+    pub fn can_notify(&self) -> bool {
+        self.events.can_notify()
+    }
+
+    pub fn notify(&self, title: &str, body: &str) {
+        self.events.notify(title, body);
+    }
+}
+
+/// Reads rows of the `settings` table. Values are stored as the frontend sends them,
+/// strings JSON-quoted and booleans as `true`/`false`.
+pub struct Settings<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl Settings<'_> {
+    pub async fn get(&self, key: &str) -> Option<String> {
+        let value: String = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(self.pool)
+            .await
+            .ok()
+            .flatten()?;
+        Some(serde_json::from_str::<String>(&value).unwrap_or(value))
+    }
+
+    pub async fn is_enabled(&self, key: &str, default: bool) -> bool {
+        match self.get(key).await {
+            Some(value) => value == "true",
+            None => default,
+        }
+    }
+
+    pub async fn number(&self, key: &str, default: i64) -> i64 {
+        self.get(key).await.and_then(|value| value.parse().ok()).unwrap_or(default)
+    }
+
+    /// Whether new mail gets an AI summary, which needs both the global and the summary switch.
+    pub async fn ai_summaries_enabled(&self) -> bool {
+        self.is_enabled("aiEnabled", false).await && self.is_enabled("aiSummarizationEnabled", false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    #[tokio::test]
+    async fn test_settings_unquote_and_default() {
+        let pool = setup_test_db().await;
+        sqlx::query("INSERT INTO settings (key, value) VALUES ('aiModel', '\"gpt\"'), ('syncMonths', '6') ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let context = AppContext::new(pool, std::env::temp_dir(), Arc::new(NoEvents));
+
+        assert_eq!(context.settings().get("aiModel").await, Some("gpt".to_string()));
+        assert_eq!(context.settings().number("syncMonths", 3).await, 6);
+        assert!(context.settings().is_enabled("missingKey", true).await);
+    }
+
+    #[test]
+    fn test_collector_keeps_events_in_order() {
+        let collector = Arc::new(EventCollector::default());
+        let sink: Arc<dyn EventSink> = collector.clone();
+        sink.emit("emails-updated", Value::from(1));
+        sink.emit("sender-updated", Value::from("a@example.com"));
+        sink.emit("emails-updated", Value::from(2));
+
+        assert_eq!(collector.named("emails-updated"), vec![Value::from(1), Value::from(2)]);
+    }
+}
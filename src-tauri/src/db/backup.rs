@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use argon2::Argon2;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::KeyInit;
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use tauri::{AppHandle, Manager};
+use crate::utils::attachments::get_attachments_dir;
+use crate::utils::security::EncryptedStore;
+
+const MAGIC: &[u8; 8] = b"DUEAMBK1";
+const SALT_LEN: usize = 16;
+/// 12 byte ChaCha20 nonce minus the 5 bytes the STREAM construction uses for its counter.
+const NONCE_PREFIX_LEN: usize = 7;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+const MANIFEST: &str = "manifest.json";
+const DATABASE: &str = "dueam.db";
+const ACCOUNTS: &str = "accounts.json";
+const SECRETS: &str = "secrets.json";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    app_version: String,
+    schema_version: i64,
+    created_at: String,
+}
+
+/// The backup key comes from the passphrase rather than the keychain,
+/// so a backup can be restored on another machine.
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, String> {
+    if passphrase.is_empty() {
+        return Err("A passphrase is required".to_string());
+    }
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+/// Writes named entries into an archive that is encrypted in chunks as it is written,
+/// so large attachment stores never have to fit in memory.
+pub struct BackupWriter<W: Write> {
+    inner: W,
+    encryptor: Option<EncryptorBE32<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BackupWriter<W> {
+    pub fn new(mut inner: W, passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_PREFIX_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let cipher = derive_key(passphrase, &salt)?;
+
+        inner.write_all(MAGIC).map_err(|e| e.to_string())?;
+        inner.write_all(&salt).map_err(|e| e.to_string())?;
+        inner.write_all(&nonce).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            inner,
+            encryptor: Some(EncryptorBE32::from_aead(cipher, nonce.as_ref().into())),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+
+    pub fn add_entry(&mut self, name: &str, data: &mut impl Read, len: u64) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("Entry names cannot be empty".to_string());
+        }
+        self.write_all(&(name.len() as u32).to_be_bytes()).map_err(|e| e.to_string())?;
+        self.write_all(name.as_bytes()).map_err(|e| e.to_string())?;
+        self.write_all(&len.to_be_bytes()).map_err(|e| e.to_string())?;
+        let copied = io::copy(&mut data.take(len), self).map_err(|e| e.to_string())?;
+        if copied != len {
+            return Err(format!("{} changed while it was being backed up", name));
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, String> {
+        // An empty name marks the end of the entries
+        self.write_all(&0u32.to_be_bytes()).map_err(|e| e.to_string())?;
+        let encryptor = self.encryptor.take().ok_or("Backup already finished")?;
+        let last = encryptor.encrypt_last(self.buffer.as_slice()).map_err(|e| e.to_string())?;
+        self.inner.write_all(&last).map_err(|e| e.to_string())?;
+        self.inner.flush().map_err(|e| e.to_string())?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BackupWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        // A full chunk is only sealed once more data follows, the final one is sealed by `finish`
+        if self.buffer.len() == CHUNK_SIZE {
+            let encryptor = self.encryptor.as_mut().ok_or_else(|| io::Error::other("Backup already finished"))?;
+            let chunk = encryptor.encrypt_next(self.buffer.as_slice()).map_err(|_| io::Error::other("Encryption failed"))?;
+            self.inner.write_all(&chunk)?;
+            self.buffer.clear();
+        }
+
+        let n = data.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the entries of an archive made by [`BackupWriter`], checking each chunk as it goes.
+pub struct BackupReader<R: Read> {
+    inner: BufReader<R>,
+    decryptor: Option<DecryptorBE32<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> BackupReader<R> {
+    pub fn new(inner: R, passphrase: &str) -> Result<Self, String> {
+        let mut inner = BufReader::new(inner);
+        let mut header = [0u8; MAGIC.len() + SALT_LEN + NONCE_PREFIX_LEN];
+        inner.read_exact(&mut header).map_err(|_| "Not a Dueam backup".to_string())?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err("Not a Dueam backup".to_string());
+        }
+        let salt = &header[MAGIC.len()..MAGIC.len() + SALT_LEN];
+        let nonce = &header[MAGIC.len() + SALT_LEN..];
+        let cipher = derive_key(passphrase, salt)?;
+
+        Ok(Self {
+            inner,
+            decryptor: Some(DecryptorBE32::from_aead(cipher, nonce.into())),
+            buffer: Vec::new(),
+            position: 0,
+        })
+    }
+
+    /// Name and size of the next entry, or `None` after the last one.
+    pub fn next_entry(&mut self) -> Result<Option<(String, u64)>, String> {
+        let mut len = [0u8; 4];
+        self.read_exact(&mut len).map_err(|e| e.to_string())?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut name = vec![0u8; len];
+        self.read_exact(&mut name).map_err(|e| e.to_string())?;
+        let mut size = [0u8; 8];
+        self.read_exact(&mut size).map_err(|e| e.to_string())?;
+
+        let name = String::from_utf8(name).map_err(|e| e.to_string())?;
+        Ok(Some((name, u64::from_be_bytes(size))))
+    }
+
+    pub fn copy_entry(&mut self, size: u64, out: &mut impl Write) -> Result<(), String> {
+        let copied = io::copy(&mut self.take(size), out).map_err(|e| e.to_string())?;
+        if copied != size {
+            return Err("Backup is truncated".to_string());
+        }
+        Ok(())
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE + TAG_LEN);
+        (&mut self.inner).take((CHUNK_SIZE + TAG_LEN) as u64).read_to_end(&mut chunk)?;
+        let is_last = self.inner.fill_buf()?.is_empty();
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Wrong passphrase or damaged backup");
+        self.buffer = if is_last {
+            let decryptor = self.decryptor.take().ok_or_else(invalid)?;
+            decryptor.decrypt_last(chunk.as_slice()).map_err(|_| invalid())?
+        } else {
+            let decryptor = self.decryptor.as_mut().ok_or_else(invalid)?;
+            decryptor.decrypt_next(chunk.as_slice()).map_err(|_| invalid())?
+        };
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BackupReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+            self.read_chunk()?;
+        }
+        let n = out.len().min(self.buffer.len() - self.position);
+        out[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Newest migration applied to a database.
+async fn schema_version(pool: &SqlitePool) -> Result<i64, String> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Makes sure every migration applied to a restored database is one this build knows, with the
+/// same contents, and then applies the ones it is missing.
+async fn check_and_migrate(pool: &SqlitePool) -> Result<(), String> {
+    let migrator = sqlx::migrate!("./migrations");
+    let known: HashMap<i64, &[u8]> = migrator.iter().map(|m| (m.version, m.checksum.as_ref())).collect();
+
+    let applied: Vec<(i64, Vec<u8>)> = sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations WHERE success = 1")
+        .fetch_all(pool)
+        .await
+        .map_err(|_| "The backup does not contain a Dueam database".to_string())?;
+
+    for (version, checksum) in applied {
+        match known.get(&version) {
+            None => return Err(format!("The backup was made by a newer version of Dueam (schema {})", version)),
+            Some(expected) if *expected != checksum.as_slice() => {
+                return Err(format!("The backup's schema {} does not match this version of Dueam", version));
+            }
+            Some(_) => {}
+        }
+    }
+
+    migrator.run(pool).await.map_err(|e| e.to_string())
+}
+
+fn is_attachment_hash(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn add_file<W: Write>(writer: &mut BackupWriter<W>, name: &str, path: &Path) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    writer.add_entry(name, &mut file, len)
+}
+
+fn add_bytes<W: Write>(writer: &mut BackupWriter<W>, name: &str, data: &[u8]) -> Result<(), String> {
+    writer.add_entry(name, &mut &data[..], data.len() as u64)
+}
+
+/// Writes the archive. It hashes the passphrase and copies the whole attachment store, so it
+/// runs on a blocking thread.
+fn write_backup(
+    path: &Path,
+    passphrase: &str,
+    manifest: &Manifest,
+    snapshot: &Path,
+    app_dir: &Path,
+    store: &EncryptedStore,
+    attachments_dir: &Path,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = BackupWriter::new(BufWriter::new(file), passphrase)?;
+
+    add_bytes(&mut writer, MANIFEST, &serde_json::to_vec(manifest).map_err(|e| e.to_string())?)?;
+    add_file(&mut writer, DATABASE, snapshot)?;
+    for (name, file_name) in [(ACCOUNTS, "accounts.json.enc"), (SECRETS, "secrets.json.enc")] {
+        let encrypted = app_dir.join(file_name);
+        if encrypted.exists() {
+            add_bytes(&mut writer, name, &store.load(encrypted)?)?;
+        }
+    }
+
+    for entry in fs::read_dir(attachments_dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_file() && is_attachment_hash(&name) {
+            add_file(&mut writer, &format!("{}{}", ATTACHMENTS_PREFIX, name), &entry.path())?;
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes the database, the account registry, secrets and the attachment store into one
+/// passphrase-encrypted file.
+#[tauri::command]
+pub async fn create_backup(app_handle: AppHandle, path: String, passphrase: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let app_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+
+    // VACUUM INTO takes a consistent snapshot while the app keeps using the database
+    let snapshot = app_dir.join("backup-snapshot.db");
+    let _ = fs::remove_file(&snapshot);
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot.to_string_lossy().to_string())
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let result = async {
+        let manifest = Manifest {
+            app_version: app_handle.package_info().version.to_string(),
+            schema_version: schema_version(&pool).await?,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let store = EncryptedStore::new().await?;
+        let attachments_dir = get_attachments_dir(&app_handle)?;
+
+        let (path, passphrase, snapshot, app_dir) = (PathBuf::from(&path), passphrase.clone(), snapshot.clone(), app_dir.clone());
+        tokio::task::spawn_blocking(move || write_backup(&path, &passphrase, &manifest, &snapshot, &app_dir, &store, &attachments_dir))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+    .await;
+
+    let _ = fs::remove_file(&snapshot);
+    if result.is_err() {
+        let _ = fs::remove_file(&path);
+    }
+    result
+}
+
+/// Unpacks a backup into `staging`, returning the paths of the files it contained.
+fn unpack(path: &Path, passphrase: &str, staging: &Path) -> Result<HashMap<String, PathBuf>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = BackupReader::new(file, passphrase)?;
+    let mut files = HashMap::new();
+    fs::create_dir_all(staging.join("attachments")).map_err(|e| e.to_string())?;
+
+    while let Some((name, size)) = reader.next_entry()? {
+        let target = match name.as_str() {
+            MANIFEST | DATABASE | ACCOUNTS | SECRETS => staging.join(&name),
+            other => match other.strip_prefix(ATTACHMENTS_PREFIX) {
+                Some(hash) if is_attachment_hash(hash) => staging.join("attachments").join(hash),
+                _ => return Err(format!("Unexpected entry {} in backup", name)),
+            },
+        };
+        let mut out = BufWriter::new(File::create(&target).map_err(|e| e.to_string())?);
+        reader.copy_entry(size, &mut out)?;
+        out.flush().map_err(|e| e.to_string())?;
+        files.insert(name, target);
+    }
+
+    for required in [MANIFEST, DATABASE] {
+        if !files.contains_key(required) {
+            return Err(format!("Backup is missing {}", required));
+        }
+    }
+    Ok(files)
+}
+
+/// Replaces the current database, accounts, secrets and attachments with the contents of a backup,
+/// then restarts the app so everything is loaded from the restored files.
+#[tauri::command]
+pub async fn restore_backup(app_handle: AppHandle, path: String, passphrase: String) -> Result<(), String> {
+    let app_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let staging = app_dir.join("restore-staging");
+    let _ = fs::remove_dir_all(&staging);
+
+    // Unpacking decrypts the whole archive, so like writing it, it runs on a blocking thread
+    let unpacked = {
+        let (path, staging) = (PathBuf::from(&path), staging.clone());
+        tokio::task::spawn_blocking(move || unpack(&path, &passphrase, &staging))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|files| files)
+    };
+    let files = match unpacked {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    let staged_db = files[DATABASE].clone();
+    let check = async {
+        let options = SqliteConnectOptions::new().filename(&staged_db);
+        let staged_pool = SqlitePool::connect_with(options).await.map_err(|e| e.to_string())?;
+        let result = check_and_migrate(&staged_pool).await;
+        staged_pool.close().await;
+        result
+    }
+    .await;
+    if let Err(e) = check {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let store = match EncryptedStore::new().await {
+        Ok(store) => store,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    let attachments_dir = get_attachments_dir(&app_handle)?;
+
+    // Nothing may write to the old database while it is being replaced. From here on the app
+    // restarts on whichever data ends up in place, the restored or the previous.
+    app_handle.state::<SqlitePool>().close().await;
+
+    let previous = app_dir.join("restore-previous");
+    let installed = {
+        let (app_dir, staging, previous) = (app_dir.clone(), staging.clone(), previous.clone());
+        tokio::task::spawn_blocking(move || install(&app_dir, &staging, &previous, &files, &store, &attachments_dir))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|installed| installed)
+    };
+    match installed {
+        Ok(()) => log::info!("Restored backup from {}, restarting", path),
+        Err(e) => {
+            log::error!("Restoring backup from {} failed, putting the previous data back: {}", path, e);
+            roll_back(&app_dir, &previous);
+        }
+    }
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_dir_all(&previous);
+    app_handle.restart();
+}
+
+/// The files of the live data a restore replaces.
+const REPLACED: [&str; 5] = ["dueam.db", "dueam.db-wal", "dueam.db-shm", "accounts.json.enc", "secrets.json.enc"];
+
+/// Moves the staged files into place. The replaced ones are kept in `previous` until the app
+/// restarts, so a failure part way can be undone.
+fn install(
+    app_dir: &Path,
+    staging: &Path,
+    previous: &Path,
+    files: &HashMap<String, PathBuf>,
+    store: &EncryptedStore,
+    attachments_dir: &Path,
+) -> Result<(), String> {
+    let _ = fs::remove_dir_all(previous);
+    fs::create_dir_all(previous).map_err(|e| e.to_string())?;
+    for name in REPLACED {
+        let current = app_dir.join(name);
+        if current.exists() {
+            fs::rename(&current, previous.join(name)).map_err(|e| e.to_string())?;
+        }
+    }
+
+    fs::rename(&files[DATABASE], app_dir.join("dueam.db")).map_err(|e| e.to_string())?;
+    for (name, file_name) in [(ACCOUNTS, "accounts.json.enc"), (SECRETS, "secrets.json.enc")] {
+        if let Some(staged) = files.get(name) {
+            store.save(app_dir.join(file_name), &fs::read(staged).map_err(|e| e.to_string())?)?;
+        }
+    }
+
+    // Attachments are content addressed, so files already present are identical, and ones
+    // left over from a failed restore do no harm
+    for entry in fs::read_dir(staging.join("attachments")).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let target = attachments_dir.join(entry.file_name());
+        if !target.exists() {
+            fs::rename(entry.path(), target).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts back the files `install` moved aside.
+fn roll_back(app_dir: &Path, previous: &Path) {
+    for name in REPLACED {
+        let _ = fs::remove_file(app_dir.join(name));
+        let saved = previous.join(name);
+        if saved.exists() {
+            if let Err(e) = fs::rename(&saved, app_dir.join(name)) {
+                log::error!("Could not put back {}: {}", name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    fn archive(entries: &[(&str, Vec<u8>)], passphrase: &str) -> Vec<u8> {
+        let mut writer = BackupWriter::new(Vec::new(), passphrase).unwrap();
+        for (name, data) in entries {
+            add_bytes(&mut writer, name, data).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn read_all(data: &[u8], passphrase: &str) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let mut reader = BackupReader::new(data, passphrase)?;
+        let mut entries = Vec::new();
+        while let Some((name, size)) = reader.next_entry()? {
+            let mut out = Vec::new();
+            reader.copy_entry(size, &mut out)?;
+            entries.push((name, out));
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    fn test_round_trip_across_chunks() {
+        let large: Vec<u8> = (0..CHUNK_SIZE * 3 + 17).map(|i| (i % 251) as u8).collect();
+        let entries = vec![
+            (MANIFEST, b"{}".to_vec()),
+            ("attachments/abc123", large),
+            (SECRETS, Vec::new()),
+        ];
+
+        let data = archive(&entries, "correct horse");
+        let read = read_all(&data, "correct horse").unwrap();
+
+        assert_eq!(read.len(), 3);
+        for ((name, expected), (read_name, read_data)) in entries.iter().zip(read) {
+            assert_eq!(*name, read_name);
+            assert_eq!(*expected, read_data);
+        }
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering_are_rejected() {
+        let data = archive(&[(MANIFEST, b"{}".to_vec())], "correct horse");
+        assert!(read_all(&data, "wrong horse").is_err());
+
+        let mut tampered = data.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(read_all(&tampered, "correct horse").is_err());
+
+        // Dropping the final chunk must not look like a complete backup
+        let large = vec![7u8; CHUNK_SIZE * 2];
+        let data = archive(&[(DATABASE, large)], "correct horse");
+        assert!(read_all(&data[..data.len() - 100], "correct horse").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_and_migrate_rejects_newer_schema() {
+        let pool = setup_test_db().await;
+        check_and_migrate(&pool).await.expect("Current schema should be accepted");
+
+        sqlx::query("INSERT INTO _sqlx_migrations (version, description, installed_on, success, checksum, execution_time) VALUES (9999, 'future', CURRENT_TIMESTAMP, 1, x'00', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let err = check_and_migrate(&pool).await.unwrap_err();
+        assert!(err.contains("newer version"));
+    }
+}
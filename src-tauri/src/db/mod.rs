@@ -1,2 +1,4 @@
 pub mod setup;
-pub mod settings;
\ No newline at end of file
+pub mod settings;
+pub mod secrets;
+pub mod backup;
@@ -0,0 +1,140 @@
+use crate::utils::security::EncryptedStore;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// Settings keys whose values are secrets. They never live in the `settings` table;
+/// `get_settings` only reports whether they are set.
+pub const SECRET_SETTING_KEYS: &[&str] = &["aiApiKey"];
+
+/// Placeholder returned to the frontend in place of a stored secret.
+pub const REDACTED_SECRET: &str = "********";
+
+pub fn is_secret_key(key: &str) -> bool {
+    SECRET_SETTING_KEYS.contains(&key)
+}
+
+pub struct SecretStore {
+    store: EncryptedStore,
+    storage_path: PathBuf,
+}
+
+impl SecretStore {
+    pub async fn new<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<Self, String> {
+        let data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+        Self::open(&data_dir).await
+    }
+
+    /// Opens the secret store kept in `data_dir`, without a running app.
+    pub async fn open(data_dir: &Path) -> Result<Self, String> {
+        let store = EncryptedStore::new().await?;
+        Ok(Self {
+            store,
+            storage_path: data_dir.join("secrets.json.enc"),
+        })
+    }
+
+    fn load(&self) -> Result<HashMap<String, String>, String> {
+        if !self.storage_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let data = self.store.load(self.storage_path.clone())?;
+        serde_json::from_slice(&data).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, secrets: &HashMap<String, String>) -> Result<(), String> {
+        let data = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+        self.store.save(self.storage_path.clone(), &data)
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        Ok(self.load()?.remove(key).filter(|v| !v.is_empty()))
+    }
+
+    pub fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        let mut secrets = self.load()?;
+        if value.is_empty() {
+            secrets.remove(key);
+        } else {
+            secrets.insert(key.to_string(), value.to_string());
+        }
+        self.save(&secrets)
+    }
+
+    /// Moves secrets that older versions stored as plaintext settings rows into the
+    /// encrypted store and deletes the rows.
+    pub async fn migrate_plaintext_settings(&self, pool: &SqlitePool) -> Result<(), String> {
+        for key in SECRET_SETTING_KEYS {
+            let row: Option<(String,)> = sqlx::query_as("SELECT value FROM settings WHERE key = ?")
+                .bind(key)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let Some((value,)) = row else { continue };
+
+            let unquoted = serde_json::from_str::<String>(&value).unwrap_or(value);
+            if !unquoted.is_empty() && self.get(key)?.is_none() {
+                log::info!("Moving setting {} to the encrypted secret store", key);
+                self.set(key, &unquoted)?;
+            }
+
+            sqlx::query("DELETE FROM settings WHERE key = ?")
+                .bind(key)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn new_test(store: EncryptedStore, storage_path: PathBuf) -> Self {
+        Self { store, storage_path }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_migrate_plaintext_api_key() {
+        let pool = setup_test_db().await;
+        sqlx::query("UPDATE settings SET value = ? WHERE key = 'aiApiKey'")
+            .bind("\"sk-plaintext\"")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let dir = tempdir().unwrap();
+        let store = SecretStore::new_test(EncryptedStore::new_test([3u8; 32]), dir.path().join("secrets.json.enc"));
+
+        store.migrate_plaintext_settings(&pool).await.expect("Migration failed");
+
+        assert_eq!(store.get("aiApiKey").unwrap(), Some("sk-plaintext".to_string()));
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM settings WHERE key = 'aiApiKey'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_empty_value_clears_secret() {
+        let dir = tempdir().unwrap();
+        let store = SecretStore::new_test(EncryptedStore::new_test([3u8; 32]), dir.path().join("secrets.json.enc"));
+
+        store.set("aiApiKey", "sk-123").unwrap();
+        assert_eq!(store.get("aiApiKey").unwrap(), Some("sk-123".to_string()));
+
+        store.set("aiApiKey", "").unwrap();
+        assert_eq!(store.get("aiApiKey").unwrap(), None);
+    }
+}
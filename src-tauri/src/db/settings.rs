@@ -1,6 +1,7 @@
 use tauri::{AppHandle, Manager};
 use sqlx::sqlite::SqlitePool;
 use std::collections::HashMap;
+use crate::db::secrets::{SecretStore, SECRET_SETTING_KEYS, REDACTED_SECRET, is_secret_key};
 
 #[tauri::command]
 pub async fn get_settings(app_handle: AppHandle) -> Result<HashMap<String, String>, String> {
@@ -10,11 +11,31 @@ pub async fn get_settings(app_handle: AppHandle) -> Result<HashMap<String, Strin
         .await
         .map_err(|e| e.to_string())?;
 
-    Ok(rows.into_iter().collect())
+    let mut settings: HashMap<String, String> = rows.into_iter()
+        .filter(|(key, _)| !is_secret_key(key))
+        .collect();
+
+    // Secrets are never sent to the frontend, only whether they are set
+    let secrets = SecretStore::new(&app_handle).await?;
+    for key in SECRET_SETTING_KEYS {
+        let redacted = if secrets.get(key)?.is_some() { REDACTED_SECRET } else { "" };
+        settings.insert(key.to_string(), serde_json::to_string(redacted).map_err(|e| e.to_string())?);
+    }
+
+    Ok(settings)
 }
 
 #[tauri::command]
 pub async fn update_setting(app_handle: AppHandle, key: String, value: String) -> Result<(), String> {
+    if is_secret_key(&key) {
+        let unquoted = serde_json::from_str::<String>(&value).unwrap_or(value);
+        // The frontend echoes the placeholder back when the field was left untouched
+        if unquoted == REDACTED_SECRET {
+            return Ok(());
+        }
+        return SecretStore::new(&app_handle).await?.set(&key, &unquoted);
+    }
+
     let pool = app_handle.state::<SqlitePool>();
     sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
         .bind(key)
@@ -1,11 +1,18 @@
 use sqlx::sqlite::{SqlitePool, SqliteConnectOptions};
+use std::path::Path;
 use tauri::AppHandle;
 use tauri::Manager;
+use crate::db::secrets::SecretStore;
 
 pub async fn setup_database(app_handle: &AppHandle) -> Result<SqlitePool, String> {
     let app_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("dueam.db");
+    open_database(&app_dir).await
+}
+
+/// Opens (creating and migrating if needed) the database kept in `data_dir`.
+pub async fn open_database(data_dir: &Path) -> Result<SqlitePool, String> {
+    std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    let db_path = data_dir.join("dueam.db");
 
     log::info!("Database path: {:?}", db_path);
 
@@ -20,5 +27,10 @@ pub async fn setup_database(app_handle: &AppHandle) -> Result<SqlitePool, String
         .await
         .map_err(|e| e.to_string())?;
 
+    // Move secrets left in the plaintext settings table by older versions
+    SecretStore::open(data_dir).await?
+        .migrate_plaintext_settings(&pool)
+        .await?;
+
     Ok(pool)
 }
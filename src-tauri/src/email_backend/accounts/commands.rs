@@ -1,13 +1,19 @@
 use tauri::{AppHandle, Emitter, Manager};
 use crate::email_backend::accounts::google::get_auth_url;
 use crate::email_backend::accounts::microsoft::login_with_microsoft as microsoft_login;
+use crate::email_backend::accounts::oauth2::{builtin_providers, login_with_oauth2 as oauth2_login, OAuth2Provider};
 use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use crate::email_backend::accounts::maildir::MaildirAccount;
+use crate::email_backend::accounts::notmuch::NotmuchAccount;
 use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::email_backend::accounts::discovery::Discovery;
 use crate::email_backend::sync::SyncEngine;
 use email::backend::context::BackendContextBuilder;
 use email::imap::ImapContextBuilder;
 use email::smtp::SmtpContextBuilder;
 use email::backend::BackendBuilder;
+use crate::utils::attachments::remove_orphaned_attachments;
+use sqlx::SqlitePool;
 
 #[tauri::command]
 pub async fn login_with_google(app_handle: AppHandle) -> Result<(), String> {
@@ -21,6 +27,30 @@ pub async fn login_with_microsoft(app_handle: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_oauth2_providers() -> Result<Vec<OAuth2Provider>, String> {
+    Ok(builtin_providers().into_iter().map(|mut provider| {
+        provider.client_secret = None;
+        provider
+    }).collect())
+}
+
+#[tauri::command]
+pub async fn login_with_oauth2(app_handle: AppHandle, provider: OAuth2Provider, email: Option<String>) -> Result<(), String> {
+    // Built-in providers are looked up again so their client secret never round-trips
+    // through the frontend
+    let provider = builtin_providers().into_iter()
+        .find(|p| p.id == provider.id)
+        .unwrap_or(provider);
+    oauth2_login(&app_handle, provider, email).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn discover_account_settings(email: String) -> Result<Vec<ImapSmtpAccount>, String> {
+    Discovery::new()?.discover(&email).await
+}
+
 #[tauri::command]
 pub async fn verify_imap_smtp_credentials(account: ImapSmtpAccount) -> Result<(), String> {
     let account_enum = Account::ImapSmtp(account);
@@ -31,7 +61,10 @@ pub async fn verify_imap_smtp_credentials(account: ImapSmtpAccount) -> Result<()
     let _imap_context = BackendContextBuilder::build(imap_ctx_builder).await
         .map_err(|e| format!("IMAP Error: {}", e))?;
     
-    // 2. Verify SMTP
+    // 2. Verify SMTP, unless mail goes out through a local command
+    if account_enum.sendmail_command().is_some() {
+        return Ok(());
+    }
     let smtp_ctx_builder = SmtpContextBuilder::new(account_config.clone(), smtp_config);
     let _smtp_backend = BackendBuilder::new(account_config, smtp_ctx_builder).build().await
         .map_err(|e| format!("SMTP Error: {}", e))?;
@@ -55,6 +88,40 @@ pub async fn add_imap_smtp_account(app_handle: AppHandle, account: ImapSmtpAccou
     Ok(())
 }
 
+#[tauri::command]
+pub async fn add_maildir_account(app_handle: AppHandle, account: MaildirAccount) -> Result<(), String> {
+    account.validate()?;
+
+    let manager = AccountManager::new(&app_handle).await?;
+    manager.add_account(Account::Maildir(account.clone())).await?;
+
+    if let Some(sync_engine) = app_handle.try_state::<SyncEngine>() {
+        let registry = manager.load().await?;
+        let added_account = registry.accounts.iter().find(|a| a.email() == account.email).unwrap().clone();
+        sync_engine.trigger_sync_for_account(added_account);
+    }
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn add_notmuch_account(app_handle: AppHandle, account: NotmuchAccount) -> Result<(), String> {
+    account.validate().await?;
+
+    let manager = AccountManager::new(&app_handle).await?;
+    manager.add_account(Account::Notmuch(account.clone())).await?;
+
+    if let Some(sync_engine) = app_handle.try_state::<SyncEngine>() {
+        let registry = manager.load().await?;
+        let added_account = registry.accounts.iter().find(|a| a.email() == account.email).unwrap().clone();
+        sync_engine.trigger_sync_for_account(added_account);
+    }
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_accounts(app_handle: AppHandle) -> Result<Vec<Account>, String> {
     let manager = AccountManager::new(&app_handle).await?;
@@ -66,7 +133,92 @@ pub async fn get_accounts(app_handle: AppHandle) -> Result<Vec<Account>, String>
 }
 
 #[tauri::command]
-pub async fn remove_account(app_handle: AppHandle, index: usize) -> Result<(), String> {
+pub async fn update_account(
+    app_handle: AppHandle,
+    account_id: i64,
+    name: Option<String>,
+    imap_smtp: Option<ImapSmtpAccount>,
+) -> Result<(), String> {
+    let manager = AccountManager::new(&app_handle).await?;
+    let mut account = manager.get_account_by_id(account_id).await?;
+    let mut credentials_changed = false;
+
+    if let Some(mut settings) = imap_smtp {
+        let Account::ImapSmtp(existing) = &account else {
+            return Err("Server settings can only be changed for IMAP/SMTP accounts".to_string());
+        };
+
+        // Passwords are stripped before accounts reach the frontend, so a missing
+        // password means "keep the current one"
+        settings.id = existing.id;
+        if settings.password.is_none() {
+            settings.password = existing.password.clone();
+        }
+        if settings.smtp_password.is_none() {
+            settings.smtp_password = existing.smtp_password.clone();
+        }
+
+        verify_imap_smtp_credentials(settings.clone()).await?;
+
+        account = Account::ImapSmtp(settings);
+        credentials_changed = true;
+    }
+
+    if let Some(name) = name {
+        account.set_name(if name.trim().is_empty() { None } else { Some(name) });
+    }
+
+    manager.update_account(account.clone()).await?;
+
+    if credentials_changed {
+        if let Some(sync_engine) = app_handle.try_state::<SyncEngine>() {
+            sync_engine.stop_account(account_id).await;
+            sync_engine.trigger_sync_for_account(account);
+        }
+    }
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+/// Puts the accounts in the order the user arranged them in.
+#[tauri::command]
+pub async fn reorder_accounts(app_handle: AppHandle, account_ids: Vec<i64>) -> Result<(), String> {
     let manager = AccountManager::new(&app_handle).await?;
-    manager.remove_account(index).await
+    manager.reorder_accounts(&account_ids).await?;
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_account(app_handle: AppHandle, account_id: i64) -> Result<(), String> {
+    let manager = AccountManager::new(&app_handle).await?;
+
+    if let Some(sync_engine) = app_handle.try_state::<SyncEngine>() {
+        sync_engine.stop_account(account_id).await;
+    }
+
+    // The rows cascade away with the account, so their files are collected first
+    let pool = app_handle.state::<SqlitePool>();
+    let hashes: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT a.file_hash FROM attachments a
+         LEFT JOIN emails e ON a.email_id = e.id
+         LEFT JOIN drafts d ON a.draft_id = d.id
+         WHERE (e.account_id = ? OR d.account_id = ?) AND a.file_hash IS NOT NULL"
+    )
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    manager.remove_account(account_id).await?;
+
+    if let Err(e) = remove_orphaned_attachments(&app_handle, &pool, &hashes).await {
+        log::error!("Failed to clean up attachments for removed account {}: {}", account_id, e);
+    }
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
 }
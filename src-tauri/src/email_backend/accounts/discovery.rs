@@ -0,0 +1,410 @@
+use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use async_trait::async_trait;
+use email::autoconfig::config::{AutoConfig, SecurityType, Server, ServerType};
+use email::autoconfig::dns::DnsClient;
+use log::{debug, info};
+use std::time::Duration;
+
+/// Autoconfig locations tried for a domain, in order of trust. `{domain}` and the
+/// percent-encoded `{email}` are substituted before fetching.
+pub const DEFAULT_AUTOCONFIG_URLS: &[&str] = &[
+    "https://autoconfig.{domain}/mail/config-v1.1.xml?emailaddress={email}",
+    "https://{domain}/.well-known/autoconfig/mail/config-v1.1.xml",
+    "http://autoconfig.{domain}/mail/config-v1.1.xml?emailaddress={email}",
+    "https://autoconfig.thunderbird.net/v1.1/{domain}",
+];
+
+/// Where a candidate came from. Earlier variants are ranked higher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    Autoconfig,
+    MxAutoconfig,
+    Srv,
+    Guess,
+}
+
+#[async_trait]
+pub trait DnsLookup: Send + Sync {
+    /// The provider domain of the best MX record, e.g. `google.com`.
+    async fn mx_domain(&self, domain: &str) -> Option<String>;
+    /// Target host and port of the best `_{service}._tcp.{domain}` SRV record.
+    async fn srv(&self, domain: &str, service: &str) -> Option<(String, u16)>;
+}
+
+pub struct SystemDns(DnsClient);
+
+#[async_trait]
+impl DnsLookup for SystemDns {
+    async fn mx_domain(&self, domain: &str) -> Option<String> {
+        self.0.get_mx_domain(domain).await.ok()
+    }
+
+    async fn srv(&self, domain: &str, service: &str) -> Option<(String, u16)> {
+        let record = self.0.get_srv(domain, service).await.ok()?;
+        let target = record.target().to_string().trim_end_matches('.').to_string();
+        Some((target, record.port()))
+    }
+}
+
+pub struct Discovery {
+    http: reqwest::Client,
+    autoconfig_urls: Vec<String>,
+    dns: Box<dyn DnsLookup>,
+}
+
+impl Discovery {
+    pub fn new() -> Result<Self, String> {
+        let urls = DEFAULT_AUTOCONFIG_URLS.iter().map(|u| u.to_string()).collect();
+        Self::with_sources(urls, Box::new(SystemDns(DnsClient::new())))
+    }
+
+    /// Uses the given autoconfig URL templates and DNS resolver instead of the public ones.
+    pub fn with_sources(autoconfig_urls: Vec<String>, dns: Box<dyn DnsLookup>) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { http, autoconfig_urls, dns })
+    }
+
+    /// Returns candidate server settings for the address, best first. Passwords are left
+    /// empty; each candidate is meant to be completed and passed to
+    /// `verify_imap_smtp_credentials`.
+    pub async fn discover(&self, email: &str) -> Result<Vec<ImapSmtpAccount>, String> {
+        let email = email.trim();
+        let (local_part, domain) = email.rsplit_once('@')
+            .filter(|(local, domain)| !local.is_empty() && domain.contains('.'))
+            .ok_or_else(|| "Invalid email address".to_string())?;
+        let domain = domain.to_lowercase();
+        let ctx = Placeholders { email, local_part, domain: &domain };
+
+        let mut candidates: Vec<(Source, u8, ImapSmtpAccount)> = Vec::new();
+
+        if let Some(config) = self.best_autoconfig(&domain, email).await {
+            candidates.extend(candidates_from_autoconfig(&config, &ctx).into_iter().map(|(score, account)| (Source::Autoconfig, score, account)));
+        }
+
+        // Custom domains hosted by a large provider usually only have an ISPDB entry
+        // under the provider's domain
+        if candidates.is_empty() {
+            if let Some(mx_domain) = self.dns.mx_domain(&domain).await.filter(|d| *d != domain) {
+                debug!("Trying autoconfig for MX domain {} of {}", mx_domain, domain);
+                if let Some(config) = self.best_autoconfig(&mx_domain, email).await {
+                    candidates.extend(candidates_from_autoconfig(&config, &ctx).into_iter().map(|(score, account)| (Source::MxAutoconfig, score, account)));
+                }
+            }
+        }
+
+        if let Some((score, account)) = self.candidate_from_srv(&ctx).await {
+            candidates.push((Source::Srv, score, account));
+        }
+
+        let (score, account) = guessed_candidate(&ctx);
+        candidates.push((Source::Guess, score, account));
+
+        // Stable sort keeps the provider's own ordering among equal candidates
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let mut ranked: Vec<ImapSmtpAccount> = Vec::new();
+        for (_, _, account) in candidates {
+            let duplicate = ranked.iter().any(|r| {
+                r.imap_host.eq_ignore_ascii_case(&account.imap_host) && r.imap_port == account.imap_port
+                    && r.smtp_host.eq_ignore_ascii_case(&account.smtp_host) && r.smtp_port == account.smtp_port
+            });
+            if !duplicate {
+                ranked.push(account);
+            }
+        }
+
+        info!("Discovered {} candidate configuration(s) for {}", ranked.len(), domain);
+        Ok(ranked)
+    }
+
+    /// Asks every autoconfig location at once and returns the most trusted config found.
+    /// Less trusted locations are not waited on once a better one has answered.
+    async fn best_autoconfig(&self, domain: &str, email: &str) -> Option<AutoConfig> {
+        let email: String = url::form_urlencoded::byte_serialize(email.as_bytes()).collect();
+        let fetches: Vec<_> = self.autoconfig_urls.iter()
+            .map(|template| {
+                let url = template.replace("{domain}", domain).replace("{email}", &email);
+                let http = self.http.clone();
+                tokio::spawn(async move {
+                    fetch_autoconfig(&http, &url).await.map_err(|e| format!("No autoconfig at {}: {}", url, e))
+                })
+            })
+            .collect();
+
+        let mut found = None;
+        for fetch in fetches {
+            if found.is_some() {
+                fetch.abort();
+                continue;
+            }
+            match fetch.await {
+                Ok(Ok(config)) => found = Some(config),
+                Ok(Err(e)) => debug!("{}", e),
+                Err(e) => debug!("Autoconfig fetch failed: {}", e),
+            }
+        }
+        found
+    }
+
+    async fn candidate_from_srv(&self, ctx: &Placeholders<'_>) -> Option<(u8, ImapSmtpAccount)> {
+        let (imap_host, imap_port, imap_encryption) = match self.dns.srv(ctx.domain, "imaps").await {
+            Some((host, port)) => (host, port, "tls"),
+            None => {
+                let (host, port) = self.dns.srv(ctx.domain, "imap").await?;
+                (host, port, "starttls")
+            }
+        };
+        let (smtp_host, smtp_port) = self.dns.srv(ctx.domain, "submission").await?;
+        let smtp_encryption = if smtp_port == 465 { "tls" } else { "starttls" };
+
+        let account = ctx.account(imap_host, imap_port, ctx.email.to_string(), imap_encryption, smtp_host, smtp_port, ctx.email.to_string(), smtp_encryption);
+        Some((security_score(imap_encryption) + security_score(smtp_encryption), account))
+    }
+}
+
+async fn fetch_autoconfig(http: &reqwest::Client, url: &str) -> Result<AutoConfig, String> {
+    let response = http.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    serde_xml_rs::from_str(&body).map_err(|e| e.to_string())
+}
+
+struct Placeholders<'a> {
+    email: &'a str,
+    local_part: &'a str,
+    domain: &'a str,
+}
+
+impl Placeholders<'_> {
+    fn expand(&self, value: &str) -> String {
+        value
+            .replace("%EMAILADDRESS%", self.email)
+            .replace("%EMAILLOCALPART%", self.local_part)
+            .replace("%EMAILDOMAIN%", self.domain)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn account(&self, imap_host: String, imap_port: u16, imap_username: String, imap_encryption: &str, smtp_host: String, smtp_port: u16, smtp_username: String, smtp_encryption: &str) -> ImapSmtpAccount {
+        ImapSmtpAccount {
+            id: None,
+            email: self.email.to_string(),
+            name: None,
+            imap_host,
+            imap_port,
+            smtp_use_imap_credentials: imap_username == smtp_username,
+            imap_username,
+            imap_encryption: imap_encryption.to_string(),
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_encryption: smtp_encryption.to_string(),
+            sendmail_command: None,
+            password: None,
+            smtp_password: None,
+        }
+    }
+}
+
+fn encryption_of(server: &Server) -> &'static str {
+    match server.security_type() {
+        Some(SecurityType::Tls) => "tls",
+        Some(SecurityType::Starttls) => "starttls",
+        Some(SecurityType::Plain) | None => "none",
+    }
+}
+
+fn security_score(encryption: &str) -> u8 {
+    match encryption {
+        "tls" => 2,
+        "starttls" => 1,
+        _ => 0,
+    }
+}
+
+/// Pairs every IMAP server of the config with every SMTP server, scored by transport security.
+fn candidates_from_autoconfig(config: &AutoConfig, ctx: &Placeholders) -> Vec<(u8, ImapSmtpAccount)> {
+    let provider = config.email_provider();
+    let imap_servers: Vec<&Server> = provider.incoming_servers().into_iter()
+        .filter(|s| matches!(s.server_type(), ServerType::Imap))
+        .collect();
+    let smtp_servers: Vec<&Server> = provider.outgoing_servers().into_iter()
+        .filter(|s| matches!(s.server_type(), ServerType::Smtp))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for imap in &imap_servers {
+        let (Some(imap_host), Some(imap_port)) = (imap.hostname(), imap.port()) else { continue };
+        for smtp in &smtp_servers {
+            let (Some(smtp_host), Some(smtp_port)) = (smtp.hostname(), smtp.port()) else { continue };
+            let imap_encryption = encryption_of(imap);
+            let smtp_encryption = encryption_of(smtp);
+            let account = ctx.account(
+                ctx.expand(imap_host), *imap_port, ctx.expand(imap.username().unwrap_or("%EMAILADDRESS%")), imap_encryption,
+                ctx.expand(smtp_host), *smtp_port, ctx.expand(smtp.username().unwrap_or("%EMAILADDRESS%")), smtp_encryption,
+            );
+            candidates.push((security_score(imap_encryption) + security_score(smtp_encryption), account));
+        }
+    }
+    candidates
+}
+
+/// Last resort: the conventional `imap.` and `smtp.` hosts of the domain.
+fn guessed_candidate(ctx: &Placeholders) -> (u8, ImapSmtpAccount) {
+    let account = ctx.account(
+        format!("imap.{}", ctx.domain), 993, ctx.email.to_string(), "tls",
+        format!("smtp.{}", ctx.domain), 587, ctx.email.to_string(), "starttls",
+    );
+    (security_score("tls") + security_score("starttls"), account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const AUTOCONFIG_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<clientConfig version="1.1">
+  <emailProvider id="example.com">
+    <domain>example.com</domain>
+    <displayName>Example Mail</displayName>
+    <incomingServer type="imap">
+      <hostname>mail.example.com</hostname>
+      <port>143</port>
+      <socketType>STARTTLS</socketType>
+      <authentication>password-cleartext</authentication>
+      <username>%EMAILLOCALPART%</username>
+    </incomingServer>
+    <incomingServer type="imap">
+      <hostname>mail.example.com</hostname>
+      <port>993</port>
+      <socketType>SSL</socketType>
+      <authentication>password-cleartext</authentication>
+      <username>%EMAILLOCALPART%</username>
+    </incomingServer>
+    <outgoingServer type="smtp">
+      <hostname>smtp.%EMAILDOMAIN%</hostname>
+      <port>465</port>
+      <socketType>SSL</socketType>
+      <authentication>password-cleartext</authentication>
+      <username>%EMAILADDRESS%</username>
+    </outgoingServer>
+  </emailProvider>
+</clientConfig>"#;
+
+    #[derive(Default)]
+    struct FakeDns {
+        mx: HashMap<String, String>,
+        srv: HashMap<String, (String, u16)>,
+    }
+
+    #[async_trait]
+    impl DnsLookup for FakeDns {
+        async fn mx_domain(&self, domain: &str) -> Option<String> {
+            self.mx.get(domain).cloned()
+        }
+
+        async fn srv(&self, domain: &str, service: &str) -> Option<(String, u16)> {
+            self.srv.get(&format!("_{}._tcp.{}", service, domain)).cloned()
+        }
+    }
+
+    /// Serves `body` for requests whose path contains `domain`, 404 otherwise.
+    async fn serve_autoconfig(domain: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else { break };
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or("");
+                let response = if path.contains(domain) {
+                    format!("HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/v1.1/{{domain}}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_discover_ranks_autoconfig_candidates() {
+        let url = serve_autoconfig("example.com", AUTOCONFIG_XML).await;
+        let discovery = Discovery::with_sources(vec![url], Box::new(FakeDns::default())).unwrap();
+
+        let candidates = discovery.discover("jane@example.com").await.unwrap();
+
+        let best = &candidates[0];
+        assert_eq!(best.imap_host, "mail.example.com");
+        assert_eq!(best.imap_port, 993);
+        assert_eq!(best.imap_encryption, "tls");
+        assert_eq!(best.imap_username, "jane");
+        assert_eq!(best.smtp_host, "smtp.example.com");
+        assert_eq!(best.smtp_username, "jane@example.com");
+        assert!(!best.smtp_use_imap_credentials);
+        assert!(best.password.is_none());
+
+        assert_eq!(candidates[1].imap_port, 143);
+        assert_eq!(candidates.last().unwrap().imap_host, "imap.example.com");
+    }
+
+    #[tokio::test]
+    async fn test_discover_follows_mx_and_srv() {
+        let url = serve_autoconfig("example.com", AUTOCONFIG_XML).await;
+        let mut dns = FakeDns::default();
+        dns.mx.insert("custom.org".to_string(), "example.com".to_string());
+        dns.srv.insert("_imaps._tcp.custom.org".to_string(), ("imap.custom.org".to_string(), 993));
+        dns.srv.insert("_submission._tcp.custom.org".to_string(), ("submit.custom.org".to_string(), 587));
+        let discovery = Discovery::with_sources(vec![url], Box::new(dns)).unwrap();
+
+        let candidates = discovery.discover("bob@custom.org").await.unwrap();
+
+        assert_eq!(candidates[0].imap_host, "mail.example.com");
+        assert_eq!(candidates[0].imap_username, "bob");
+        assert_eq!(candidates[0].smtp_host, "smtp.custom.org");
+
+        let srv = candidates.iter().find(|c| c.smtp_host == "submit.custom.org").expect("SRV candidate missing");
+        assert_eq!(srv.imap_host, "imap.custom.org");
+        assert_eq!(srv.smtp_encryption, "starttls");
+        assert!(srv.smtp_use_imap_credentials);
+    }
+
+    #[tokio::test]
+    async fn test_discover_encodes_address_and_stops_at_first_hit() {
+        let url = serve_autoconfig("emailaddress=jane%2Bnews%40example.com", AUTOCONFIG_XML).await;
+        // A location that accepts connections but never answers
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_url = format!("http://{}/{{domain}}", silent.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = silent.accept().await {
+                open.push(socket);
+            }
+        });
+        let urls = vec![format!("{}?emailaddress={{email}}", url), silent_url];
+        let discovery = Discovery::with_sources(urls, Box::new(FakeDns::default())).unwrap();
+
+        let started = std::time::Instant::now();
+        let candidates = discovery.discover("jane+news@example.com").await.unwrap();
+
+        assert_eq!(candidates[0].imap_host, "mail.example.com");
+        assert_eq!(candidates[0].imap_username, "jane+news");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_discover_rejects_invalid_address() {
+        let discovery = Discovery::with_sources(vec![], Box::new(FakeDns::default())).unwrap();
+        assert!(discovery.discover("not-an-address").await.is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Errors from obtaining usable credentials for an account.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountError {
+    NotFound(String),
+    /// The OAuth2 grant was revoked or has expired; only logging in again can fix it.
+    ReauthRequired { email: String, reason: String },
+    /// The token endpoint could not be reached or failed temporarily.
+    TokenRefresh(String),
+    Other(String),
+}
+
+impl AccountError {
+    pub fn is_reauth_required(&self) -> bool {
+        matches!(self, AccountError::ReauthRequired { .. })
+    }
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountError::NotFound(account) => write!(f, "Account {} not found", account),
+            AccountError::ReauthRequired { email, reason } => {
+                write!(f, "Account {} needs to be signed in again: {}", email, reason)
+            }
+            AccountError::TokenRefresh(e) => write!(f, "Failed to refresh access token: {}", e),
+            AccountError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AccountError {}
+
+impl From<String> for AccountError {
+    fn from(e: String) -> Self {
+        AccountError::Other(e)
+    }
+}
+
+impl From<AccountError> for String {
+    fn from(e: AccountError) -> Self {
+        e.to_string()
+    }
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Emitted as `account-needs-reauth` when an account's OAuth2 grant is no longer valid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReauthRequiredEvent {
+    pub account_id: i64,
+    pub email: String,
+    pub reason: String,
+}
@@ -1,10 +1,7 @@
-use email::account::config::oauth2::{OAuth2Config, OAuth2Scopes::Scopes};
+use crate::email_backend::accounts::oauth2::OAuth2Provider;
 use email::account::Error;
-use email::imap::config::ImapConfig;
-use oauth::v2_0::{AuthorizationCodeGrant, Client};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_opener::OpenerExt;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GoogleAccount {
@@ -16,86 +13,24 @@ pub struct GoogleAccount {
     pub access_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<String>,
+    /// Unix timestamp at which the access token expires, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_expires_at: Option<i64>,
 }
 
 pub struct GoogleOAuth2Config {
-    base: OAuth2Config,
-    client_secret: Option<String>,
-    #[allow(dead_code)]
-    imap_config: ImapConfig,
+    provider: OAuth2Provider,
 }
 
 impl GoogleOAuth2Config {
     pub fn new() -> Result<Self, String> {
-        let client_id = env!("GOOGLE_CLIENT_ID").to_string();
-        let client_secret = env!("GOOGLE_CLIENT_SECRET").to_string();
-
-        let mut config = OAuth2Config::default();
-        config.client_id = client_id;
-        config.auth_url = "https://accounts.google.com/o/oauth2/auth".into();
-        config.token_url = "https://www.googleapis.com/oauth2/v3/token".into();
-        config.pkce = true;
-        config.scopes = Scopes(vec![
-            "https://mail.google.com/".into(),
-            "https://www.googleapis.com/auth/userinfo.email".into(),
-            "https://www.googleapis.com/auth/userinfo.profile".into(),
-            "https://www.googleapis.com/auth/contacts.readonly".into(),
-        ]);
-
         Ok(GoogleOAuth2Config {
-            base: config,
-            client_secret: Some(client_secret),
-            imap_config: ImapConfig::default(),
+            provider: OAuth2Provider::google(),
         })
     }
 
     pub async fn get_url(&self, app_handle: &AppHandle) -> Result<GoogleAccount, Error> {
-        let redirect_scheme = match self.base.redirect_scheme.as_ref() {
-            Some(scheme) => scheme.clone(),
-            None => "http".into(),
-        };
-
-        let redirect_host = match self.base.redirect_host.as_ref() {
-            Some(host) => host.clone(),
-            None => OAuth2Config::LOCALHOST.to_owned(),
-        };
-
-        let redirect_port = match self.base.redirect_port {
-            Some(port) => port,
-            None => OAuth2Config::get_first_available_port()?,
-        };
-
-
-        let client = Client::new(
-            self.base.client_id.clone(),
-            self.client_secret.clone(),
-            self.base.auth_url.clone(),
-            self.base.token_url.clone(),
-            redirect_scheme,
-            redirect_host,
-            redirect_port,
-        )
-            .map_err(Error::BuildOauthClientError)?;
-
-        let mut auth_code_grant = AuthorizationCodeGrant::new();
-
-        if self.base.pkce {
-            auth_code_grant = auth_code_grant.with_pkce();
-        }
-
-        for scope in self.base.scopes.clone() {
-            auth_code_grant = auth_code_grant.with_scope(scope);
-        }
-
-        let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
-
-        app_handle.opener().open_url(redirect_url, None::<&str>).expect("Error when opening oauth url");
-
-
-        let (access_token, refresh_token) = auth_code_grant
-            .wait_for_redirection(&client, csrf_token)
-            .await
-            .map_err(Error::WaitForOauthRedirectionError)?;
+        let (access_token, refresh_token) = self.provider.authorize(app_handle).await?;
 
         // Fetch user info from Google API
         let user_info_client = reqwest::Client::new();
@@ -120,6 +55,7 @@ impl GoogleOAuth2Config {
             picture,
             access_token: Some(access_token),
             refresh_token,
+            token_expires_at: None,
         })
     }
 }
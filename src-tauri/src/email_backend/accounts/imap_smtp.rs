@@ -14,6 +14,9 @@ pub struct ImapSmtpAccount {
     pub smtp_username: String,
     pub smtp_encryption: String, // "tls", "starttls", "none"
     pub smtp_use_imap_credentials: bool,
+    /// Hands outgoing mail to this command, e.g. `msmtp -t`, instead of the SMTP server.
+    #[serde(default)]
+    pub sendmail_command: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub password: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
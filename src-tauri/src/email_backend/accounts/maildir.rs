@@ -0,0 +1,48 @@
+use email::account::config::AccountConfig;
+use email::maildir::config::MaildirConfig;
+use email::maildir::MaildirContextBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A local Maildir tree, e.g. kept in sync by mbsync or offlineimap.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaildirAccount {
+    pub id: Option<i64>,
+    pub email: String,
+    pub name: Option<String>,
+    pub root_dir: PathBuf,
+    /// Maildir++ layout: the root is the inbox and subfolders are dot-prefixed.
+    #[serde(default)]
+    pub maildirpp: bool,
+    /// Command that outgoing mail is piped to, e.g. `msmtp -t`. Without it the account cannot send.
+    #[serde(default)]
+    pub sendmail_command: Option<String>,
+}
+
+impl MaildirAccount {
+    pub fn get_configs(&self) -> (Arc<AccountConfig>, Arc<MaildirConfig>) {
+        let account_config = Arc::new(AccountConfig {
+            name: self.email.clone(),
+            email: self.email.clone(),
+            ..Default::default()
+        });
+
+        let maildir_config = Arc::new(MaildirConfig {
+            root_dir: self.root_dir.clone(),
+            maildirpp: self.maildirpp,
+        });
+
+        (account_config, maildir_config)
+    }
+
+    /// Checks that the root directory exists, so a typo does not silently create an empty tree.
+    pub fn validate(&self) -> Result<(), String> {
+        let (account_config, maildir_config) = self.get_configs();
+        let root = MaildirContextBuilder::new(account_config, maildir_config).expanded_root_dir();
+        if !root.is_dir() {
+            return Err(format!("Maildir root {} is not a directory", root.display()));
+        }
+        Ok(())
+    }
+}
@@ -3,23 +3,34 @@ use tauri::Manager;
 use crate::email_backend::accounts::google::GoogleAccount;
 use crate::email_backend::accounts::microsoft::MicrosoftAccount;
 use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use crate::email_backend::accounts::maildir::MaildirAccount;
+use crate::email_backend::accounts::notmuch::NotmuchAccount;
+use crate::email_backend::accounts::oauth2::{encryption_from_str, OAuth2Account, OAuth2Provider};
+use crate::email_backend::accounts::error::AccountError;
+use crate::email_backend::sync::backend::BackendKind;
 use crate::utils::security::EncryptedStore;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use sqlx::sqlite::SqlitePool;
 use email::account::config::AccountConfig;
-use email::account::config::oauth2::OAuth2Config;
 use email::account::config::passwd::PasswordConfig;
 use email::imap::config::{ImapConfig, ImapAuthConfig};
 use email::smtp::config::{SmtpConfig, SmtpAuthConfig};
 use secret::Secret;
 
+/// Access tokens are refreshed when they expire within this many seconds.
+pub const TOKEN_REFRESH_MARGIN_SECS: i64 = 300;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", content = "data")]
 pub enum Account {
     Google(GoogleAccount),
     Microsoft(MicrosoftAccount),
     ImapSmtp(ImapSmtpAccount),
+    OAuth2(OAuth2Account),
+    Maildir(MaildirAccount),
+    Notmuch(NotmuchAccount),
 }
 
 impl Account {
@@ -28,6 +39,9 @@ impl Account {
             Account::Google(a) => &a.email,
             Account::Microsoft(a) => &a.email,
             Account::ImapSmtp(a) => &a.email,
+            Account::OAuth2(a) => &a.email,
+            Account::Maildir(a) => &a.email,
+            Account::Notmuch(a) => &a.email,
         }
     }
 
@@ -36,6 +50,9 @@ impl Account {
             Account::Google(a) => a.id,
             Account::Microsoft(a) => a.id,
             Account::ImapSmtp(a) => a.id,
+            Account::OAuth2(a) => a.id,
+            Account::Maildir(a) => a.id,
+            Account::Notmuch(a) => a.id,
         }
     }
 
@@ -44,6 +61,31 @@ impl Account {
             Account::Google(a) => a.id = Some(id),
             Account::Microsoft(a) => a.id = Some(id),
             Account::ImapSmtp(a) => a.id = Some(id),
+            Account::OAuth2(a) => a.id = Some(id),
+            Account::Maildir(a) => a.id = Some(id),
+            Account::Notmuch(a) => a.id = Some(id),
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Account::Google(a) => a.name.as_deref(),
+            Account::Microsoft(a) => a.name.as_deref(),
+            Account::ImapSmtp(a) => a.name.as_deref(),
+            Account::OAuth2(a) => a.name.as_deref(),
+            Account::Maildir(a) => a.name.as_deref(),
+            Account::Notmuch(a) => a.name.as_deref(),
+        }
+    }
+
+    pub fn set_name(&mut self, name: Option<String>) {
+        match self {
+            Account::Google(a) => a.name = name,
+            Account::Microsoft(a) => a.name = name,
+            Account::ImapSmtp(a) => a.name = name,
+            Account::OAuth2(a) => a.name = name,
+            Account::Maildir(a) => a.name = name,
+            Account::Notmuch(a) => a.name = name,
         }
     }
 
@@ -52,9 +94,68 @@ impl Account {
             Account::Google(_) => "google",
             Account::Microsoft(_) => "microsoft",
             Account::ImapSmtp(_) => "imap_smtp",
+            Account::OAuth2(_) => "oauth2",
+            Account::Maildir(_) => "maildir",
+            Account::Notmuch(_) => "notmuch",
+        }
+    }
+
+    pub fn backend_kind(&self) -> BackendKind {
+        match self {
+            Account::Maildir(_) => BackendKind::Maildir,
+            Account::Notmuch(_) => BackendKind::Notmuch,
+            _ => BackendKind::Imap,
+        }
+    }
+
+    pub fn picture(&self) -> Option<&str> {
+        match self {
+            Account::Google(a) => a.picture.as_deref(),
+            Account::Microsoft(a) => a.picture.as_deref(),
+            Account::ImapSmtp(_) => None,
+            Account::OAuth2(a) => a.picture.as_deref(),
+            Account::Maildir(_) | Account::Notmuch(_) => None,
+        }
+    }
+
+    /// The local command outgoing mail is piped to, if the account sends through one.
+    pub fn sendmail_command(&self) -> Option<&str> {
+        let command = match self {
+            Account::ImapSmtp(a) => a.sendmail_command.as_deref(),
+            Account::Maildir(a) => a.sendmail_command.as_deref(),
+            Account::Notmuch(a) => a.sendmail_command.as_deref(),
+            Account::Google(_) | Account::Microsoft(_) | Account::OAuth2(_) => None,
+        };
+        command.filter(|c| !c.trim().is_empty())
+    }
+
+    fn oauth2_tokens(&self) -> Option<(&Option<String>, &Option<String>, Option<i64>)> {
+        match self {
+            Account::Google(a) => Some((&a.access_token, &a.refresh_token, a.token_expires_at)),
+            Account::Microsoft(a) => Some((&a.access_token, &a.refresh_token, a.token_expires_at)),
+            Account::OAuth2(a) => Some((&a.access_token, &a.refresh_token, a.token_expires_at)),
+            Account::ImapSmtp(_) | Account::Maildir(_) | Account::Notmuch(_) => None,
+        }
+    }
+
+    /// Whether the account uses OAuth2 and its access token is missing, of unknown age or
+    /// about to expire, and a refresh token is available to renew it.
+    pub fn needs_token_refresh(&self) -> bool {
+        match self.oauth2_tokens() {
+            Some((access_token, Some(_), expires_at)) => match (access_token, expires_at) {
+                (Some(_), Some(expires_at)) => expires_at - chrono::Utc::now().timestamp() < TOKEN_REFRESH_MARGIN_SECS,
+                _ => true,
+            },
+            _ => false,
         }
     }
 
+    /// Whether the account is an OAuth2 account without any usable token, e.g. after its
+    /// grant was revoked.
+    pub fn needs_reauth(&self) -> bool {
+        matches!(self.oauth2_tokens(), Some((None, None, _)))
+    }
+
     pub fn strip_secrets(&mut self) {
         match self {
             Account::Google(a) => {
@@ -69,88 +170,25 @@ impl Account {
                 a.password = None;
                 a.smtp_password = None;
             }
+            Account::OAuth2(a) => {
+                a.access_token = None;
+                a.refresh_token = None;
+                a.provider.client_secret = None;
+            }
+            Account::Maildir(_) | Account::Notmuch(_) => {}
         }
     }
 
     pub fn get_configs(&self) -> Result<(Arc<AccountConfig>, Arc<ImapConfig>, Arc<SmtpConfig>), String> {
         match self {
             Account::Google(google) => {
-                let client_id = env!("GOOGLE_CLIENT_ID").to_string();
-                let client_secret = env!("GOOGLE_CLIENT_SECRET").to_string();
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: Some(Secret::new_raw(client_secret)),
-                    auth_url: "https://accounts.google.com/o/oauth2/auth".into(),
-                    token_url: "https://www.googleapis.com/oauth2/v3/token".into(),
-                    access_token: google.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: google.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
-                };
-
-                let account_config = Arc::new(AccountConfig {
-                    name: google.email.clone(),
-                    email: google.email.clone(),
-                    ..Default::default()
-                });
-
-                let imap_config = Arc::new(ImapConfig {
-                    host: "imap.gmail.com".into(),
-                    port: 993,
-                    login: google.email.clone(),
-                    auth: ImapAuthConfig::OAuth2(oauth2_config.clone()),
-                    ..Default::default()
-                });
-
-                let smtp_config = Arc::new(SmtpConfig {
-                    host: "smtp.gmail.com".into(),
-                    port: 587,
-                    login: google.email.clone(),
-                    auth: SmtpAuthConfig::OAuth2(oauth2_config),
-                    encryption: Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
-                    ..Default::default()
-                });
-
-                Ok((account_config, imap_config, smtp_config))
+                Ok(OAuth2Provider::google().get_configs(&google.email, google.access_token.as_ref(), google.refresh_token.as_ref()))
             }
             Account::Microsoft(microsoft) => {
-                let client_id = env!("MICROSOFT_CLIENT_ID").to_string();
-                let client_secret = option_env!("MICROSOFT_CLIENT_SECRET");
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: client_secret.map(|s| Secret::new_raw(s.to_string())),
-                    auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into(),
-                    token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token".into(),
-                    access_token: microsoft.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: microsoft.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
-                };
-
-                let account_config = Arc::new(AccountConfig {
-                    name: microsoft.email.clone(),
-                    email: microsoft.email.clone(),
-                    ..Default::default()
-                });
-
-                let imap_config = Arc::new(ImapConfig {
-                    host: "outlook.office365.com".into(),
-                    port: 993,
-                    login: microsoft.email.clone(),
-                    auth: ImapAuthConfig::OAuth2(oauth2_config.clone()),
-                    ..Default::default()
-                });
-
-                let smtp_config = Arc::new(SmtpConfig {
-                    host: "smtp.office365.com".into(),
-                    port: 587,
-                    login: microsoft.email.clone(),
-                    auth: SmtpAuthConfig::OAuth2(oauth2_config),
-                    encryption: Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
-                    ..Default::default()
-                });
-
-                Ok((account_config, imap_config, smtp_config))
+                Ok(OAuth2Provider::microsoft().get_configs(&microsoft.email, microsoft.access_token.as_ref(), microsoft.refresh_token.as_ref()))
+            }
+            Account::OAuth2(oauth2) => {
+                Ok(oauth2.provider.get_configs(&oauth2.email, oauth2.access_token.as_ref(), oauth2.refresh_token.as_ref()))
             }
             Account::ImapSmtp(imap_smtp) => {
                 let account_config = Arc::new(AccountConfig {
@@ -159,27 +197,15 @@ impl Account {
                     ..Default::default()
                 });
 
-                let imap_encryption = match imap_smtp.imap_encryption.as_str() {
-                    "tls" => Some(email::tls::Encryption::Tls(email::tls::Tls::default())),
-                    "starttls" => Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
-                    _ => None,
-                };
-
                 let imap_config = Arc::new(ImapConfig {
                     host: imap_smtp.imap_host.clone(),
                     port: imap_smtp.imap_port,
                     login: imap_smtp.imap_username.clone(),
-                    encryption: imap_encryption,
+                    encryption: encryption_from_str(&imap_smtp.imap_encryption),
                     auth: ImapAuthConfig::Password(PasswordConfig(Secret::new_raw(imap_smtp.password.clone().unwrap_or_default()))),
                     ..Default::default()
                 });
 
-                let smtp_encryption = match imap_smtp.smtp_encryption.as_str() {
-                    "tls" => Some(email::tls::Encryption::Tls(email::tls::Tls::default())),
-                    "starttls" => Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
-                    _ => None,
-                };
-
                 let smtp_login = if imap_smtp.smtp_use_imap_credentials {
                     imap_smtp.imap_username.clone()
                 } else {
@@ -196,13 +222,14 @@ impl Account {
                     host: imap_smtp.smtp_host.clone(),
                     port: imap_smtp.smtp_port,
                     login: smtp_login,
-                    encryption: smtp_encryption,
+                    encryption: encryption_from_str(&imap_smtp.smtp_encryption),
                     auth: SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw(smtp_password))),
                     ..Default::default()
                 });
 
                 Ok((account_config, imap_config, smtp_config))
             }
+            Account::Maildir(_) | Account::Notmuch(_) => Err(format!("{} accounts have no IMAP or SMTP server", self.account_type())),
         }
     }
 }
@@ -212,52 +239,77 @@ pub struct AccountRegistry {
     pub accounts: Vec<Account>,
 }
 
-pub struct AccountManager<R: tauri::Runtime = tauri::Wry> {
-    app_handle: tauri::AppHandle<R>,
+pub struct AccountManager {
+    pool: SqlitePool,
     store: EncryptedStore,
-    #[cfg(test)]
-    storage_path_override: Option<PathBuf>,
+    storage_path: PathBuf,
+    locks: Arc<RegistryLocks>,
+}
+
+/// Locks shared by every manager of one registry file. Managers are opened per call, so the
+/// locks live in a table for the whole process.
+#[derive(Default)]
+struct RegistryLocks {
+    /// Held around every read and read-modify-write of the registry file.
+    registry: tokio::sync::Mutex<()>,
+    /// Held for the whole of a token refresh, one per account email.
+    refreshes: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl RegistryLocks {
+    fn of(storage_path: &Path) -> Arc<Self> {
+        static LOCKS: OnceLock<std::sync::Mutex<HashMap<PathBuf, Arc<RegistryLocks>>>> = OnceLock::new();
+        LOCKS.get_or_init(Default::default).lock().unwrap()
+            .entry(storage_path.to_path_buf())
+            .or_default()
+            .clone()
+    }
+
+    fn refresh(&self, email: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.refreshes.lock().unwrap().entry(email.to_string()).or_default().clone()
+    }
 }
 
-impl<R: tauri::Runtime> AccountManager<R> {
-    pub async fn new(app_handle: &tauri::AppHandle<R>) -> Result<Self, String> {
+impl AccountManager {
+    pub async fn new<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<Self, String> {
+        let data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+        Self::open(app_handle.state::<SqlitePool>().inner().clone(), &data_dir).await
+    }
+
+    /// Opens the account registry kept in `data_dir`, without a running app.
+    pub async fn open(pool: SqlitePool, data_dir: &Path) -> Result<Self, String> {
         let store = EncryptedStore::new().await?;
+        let storage_path = data_dir.join("accounts.json.enc");
         Ok(Self {
-            app_handle: app_handle.clone(),
+            pool,
             store,
-            #[cfg(test)]
-            storage_path_override: None,
+            locks: RegistryLocks::of(&storage_path),
+            storage_path,
         })
     }
 
-    fn get_storage_path(&self) -> PathBuf {
-        #[cfg(test)]
-        if let Some(path) = &self.storage_path_override {
-            return path.clone();
-        }
-
-        self.app_handle.path().app_data_dir()
-            .expect("Failed to get app data dir")
-            .join("accounts.json.enc")
+    pub async fn load(&self) -> Result<AccountRegistry, String> {
+        let _registry = self.locks.registry.lock().await;
+        self.read().await
     }
 
-    pub async fn load(&self) -> Result<AccountRegistry, String> {
-        let path = self.get_storage_path();
-        if !path.exists() {
+    /// Reads the registry, with the registry lock already held by the caller.
+    async fn read(&self) -> Result<AccountRegistry, String> {
+        if !self.storage_path.exists() {
             return Ok(AccountRegistry::default());
         }
 
-        let data = self.store.load(path)?;
+        let data = self.store.load(self.storage_path.clone())?;
         let mut registry: AccountRegistry = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
 
-        let pool = self.app_handle.state::<SqlitePool>();
+        let pool = &self.pool;
 
         for account in &mut registry.accounts {
             let row: Option<(i64, Option<String>, Option<String>)> = sqlx::query_as(
                 "SELECT id, name, picture FROM accounts WHERE email = ?"
             )
             .bind(account.email())
-            .fetch_optional(&*pool)
+            .fetch_optional(pool)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -277,6 +329,19 @@ impl<R: tauri::Runtime> AccountManager<R> {
                         imap_smtp.id = Some(id);
                         imap_smtp.name = name;
                     }
+                    Account::OAuth2(oauth2) => {
+                        oauth2.id = Some(id);
+                        oauth2.name = name;
+                        oauth2.picture = picture;
+                    }
+                    Account::Maildir(maildir) => {
+                        maildir.id = Some(id);
+                        maildir.name = name;
+                    }
+                    Account::Notmuch(notmuch) => {
+                        notmuch.id = Some(id);
+                        notmuch.name = name;
+                    }
                 }
             }
         }
@@ -291,79 +356,102 @@ impl<R: tauri::Runtime> AccountManager<R> {
             .ok_or_else(|| format!("Account with ID {} not found", id))
     }
 
-    pub async fn save(&self, registry: &AccountRegistry) -> Result<(), String> {
-        let path = self.get_storage_path();
+    /// Writes the registry, with the registry lock held by the caller since it was read.
+    async fn save(&self, registry: &AccountRegistry) -> Result<(), String> {
         let data = serde_json::to_vec(registry).map_err(|e| e.to_string())?;
-        self.store.save(path, &data)
+        self.store.save(self.storage_path.clone(), &data)
     }
 
-    pub async fn refresh_access_token(&self, email: &str) -> Result<String, String> {
-        let mut registry = self.load().await?;
-        let account = registry.accounts.iter_mut()
+    /// Refreshes the OAuth2 access token of an account. When the grant was revoked the stored
+    /// tokens are dropped, so the account stays in `ReauthRequired` until the user logs in again.
+    pub async fn refresh_access_token(&self, email: &str) -> Result<String, AccountError> {
+        // Rotating refresh tokens are single use, so two refreshes of an account must not overlap
+        let refresh_lock = self.locks.refresh(email);
+        let _refreshing = refresh_lock.lock().await;
+
+        let account = self.load().await?.accounts.into_iter()
             .find(|a| a.email() == email)
-            .ok_or_else(|| format!("Account {} not found", email))?;
-            
-        match account {
-            Account::Google(google) => {
-                let client_id = env!("GOOGLE_CLIENT_ID").to_string();
-                let client_secret = env!("GOOGLE_CLIENT_SECRET").to_string();
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: Some(Secret::new_raw(client_secret)),
-                    auth_url: "https://accounts.google.com/o/oauth2/auth".into(),
-                    token_url: "https://www.googleapis.com/oauth2/v3/token".into(),
-                    access_token: google.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: google.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
-                };
+            .ok_or_else(|| AccountError::NotFound(email.to_string()))?;
+
+        let needs_refresh = account.needs_token_refresh();
+        let (provider, access_token, refresh_token) = match account {
+            Account::Google(a) => (OAuth2Provider::google(), a.access_token, a.refresh_token),
+            Account::Microsoft(a) => (OAuth2Provider::microsoft(), a.access_token, a.refresh_token),
+            Account::OAuth2(a) => (a.provider, a.access_token, a.refresh_token),
+            Account::ImapSmtp(_) | Account::Maildir(_) | Account::Notmuch(_) => return Err(AccountError::Other("Only OAuth2 accounts support token refresh".into())),
+        };
+
+        // A refresh that held the lock before this one may have renewed the token already
+        if let (Some(access_token), false) = (&access_token, needs_refresh) {
+            return Ok(access_token.clone());
+        }
 
-                let (access_token, new_refresh_token) = oauth2_config.refresh_access_token().await.map_err(|e| e.to_string())?;
-                
-                google.access_token = Some(access_token.clone());
-                if let Some(new_refresh) = new_refresh_token {
-                    google.refresh_token = Some(new_refresh);
+        let Some(current_refresh_token) = refresh_token else {
+            return Err(AccountError::ReauthRequired {
+                email: email.to_string(),
+                reason: "no refresh token is stored".into(),
+            });
+        };
+
+        let tokens = match provider.refresh_tokens(email, &current_refresh_token).await {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                if e.is_reauth_required() {
+                    self.set_tokens(email, None, None, None).await?;
                 }
-                
-                let access_token_val = access_token;
-                
-                self.save(&registry).await?;
-                
-                Ok(access_token_val)
+                return Err(e);
             }
-            Account::Microsoft(microsoft) => {
-                let client_id = env!("MICROSOFT_CLIENT_ID").to_string();
-                let client_secret = option_env!("MICROSOFT_CLIENT_SECRET");
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: client_secret.map(|s| Secret::new_raw(s.to_string())),
-                    auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into(),
-                    token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token".into(),
-                    access_token: microsoft.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: microsoft.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
-                };
+        };
 
-                let (access_token, new_refresh_token) = oauth2_config.refresh_access_token().await.map_err(|e| e.to_string())?;
-                
-                microsoft.access_token = Some(access_token.clone());
-                if let Some(new_refresh) = new_refresh_token {
-                    microsoft.refresh_token = Some(new_refresh);
-                }
-                
-                let access_token_val = access_token;
-                
-                self.save(&registry).await?;
-                
-                Ok(access_token_val)
-            }
-            Account::ImapSmtp(_) => Err("IMAP/SMTP accounts do not support token refresh".into()),
+        let refresh_token = tokens.refresh_token.or(Some(current_refresh_token));
+        self.set_tokens(email, Some(tokens.access_token.clone()), refresh_token, tokens.expires_at).await?;
+
+        Ok(tokens.access_token)
+    }
+
+    /// Stores the OAuth2 tokens of an account.
+    async fn set_tokens(&self, email: &str, access: Option<String>, refresh: Option<String>, expires: Option<i64>) -> Result<(), String> {
+        let _registry = self.locks.registry.lock().await;
+        let mut registry = self.read().await?;
+        let account = registry.accounts.iter_mut()
+            .find(|a| a.email() == email)
+            .ok_or_else(|| format!("Account {} not found", email))?;
+
+        let (access_token, refresh_token, expires_at) = match account {
+            Account::Google(a) => (&mut a.access_token, &mut a.refresh_token, &mut a.token_expires_at),
+            Account::Microsoft(a) => (&mut a.access_token, &mut a.refresh_token, &mut a.token_expires_at),
+            Account::OAuth2(a) => (&mut a.access_token, &mut a.refresh_token, &mut a.token_expires_at),
+            Account::ImapSmtp(_) | Account::Maildir(_) | Account::Notmuch(_) => return Ok(()),
+        };
+        *access_token = access;
+        *refresh_token = refresh;
+        *expires_at = expires;
+
+        self.save(&registry).await
+    }
+
+    /// Returns the account with an access token that is valid for at least
+    /// `TOKEN_REFRESH_MARGIN_SECS`, refreshing it first if needed.
+    pub async fn get_authenticated_account(&self, id: i64) -> Result<Account, AccountError> {
+        let account = self.get_account_by_id(id).await?;
+
+        if account.needs_reauth() {
+            return Err(AccountError::ReauthRequired {
+                email: account.email().to_string(),
+                reason: "no valid token is stored".into(),
+            });
         }
+
+        if !account.needs_token_refresh() {
+            return Ok(account);
+        }
+
+        self.refresh_access_token(account.email()).await?;
+        Ok(self.get_account_by_id(id).await?)
     }
 
     pub async fn add_account(&self, mut account: Account) -> Result<(), String> {
-        let pool = self.app_handle.state::<SqlitePool>();
+        let pool = &self.pool;
 
         // 1. Save to Database
         let row: (i64,) = sqlx::query_as(
@@ -373,54 +461,81 @@ impl<R: tauri::Runtime> AccountManager<R> {
         )
         .bind(account.email())
         .bind(account.account_type())
-        .bind(match &account {
-            Account::Google(a) => a.name.as_deref(),
-            Account::Microsoft(a) => a.name.as_deref(),
-            Account::ImapSmtp(a) => a.name.as_deref(),
-        })
-        .bind(match &account {
-            Account::Google(a) => a.picture.as_deref(),
-            Account::Microsoft(a) => a.picture.as_deref(),
-            Account::ImapSmtp(_) => None,
-        })
-        .fetch_one(&*pool)
+        .bind(account.name())
+        .bind(account.picture())
+        .fetch_one(pool)
         .await
         .map_err(|e: sqlx::Error| e.to_string())?;
 
         account.set_id(row.0);
 
         // 2. Save to Encrypted Store
-        let mut registry = self.load().await?;
+        let _registry = self.locks.registry.lock().await;
+        let mut registry = self.read().await?;
         // Remove existing account with same email if exists
         registry.accounts.retain(|a| a.email() != account.email());
         registry.accounts.push(account);
         self.save(&registry).await
     }
 
-    pub async fn remove_account(&self, index: usize) -> Result<(), String> {
-        let mut registry = self.load().await?;
-        if index < registry.accounts.len() {
-            let account = registry.accounts.remove(index);
-
-            // Remove from database
-            if let Some(id) = account.id() {
-                let pool = self.app_handle.state::<SqlitePool>();
-                sqlx::query("DELETE FROM accounts WHERE id = ?")
-                    .bind(id)
-                    .execute(&*pool)
-                    .await
-                    .map_err(|e: sqlx::Error| e.to_string())?;
-            }
+    pub async fn update_account(&self, account: Account) -> Result<(), String> {
+        let id = account.id().ok_or("Account ID missing")?;
+        let _registry = self.locks.registry.lock().await;
+        let mut registry = self.read().await?;
+        let existing = registry.accounts.iter_mut()
+            .find(|a| a.id() == Some(id))
+            .ok_or_else(|| format!("Account with ID {} not found", id))?;
 
-            self.save(&registry).await
-        } else {
-            Err("Account index out of bounds".to_string())
+        if existing.email() != account.email() {
+            return Err("Changing the email address of an account is not supported".to_string());
         }
+
+        let pool = &self.pool;
+        sqlx::query("UPDATE accounts SET name = ? WHERE id = ?")
+            .bind(account.name())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e: sqlx::Error| e.to_string())?;
+
+        *existing = account;
+        self.save(&registry).await
+    }
+
+    /// Puts the accounts in the order of `ids`. Accounts missing from it keep their order
+    /// after the listed ones.
+    pub async fn reorder_accounts(&self, ids: &[i64]) -> Result<(), String> {
+        let _registry = self.locks.registry.lock().await;
+        let mut registry = self.read().await?;
+        registry.accounts.sort_by_key(|a| {
+            a.id().and_then(|id| ids.iter().position(|&i| i == id)).unwrap_or(ids.len())
+        });
+        self.save(&registry).await
+    }
+
+    pub async fn remove_account(&self, id: i64) -> Result<Account, String> {
+        let _registry = self.locks.registry.lock().await;
+        let mut registry = self.read().await?;
+        let index = registry.accounts.iter()
+            .position(|a| a.id() == Some(id))
+            .ok_or_else(|| format!("Account with ID {} not found", id))?;
+        let account = registry.accounts.remove(index);
+
+        // Folders, emails, drafts and their attachment rows cascade from the account
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM accounts WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e: sqlx::Error| e.to_string())?;
+
+        self.save(&registry).await?;
+        Ok(account)
     }
 
     #[cfg(test)]
-    pub fn new_test(app_handle: tauri::AppHandle<R>, store: EncryptedStore, storage_path: Option<PathBuf>) -> Self {
-        Self { app_handle, store, storage_path_override: storage_path }
+    pub fn new_test(pool: SqlitePool, store: EncryptedStore, storage_path: PathBuf) -> Self {
+        Self { pool, store, locks: RegistryLocks::of(&storage_path), storage_path }
     }
 }
 
@@ -430,7 +545,6 @@ mod tests {
     use crate::email_backend::accounts::google::GoogleAccount;
     use crate::utils::security::EncryptedStore;
     use crate::utils::test_utils::setup_test_db;
-    use tauri::test::mock_builder;
     use tempfile::tempdir;
 
     #[test]
@@ -442,6 +556,7 @@ mod tests {
             picture: None,
             access_token: Some("secret_access".to_string()),
             refresh_token: Some("secret_refresh".to_string()),
+            token_expires_at: None,
         });
 
         account.strip_secrets();
@@ -455,18 +570,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_needs_token_refresh() {
+        let now = chrono::Utc::now().timestamp();
+        let google = |access: Option<&str>, refresh: Option<&str>, expires_at: Option<i64>| Account::Google(GoogleAccount {
+            id: Some(1),
+            email: "test@gmail.com".to_string(),
+            name: None,
+            picture: None,
+            access_token: access.map(|s| s.to_string()),
+            refresh_token: refresh.map(|s| s.to_string()),
+            token_expires_at: expires_at,
+        });
+
+        assert!(!google(Some("a"), Some("r"), Some(now + 3600)).needs_token_refresh());
+        assert!(google(Some("a"), Some("r"), Some(now + 60)).needs_token_refresh());
+        assert!(google(Some("a"), Some("r"), None).needs_token_refresh());
+        assert!(!google(Some("a"), None, None).needs_token_refresh());
+
+        let revoked = google(None, None, None);
+        assert!(!revoked.needs_token_refresh());
+        assert!(revoked.needs_reauth());
+    }
+
     #[tokio::test]
     async fn test_add_account_integration() {
         let pool = setup_test_db().await;
-        let app = mock_builder().build(tauri::generate_context!()).unwrap();
-        app.manage(pool);
 
         let dir = tempdir().unwrap();
         let storage_path = dir.path().join("accounts.json.enc");
 
         let key = [0u8; 32];
         let store = EncryptedStore::new_test(key);
-        let manager = AccountManager::new_test(app.handle().clone(), store, Some(storage_path));
+        let manager = AccountManager::new_test(pool.clone(), store, storage_path);
 
         let account = Account::Google(GoogleAccount {
             id: None,
@@ -475,6 +611,7 @@ mod tests {
             picture: None,
             access_token: Some("access".to_string()),
             refresh_token: Some("refresh".to_string()),
+            token_expires_at: None,
         });
 
         manager.add_account(account).await.expect("Failed to add account");
@@ -485,11 +622,51 @@ mod tests {
         assert!(registry.accounts[0].id().is_some());
 
         // Verify it was saved to the database too
-        let db_pool = app.state::<SqlitePool>();
         let count: (i64,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM accounts")
-            .fetch_one(&*db_pool)
+            .fetch_one(&pool)
             .await
             .unwrap();
         assert_eq!(count.0, 1);
     }
+
+    #[tokio::test]
+    async fn test_update_and_remove_account_by_id() {
+        let pool = setup_test_db().await;
+
+        let dir = tempdir().unwrap();
+        let storage_path = dir.path().join("accounts.json.enc");
+        let manager = AccountManager::new_test(pool, EncryptedStore::new_test([0u8; 32]), storage_path);
+
+        for email in ["first@gmail.com", "second@gmail.com"] {
+            manager.add_account(Account::Google(GoogleAccount {
+                id: None,
+                email: email.to_string(),
+                name: None,
+                picture: None,
+                access_token: Some("access".to_string()),
+                refresh_token: Some("refresh".to_string()),
+                token_expires_at: None,
+            })).await.expect("Failed to add account");
+        }
+
+        let registry = manager.load().await.unwrap();
+        let second_id = registry.accounts.iter().find(|a| a.email() == "second@gmail.com").unwrap().id().unwrap();
+
+        manager.reorder_accounts(&[second_id]).await.expect("Failed to reorder accounts");
+        let emails: Vec<String> = manager.load().await.unwrap().accounts.iter().map(|a| a.email().to_string()).collect();
+        assert_eq!(emails, vec!["second@gmail.com", "first@gmail.com"]);
+
+        let mut second = manager.get_account_by_id(second_id).await.unwrap();
+        second.set_name(Some("Renamed".to_string()));
+        manager.update_account(second).await.expect("Failed to update account");
+        assert_eq!(manager.get_account_by_id(second_id).await.unwrap().name(), Some("Renamed"));
+
+        let removed = manager.remove_account(second_id).await.expect("Failed to remove account");
+        assert_eq!(removed.email(), "second@gmail.com");
+
+        let registry = manager.load().await.unwrap();
+        assert_eq!(registry.accounts.len(), 1);
+        assert_eq!(registry.accounts[0].email(), "first@gmail.com");
+        assert!(manager.remove_account(second_id).await.is_err());
+    }
 }
@@ -1,9 +1,7 @@
-use email::account::config::oauth2::{OAuth2Config, OAuth2Scopes::Scopes};
+use crate::email_backend::accounts::oauth2::OAuth2Provider;
 use email::account::Error;
-use oauth::v2_0::{AuthorizationCodeGrant, Client};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_opener::OpenerExt;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MicrosoftAccount {
@@ -15,94 +13,24 @@ pub struct MicrosoftAccount {
     pub access_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<String>,
+    /// Unix timestamp at which the access token expires, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_expires_at: Option<i64>,
 }
 
 pub struct MicrosoftOAuth2Config {
-    base: OAuth2Config,
-    client_secret: Option<String>,
+    provider: OAuth2Provider,
 }
 
 impl MicrosoftOAuth2Config {
     pub fn new() -> Result<Self, String> {
-        let client_id = env!("MICROSOFT_CLIENT_ID").to_string();
-        let client_secret = option_env!("MICROSOFT_CLIENT_SECRET").map(|s| s.to_string());
-
-        let mut config = OAuth2Config::default();
-        config.client_id = client_id;
-        // Using "common" endpoint for multi-tenant apps
-        config.auth_url = "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into();
-        config.token_url = "https://login.microsoftonline.com/common/oauth2/v2.0/token".into();
-        config.scopes = Scopes(vec![
-            "https://outlook.office.com/IMAP.AccessAsUser.All".into(),
-            "https://outlook.office.com/SMTP.Send".into(),
-            "User.Read".into(),
-            "offline_access".into(),
-            "openid".into(),
-            "profile".into(),
-            "email".into(),
-        ]);
-        config.pkce = true;
-        config.redirect_host = Some("127.0.0.1".into());
-        config.redirect_port = Some(11432);
-
         Ok(MicrosoftOAuth2Config {
-            base: config,
-            client_secret,
+            provider: OAuth2Provider::microsoft(),
         })
     }
 
     pub async fn get_url(&self, app_handle: &AppHandle) -> Result<MicrosoftAccount, Error> {
-        let redirect_scheme = match self.base.redirect_scheme.as_ref() {
-            Some(scheme) => scheme.clone(),
-            None => "http".into(),
-        };
-
-        let redirect_host = match self.base.redirect_host.as_ref() {
-            Some(host) => host.clone(),
-            None => OAuth2Config::LOCALHOST.to_owned(),
-        };
-
-        let redirect_port = match self.base.redirect_port {
-            Some(port) => port,
-            None => OAuth2Config::get_first_available_port()?,
-        };
-
-        let client = Client::new(
-            self.base.client_id.clone(),
-            self.client_secret.clone(),
-            self.base.auth_url.clone(),
-            self.base.token_url.clone(),
-            redirect_scheme,
-            redirect_host,
-            redirect_port,
-        )
-            .map_err(Error::BuildOauthClientError)?;
-
-        let mut auth_code_grant = AuthorizationCodeGrant::new();
-
-        if self.base.pkce {
-            auth_code_grant = auth_code_grant.with_pkce();
-        }
-
-        for scope in self.base.scopes.clone() {
-            auth_code_grant = auth_code_grant.with_scope(scope);
-        }
-
-        let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
-
-        app_handle.opener().open_url(redirect_url, None::<&str>).expect("Error when opening oauth url");
-
-        let (access_token, refresh_token) = auth_code_grant
-            .wait_for_redirection(&client, csrf_token)
-            .await
-            .map_err(|e| {
-                use std::error::Error as _;
-                let mut msg = format!("OAuth redirection failed: {}", e);
-                if let Some(source) = e.source() {
-                    msg.push_str(&format!(" (caused by: {})", source));
-                }
-                Error::GetAccountConfigNotFoundError(msg)
-            })?;
+        let (access_token, refresh_token) = self.provider.authorize(app_handle).await?;
 
         // Fetch user info from Microsoft Graph API
         let user_info_client = reqwest::Client::new();
@@ -138,6 +66,7 @@ impl MicrosoftOAuth2Config {
             picture,
             access_token: Some(access_token),
             refresh_token,
+            token_expires_at: None,
         })
     }
 }
@@ -1,5 +1,11 @@
 pub mod google;
 pub mod microsoft;
+pub mod oauth2;
 pub mod imap_smtp;
+pub mod maildir;
+pub mod notmuch;
+pub mod discovery;
 pub mod manager;
+pub mod error;
+pub mod events;
 pub mod commands;
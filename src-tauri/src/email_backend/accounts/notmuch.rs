@@ -0,0 +1,59 @@
+use email::account::config::AccountConfig;
+use email::backend::context::BackendContextBuilder;
+use email::notmuch::config::NotmuchConfig;
+use email::notmuch::NotmuchContextBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A notmuch database. Its tags show up as labels, and flags set here are written back as tags.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotmuchAccount {
+    pub id: Option<i64>,
+    pub email: String,
+    pub name: Option<String>,
+    /// Falls back to the database configured in the notmuch config file.
+    #[serde(default)]
+    pub database_path: Option<PathBuf>,
+    #[serde(default)]
+    pub config_path: Option<PathBuf>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Maildir++ layout: the root is the inbox and subfolders are dot-prefixed.
+    #[serde(default)]
+    pub maildirpp: bool,
+    /// Command that outgoing mail is piped to, e.g. `msmtp -t`. Without it the account cannot send.
+    #[serde(default)]
+    pub sendmail_command: Option<String>,
+}
+
+impl NotmuchAccount {
+    pub fn get_configs(&self) -> (Arc<AccountConfig>, Arc<NotmuchConfig>) {
+        let account_config = Arc::new(AccountConfig {
+            name: self.email.clone(),
+            email: self.email.clone(),
+            ..Default::default()
+        });
+
+        let notmuch_config = Arc::new(NotmuchConfig {
+            database_path: self.database_path.clone(),
+            maildir_path: None,
+            config_path: self.config_path.clone(),
+            profile: self.profile.clone(),
+            maildirpp: self.maildirpp,
+        });
+
+        (account_config, notmuch_config)
+    }
+
+    /// Opens the database once, so a wrong path or profile is reported before the account is saved.
+    pub async fn validate(&self) -> Result<(), String> {
+        let (account_config, notmuch_config) = self.get_configs();
+        let context = NotmuchContextBuilder::new(account_config, notmuch_config)
+            .build()
+            .await
+            .map_err(|e| e.to_string())?;
+        let context = context.lock().await;
+        context.open_db().map(|_| ()).map_err(|e| e.to_string())
+    }
+}
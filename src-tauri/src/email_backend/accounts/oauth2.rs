@@ -0,0 +1,593 @@
+use email::account::config::oauth2::{OAuth2Config, OAuth2Scopes::Scopes};
+use email::account::config::AccountConfig;
+use email::account::Error;
+use email::imap::config::{ImapAuthConfig, ImapConfig};
+use email::smtp::config::{SmtpAuthConfig, SmtpConfig};
+use oauth::v2_0::{AuthorizationCodeGrant, Client};
+use secret::Secret;
+use crate::email_backend::accounts::error::AccountError;
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+/// Everything needed to log in to a mail provider over OAuth2 and reach its IMAP/SMTP
+/// servers with XOAUTH2.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OAuth2Provider {
+    pub id: String,
+    pub name: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub pkce: bool,
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub redirect_host: Option<String>,
+    #[serde(default)]
+    pub redirect_port: Option<u16>,
+    /// OpenID Connect userinfo endpoint used to look up the address after login.
+    #[serde(default)]
+    pub userinfo_url: Option<String>,
+    pub imap_host: String,
+    pub imap_port: u16,
+    pub imap_encryption: String, // "tls", "starttls", "none"
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_encryption: String, // "tls", "starttls", "none"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OAuth2Account {
+    pub id: Option<i64>,
+    pub email: String,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub provider: OAuth2Provider,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Unix timestamp at which the access token expires, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_expires_at: Option<i64>,
+}
+
+/// Lifetime assumed for access tokens when the server does not send one, so they are still
+/// refreshed ahead of time rather than on every check.
+const DEFAULT_TOKEN_LIFETIME_SECS: i64 = 3600;
+
+/// Tokens returned by a refresh. `expires_at` is a unix timestamp.
+#[derive(Debug)]
+pub struct RefreshedTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Token endpoint errors (RFC 6749 section 5.2) that mean the grant itself is no longer usable.
+const REAUTH_ERRORS: &[&str] = &["invalid_grant", "invalid_client", "unauthorized_client"];
+
+pub fn encryption_from_str(value: &str) -> Option<email::tls::Encryption> {
+    match value {
+        "tls" => Some(email::tls::Encryption::Tls(email::tls::Tls::default())),
+        "starttls" => Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
+        _ => None,
+    }
+}
+
+impl OAuth2Provider {
+    pub fn google() -> Self {
+        Self {
+            id: "google".into(),
+            name: "Google".into(),
+            auth_url: "https://accounts.google.com/o/oauth2/auth".into(),
+            token_url: "https://www.googleapis.com/oauth2/v3/token".into(),
+            scopes: vec![
+                "https://mail.google.com/".into(),
+                "https://www.googleapis.com/auth/userinfo.email".into(),
+                "https://www.googleapis.com/auth/userinfo.profile".into(),
+                "https://www.googleapis.com/auth/contacts.readonly".into(),
+            ],
+            pkce: true,
+            client_id: env!("GOOGLE_CLIENT_ID").into(),
+            client_secret: Some(env!("GOOGLE_CLIENT_SECRET").into()),
+            redirect_host: None,
+            redirect_port: None,
+            userinfo_url: Some("https://www.googleapis.com/oauth2/v3/userinfo".into()),
+            imap_host: "imap.gmail.com".into(),
+            imap_port: 993,
+            imap_encryption: "tls".into(),
+            smtp_host: "smtp.gmail.com".into(),
+            smtp_port: 587,
+            smtp_encryption: "starttls".into(),
+        }
+    }
+
+    pub fn microsoft() -> Self {
+        Self {
+            id: "microsoft".into(),
+            name: "Microsoft 365".into(),
+            // Using "common" endpoint for multi-tenant apps
+            auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into(),
+            token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token".into(),
+            scopes: vec![
+                "https://outlook.office.com/IMAP.AccessAsUser.All".into(),
+                "https://outlook.office.com/SMTP.Send".into(),
+                "User.Read".into(),
+                "offline_access".into(),
+                "openid".into(),
+                "profile".into(),
+                "email".into(),
+            ],
+            pkce: true,
+            client_id: env!("MICROSOFT_CLIENT_ID").into(),
+            client_secret: option_env!("MICROSOFT_CLIENT_SECRET").map(|s| s.into()),
+            redirect_host: Some("127.0.0.1".into()),
+            redirect_port: Some(11432),
+            userinfo_url: None,
+            imap_host: "outlook.office365.com".into(),
+            imap_port: 993,
+            imap_encryption: "tls".into(),
+            smtp_host: "smtp.office365.com".into(),
+            smtp_port: 587,
+            smtp_encryption: "starttls".into(),
+        }
+    }
+
+    pub fn oauth2_config(&self, access_token: Option<&String>, refresh_token: Option<&String>) -> OAuth2Config {
+        OAuth2Config {
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.as_ref().map(|s| Secret::new_raw(s.clone())),
+            auth_url: self.auth_url.clone(),
+            token_url: self.token_url.clone(),
+            access_token: access_token.map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
+            refresh_token: refresh_token.map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
+            pkce: self.pkce,
+            redirect_host: self.redirect_host.clone(),
+            redirect_port: self.redirect_port,
+            scopes: Scopes(self.scopes.clone()),
+            ..Default::default()
+        }
+    }
+
+    pub fn get_configs(&self, email: &str, access_token: Option<&String>, refresh_token: Option<&String>) -> (Arc<AccountConfig>, Arc<ImapConfig>, Arc<SmtpConfig>) {
+        let oauth2_config = self.oauth2_config(access_token, refresh_token);
+
+        let account_config = Arc::new(AccountConfig {
+            name: email.to_string(),
+            email: email.to_string(),
+            ..Default::default()
+        });
+
+        let imap_config = Arc::new(ImapConfig {
+            host: self.imap_host.clone(),
+            port: self.imap_port,
+            login: email.to_string(),
+            encryption: encryption_from_str(&self.imap_encryption),
+            auth: ImapAuthConfig::OAuth2(oauth2_config.clone()),
+            ..Default::default()
+        });
+
+        let smtp_config = Arc::new(SmtpConfig {
+            host: self.smtp_host.clone(),
+            port: self.smtp_port,
+            login: email.to_string(),
+            encryption: encryption_from_str(&self.smtp_encryption),
+            auth: SmtpAuthConfig::OAuth2(oauth2_config),
+            ..Default::default()
+        });
+
+        (account_config, imap_config, smtp_config)
+    }
+
+    /// Runs the authorization code flow in the browser and returns the access and refresh tokens.
+    pub async fn authorize(&self, app_handle: &AppHandle) -> Result<(String, Option<String>), Error> {
+        let redirect_host = match self.redirect_host.as_ref() {
+            Some(host) => host.clone(),
+            None => OAuth2Config::LOCALHOST.to_owned(),
+        };
+
+        let redirect_port = match self.redirect_port {
+            Some(port) => port,
+            None => OAuth2Config::get_first_available_port()?,
+        };
+
+        let client = Client::new(
+            self.client_id.clone(),
+            self.client_secret.clone(),
+            self.auth_url.clone(),
+            self.token_url.clone(),
+            "http".into(),
+            redirect_host,
+            redirect_port,
+        )
+            .map_err(Error::BuildOauthClientError)?;
+
+        let mut auth_code_grant = AuthorizationCodeGrant::new();
+
+        if self.pkce {
+            auth_code_grant = auth_code_grant.with_pkce();
+        }
+
+        for scope in self.scopes.clone() {
+            auth_code_grant = auth_code_grant.with_scope(scope);
+        }
+
+        let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
+
+        app_handle
+            .opener()
+            .open_url(redirect_url, None::<&str>)
+            .map_err(|e| Error::GetAccountConfigNotFoundError(format!("Could not open the login page: {}", e)))?;
+
+        auth_code_grant
+            .wait_for_redirection(&client, csrf_token)
+            .await
+            .map_err(|e| {
+                use std::error::Error as _;
+                let mut msg = format!("OAuth redirection failed: {}", e);
+                if let Some(source) = e.source() {
+                    msg.push_str(&format!(" (caused by: {})", source));
+                }
+                Error::GetAccountConfigNotFoundError(msg)
+            })
+    }
+
+    /// Fetches the OpenID Connect userinfo of the logged in user, if the provider has an endpoint.
+    pub async fn fetch_userinfo(&self, access_token: &str) -> Result<Option<serde_json::Value>, String> {
+        let Some(url) = &self.userinfo_url else { return Ok(None) };
+
+        let response = reqwest::Client::new()
+            .get(url)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        let user_info: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            return Err(format!("Userinfo request failed ({}): {}", status, user_info));
+        }
+
+        Ok(Some(user_info))
+    }
+
+    /// Exchanges a refresh token for a new access token. A revoked or expired grant is reported
+    /// as `AccountError::ReauthRequired`.
+    pub async fn refresh_tokens(&self, email: &str, refresh_token: &str) -> Result<RefreshedTokens, AccountError> {
+        let mut form = vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", self.client_id.as_str()),
+        ];
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret.as_str()));
+        }
+
+        let response = reqwest::Client::new()
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| AccountError::TokenRefresh(e.to_string()))?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| AccountError::TokenRefresh(e.to_string()))?;
+
+        if status.is_success() {
+            let token: TokenResponse = serde_json::from_str(&body).map_err(|e| AccountError::TokenRefresh(e.to_string()))?;
+            return Ok(RefreshedTokens {
+                access_token: token.access_token,
+                refresh_token: token.refresh_token,
+                expires_at: Some(chrono::Utc::now().timestamp() + token.expires_in.unwrap_or(DEFAULT_TOKEN_LIFETIME_SECS)),
+            });
+        }
+
+        match serde_json::from_str::<TokenErrorResponse>(&body) {
+            Ok(e) if REAUTH_ERRORS.contains(&e.error.as_str()) => Err(AccountError::ReauthRequired {
+                email: email.to_string(),
+                reason: e.error_description.unwrap_or(e.error),
+            }),
+            Ok(e) => Err(AccountError::TokenRefresh(format!("{} ({})", e.error, status))),
+            Err(_) => Err(AccountError::TokenRefresh(format!("HTTP {}: {}", status, body))),
+        }
+    }
+}
+
+/// Providers that are offered in the account picker. Each one only shows up when a client ID
+/// was registered for it at build time.
+pub fn builtin_providers() -> Vec<OAuth2Provider> {
+    let mut providers = Vec::new();
+
+    if let Some(client_id) = option_env!("FASTMAIL_CLIENT_ID") {
+        providers.push(OAuth2Provider {
+            id: "fastmail".into(),
+            name: "Fastmail".into(),
+            auth_url: "https://api.fastmail.com/oauth/authorize".into(),
+            token_url: "https://api.fastmail.com/oauth/refresh".into(),
+            scopes: vec![
+                "https://www.fastmail.com/dev/protocol-imap".into(),
+                "https://www.fastmail.com/dev/protocol-smtp".into(),
+            ],
+            pkce: true,
+            client_id: client_id.into(),
+            client_secret: option_env!("FASTMAIL_CLIENT_SECRET").map(|s| s.into()),
+            redirect_host: None,
+            redirect_port: None,
+            userinfo_url: None,
+            imap_host: "imap.fastmail.com".into(),
+            imap_port: 993,
+            imap_encryption: "tls".into(),
+            smtp_host: "smtp.fastmail.com".into(),
+            smtp_port: 465,
+            smtp_encryption: "tls".into(),
+        });
+    }
+
+    if let Some(client_id) = option_env!("YAHOO_CLIENT_ID") {
+        providers.push(OAuth2Provider {
+            id: "yahoo".into(),
+            name: "Yahoo Mail".into(),
+            auth_url: "https://api.login.yahoo.com/oauth2/request_auth".into(),
+            token_url: "https://api.login.yahoo.com/oauth2/get_token".into(),
+            scopes: vec!["mail-w".into(), "openid".into(), "email".into(), "profile".into()],
+            pkce: true,
+            client_id: client_id.into(),
+            client_secret: option_env!("YAHOO_CLIENT_SECRET").map(|s| s.into()),
+            redirect_host: None,
+            redirect_port: None,
+            userinfo_url: Some("https://api.login.yahoo.com/openid/v1/userinfo".into()),
+            imap_host: "imap.mail.yahoo.com".into(),
+            imap_port: 993,
+            imap_encryption: "tls".into(),
+            smtp_host: "smtp.mail.yahoo.com".into(),
+            smtp_port: 465,
+            smtp_encryption: "tls".into(),
+        });
+    }
+
+    if let Some(client_id) = option_env!("ZOHO_CLIENT_ID") {
+        providers.push(OAuth2Provider {
+            id: "zoho".into(),
+            name: "Zoho Mail".into(),
+            auth_url: "https://accounts.zoho.com/oauth/v2/auth".into(),
+            token_url: "https://accounts.zoho.com/oauth/v2/token".into(),
+            scopes: vec!["ZohoMail.messages.ALL".into(), "ZohoMail.accounts.READ".into()],
+            pkce: false,
+            client_id: client_id.into(),
+            client_secret: option_env!("ZOHO_CLIENT_SECRET").map(|s| s.into()),
+            redirect_host: None,
+            redirect_port: None,
+            userinfo_url: None,
+            imap_host: "imap.zoho.com".into(),
+            imap_port: 993,
+            imap_encryption: "tls".into(),
+            smtp_host: "smtp.zoho.com".into(),
+            smtp_port: 465,
+            smtp_encryption: "tls".into(),
+        });
+    }
+
+    providers
+}
+
+async fn add_oauth2_account(app_handle: &AppHandle, provider: OAuth2Provider, email: Option<String>) -> Result<OAuth2Account, String> {
+    let (access_token, refresh_token) = provider.authorize(app_handle).await.map_err(|e| e.to_string())?;
+
+    let user_info = provider.fetch_userinfo(&access_token).await?;
+    let info_str = |key: &str| user_info.as_ref().and_then(|i| i[key].as_str()).map(|s| s.to_string());
+
+    let email = info_str("email")
+        .or(email)
+        .ok_or("The provider did not return an email address; enter it before logging in")?;
+
+    let account = OAuth2Account {
+        id: None,
+        email,
+        name: info_str("name"),
+        picture: info_str("picture"),
+        provider,
+        access_token: Some(access_token),
+        refresh_token,
+        token_expires_at: None,
+    };
+
+    let manager = AccountManager::new(app_handle).await?;
+    manager.add_account(Account::OAuth2(account.clone())).await?;
+
+    let registry = manager.load().await?;
+    if let Some(added_account) = registry.accounts.iter().find(|a| a.email() == account.email) {
+        if let Some(sync_engine) = app_handle.try_state::<crate::email_backend::sync::SyncEngine>() {
+            sync_engine.trigger_sync_for_account(added_account.clone());
+        }
+    }
+
+    Ok(account)
+}
+
+/// Logs in with a built-in or custom provider. `email` is used when the provider has no
+/// userinfo endpoint to read the address from.
+pub async fn login_with_oauth2(app_handle: &AppHandle, provider: OAuth2Provider, email: Option<String>) {
+    match add_oauth2_account(app_handle, provider, email).await {
+        Ok(account) => {
+            let _ = app_handle.emit("emails-updated", ());
+
+            let mut public_account = Account::OAuth2(account);
+            public_account.strip_secrets();
+            let _ = app_handle.emit("oauth2-account-added", public_account);
+        }
+        Err(e) => {
+            let _ = app_handle.emit("oauth2-account-error", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers a single request on a local port with the given status line and JSON body.
+    async fn serve_token_response(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let response = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+        format!("http://{}/token", addr)
+    }
+
+    fn custom_provider() -> OAuth2Provider {
+        OAuth2Provider {
+            id: "keycloak".into(),
+            name: "Company Mail".into(),
+            auth_url: "https://sso.example.com/realms/mail/protocol/openid-connect/auth".into(),
+            token_url: "https://sso.example.com/realms/mail/protocol/openid-connect/token".into(),
+            scopes: vec!["openid".into(), "email".into()],
+            pkce: true,
+            client_id: "dueam".into(),
+            client_secret: Some("shh".into()),
+            redirect_host: None,
+            redirect_port: None,
+            userinfo_url: Some("https://sso.example.com/realms/mail/protocol/openid-connect/userinfo".into()),
+            imap_host: "mail.example.com".into(),
+            imap_port: 143,
+            imap_encryption: "starttls".into(),
+            smtp_host: "mail.example.com".into(),
+            smtp_port: 587,
+            smtp_encryption: "starttls".into(),
+        }
+    }
+
+    #[test]
+    fn test_custom_provider_uses_xoauth2() {
+        let access = "access".to_string();
+        let refresh = "refresh".to_string();
+        let (_, imap_config, smtp_config) = custom_provider().get_configs("jane@example.com", Some(&access), Some(&refresh));
+
+        assert_eq!(imap_config.host, "mail.example.com");
+        assert_eq!(imap_config.port, 143);
+        assert_eq!(imap_config.login, "jane@example.com");
+        assert!(matches!(imap_config.encryption, Some(email::tls::Encryption::StartTls(_))));
+        match &imap_config.auth {
+            ImapAuthConfig::OAuth2(config) => {
+                assert_eq!(config.token_url, "https://sso.example.com/realms/mail/protocol/openid-connect/token");
+                assert!(config.pkce);
+            }
+            _ => panic!("expected OAuth2 IMAP auth"),
+        }
+        assert!(matches!(smtp_config.auth, SmtpAuthConfig::OAuth2(_)));
+    }
+
+    #[test]
+    fn test_strip_secrets_removes_client_secret() {
+        let mut account = Account::OAuth2(OAuth2Account {
+            id: Some(1),
+            email: "jane@example.com".into(),
+            name: None,
+            picture: None,
+            provider: custom_provider(),
+            access_token: Some("access".into()),
+            refresh_token: Some("refresh".into()),
+            token_expires_at: None,
+        });
+
+        account.strip_secrets();
+
+        let Account::OAuth2(a) = account else { panic!("expected OAuth2 account") };
+        assert!(a.access_token.is_none());
+        assert!(a.refresh_token.is_none());
+        assert!(a.provider.client_secret.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_records_expiry() {
+        let mut provider = custom_provider();
+        provider.token_url = serve_token_response("200 OK", r#"{"access_token":"new-access","expires_in":3600,"token_type":"Bearer"}"#).await;
+
+        let tokens = provider.refresh_tokens("jane@example.com", "refresh").await.unwrap();
+
+        assert_eq!(tokens.access_token, "new-access");
+        assert!(tokens.refresh_token.is_none());
+        let expires_in = tokens.expires_at.unwrap() - chrono::Utc::now().timestamp();
+        assert!((3590..=3600).contains(&expires_in));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_without_lifetime_assumes_default() {
+        let mut provider = custom_provider();
+        provider.token_url = serve_token_response("200 OK", r#"{"access_token":"new-access","token_type":"Bearer"}"#).await;
+
+        let tokens = provider.refresh_tokens("jane@example.com", "refresh").await.unwrap();
+
+        let expires_in = tokens.expires_at.unwrap() - chrono::Utc::now().timestamp();
+        assert!((DEFAULT_TOKEN_LIFETIME_SECS - 10..=DEFAULT_TOKEN_LIFETIME_SECS).contains(&expires_in));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refreshes_use_the_refresh_token_once() {
+        use crate::utils::security::EncryptedStore;
+        use crate::utils::test_utils::setup_test_db;
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = AccountManager::new_test(setup_test_db().await, EncryptedStore::new_test([0u8; 32]), dir.path().join("accounts.json.enc"));
+        let mut provider = custom_provider();
+        // The server answers a single request, a second refresh would fail to connect
+        provider.token_url = serve_token_response("200 OK", r#"{"access_token":"new-access","refresh_token":"rotated","expires_in":3600}"#).await;
+        manager.add_account(Account::OAuth2(OAuth2Account {
+            id: None,
+            email: "jane@example.com".into(),
+            name: None,
+            picture: None,
+            provider,
+            access_token: Some("old-access".into()),
+            refresh_token: Some("refresh".into()),
+            token_expires_at: None,
+        })).await.unwrap();
+
+        let (first, second) = tokio::join!(
+            manager.refresh_access_token("jane@example.com"),
+            manager.refresh_access_token("jane@example.com"),
+        );
+
+        assert_eq!(first.unwrap(), "new-access");
+        assert_eq!(second.unwrap(), "new-access");
+        let registry = manager.load().await.unwrap();
+        let Account::OAuth2(account) = &registry.accounts[0] else { panic!("expected OAuth2 account") };
+        assert_eq!(account.refresh_token.as_deref(), Some("rotated"));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_grant_requires_reauth() {
+        let mut provider = custom_provider();
+        provider.token_url = serve_token_response("400 Bad Request", r#"{"error":"invalid_grant","error_description":"Token has been expired or revoked."}"#).await;
+
+        let err = provider.refresh_tokens("jane@example.com", "refresh").await.unwrap_err();
+
+        assert!(err.is_reauth_required());
+    }
+}
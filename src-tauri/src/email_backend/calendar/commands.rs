@@ -0,0 +1,11 @@
+use tauri::Manager;
+use crate::email_backend::calendar::invites::{self, CalendarEvent};
+use crate::email_backend::sync::SyncEngine;
+
+/// Answers the invitation in an email with "accepted", "tentative" or "declined" and returns the
+/// event with the response recorded.
+#[tauri::command]
+pub async fn respond_to_invite<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, response: String) -> Result<CalendarEvent, String> {
+    let engine = app_handle.state::<SyncEngine>();
+    invites::respond(&engine, email_id, &response).await
+}
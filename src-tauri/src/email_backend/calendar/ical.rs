@@ -0,0 +1,346 @@
+//! Just enough iCalendar (RFC 5545) to read meeting invitations and write iTIP replies (RFC 5546).
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One property line, after unfolding. vCard uses the same content-line format.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+pub(crate) fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n').map(|l| l.trim_end_matches('\r')) {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if !line.is_empty() => lines.push(line.to_string()),
+            _ => {}
+        }
+    }
+    lines
+}
+
+pub(crate) fn parse_property(line: &str) -> Option<Property> {
+    // The value starts at the first colon outside a quoted parameter
+    let mut quoted = false;
+    let split = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ':' && !quoted
+    })?.0;
+    let (head, value) = (&line[..split], &line[split + 1..]);
+
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.trim().to_ascii_uppercase(), value.trim().trim_matches('"').to_string()))
+        })
+        .collect();
+    Some(Property { name, params, value: value.to_string() })
+}
+
+pub(crate) fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+fn mailto(value: &str) -> String {
+    let value = value.trim();
+    value.get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("mailto:"))
+        .map(|_| value[7..].to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// A DTSTART or DTEND. Times with a zone are converted to UTC, floating and zoned local times
+/// keep their wall-clock time, and dates mark all-day events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventTime {
+    /// RFC 3339 in UTC, a local `YYYY-MM-DDTHH:MM:SS`, or a `YYYY-MM-DD` date.
+    pub value: String,
+    pub all_day: bool,
+    pub timezone: Option<String>,
+}
+
+fn parse_time(property: &Property) -> Option<EventTime> {
+    let raw = property.value.trim();
+    if property.param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || raw.len() == 8 {
+        let date = NaiveDate::parse_from_str(raw, "%Y%m%d").ok()?;
+        return Some(EventTime { value: date.format("%Y-%m-%d").to_string(), all_day: true, timezone: None });
+    }
+    if let Some(utc) = raw.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?.and_utc();
+        return Some(EventTime {
+            value: time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            all_day: false,
+            timezone: Some("UTC".to_string()),
+        });
+    }
+    let time = NaiveDateTime::parse_from_str(raw, "%Y%m%dT%H%M%S").ok()?;
+    Some(EventTime {
+        value: time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        all_day: false,
+        timezone: property.param("TZID").map(str::to_string),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attendee {
+    pub address: String,
+    pub name: Option<String>,
+    /// NEEDS-ACTION, ACCEPTED, TENTATIVE or DECLINED.
+    pub partstat: String,
+}
+
+/// A VEVENT together with the METHOD of the calendar it came in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invite {
+    /// REQUEST, CANCEL, REPLY, or PUBLISH when the calendar has none.
+    pub method: String,
+    pub uid: String,
+    pub sequence: i64,
+    pub recurrence_id: Option<String>,
+    /// Parameters of the RECURRENCE-ID, like TZID or VALUE=DATE, which the reply repeats.
+    pub recurrence_id_params: Vec<(String, String)>,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub organizer: Option<String>,
+    pub organizer_name: Option<String>,
+    pub attendees: Vec<Attendee>,
+    pub start: Option<EventTime>,
+    pub end: Option<EventTime>,
+    /// CONFIRMED, TENTATIVE or CANCELLED.
+    pub status: Option<String>,
+}
+
+/// Reads every VEVENT of an iCalendar object. Events without a UID are skipped.
+pub fn parse_invites(text: &str) -> Vec<Invite> {
+    let mut method = None;
+    let mut invites = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    // VALARMs and other components nested in an event are skipped
+    let mut nested = 0usize;
+
+    for property in unfold(text).iter().filter_map(|line| parse_property(line)) {
+        let component = property.value.trim().to_ascii_uppercase();
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if component == "VEVENT" => current = Some(Vec::new()),
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if component == "VEVENT" => {
+                if let Some(invite) = current.take().and_then(|props| build_invite(method.as_deref(), &props)) {
+                    invites.push(invite);
+                }
+            }
+            ("METHOD", None) => method = Some(property.value.trim().to_ascii_uppercase()),
+            (_, Some(props)) if nested == 0 => props.push(property),
+            _ => {}
+        }
+    }
+
+    // A METHOD after the events still applies to them
+    if let Some(method) = method {
+        for invite in &mut invites {
+            invite.method = method.clone();
+        }
+    }
+    invites
+}
+
+fn build_invite(method: Option<&str>, props: &[Property]) -> Option<Invite> {
+    let get = |name: &str| props.iter().find(|p| p.name == name);
+    let text = |name: &str| get(name).map(|p| unescape(&p.value)).filter(|v| !v.trim().is_empty());
+
+    let uid = get("UID")?.value.trim().to_string();
+    let organizer = get("ORGANIZER");
+    Some(Invite {
+        method: method.unwrap_or("PUBLISH").to_string(),
+        uid,
+        sequence: get("SEQUENCE").and_then(|p| p.value.trim().parse().ok()).unwrap_or(0),
+        recurrence_id: get("RECURRENCE-ID").map(|p| p.value.trim().to_string()),
+        recurrence_id_params: get("RECURRENCE-ID").map(|p| p.params.clone()).unwrap_or_default(),
+        summary: text("SUMMARY"),
+        location: text("LOCATION"),
+        description: text("DESCRIPTION"),
+        organizer: organizer.map(|p| mailto(&p.value)),
+        organizer_name: organizer.and_then(|p| p.param("CN")).map(str::to_string),
+        attendees: props.iter()
+            .filter(|p| p.name == "ATTENDEE")
+            .map(|p| Attendee {
+                address: mailto(&p.value),
+                name: p.param("CN").map(str::to_string),
+                partstat: p.param("PARTSTAT").unwrap_or("NEEDS-ACTION").to_ascii_uppercase(),
+            })
+            .collect(),
+        start: get("DTSTART").and_then(parse_time),
+        end: get("DTEND").and_then(parse_time),
+        status: get("STATUS").map(|p| p.value.trim().to_ascii_uppercase()),
+    })
+}
+
+/// Folds a content line at 75 octets, without splitting a character.
+pub(crate) fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+/// Quotes a parameter value when it holds a character that would end it.
+fn param_value(value: &str) -> String {
+    if value.contains([':', ';', ',']) {
+        format!("\"{}\"", value.replace('"', ""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes the iTIP REPLY in which `attendee` answers an invitation with `partstat`
+/// (ACCEPTED, TENTATIVE or DECLINED).
+pub fn reply_ics(invite: &Invite, attendee: &Attendee, partstat: &str) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "PRODID:-//Dueam//Mail//EN".to_string(),
+        "VERSION:2.0".to_string(),
+        "METHOD:REPLY".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", invite.uid),
+        format!("SEQUENCE:{}", invite.sequence),
+        format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+    ];
+    if let Some(recurrence_id) = &invite.recurrence_id {
+        let params: String = invite.recurrence_id_params.iter().map(|(name, value)| format!(";{}={}", name, param_value(value))).collect();
+        lines.push(format!("RECURRENCE-ID{}:{}", params, recurrence_id));
+    }
+    if let Some(organizer) = &invite.organizer {
+        lines.push(format!("ORGANIZER:mailto:{}", organizer));
+    }
+    let name = attendee.name.as_ref().map(|n| format!(";CN=\"{}\"", n.replace('"', ""))).unwrap_or_default();
+    lines.push(format!("ATTENDEE;PARTSTAT={}{}:mailto:{}", partstat, name, attendee.address));
+    if let Some(summary) = &invite.summary {
+        lines.push(format!("SUMMARY:{}", escape(summary)));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = "BEGIN:VCALENDAR\r\n\
+        METHOD:REQUEST\r\n\
+        PRODID:-//Example//EN\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:meeting-42@example.com\r\n\
+        SEQUENCE:1\r\n\
+        DTSTART;TZID=Europe/Berlin:20240305T100000\r\n\
+        DTEND:20240305T100000Z\r\n\
+        SUMMARY:Planning\\, Q2\r\n\
+        DESCRIPTION:Agenda:\\nBudget\r\n\
+        LOCATION:Room 1\r\n\
+        ORGANIZER;CN=\"Ann: Lead\":mailto:ann@example.com\r\n\
+        ATTENDEE;CN=Me;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:me@exa\r\n mple.com\r\n\
+        BEGIN:VALARM\r\n\
+        DESCRIPTION:Reminder\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_request() {
+        let invites = parse_invites(REQUEST);
+        assert_eq!(invites.len(), 1);
+        let invite = &invites[0];
+        assert_eq!(invite.method, "REQUEST");
+        assert_eq!(invite.uid, "meeting-42@example.com");
+        assert_eq!(invite.sequence, 1);
+        assert_eq!(invite.summary.as_deref(), Some("Planning, Q2"));
+        assert_eq!(invite.description.as_deref(), Some("Agenda:\nBudget"));
+        assert_eq!(invite.organizer.as_deref(), Some("ann@example.com"));
+        assert_eq!(invite.organizer_name.as_deref(), Some("Ann: Lead"));
+        assert_eq!(invite.attendees, vec![Attendee {
+            address: "me@example.com".to_string(),
+            name: Some("Me".to_string()),
+            partstat: "NEEDS-ACTION".to_string(),
+        }]);
+        assert_eq!(invite.start, Some(EventTime {
+            value: "2024-03-05T10:00:00".to_string(),
+            all_day: false,
+            timezone: Some("Europe/Berlin".to_string()),
+        }));
+        assert_eq!(invite.end.as_ref().map(|t| t.value.as_str()), Some("2024-03-05T10:00:00Z"));
+
+        let cancel = parse_invites(&REQUEST.replace("METHOD:REQUEST", "METHOD:CANCEL"));
+        assert_eq!(cancel[0].method, "CANCEL");
+    }
+
+    #[test]
+    fn test_reply_round_trips() {
+        let invite = &parse_invites(REQUEST)[0];
+        let ics = reply_ics(invite, &invite.attendees[0], "ACCEPTED");
+        assert!(ics.lines().all(|line| line.len() <= 76));
+
+        let reply = &parse_invites(&ics)[0];
+        assert_eq!(reply.method, "REPLY");
+        assert_eq!(reply.uid, invite.uid);
+        assert_eq!(reply.sequence, 1);
+        assert_eq!(reply.organizer, invite.organizer);
+        assert_eq!(reply.attendees[0].partstat, "ACCEPTED");
+        assert_eq!(reply.summary, invite.summary);
+        assert_eq!(reply.recurrence_id, None);
+    }
+
+    #[test]
+    fn test_reply_keeps_recurrence_id_parameters() {
+        for recurrence_id in ["RECURRENCE-ID;TZID=Europe/Berlin:20240312T100000", "RECURRENCE-ID;VALUE=DATE:20240312"] {
+            let request = REQUEST.replace("SEQUENCE:1\r\n", &format!("SEQUENCE:1\r\n{}\r\n", recurrence_id));
+            let invite = &parse_invites(&request)[0];
+            let ics = reply_ics(invite, &invite.attendees[0], "DECLINED");
+            assert!(ics.lines().any(|line| line == recurrence_id), "{} missing from {}", recurrence_id, ics);
+
+            let reply = &parse_invites(&ics)[0];
+            assert_eq!(reply.recurrence_id, invite.recurrence_id);
+            assert_eq!(reply.recurrence_id_params, invite.recurrence_id_params);
+        }
+    }
+}
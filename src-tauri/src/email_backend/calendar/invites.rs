@@ -0,0 +1,402 @@
+//! Meeting invitations carried by messages (iMIP, RFC 6047): storing them when a message is
+//! read or indexed, and answering them through the account's SMTP.
+
+use log::info;
+use mail_builder::headers::content_type::ContentType;
+use mail_builder::mime::MimePart;
+use mail_builder::MessageBuilder;
+use mail_parser::{MessageParser, MimeHeaders, PartType};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use crate::email_backend::calendar::ical::{parse_invites, reply_ics, Attendee, Invite};
+use crate::email_backend::emails::transport::Transport;
+use crate::email_backend::sync::SyncEngine;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CalendarEvent {
+    pub id: i64,
+    pub email_id: i64,
+    pub uid: String,
+    pub sequence: i64,
+    pub method: String,
+    pub status: Option<String>,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub organizer: Option<String>,
+    pub organizer_name: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub all_day: bool,
+    pub timezone: Option<String>,
+    /// JSON array of attendees.
+    pub attendees: String,
+    /// "new", "updated", "outdated" (a later message changed the event), "cancelled" or "reply".
+    pub change: String,
+    pub response: Option<String>,
+}
+
+const EVENT_COLUMNS: &str = "id, email_id, uid, sequence, method, status, summary, location, description, organizer,
+    organizer_name, starts_at, ends_at, all_day, timezone, attendees, change, response";
+
+/// The iCalendar bodies of a message: text/calendar parts and .ics attachments.
+pub fn calendar_parts(raw: &[u8]) -> Vec<String> {
+    let Some(message) = MessageParser::default().parse(raw) else {
+        return Vec::new();
+    };
+    message.parts.iter()
+        .filter(|part| !matches!(part.body, PartType::Multipart(_) | PartType::Message(_)))
+        .filter(|part| {
+            let calendar_type = part.content_type().is_some_and(|ct| {
+                let subtype = ct.subtype().unwrap_or_default();
+                (ct.ctype().eq_ignore_ascii_case("text") && subtype.eq_ignore_ascii_case("calendar"))
+                    || (ct.ctype().eq_ignore_ascii_case("application") && subtype.eq_ignore_ascii_case("ics"))
+            });
+            calendar_type || part.attachment_name().is_some_and(|name| name.to_ascii_lowercase().ends_with(".ics"))
+        })
+        .map(|part| String::from_utf8_lossy(part.contents()).into_owned())
+        .collect()
+}
+
+/// How an invite relates to what earlier messages said about the same event.
+async fn change_of(pool: &SqlitePool, account_id: i64, invite: &Invite, recurrence_id: &str) -> Result<&'static str, String> {
+    match invite.method.as_str() {
+        "CANCEL" => return Ok("cancelled"),
+        "REPLY" => return Ok("reply"),
+        _ => {}
+    }
+    let latest: Option<i64> = sqlx::query_scalar(
+        "SELECT MAX(sequence) FROM calendar_events WHERE account_id = ? AND uid = ? AND recurrence_id = ? AND method != 'REPLY'"
+    )
+    .bind(account_id)
+    .bind(&invite.uid)
+    .bind(recurrence_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(match latest {
+        None => "new",
+        Some(latest) if invite.sequence > latest => "updated",
+        Some(latest) if invite.sequence < latest => "outdated",
+        Some(_) => "new",
+    })
+}
+
+/// Whether a cancellation stored earlier covers this invite. Messages are indexed newest first,
+/// so a cancellation is often stored before the request it cancels.
+async fn cancelled_earlier(pool: &SqlitePool, account_id: i64, invite: &Invite, recurrence_id: &str) -> Result<bool, String> {
+    sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM calendar_events WHERE account_id = ? AND uid = ? AND method = 'CANCEL'
+            AND (recurrence_id = ? OR recurrence_id = '') AND sequence >= ?)"
+    )
+    .bind(account_id)
+    .bind(&invite.uid)
+    .bind(recurrence_id)
+    .bind(invite.sequence)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Stores the invitations in a message. Cancellations mark the event cancelled on every message
+/// that carried it, whichever arrives first, and updates mark earlier versions outdated. Messages
+/// already stored are skipped.
+pub async fn save_invites(pool: &SqlitePool, email_id: i64, raw: &[u8]) -> Result<(), String> {
+    let invites: Vec<(Invite, String)> = calendar_parts(raw).into_iter()
+        .flat_map(|ics| parse_invites(&ics).into_iter().map(move |invite| (invite, ics.clone())))
+        .collect();
+    if invites.is_empty() {
+        return Ok(());
+    }
+    let account_id: i64 = sqlx::query_scalar("SELECT account_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for (invite, ics) in invites {
+        let recurrence_id = invite.recurrence_id.clone().unwrap_or_default();
+        let stored: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM calendar_events WHERE email_id = ? AND uid = ? AND recurrence_id = ?)")
+            .bind(email_id)
+            .bind(&invite.uid)
+            .bind(&recurrence_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        if stored {
+            continue;
+        }
+
+        let change = change_of(pool, account_id, &invite, &recurrence_id).await?;
+        match change {
+            "cancelled" => {
+                sqlx::query(
+                    "UPDATE calendar_events SET status = 'CANCELLED'
+                     WHERE account_id = ? AND uid = ? AND (recurrence_id = ? OR ? = '') AND sequence <= ?"
+                )
+                    .bind(account_id)
+                    .bind(&invite.uid)
+                    .bind(&recurrence_id)
+                    .bind(&recurrence_id)
+                    .bind(invite.sequence)
+                    .execute(pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            "updated" => {
+                sqlx::query("UPDATE calendar_events SET change = 'outdated' WHERE account_id = ? AND uid = ? AND recurrence_id = ? AND method = 'REQUEST'")
+                    .bind(account_id)
+                    .bind(&invite.uid)
+                    .bind(&recurrence_id)
+                    .execute(pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            _ => {}
+        }
+
+        let cancelled = match change {
+            "cancelled" => true,
+            "reply" => false,
+            _ => cancelled_earlier(pool, account_id, &invite, &recurrence_id).await?,
+        };
+        let status = if cancelled { Some("CANCELLED".to_string()) } else { invite.status.clone() };
+        let (starts_at, all_day, timezone) = match &invite.start {
+            Some(start) => (Some(start.value.clone()), start.all_day, start.timezone.clone()),
+            None => (None, false, None),
+        };
+        sqlx::query(
+            "INSERT INTO calendar_events (email_id, account_id, uid, recurrence_id, sequence, method, status, summary, location,
+                description, organizer, organizer_name, starts_at, ends_at, all_day, timezone, attendees, change, ics)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(email_id)
+        .bind(account_id)
+        .bind(&invite.uid)
+        .bind(&recurrence_id)
+        .bind(invite.sequence)
+        .bind(&invite.method)
+        .bind(status)
+        .bind(&invite.summary)
+        .bind(&invite.location)
+        .bind(&invite.description)
+        .bind(&invite.organizer)
+        .bind(&invite.organizer_name)
+        .bind(starts_at)
+        .bind(invite.end.as_ref().map(|end| end.value.clone()))
+        .bind(all_day)
+        .bind(timezone)
+        .bind(serde_json::to_string(&invite.attendees).map_err(|e| e.to_string())?)
+        .bind(change)
+        .bind(&ics)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// The invitation a message carries, if any.
+pub async fn email_invite(pool: &SqlitePool, email_id: i64) -> Result<Option<CalendarEvent>, String> {
+    sqlx::query_as(&format!("SELECT {} FROM calendar_events WHERE email_id = ? ORDER BY id LIMIT 1", EVENT_COLUMNS))
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Maps a response from the frontend to its iCalendar PARTSTAT and the subject prefix of the reply.
+fn partstat(response: &str) -> Option<(&'static str, &'static str)> {
+    match response {
+        "accepted" => Some(("ACCEPTED", "Accepted")),
+        "tentative" => Some(("TENTATIVE", "Tentative")),
+        "declined" => Some(("DECLINED", "Declined")),
+        _ => None,
+    }
+}
+
+/// Builds the reply message: a short text for people and a METHOD:REPLY calendar part for their client.
+pub fn reply_message(from: &str, invite: &Invite, response: &str) -> Result<Vec<u8>, String> {
+    let (partstat, verb) = partstat(response).ok_or_else(|| format!("Unknown response '{}'", response))?;
+    let organizer = invite.organizer.as_deref().ok_or("The invitation has no organizer to reply to")?;
+    let attendee = invite.attendees.iter()
+        .find(|a| a.address.eq_ignore_ascii_case(from))
+        .cloned()
+        .unwrap_or_else(|| Attendee { address: from.to_string(), name: None, partstat: "NEEDS-ACTION".to_string() });
+
+    let summary = invite.summary.as_deref().unwrap_or("Invitation");
+    let text = format!("{} has {} the invitation \"{}\".", from, verb.to_lowercase(), summary);
+    let calendar = ContentType::new("text/calendar").attribute("method", "REPLY").attribute("charset", "utf-8");
+
+    MessageBuilder::new()
+        .from(from)
+        .to(organizer)
+        .subject(format!("{}: {}", verb, summary))
+        .body(MimePart::new("multipart/alternative", vec![
+            MimePart::new("text/plain", text),
+            MimePart::new(calendar, reply_ics(invite, &attendee, partstat)),
+        ]))
+        .write_to_vec()
+        .map_err(|e| e.to_string())
+}
+
+/// Accepts, tentatively accepts or declines the invitation in a message and remembers the answer.
+pub async fn respond(engine: &SyncEngine, email_id: i64, response: &str) -> Result<CalendarEvent, String> {
+    let pool = engine.app().pool();
+    let (account_id, uid, ics, status): (i64, String, String, Option<String>) = sqlx::query_as(
+        "SELECT account_id, uid, ics, status FROM calendar_events WHERE email_id = ? AND method = 'REQUEST' ORDER BY id LIMIT 1"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or("This message has no invitation to answer")?;
+    if status.as_deref() == Some("CANCELLED") {
+        return Err("This event was cancelled".to_string());
+    }
+
+    let invite = parse_invites(&ics).into_iter()
+        .find(|invite| invite.uid == uid)
+        .ok_or("The invitation could not be read")?;
+
+    let account = engine.app().accounts().await?.get_authenticated_account(account_id).await?;
+    let message = reply_message(account.email(), &invite, response)?;
+    Transport::for_account(&account)?.send(&message).await?;
+    info!("Answered invitation {} with {}", invite.uid, response);
+
+    sqlx::query("UPDATE calendar_events SET response = ? WHERE account_id = ? AND uid = ? AND method = 'REQUEST'")
+        .bind(response)
+        .bind(account_id)
+        .bind(&invite.uid)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    email_invite(pool, email_id).await?.ok_or_else(|| "Invitation not found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    fn invite_mail(method: &str, sequence: i64) -> Vec<u8> {
+        format!(
+            "From: Ann <ann@example.com>\r\n\
+             To: me@example.com\r\n\
+             Subject: Invitation: Planning\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/alternative; boundary=\"b\"\r\n\
+             \r\n\
+             --b\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             You are invited.\r\n\
+             --b\r\n\
+             Content-Type: text/calendar; charset=utf-8; method={method}\r\n\
+             \r\n\
+             BEGIN:VCALENDAR\r\n\
+             METHOD:{method}\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:planning@example.com\r\n\
+             SEQUENCE:{sequence}\r\n\
+             DTSTART:20240305T090000Z\r\n\
+             SUMMARY:Planning\r\n\
+             ORGANIZER:mailto:ann@example.com\r\n\
+             ATTENDEE;PARTSTAT=NEEDS-ACTION:mailto:me@example.com\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n\
+             --b--\r\n"
+        ).into_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_updates_and_cancellations_are_detected() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let folder_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let mut ids = Vec::new();
+        for (n, (method, sequence)) in [("REQUEST", 0), ("REQUEST", 1), ("CANCEL", 2)].into_iter().enumerate() {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (account_id, folder_id, remote_id, sender_address, date, flags) VALUES (?, ?, ?, 'ann@example.com', '2024-03-01T10:00:00Z', '[]') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(n.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            let raw = invite_mail(method, sequence);
+            save_invites(&pool, id, &raw).await.unwrap();
+            // Reading the message again stores nothing new
+            save_invites(&pool, id, &raw).await.unwrap();
+            ids.push(id);
+        }
+
+        let first = email_invite(&pool, ids[0]).await.unwrap().unwrap();
+        assert_eq!((first.summary.as_deref(), first.starts_at.as_deref()), (Some("Planning"), Some("2024-03-05T09:00:00Z")));
+        assert_eq!((first.change.as_str(), first.status.as_deref()), ("outdated", Some("CANCELLED")));
+        assert_eq!(email_invite(&pool, ids[1]).await.unwrap().unwrap().change, "updated");
+        assert_eq!(email_invite(&pool, ids[2]).await.unwrap().unwrap().change, "cancelled");
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM calendar_events").fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_indexed_before_its_request() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let folder_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        // Newest first: the cancellation, the request it cancels, then a new request made later
+        let mut statuses = Vec::new();
+        for (n, (method, sequence)) in [("CANCEL", 1), ("REQUEST", 1), ("REQUEST", 2)].into_iter().enumerate() {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (account_id, folder_id, remote_id, sender_address, date, flags) VALUES (?, ?, ?, 'ann@example.com', '2024-03-01T10:00:00Z', '[]') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(n.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            save_invites(&pool, id, &invite_mail(method, sequence)).await.unwrap();
+            statuses.push(email_invite(&pool, id).await.unwrap().unwrap().status);
+        }
+
+        assert_eq!(statuses[0].as_deref(), Some("CANCELLED"));
+        assert_eq!(statuses[1].as_deref(), Some("CANCELLED"));
+        assert_ne!(statuses[2].as_deref(), Some("CANCELLED"));
+    }
+
+    #[test]
+    fn test_reply_message_goes_to_the_organizer() {
+        let ics = String::from_utf8(invite_mail("REQUEST", 1)).unwrap();
+        let invite = &parse_invites(&ics)[0];
+        let message = reply_message("me@example.com", invite, "declined").unwrap();
+
+        let parsed = MessageParser::default().parse(&message).unwrap();
+        assert_eq!(parsed.subject(), Some("Declined: Planning"));
+        let reply = &parse_invites(&calendar_parts(&message)[0])[0];
+        assert_eq!(reply.method, "REPLY");
+        assert_eq!(reply.attendees[0].address, "me@example.com");
+        assert_eq!(reply.attendees[0].partstat, "DECLINED");
+        assert!(reply_message("me@example.com", invite, "maybe").is_err());
+    }
+}
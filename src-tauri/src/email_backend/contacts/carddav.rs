@@ -0,0 +1,604 @@
+//! A CardDAV (RFC 6352) client that pulls address books into the contacts. Each sync lists the
+//! cards' ETags and fetches only the cards that are new or changed since the last one.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use log::{info, warn};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use url::Url;
+use crate::context::AppContext;
+use crate::email_backend::accounts::manager::Account;
+use crate::email_backend::contacts::commands::save_card;
+use crate::email_backend::contacts::vcard::parse_vcards;
+
+/// Cards requested per addressbook-multiget REPORT.
+const MULTIGET_BATCH: usize = 50;
+const MAX_REDIRECTS: usize = 5;
+
+const DISCOVERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+  <d:prop><d:resourcetype/><d:displayname/><d:current-user-principal/><card:addressbook-home-set/></d:prop>
+</d:propfind>"#;
+
+const ETAGS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getetag/></d:prop></d:propfind>"#;
+
+#[derive(Debug, Deserialize)]
+struct Multistatus {
+    #[serde(rename = "response", default)]
+    responses: Vec<DavResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DavResponse {
+    href: String,
+    #[serde(rename = "propstat", default)]
+    propstats: Vec<Propstat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Propstat {
+    #[serde(default)]
+    prop: Prop,
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Prop {
+    getetag: Option<String>,
+    resourcetype: Option<ResourceType>,
+    displayname: Option<String>,
+    #[serde(rename = "current-user-principal")]
+    principal: Option<Href>,
+    #[serde(rename = "addressbook-home-set")]
+    home: Option<Href>,
+    #[serde(rename = "address-data")]
+    address_data: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Href {
+    href: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ResourceType {
+    collection: Option<Empty>,
+    addressbook: Option<Empty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Empty {}
+
+impl DavResponse {
+    /// The properties the server actually returned; 404 propstats list the ones it does not have.
+    fn props(&self) -> impl Iterator<Item = &Prop> {
+        self.propstats.iter().filter(|p| p.status.is_empty() || p.status.contains(" 200")).map(|p| &p.prop)
+    }
+
+    fn is_address_book(&self) -> bool {
+        self.props().any(|p| p.resourcetype.as_ref().is_some_and(|r| r.addressbook.is_some()))
+    }
+
+    fn is_collection(&self) -> bool {
+        self.props().any(|p| p.resourcetype.as_ref().is_some_and(|r| r.collection.is_some()))
+    }
+
+    fn href_prop(&self, get: impl Fn(&Prop) -> Option<&Href>) -> Option<String> {
+        self.props().find_map(|p| get(p).and_then(|h| h.href.clone()))
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// CardDAV signs in with the account's IMAP password, so it only goes to https URLs, or to plain
+/// http on this machine.
+pub fn check_secure(url: &Url) -> Result<(), String> {
+    let loopback = match url.host() {
+        Some(url::Host::Domain(host)) => host == "localhost",
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        _ => Err(format!("{} is not an https URL, the password would be sent unencrypted", url)),
+    }
+}
+
+/// Compares collection URLs regardless of a trailing slash.
+fn same_collection(a: &Url, b: &Url) -> bool {
+    a.as_str().trim_end_matches('/') == b.as_str().trim_end_matches('/')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressBook {
+    pub url: Url,
+    pub name: Option<String>,
+}
+
+/// A card on the server: its URL, ETag and, once fetched, its vCard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteCard {
+    pub url: Url,
+    pub etag: String,
+    pub vcard: Option<String>,
+}
+
+pub struct CardDavClient {
+    http: reqwest::Client,
+    username: String,
+    password: String,
+}
+
+impl CardDavClient {
+    pub fn new(username: &str, password: &str) -> Result<Self, String> {
+        // Redirects are followed by hand, reqwest would turn PROPFIND into GET on a 301
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { http, username: username.to_string(), password: password.to_string() })
+    }
+
+    /// Sends a PROPFIND or REPORT and returns the multistatus along with the URL that answered it.
+    /// Redirects are only followed on the same origin, the credentials go with every request.
+    async fn dav(&self, method: &str, url: &Url, depth: &str, body: String) -> Result<(Url, Multistatus), String> {
+        let method = Method::from_bytes(method.as_bytes()).map_err(|e| e.to_string())?;
+        check_secure(url)?;
+        let origin = url.origin();
+        let mut url = url.clone();
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self.http.request(method.clone(), url.clone())
+                .basic_auth(&self.username, Some(&self.password))
+                .header("Depth", depth)
+                .header("Content-Type", "application/xml; charset=utf-8")
+                .body(body.clone())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response.headers().get("Location")
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| format!("{} redirected without a location", url))?;
+                let target = url.join(location).map_err(|e| e.to_string())?;
+                if target.origin() != origin {
+                    return Err(format!("{} redirected to another server ({}), not following it with the password", url, target));
+                }
+                url = target;
+                continue;
+            }
+            if status == StatusCode::UNAUTHORIZED {
+                return Err("The CardDAV server rejected the username or password".to_string());
+            }
+            if !status.is_success() {
+                return Err(format!("{} answered {}", url, status));
+            }
+            let text = response.text().await.map_err(|e| e.to_string())?;
+            let multistatus = serde_xml_rs::from_str(&text).map_err(|e| format!("Unreadable answer from {}: {}", url, e))?;
+            return Ok((url, multistatus));
+        }
+        Err(format!("Too many redirects from {}", url))
+    }
+
+    /// Finds the address books behind a URL, which may be the server, the user's principal, their
+    /// address book home or an address book itself.
+    pub async fn address_books(&self, start: &Url) -> Result<Vec<AddressBook>, String> {
+        let start = match start.path() {
+            "" | "/" => start.join("/.well-known/carddav").map_err(|e| e.to_string())?,
+            _ => start.clone(),
+        };
+        let (base, found) = self.dav("PROPFIND", &start, "0", DISCOVERY.to_string()).await?;
+        if let Some(book) = found.responses.iter().find(|r| r.is_address_book()) {
+            let url = base.join(&book.href).map_err(|e| e.to_string())?;
+            return Ok(vec![AddressBook { url, name: book.props().find_map(|p| p.displayname.clone()) }]);
+        }
+
+        let home = found.responses.iter().find_map(|r| r.href_prop(|p| p.home.as_ref()));
+        let principal = found.responses.iter().find_map(|r| r.href_prop(|p| p.principal.as_ref()));
+        let home = match (home, principal) {
+            (Some(home), _) => base.join(&home).map_err(|e| e.to_string())?,
+            (None, Some(principal)) => {
+                let principal = base.join(&principal).map_err(|e| e.to_string())?;
+                let (base, found) = self.dav("PROPFIND", &principal, "0", DISCOVERY.to_string()).await?;
+                let home = found.responses.iter()
+                    .find_map(|r| r.href_prop(|p| p.home.as_ref()))
+                    .ok_or("The server did not say where the address books are")?;
+                base.join(&home).map_err(|e| e.to_string())?
+            }
+            // Nothing to follow, so the URL should be the home itself
+            (None, None) => base,
+        };
+
+        let (base, listing) = self.dav("PROPFIND", &home, "1", DISCOVERY.to_string()).await?;
+        let books: Vec<AddressBook> = listing.responses.iter()
+            .filter(|r| r.is_address_book())
+            .filter_map(|r| {
+                let url = base.join(&r.href).ok()?;
+                Some(AddressBook { url, name: r.props().find_map(|p| p.displayname.clone()) })
+            })
+            .collect();
+        if books.is_empty() {
+            return Err(format!("No address books found at {}", start));
+        }
+        Ok(books)
+    }
+
+    /// Lists the cards in an address book with their ETags.
+    pub async fn etags(&self, book: &Url) -> Result<Vec<RemoteCard>, String> {
+        let (base, listing) = self.dav("PROPFIND", book, "1", ETAGS.to_string()).await?;
+        Ok(listing.responses.iter()
+            .filter(|r| !r.is_collection())
+            .filter_map(|r| {
+                let url = base.join(&r.href).ok()?;
+                let etag = r.props().find_map(|p| p.getetag.clone()).filter(|e| !e.is_empty())?;
+                Some(RemoteCard { url, etag, vcard: None })
+            })
+            .filter(|card| !same_collection(&card.url, book))
+            .collect())
+    }
+
+    /// Fetches the vCards of the given cards with addressbook-multiget REPORTs.
+    pub async fn fetch(&self, book: &Url, cards: &[Url]) -> Result<Vec<RemoteCard>, String> {
+        let mut fetched = Vec::new();
+        for batch in cards.chunks(MULTIGET_BATCH) {
+            let hrefs: String = batch.iter().map(|url| format!("<d:href>{}</d:href>", xml_escape(url.path()))).collect();
+            let body = format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<card:addressbook-multiget xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+  <d:prop><d:getetag/><card:address-data/></d:prop>{}
+</card:addressbook-multiget>"#,
+                hrefs
+            );
+            let (base, found) = self.dav("REPORT", book, "1", body).await?;
+            for response in &found.responses {
+                let Ok(url) = base.join(&response.href) else {
+                    continue;
+                };
+                let etag = response.props().find_map(|p| p.getetag.clone()).unwrap_or_default();
+                let vcard = response.props().find_map(|p| p.address_data.clone());
+                if vcard.is_some() {
+                    fetched.push(RemoteCard { url, etag, vcard });
+                }
+            }
+        }
+        Ok(fetched)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CardDavAccount {
+    pub account_id: i64,
+    pub url: String,
+    pub username: Option<String>,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SyncSummary {
+    pub address_books: usize,
+    /// Cards that were new or changed.
+    pub fetched: usize,
+    pub removed: usize,
+}
+
+/// Drops a card that left the server. Its addresses stop being contacts unless another card of
+/// the account still has them.
+async fn remove_card(pool: &SqlitePool, account_id: i64, account_email: &str, href: &str) -> Result<(), String> {
+    let addresses: Option<String> = sqlx::query_scalar("DELETE FROM carddav_cards WHERE account_id = ? AND href = ? RETURNING addresses")
+        .bind(account_id)
+        .bind(href)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let addresses: Vec<String> = addresses.and_then(|a| serde_json::from_str(&a).ok()).unwrap_or_default();
+
+    for address in addresses {
+        sqlx::query(
+            "UPDATE senders SET is_contact = 0, updated_at = CURRENT_TIMESTAMP
+             WHERE address = ? AND account_email = ?
+               AND NOT EXISTS (SELECT 1 FROM carddav_cards c, json_each(c.addresses) a WHERE c.account_id = ? AND a.value = ?)"
+        )
+        .bind(&address)
+        .bind(account_email)
+        .bind(account_id)
+        .bind(&address)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Brings the contacts up to date with every address book behind `url`.
+pub async fn sync_address_books(pool: &SqlitePool, client: &CardDavClient, account_id: i64, account_email: &str, url: &Url) -> Result<SyncSummary, String> {
+    let books = client.address_books(url).await?;
+    let mut summary = SyncSummary { address_books: books.len(), ..SyncSummary::default() };
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for book in &books {
+        let known: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+            "SELECT href, etag FROM carddav_cards WHERE account_id = ? AND address_book = ?"
+        )
+        .bind(account_id)
+        .bind(book.url.as_str())
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+
+        let remote = client.etags(&book.url).await?;
+        seen.extend(remote.iter().map(|card| card.url.to_string()));
+        let changed: Vec<Url> = remote.iter()
+            .filter(|card| known.get(card.url.as_str()) != Some(&card.etag))
+            .map(|card| card.url.clone())
+            .collect();
+
+        for card in client.fetch(&book.url, &changed).await? {
+            let vcards = parse_vcards(card.vcard.as_deref().unwrap_or_default());
+            let mut addresses = Vec::new();
+            for vcard in &vcards {
+                save_card(pool, vcard, Some(account_email)).await?;
+                addresses.extend(vcard.emails.iter().cloned());
+            }
+            // A multiget answer may leave out the ETag, the listing has it
+            let etag = if card.etag.is_empty() {
+                remote.iter().find(|r| r.url == card.url).map(|r| r.etag.clone()).unwrap_or_default()
+            } else {
+                card.etag.clone()
+            };
+            sqlx::query(
+                "INSERT INTO carddav_cards (account_id, href, address_book, etag, addresses) VALUES (?, ?, ?, ?, ?)
+                 ON CONFLICT (account_id, href) DO UPDATE SET
+                    address_book = excluded.address_book, etag = excluded.etag, addresses = excluded.addresses"
+            )
+            .bind(account_id)
+            .bind(card.url.as_str())
+            .bind(book.url.as_str())
+            .bind(etag)
+            .bind(serde_json::to_string(&addresses).map_err(|e| e.to_string())?)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            summary.fetched += 1;
+        }
+    }
+
+    let stored: Vec<String> = sqlx::query_scalar("SELECT href FROM carddav_cards WHERE account_id = ?")
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    for href in stored.iter().filter(|href| !seen.contains(href.as_str())) {
+        remove_card(pool, account_id, account_email, href).await?;
+        summary.removed += 1;
+    }
+
+    Ok(summary)
+}
+
+async fn sync_with_credentials(app: &AppContext, config: &CardDavAccount) -> Result<SyncSummary, String> {
+    let url = Url::parse(&config.url).map_err(|e| e.to_string())?;
+    let (username, password, email) = match app.accounts().await?.get_authenticated_account(config.account_id).await? {
+        Account::ImapSmtp(account) => (account.imap_username, account.password.unwrap_or_default(), account.email),
+        _ => return Err("CardDAV sync works with IMAP accounts".to_string()),
+    };
+    let username = config.username.clone().filter(|u| !u.is_empty()).unwrap_or(username);
+    let client = CardDavClient::new(&username, &password)?;
+    sync_address_books(app.pool(), &client, config.account_id, &email, &url).await
+}
+
+/// Syncs the CardDAV server set up for an account, signing in with its IMAP credentials.
+pub async fn sync_account(app: &AppContext, account_id: i64) -> Result<SyncSummary, String> {
+    let pool = app.pool();
+    let config: CardDavAccount = sqlx::query_as(
+        "SELECT account_id, url, username, last_synced_at, last_error FROM carddav_accounts WHERE account_id = ?"
+    )
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or("No CardDAV server is set up for this account")?;
+
+    let result = sync_with_credentials(app, &config).await;
+    let error = result.as_ref().err().cloned();
+    sqlx::query("UPDATE carddav_accounts SET last_synced_at = CASE WHEN ? IS NULL THEN CURRENT_TIMESTAMP ELSE last_synced_at END, last_error = ? WHERE account_id = ?")
+        .bind(&error)
+        .bind(&error)
+        .bind(account_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Ok(summary) = &result {
+        info!("CardDAV sync for account {}: {} fetched, {} removed", account_id, summary.fetched, summary.removed);
+    }
+    result
+}
+
+/// Syncs every account that has a CardDAV server set up.
+pub async fn sync_all(app: &AppContext) -> Result<(), String> {
+    let account_ids: Vec<i64> = sqlx::query_scalar("SELECT account_id FROM carddav_accounts")
+        .fetch_all(app.pool())
+        .await
+        .map_err(|e| e.to_string())?;
+    for account_id in account_ids {
+        if let Err(e) = sync_account(app, account_id).await {
+            warn!("CardDAV sync for account {} failed: {}", account_id, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use crate::utils::test_utils::setup_test_db;
+
+    const BOOK: &str = "/dav/books/me/contacts/";
+
+    /// The cards the stub serves by path, with their ETags, and the paths each REPORT asked for.
+    #[derive(Default)]
+    struct Server {
+        cards: Vec<(String, String, String)>,
+        fetched: Vec<Vec<String>>,
+    }
+
+    fn multistatus(responses: &str) -> String {
+        format!(r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">{}</d:multistatus>"#, responses)
+    }
+
+    fn response(href: &str, props: &str) -> String {
+        format!("<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>", href, props)
+    }
+
+    fn answer(server: &Mutex<Server>, method: &str, path: &str, body: &str) -> (String, String) {
+        let mut server = server.lock().unwrap();
+        let xml = match (method, path) {
+            ("PROPFIND", "/.well-known/carddav") => return ("301 Moved Permanently\r\nLocation: /dav/".to_string(), String::new()),
+            ("PROPFIND", "/moved/") => return ("301 Moved Permanently\r\nLocation: https://contacts.example.net/dav/".to_string(), String::new()),
+            ("PROPFIND", "/dav/") => response("/dav/", "<d:current-user-principal><d:href>/dav/principals/me/</d:href></d:current-user-principal>"),
+            ("PROPFIND", "/dav/principals/me/") => response(
+                "/dav/principals/me/",
+                "<card:addressbook-home-set><d:href>/dav/books/me/</d:href></card:addressbook-home-set>",
+            ),
+            ("PROPFIND", "/dav/books/me/") => {
+                response("/dav/books/me/", "<d:resourcetype><d:collection/></d:resourcetype>")
+                    + &response(BOOK, "<d:resourcetype><d:collection/><card:addressbook/></d:resourcetype><d:displayname>Contacts</d:displayname>")
+            }
+            ("PROPFIND", BOOK) => {
+                let cards: String = server.cards.iter()
+                    .map(|(href, etag, _)| response(href, &format!("<d:resourcetype/><d:getetag>\"{}\"</d:getetag>", etag)))
+                    .collect();
+                response(BOOK, "<d:resourcetype><d:collection/><card:addressbook/></d:resourcetype>") + &cards
+            }
+            ("REPORT", BOOK) => {
+                let asked: Vec<String> = server.cards.iter()
+                    .map(|(href, _, _)| href.clone())
+                    .filter(|href| body.contains(&format!("<d:href>{}</d:href>", href)))
+                    .collect();
+                server.fetched.push(asked.clone());
+                server.cards.iter()
+                    .filter(|(href, _, _)| asked.contains(href))
+                    .map(|(href, etag, vcard)| response(href, &format!("<d:getetag>\"{}\"</d:getetag><card:address-data>{}</card:address-data>", etag, vcard)))
+                    .collect()
+            }
+            _ => return ("404 Not Found".to_string(), String::new()),
+        };
+        ("207 Multi-Status".to_string(), multistatus(&xml))
+    }
+
+    async fn serve(listener: TcpListener, server: Arc<Mutex<Server>>) {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            let (head, body) = loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head.lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if body.len() >= length || read == 0 {
+                        break (head.to_string(), body.to_string());
+                    }
+                }
+            };
+            let mut request_line = head.split_whitespace();
+            let (method, path) = (request_line.next().unwrap(), request_line.next().unwrap());
+            assert!(head.to_lowercase().contains("authorization: basic"));
+
+            let (status, xml) = answer(&server, method, path, &body);
+            let reply = format!("HTTP/1.1 {}\r\nContent-Type: application/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, xml.len(), xml);
+            stream.write_all(reply.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn card(name: &str, address: &str, title: &str) -> String {
+        format!("BEGIN:VCARD\r\nVERSION:3.0\r\nFN:{}\r\nEMAIL:{}\r\nTITLE:{}\r\nEND:VCARD\r\n", name, address, title)
+    }
+
+    #[tokio::test]
+    async fn test_sync_fetches_only_changed_cards() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let server = Arc::new(Mutex::new(Server {
+            cards: vec![
+                (format!("{}ada.vcf", BOOK), "1".to_string(), card("Ada Lovelace", "ada@example.com", "Countess")),
+                (format!("{}grace.vcf", BOOK), "1".to_string(), card("Grace Hopper", "grace@example.com", "Captain")),
+            ],
+            ..Server::default()
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port())).unwrap();
+        tokio::spawn(serve(listener, server.clone()));
+        let client = CardDavClient::new("me", "secret").unwrap();
+
+        let books = client.address_books(&url).await.unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].name.as_deref(), Some("Contacts"));
+
+        let summary = sync_address_books(&pool, &client, account_id, "me@example.com", &url).await.unwrap();
+        assert_eq!(summary, SyncSummary { address_books: 1, fetched: 2, removed: 0 });
+        let contact: (Option<String>, bool, Option<String>) = sqlx::query_as("SELECT name, is_contact, account_email FROM senders WHERE address = 'ada@example.com'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(contact, (Some("Ada Lovelace".to_string()), true, Some("me@example.com".to_string())));
+
+        {
+            let mut server = server.lock().unwrap();
+            server.cards.remove(0);
+            server.cards[0] = (format!("{}grace.vcf", BOOK), "2".to_string(), card("Grace Hopper", "grace@example.com", "Rear Admiral"));
+        }
+        let summary = sync_address_books(&pool, &client, account_id, "me@example.com", &url).await.unwrap();
+        assert_eq!(summary, SyncSummary { address_books: 1, fetched: 1, removed: 1 });
+        assert_eq!(server.lock().unwrap().fetched.last().unwrap(), &vec![format!("{}grace.vcf", BOOK)]);
+
+        let title: Option<String> = sqlx::query_scalar("SELECT job_title FROM senders WHERE address = 'grace@example.com'").fetch_one(&pool).await.unwrap();
+        assert_eq!(title.as_deref(), Some("Rear Admiral"));
+        let ada_is_contact: bool = sqlx::query_scalar("SELECT is_contact FROM senders WHERE address = 'ada@example.com'").fetch_one(&pool).await.unwrap();
+        assert!(!ada_is_contact);
+
+        // Nothing changed, so nothing is fetched
+        let summary = sync_address_books(&pool, &client, account_id, "me@example.com", &url).await.unwrap();
+        assert_eq!(summary, SyncSummary { address_books: 1, fetched: 0, removed: 0 });
+        assert_eq!(server.lock().unwrap().fetched.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_password_stays_on_secure_same_origin_urls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://127.0.0.1:{}/moved/", listener.local_addr().unwrap().port())).unwrap();
+        tokio::spawn(serve(listener, Arc::new(Mutex::new(Server::default()))));
+        let client = CardDavClient::new("me", "secret").unwrap();
+
+        let err = client.address_books(&url).await.unwrap_err();
+        assert!(err.contains("another server"), "{}", err);
+
+        let err = client.address_books(&Url::parse("http://dav.example.com/").unwrap()).await.unwrap_err();
+        assert!(err.contains("not an https URL"), "{}", err);
+        assert!(check_secure(&Url::parse("https://dav.example.com/").unwrap()).is_ok());
+        assert!(check_secure(&Url::parse("http://localhost:5232/").unwrap()).is_ok());
+    }
+}
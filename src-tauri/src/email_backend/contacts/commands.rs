@@ -0,0 +1,131 @@
+use sqlx::SqlitePool;
+use tauri::Manager;
+use url::Url;
+use crate::context::AppContext;
+use crate::email_backend::contacts::carddav::{self, CardDavAccount, SyncSummary};
+use crate::email_backend::contacts::vcard::{parse_vcards, write_vcard, VCard, Version};
+
+/// Saves every address on a card as a contact. Fields the card leaves empty keep what
+/// enrichment or an earlier sync found.
+pub async fn save_card(pool: &SqlitePool, card: &VCard, account_email: Option<&str>) -> Result<(), String> {
+    for address in &card.emails {
+        sqlx::query(
+            "INSERT INTO senders (address, name, avatar_url, company, job_title, website_url, is_contact, account_email, last_synced_at)
+             VALUES (?, ?, ?, ?, ?, ?, 1, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(address) DO UPDATE SET
+                name = COALESCE(excluded.name, senders.name),
+                avatar_url = COALESCE(excluded.avatar_url, senders.avatar_url),
+                company = COALESCE(excluded.company, senders.company),
+                job_title = COALESCE(excluded.job_title, senders.job_title),
+                website_url = COALESCE(excluded.website_url, senders.website_url),
+                is_contact = 1,
+                account_email = COALESCE(excluded.account_email, senders.account_email),
+                last_synced_at = CURRENT_TIMESTAMP,
+                updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(address)
+        .bind(&card.name)
+        .bind(&card.photo_url)
+        .bind(&card.organization)
+        .bind(&card.title)
+        .bind(&card.url)
+        .bind(account_email)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Every contact as a card of its own.
+pub async fn contact_cards(pool: &SqlitePool) -> Result<Vec<VCard>, String> {
+    #[allow(clippy::type_complexity)]
+    let rows: Vec<(String, Option<String>, Option<String>, Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT address, name, company, job_title, avatar_url, website_url FROM senders WHERE is_contact = 1 ORDER BY COALESCE(name, address)"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter()
+        .map(|(address, name, organization, title, photo_url, url)| VCard {
+            uid: Some(format!("{:x}", md5::compute(address.as_bytes()))),
+            name,
+            emails: vec![address],
+            organization,
+            title,
+            photo_url,
+            url,
+        })
+        .collect())
+}
+
+/// Imports the contacts in a .vcf file and returns how many addresses were saved.
+#[tauri::command]
+pub async fn import_vcards<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, path: String) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let cards = parse_vcards(&text);
+    for card in &cards {
+        save_card(&pool, card, None).await?;
+    }
+    Ok(cards.iter().map(|card| card.emails.len()).sum())
+}
+
+/// Writes all contacts to a .vcf file in vCard 3.0 or 4.0 and returns how many were written.
+#[tauri::command]
+pub async fn export_vcards<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, path: String, version: Option<Version>) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let cards = contact_cards(&pool).await?;
+    let text: String = cards.iter().map(|card| write_vcard(card, version.unwrap_or_default())).collect();
+    std::fs::write(&path, text).map_err(|e| e.to_string())?;
+    Ok(cards.len())
+}
+
+#[tauri::command]
+pub async fn get_carddav_accounts<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<CardDavAccount>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT account_id, url, username, last_synced_at, last_error FROM carddav_accounts")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sets the CardDAV server of an account; an empty URL turns the sync off and forgets its cards.
+#[tauri::command]
+pub async fn set_carddav_account<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, url: String, username: Option<String>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let url = url.trim();
+    if url.is_empty() {
+        sqlx::query("DELETE FROM carddav_cards WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        sqlx::query("DELETE FROM carddav_accounts WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    carddav::check_secure(&url)?;
+    sqlx::query(
+        "INSERT INTO carddav_accounts (account_id, url, username) VALUES (?, ?, ?)
+         ON CONFLICT (account_id) DO UPDATE SET url = excluded.url, username = excluded.username, last_error = NULL"
+    )
+    .bind(account_id)
+    .bind(url.as_str())
+    .bind(username.filter(|u| !u.trim().is_empty()))
+    .execute(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn sync_carddav<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<SyncSummary, String> {
+    carddav::sync_account(&app_handle.state::<AppContext>(), account_id).await
+}
@@ -0,0 +1,3 @@
+pub mod carddav;
+pub mod commands;
+pub mod vcard;
@@ -0,0 +1,217 @@
+//! Reading and writing vCard 3.0 (RFC 2426) and 4.0 (RFC 6350) for the contacts in `senders`.
+
+use serde::{Deserialize, Serialize};
+use crate::email_backend::calendar::ical::{escape, fold, parse_property, unescape, unfold};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Version {
+    #[default]
+    #[serde(rename = "3.0")]
+    V3,
+    #[serde(rename = "4.0")]
+    V4,
+}
+
+/// The parts of a vCard the app keeps. A card can hold several addresses, each of them becomes
+/// its own contact.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VCard {
+    pub uid: Option<String>,
+    pub name: Option<String>,
+    pub emails: Vec<String>,
+    pub organization: Option<String>,
+    pub title: Option<String>,
+    pub photo_url: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Splits a structured value like N or ORG at the semicolons that are not escaped.
+fn components(value: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                parts.last_mut().unwrap().push(c);
+                if let Some(next) = chars.next() {
+                    parts.last_mut().unwrap().push(next);
+                }
+            }
+            ';' => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts.iter().map(|part| unescape(part).trim().to_string()).collect()
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+/// Reads every card in a .vcf file. Cards without an email address are skipped.
+pub fn parse_vcards(text: &str) -> Vec<VCard> {
+    let mut cards = Vec::new();
+    let mut current: Option<VCard> = None;
+    let mut structured_name: Option<String> = None;
+
+    for property in unfold(text).iter().filter_map(|line| parse_property(line)) {
+        // Apple and Google prefix properties with a group, as in `item1.EMAIL`
+        let name = property.name.rsplit('.').next().unwrap_or_default();
+        let value = property.value.trim();
+        match (name, current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VCARD") => {
+                current = Some(VCard::default());
+                structured_name = None;
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VCARD") => {
+                let mut card = current.take().unwrap_or_default();
+                if card.name.is_none() {
+                    card.name = structured_name.take();
+                }
+                if !card.emails.is_empty() {
+                    cards.push(card);
+                }
+            }
+            ("UID", Some(card)) => card.uid = non_empty(value.to_string()),
+            ("FN", Some(card)) => card.name = non_empty(unescape(value).trim().to_string()),
+            ("N", Some(_)) => {
+                // Family; Given; Additional; Prefix; Suffix
+                let parts = components(value);
+                let ordered = [parts.get(3), parts.get(1), parts.get(2), parts.first(), parts.get(4)];
+                let joined: Vec<&str> = ordered.iter().flatten().map(|p| p.as_str()).filter(|p| !p.is_empty()).collect();
+                structured_name = non_empty(joined.join(" "));
+            }
+            ("EMAIL", Some(card)) => {
+                let address = value.trim_start_matches("mailto:").to_lowercase();
+                if address.contains('@') && !card.emails.contains(&address) {
+                    card.emails.push(address);
+                }
+            }
+            ("ORG", Some(card)) => card.organization = components(value).into_iter().next().and_then(non_empty),
+            ("TITLE", Some(card)) => card.title = non_empty(unescape(value).trim().to_string()),
+            ("URL", Some(card)) => card.url = non_empty(unescape(value).trim().to_string()),
+            // Inline photos are left out, only links fit in the contact's avatar
+            ("PHOTO", Some(card)) if value.starts_with("http://") || value.starts_with("https://") => {
+                card.photo_url = Some(value.to_string());
+            }
+            _ => {}
+        }
+    }
+    cards
+}
+
+/// Writes a card in the given version, with CRLF line endings and folded lines.
+pub fn write_vcard(card: &VCard, version: Version) -> String {
+    let full_name = card.name.clone()
+        .or_else(|| card.emails.first().cloned())
+        .unwrap_or_default();
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        match version {
+            Version::V3 => "VERSION:3.0".to_string(),
+            Version::V4 => "VERSION:4.0".to_string(),
+        },
+    ];
+    if let Some(uid) = &card.uid {
+        lines.push(format!("UID:{}", uid));
+    }
+    lines.push(format!("FN:{}", escape(&full_name)));
+    if version == Version::V3 || card.name.is_some() {
+        // N is required in 3.0; the last word is taken as the family name
+        let (given, family) = match card.name.as_deref().and_then(|n| n.rsplit_once(' ')) {
+            Some((given, family)) => (given, family),
+            None => ("", card.name.as_deref().unwrap_or_default()),
+        };
+        lines.push(format!("N:{};{};;;", escape(family), escape(given)));
+    }
+    for email in &card.emails {
+        lines.push(match version {
+            Version::V3 => format!("EMAIL;TYPE=INTERNET:{}", email),
+            Version::V4 => format!("EMAIL:{}", email),
+        });
+    }
+    if let Some(organization) = &card.organization {
+        lines.push(format!("ORG:{}", escape(organization)));
+    }
+    if let Some(title) = &card.title {
+        lines.push(format!("TITLE:{}", escape(title)));
+    }
+    if let Some(url) = &card.url {
+        lines.push(format!("URL:{}", url));
+    }
+    if let Some(photo) = &card.photo_url {
+        lines.push(match version {
+            Version::V3 => format!("PHOTO;VALUE=uri:{}", photo),
+            Version::V4 => format!("PHOTO:{}", photo),
+        });
+    }
+    lines.push("END:VCARD".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_v3_and_v4() {
+        let text = "BEGIN:VCARD\r\n\
+            VERSION:3.0\r\n\
+            N:Lovelace;Ada;;Countess;\r\n\
+            item1.EMAIL;TYPE=INTERNET,WORK:Ada@Example.com\r\n\
+            EMAIL;INTERNET:ada@home.example\r\n\
+            ORG:Analytical Engines\\, Ltd;Research\r\n\
+            PHOTO;ENCODING=b;TYPE=JPEG:/9j/4AAQSkZJRg\r\n\
+            END:VCARD\r\n\
+            BEGIN:VCARD\r\n\
+            VERSION:4.0\r\n\
+            UID:urn:uuid:42\r\n\
+            FN:Grace Hopper\r\n\
+            EMAIL;PREF=1:grace@example.com\r\n\
+            TITLE:Rear Admiral\r\n\
+            PHOTO:https://example.com/grace.jpg\r\n\
+            END:VCARD\r\n\
+            BEGIN:VCARD\r\n\
+            VERSION:4.0\r\n\
+            FN:No Address\r\n\
+            END:VCARD\r\n";
+
+        let cards = parse_vcards(text);
+        assert_eq!(cards, vec![
+            VCard {
+                name: Some("Countess Ada Lovelace".to_string()),
+                emails: vec!["ada@example.com".to_string(), "ada@home.example".to_string()],
+                organization: Some("Analytical Engines, Ltd".to_string()),
+                ..VCard::default()
+            },
+            VCard {
+                uid: Some("urn:uuid:42".to_string()),
+                name: Some("Grace Hopper".to_string()),
+                emails: vec!["grace@example.com".to_string()],
+                title: Some("Rear Admiral".to_string()),
+                photo_url: Some("https://example.com/grace.jpg".to_string()),
+                ..VCard::default()
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_write_round_trips() {
+        let card = VCard {
+            uid: Some("abc".to_string()),
+            name: Some("Grace Brewster Hopper".to_string()),
+            emails: vec!["grace@example.com".to_string()],
+            organization: Some("Navy; Reserve".to_string()),
+            title: None,
+            photo_url: Some("https://example.com/grace.jpg".to_string()),
+            url: Some("https://example.com".to_string()),
+        };
+        for version in [Version::V3, Version::V4] {
+            let text = write_vcard(&card, version);
+            assert!(text.contains("N:Hopper;Grace Brewster;;;\r\n"));
+            assert_eq!(parse_vcards(&text), vec![card.clone()]);
+        }
+        assert!(write_vcard(&card, Version::V3).contains("EMAIL;TYPE=INTERNET:grace@example.com"));
+    }
+}
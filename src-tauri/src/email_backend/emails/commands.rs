@@ -1,22 +1,31 @@
+use crate::context::AppContext;
 use crate::email_backend::emails::events::EmailEvent;
 use tauri::{Manager, Emitter};
-use log::info;
 use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
 use crate::email_backend::accounts::manager::AccountManager;
 use crate::email_backend::sync::SyncEngine;
 use crate::utils::attachments::{save_attachment_data, read_attachment_data};
-use email::backend::BackendBuilder;
-use email::smtp::SmtpContextBuilder;
-use email::message::send::SendMessage;
+use crate::email_backend::emails::transport::Transport;
 use email::envelope::Id;
 use email::flag::add::AddFlags;
+use email::flag::remove::RemoveFlags;
 use email::flag::Flag;
 use email::flag::Flags;
 use email::message::add::AddMessage;
+use email::message::Message;
+use email::message::remove::RemoveMessages;
+use email::folder::expunge::ExpungeFolder;
+use crate::email_backend::export::commands::cached_source;
+use crate::email_backend::import::commands::local_folder_id;
+use crate::email_backend::rules::types::parse_headers;
+use crate::email_backend::subscriptions::unsubscribe::save_list_headers;
+use crate::email_backend::calendar::invites::{email_invite, save_invites, CalendarEvent};
+use crate::utils::attachments::remove_orphaned_attachments;
 use mail_builder::MessageBuilder;
-use imap_client::imap_next::imap_types::sequence::Sequence;
-use imap_client::imap_next::imap_types::error::ValidationError;
+
+/// Folder that `move_to_local_archive` uses when no name is given.
+pub const LOCAL_ARCHIVE_NAME: &str = "Local Archive";
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Email {
@@ -40,12 +49,20 @@ pub struct Email {
     pub has_attachments: bool,
     pub is_reply: bool,
     pub is_forward: bool,
+    /// Where the message sorts in a list: when its snooze ended if it came back, else its date.
+    #[sqlx(default)]
+    pub sort_date: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct EmailContent {
     pub body_text: Option<String>,
     pub body_html: Option<String>,
+    /// Set for mailing list messages that say how to unsubscribe.
+    pub list_unsubscribe: Option<String>,
+    /// The meeting invitation, cancellation or reply the message carries.
+    #[sqlx(skip)]
+    pub invite: Option<CalendarEvent>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -57,6 +74,9 @@ pub struct Folder {
     pub role: Option<String>,
     pub unread_count: i32,
     pub total_count: i32,
+    /// Kept only in this app, never synced with the server.
+    pub is_local: bool,
+    pub notifications_enabled: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -87,7 +107,7 @@ pub async fn refresh_folder<R: tauri::Runtime>(
     account_id: i64,
     folder_id: i64,
 ) -> Result<(), String> {
-    SyncEngine::refresh_folder(&app_handle, account_id, folder_id).await
+    app_handle.state::<SyncEngine>().refresh_folder(account_id, folder_id).await
 }
 
 #[tauri::command]
@@ -107,8 +127,8 @@ pub async fn get_emails<R: tauri::Runtime>(
             SELECT 
                 e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, 
                 e.in_reply_to, e.references_header, e.subject, e.normalized_subject, 
-                e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, 
-                e.snippet, e.summary, e.has_attachments, f.role as folder_role,
+                e.sender_name, e.sender_address, e.recipient_to, e.date, COALESCE(e.resurfaced_at, e.date) as sort_date, e.flags, 
+                e.snippet, e.summary, e.has_attachments, f.role as folder_role, f.is_local,
                 ROW_NUMBER() OVER (
                     PARTITION BY e.account_id, e.message_id 
                     ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC
@@ -119,9 +139,9 @@ pub async fn get_emails<R: tauri::Runtime>(
             SELECT 
                 -d.id as id, d.account_id, -1 as folder_id, 'local-draft-' || d.id as remote_id, NULL as message_id, NULL as thread_id, 
                 NULL as in_reply_to, NULL as references_header, d.subject, LOWER(COALESCE(d.subject, '')) as normalized_subject, 
-                NULL as sender_name, COALESCE(d.to_address, '(No Recipient)') as sender_address, d.to_address as recipient_to, strftime('%Y-%m-%dT%H:%M:%SZ', d.updated_at) as date, '[]' as flags, 
+                NULL as sender_name, COALESCE(d.to_address, '(No Recipient)') as sender_address, d.to_address as recipient_to, strftime('%Y-%m-%dT%H:%M:%SZ', d.updated_at) as date, strftime('%Y-%m-%dT%H:%M:%SZ', d.updated_at) as sort_date, '[]' as flags, 
                 d.body_html as snippet, NULL as summary, EXISTS(SELECT 1 FROM attachments WHERE draft_id = d.id) as has_attachments, 
-                'drafts' as folder_role,
+                'drafts' as folder_role, 0 as is_local,
                 1 as msg_rn
             FROM drafts d
          ),
@@ -129,7 +149,7 @@ pub async fn get_emails<R: tauri::Runtime>(
             SELECT *,
             ROW_NUMBER() OVER (
                 PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id) 
-                ORDER BY date DESC, id DESC
+                ORDER BY sort_date DESC, id DESC
             ) as thread_rn,
             COUNT(*) OVER (
                 PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id)
@@ -137,7 +157,7 @@ pub async fn get_emails<R: tauri::Runtime>(
             FROM unique_messages
             WHERE msg_rn = 1
          )
-         SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, e.t_count as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments,
+         SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, e.t_count as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments, e.sort_date,
          (e.subject LIKE 'Re:%' OR e.subject LIKE 're:%' OR e.in_reply_to IS NOT NULL) as is_reply,
          (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward
          FROM latest_threads e 
@@ -152,6 +172,11 @@ pub async fn get_emails<R: tauri::Runtime>(
         query_builder.push_bind(aid);
     }
 
+    // Snoozed threads stay out of every other view until they come back
+    if view.as_deref() != Some("snoozed") {
+        query_builder.push(" AND e.flags NOT LIKE '%$SnoozedUntil-%'");
+    }
+
     if let Some(v) = view {
         match v.as_str() {
             "primary" => {
@@ -172,8 +197,21 @@ pub async fn get_emails<R: tauri::Runtime>(
             "archive" => {
                 query_builder.push(" AND e.folder_role = 'archive'");
             }
+            "local" => {
+                query_builder.push(" AND e.is_local = 1");
+            }
+            // Starred mail from every folder but the bin and spam
+            "starred" => {
+                query_builder.push(" AND e.flags LIKE '%\"flagged\"%' AND COALESCE(e.folder_role, '') NOT IN ('trash', 'spam')");
+            }
+            "snoozed" => {
+                query_builder.push(" AND e.flags LIKE '%$SnoozedUntil-%'");
+            }
+            "digest" => {
+                query_builder.push(" AND e.folder_role = 'digest'");
+            }
             "others" => {
-                query_builder.push(" AND (e.folder_role IS NULL OR e.folder_role = '' OR e.folder_role NOT IN ('inbox', 'spam', 'sent', 'drafts', 'trash', 'archive'))");
+                query_builder.push(" AND (e.folder_role IS NULL OR e.folder_role = '' OR e.folder_role NOT IN ('inbox', 'spam', 'sent', 'drafts', 'trash', 'archive', 'digest'))");
             }
             _ => {}
         };
@@ -194,16 +232,16 @@ pub async fn get_emails<R: tauri::Runtime>(
     // Keyset Pagination
     if let (Some(date), Some(id)) = (before_date, before_id) {
         if !has_where { query_builder.push(" WHERE "); } else { query_builder.push(" AND "); }
-        query_builder.push(" (e.date < ");
+        query_builder.push(" (e.sort_date < ");
         query_builder.push_bind(date.clone());
-        query_builder.push(" OR (e.date = ");
+        query_builder.push(" OR (e.sort_date = ");
         query_builder.push_bind(date);
         query_builder.push(" AND e.id < ");
         query_builder.push_bind(id);
         query_builder.push("))");
     }
 
-    query_builder.push(" ORDER BY e.date DESC, e.id DESC LIMIT ");
+    query_builder.push(" ORDER BY e.sort_date DESC, e.id DESC LIMIT ");
     query_builder.push_bind(limit.unwrap_or(100) as i64);
 
     let emails = query_builder
@@ -345,15 +383,16 @@ pub async fn get_thread_emails<R: tauri::Runtime>(
 pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<EmailContent, String> {
     let pool = app_handle.state::<SqlitePool>().inner().clone();
     
-    let cached_info: Option<(Option<String>, Option<String>, Option<String>, bool, i64)> = sqlx::query_as(
-        "SELECT body_text, body_html, summary, has_attachments, account_id FROM emails WHERE id = ?"
+    #[allow(clippy::type_complexity)]
+    let cached_info: Option<(Option<String>, Option<String>, Option<String>, bool, i64, Option<String>)> = sqlx::query_as(
+        "SELECT body_text, body_html, summary, has_attachments, account_id, list_unsubscribe FROM emails WHERE id = ?"
     )
     .bind(email_id)
     .fetch_optional(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    if let Some((body_text, body_html, summary, has_attachments, _account_id)) = cached_info {
+    if let Some((body_text, body_html, summary, has_attachments, _account_id, list_unsubscribe)) = cached_info {
         if body_text.is_some() || body_html.is_some() {
             // Check if we have attachments if we expect them
              let attachment_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE email_id = ?")
@@ -364,59 +403,17 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
 
              if !has_attachments || attachment_count > 0 {
                 // Content exists, check if we need to trigger summarization
-                if summary.is_none() && body_text.is_some() {
-                    let text = body_text.clone().unwrap();
-                    let handle = app_handle.clone();
-                    let pool_clone = pool.clone();
-                    
-                    tauri::async_runtime::spawn(async move {
-                        let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-                            .fetch_one(&pool_clone)
-                            .await
-                            .unwrap_or(("false".to_string(),));
-                        
-                        let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-                            .fetch_one(&pool_clone)
-                            .await
-                            .unwrap_or(("false".to_string(),));
-
-                        if ai_enabled.0 == "true" && ai_summarization_enabled.0 == "true" {
-                            // Check folder role
-                            let role: Option<String> = sqlx::query_scalar("SELECT f.role FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?")
-                                .bind(email_id)
-                                .fetch_one(&pool_clone)
-                                .await
-                                .unwrap_or(None);
-
-                            if role.as_deref() != Some("spam") && role.as_deref() != Some("trash") {
-                                if let Ok(s) = crate::email_backend::llm::summarization::summarize_email_with_ai(&handle, email_id, &text, false).await {
-                                    let sender_address: Option<String> = sqlx::query_scalar("SELECT sender_address FROM emails WHERE id = ?")
-                                        .bind(email_id)
-                                        .fetch_one(&pool_clone)
-                                        .await
-                                        .ok();
-
-                                    let _ = sqlx::query("UPDATE emails SET summary = ? WHERE id = ?")
-                                        .bind(&s)
-                                        .bind(email_id)
-                                        .execute(&pool_clone)
-                                        .await;
-                                    let _ = handle.emit("emails-updated", EmailEvent::Updated {
-                                        id: email_id,
-                                        address: sender_address,
-                                        flags: None,
-                                        summary: Some(s),
-                                        thread_count: None,
-                                    });
-                                }
-                            }
-                        }
-                    });
+                if summary.is_none() {
+                    if let Some(text) = body_text.clone() {
+                        summarize_in_background(app_handle.state::<AppContext>().inner().clone(), email_id, text);
+                    }
                 }
 
                 return Ok(EmailContent {
                     body_text,
                     body_html,
+                    list_unsubscribe,
+                    invite: email_invite(&pool, email_id).await?,
                 });
             }
         }
@@ -432,43 +429,8 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
 
     let (account_id, remote_id, _folder_path) = email_info;
 
-    // Get folder role to check for spam/trash
-    let folder_role: Option<String> = sqlx::query_scalar("SELECT role FROM folders WHERE path = ? AND account_id = ?")
-        .bind(&_folder_path)
-        .bind(account_id)
-        .fetch_one(&pool)
-        .await
-        .unwrap_or(None);
-
-    let engine = app_handle.state::<SyncEngine<R>>();
-    let context = engine.get_context(account_id).await?;
-
-    let mut client = context.client().await;
-    
-    let id = Id::single(remote_id);
-    use imap_client::imap_next::imap_types::fetch::MessageDataItemName;
-    use imap_client::imap_next::imap_types::fetch::MacroOrMessageDataItemNames;
-    let fetch_items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
-        MessageDataItemName::BodyExt {
-            section: None,
-            partial: None,
-            peek: true,
-        }
-    ]);
-    
-    // Select the mailbox first
-    client.examine_mailbox(&_folder_path).await.map_err(|e| e.to_string())?;
-
-    use std::num::NonZeroU32;
-    let uids: imap_client::imap_next::imap_types::sequence::SequenceSet = id.iter()
-        .filter_map(|s| s.parse::<u32>().ok())
-        .filter_map(|n| NonZeroU32::new(n))
-        .map(Sequence::from)
-        .collect::<Vec<_>>()
-        .try_into()
-        .map_err(|e: ValidationError| e.to_string())?;
-
-    let messages = client.fetch_messages_with_items(uids, fetch_items).await.map_err(|e| e.to_string())?;
+    let engine = app_handle.state::<SyncEngine>();
+    let messages = engine.fetch_message(account_id, &_folder_path, &remote_id).await?;
     let message = messages.first().ok_or("Email not found on server")?;
 
     let parsed = message.parsed().map_err(|e: email::Error| e.to_string())?;
@@ -477,43 +439,7 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
 
     // Trigger AI Summarization in background if enabled
     if let Some(text) = body_text.clone() {
-        let handle = app_handle.clone();
-        let pool_clone = pool.clone();
-        let folder_role_clone = folder_role.clone();
-        tauri::async_runtime::spawn(async move {
-            let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-                .fetch_one(&pool_clone)
-                .await
-                .unwrap_or(("false".to_string(),));
-            
-            let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-                .fetch_one(&pool_clone)
-                .await
-                .unwrap_or(("false".to_string(),));
-
-            if ai_enabled.0 == "true" && ai_summarization_enabled.0 == "true" && folder_role_clone.as_deref() != Some("spam") && folder_role_clone.as_deref() != Some("trash") {
-                if let Ok(s) = crate::email_backend::llm::summarization::summarize_email_with_ai(&handle, email_id, &text, false).await {
-                    let sender_address: Option<String> = sqlx::query_scalar("SELECT sender_address FROM emails WHERE id = ?")
-                        .bind(email_id)
-                        .fetch_one(&pool_clone)
-                        .await
-                        .ok();
-
-                    let _ = sqlx::query("UPDATE emails SET summary = ? WHERE id = ?")
-                        .bind(&s)
-                        .bind(email_id)
-                        .execute(&pool_clone)
-                        .await;
-                    let _ = handle.emit("emails-updated", EmailEvent::Updated {
-                        id: email_id,
-                        address: sender_address,
-                        flags: None,
-                        summary: Some(s),
-                        thread_count: None,
-                    });
-                }
-            }
-        });
+        summarize_in_background(app_handle.state::<AppContext>().inner().clone(), email_id, text);
     }
 
     let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
@@ -562,12 +488,109 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
 
     tx.commit().await.map_err(|e| e.to_string())?;
 
+    let raw = message.raw().unwrap_or_default();
+    let headers = parse_headers(raw);
+    save_list_headers(&pool, email_id, &headers).await?;
+    save_invites(&pool, email_id, raw).await?;
+    let list_unsubscribe = headers.into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("List-Unsubscribe"))
+        .map(|(_, value)| value);
+
     Ok(EmailContent {
         body_text,
         body_html,
+        list_unsubscribe,
+        invite: email_invite(&pool, email_id).await?,
     })
 }
 
+/// Stores the bodies and attachment files of a message, so it can be read without the server.
+pub(crate) async fn save_message_content<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    email_id: i64,
+    message: &Message<'_>,
+) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let parsed = message.parsed().map_err(|e| e.to_string())?;
+    let body_text: Option<String> = parsed.body_text(0).map(|b| b.to_string());
+    let body_html: Option<String> = parsed.body_html(0).map(|b| b.to_string());
+    let attachments = message.attachments().unwrap_or_default();
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query("UPDATE emails SET body_text = ?, body_html = ?, has_attachments = ? WHERE id = ?")
+        .bind(&body_text)
+        .bind(&body_html)
+        .bind(!attachments.is_empty())
+        .bind(email_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM attachments WHERE email_id = ?")
+        .bind(email_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for att in attachments {
+        let hash = save_attachment_data(app_handle, &att.body)?;
+        sqlx::query(
+            "INSERT INTO attachments (email_id, filename, mime_type, size, file_hash)
+             VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(email_id)
+        .bind(&att.filename)
+        .bind(&att.mime)
+        .bind(att.body.len() as i64)
+        .bind(hash)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Summarizes an opened message in the background when AI summaries are on, leaving spam and trash alone.
+fn summarize_in_background(app: AppContext, email_id: i64, text: String) {
+    tauri::async_runtime::spawn(async move {
+        if !app.settings().ai_summaries_enabled().await {
+            return;
+        }
+
+        let role: Option<String> = sqlx::query_scalar("SELECT f.role FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?")
+            .bind(email_id)
+            .fetch_one(app.pool())
+            .await
+            .unwrap_or(None);
+        if matches!(role.as_deref(), Some("spam") | Some("trash")) {
+            return;
+        }
+
+        if let Ok(s) = crate::email_backend::llm::summarization::summarize_email_with_ai(&app, email_id, &text, false).await {
+            let sender_address: Option<String> = sqlx::query_scalar("SELECT sender_address FROM emails WHERE id = ?")
+                .bind(email_id)
+                .fetch_one(app.pool())
+                .await
+                .ok();
+
+            let _ = sqlx::query("UPDATE emails SET summary = ? WHERE id = ?")
+                .bind(&s)
+                .bind(email_id)
+                .execute(app.pool())
+                .await;
+            app.emit("emails-updated", EmailEvent::Updated {
+                id: email_id,
+                address: sender_address,
+                flags: None,
+                summary: Some(s),
+                thread_count: None,
+            });
+        }
+    });
+}
+
 #[tauri::command]
 pub async fn regenerate_summary<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<String, String> {
     let pool = app_handle.state::<SqlitePool>();
@@ -581,7 +604,7 @@ pub async fn regenerate_summary<R: tauri::Runtime>(app_handle: tauri::AppHandle<
     let text = body_text.ok_or_else(|| "No body text found for summarization".to_string())?;
 
     // Use force=true to bypass cache
-    let summary = crate::email_backend::llm::summarization::summarize_email_with_ai(&app_handle, email_id, &text, true).await?;
+    let summary = crate::email_backend::llm::summarization::summarize_email_with_ai(&app_handle.state::<AppContext>(), email_id, &text, true).await?;
 
     sqlx::query("UPDATE emails SET summary = ? WHERE id = ?")
         .bind(&summary)
@@ -746,84 +769,187 @@ pub async fn delete_draft<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, id
 
 #[tauri::command]
 pub async fn mark_as_read<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
-    let pool = app_handle.state::<SqlitePool>();
+    let engine = app_handle.state::<SyncEngine>();
     let mut actual_updated_ids = Vec::new();
     let mut final_flags = String::new();
-    
+
     for &email_id in &email_ids {
-        let email_info: Option<(i64, String, String, String, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, f.path, e.flags, e.sender_address FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
-        )
-        .bind(email_id)
-        .fetch_optional(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
+        if let Some(flags) = update_email_flags(&engine, email_id, &[Flag::Seen], true).await? {
+            final_flags = flags;
+            actual_updated_ids.push(email_id);
+        }
+    }
 
-        let (account_id, remote_id, folder_path, current_flags, _sender_address) = match email_info {
-            Some(info) => info,
-            None => continue,
-        };
+    if !actual_updated_ids.is_empty() {
+        let _ = app_handle.emit("emails-updated", EmailEvent::UpdatedBulk {
+            ids: actual_updated_ids,
+            flags: Some(final_flags),
+        });
+    }
 
-        if current_flags.contains("\"seen\"") || current_flags.contains("seen") {
-            continue;
-        }
+    Ok(())
+}
+
+/// Adds or removes flags on the server and in the cached flags, keeping unread counts in step.
+/// Gmail keeps flags per message rather than per label, so there the message's copies in other
+/// folders change along; a star set here is the same as starring it in Gmail.
+/// Returns the new flags, or `None` when the message already had them or had none to remove.
+pub(crate) async fn update_email_flags(engine: &SyncEngine, email_id: i64, flags: &[Flag], add: bool) -> Result<Option<String>, String> {
+    let pool = engine.app().pool();
+    #[allow(clippy::type_complexity)]
+    let email_info: Option<(i64, String, Option<String>, String, String, String, bool)> = sqlx::query_as(
+        "SELECT e.account_id, a.account_type, e.message_id, e.remote_id, f.path, e.flags, f.is_local
+         FROM emails e JOIN folders f ON e.folder_id = f.id JOIN accounts a ON e.account_id = a.id WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (account_id, account_type, message_id, remote_id, folder_path, current_flags, is_local) = match email_info {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+
+    let current: Vec<String> = serde_json::from_str(&current_flags).unwrap_or_default();
+    let changed: Vec<Flag> = flags.iter()
+        .filter(|flag| current.contains(&flag.to_string()) != add)
+        .cloned()
+        .collect();
+    if changed.is_empty() {
+        return Ok(None);
+    }
 
-        let engine = app_handle.state::<SyncEngine<R>>();
+    if !is_local {
         if let Ok(backend) = engine.get_backend(account_id).await {
             let id = Id::single(remote_id);
-            let _ = backend.add_flag(&folder_path, &id, Flag::Seen).await;
+            let server_flags = Flags::from_iter(changed.clone());
+            // The server goes first, a change it refuses must not show up locally either
+            if add {
+                backend.add_flags(&folder_path, &id, &server_flags).await
+            } else {
+                backend.remove_flags(&folder_path, &id, &server_flags).await
+            }
+            .map_err(|e| e.to_string())?;
         }
+    }
 
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut copies = vec![(email_id, current_flags)];
+    if let (true, Some(message_id)) = (account_type == "google", message_id) {
+        let others: Vec<(i64, String)> = sqlx::query_as("SELECT id, flags FROM emails WHERE account_id = ? AND message_id = ? AND id != ?")
+            .bind(account_id)
+            .bind(message_id)
+            .bind(email_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        copies.extend(others);
+    }
 
-        let mut flags: Vec<String> = serde_json::from_str(&current_flags).unwrap_or_default();
-        if !flags.contains(&"seen".to_string()) {
-            flags.push("seen".to_string());
+    let names: Vec<String> = changed.iter().map(|flag| flag.to_string()).collect();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut new_flags = String::new();
+
+    for (copy_id, copy_flags) in copies {
+        let mut copy: Vec<String> = serde_json::from_str(&copy_flags).unwrap_or_default();
+        let was_seen = copy.iter().any(|f| f == "seen");
+        copy.retain(|f| !names.contains(f));
+        if add {
+            copy.extend(names.iter().cloned());
         }
-
-        final_flags = serde_json::to_string(&flags).unwrap_or_default();
+        let is_seen = copy.iter().any(|f| f == "seen");
+        let updated = serde_json::to_string(&copy).unwrap_or_default();
 
         sqlx::query("UPDATE emails SET flags = ? WHERE id = ?")
-            .bind(&final_flags)
-            .bind(email_id)
+            .bind(&updated)
+            .bind(copy_id)
             .execute(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
 
-        sqlx::query("UPDATE folders SET unread_count = MAX(0, unread_count - 1) WHERE id = (SELECT folder_id FROM emails WHERE id = ?)")
-            .bind(email_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+        if was_seen != is_seen {
+            sqlx::query("UPDATE folders SET unread_count = MAX(0, unread_count + ?) WHERE id = (SELECT folder_id FROM emails WHERE id = ?)")
+                .bind(if is_seen { -1 } else { 1 })
+                .bind(copy_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
 
-        tx.commit().await.map_err(|e| e.to_string())?;
-        actual_updated_ids.push(email_id);
+        if copy_id == email_id {
+            new_flags = updated;
+        }
     }
 
-    if !actual_updated_ids.is_empty() {
-        let _ = app_handle.emit("emails-updated", EmailEvent::UpdatedBulk {
-            ids: actual_updated_ids,
-            flags: Some(final_flags),
-        });
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(Some(new_flags))
+}
+
+/// Parses flag names as the frontend shows them: `seen`, `flagged` and the like, or any keyword.
+/// Keywords must be IMAP atoms, so spaces, quotes and characters like `(` or `*` are refused.
+pub(crate) fn parse_flags(flags: &[String]) -> Result<Vec<Flag>, String> {
+    flags.iter()
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .map(|f| match Flag::from(f) {
+            Flag::Custom(keyword) if !is_keyword_atom(&keyword) => Err(format!("\"{}\" cannot be used as a label", keyword)),
+            flag => Ok(flag),
+        })
+        .collect()
+}
+
+fn is_keyword_atom(keyword: &str) -> bool {
+    keyword.chars().all(|c| c.is_ascii_graphic() && !matches!(c, '(' | ')' | '{' | '%' | '*' | '"' | '\\' | ']'))
+}
+
+async fn change_flags<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_ids: &[i64], flags: &[String], add: bool) -> Result<(), String> {
+    let engine = app_handle.state::<SyncEngine>();
+    let flags = parse_flags(flags)?;
+    if flags.is_empty() {
+        return Ok(());
     }
 
+    for &email_id in email_ids {
+        if let Some(new_flags) = update_email_flags(&engine, email_id, &flags, add).await? {
+            let _ = app_handle.emit("emails-updated", EmailEvent::Updated {
+                id: email_id,
+                address: None,
+                flags: Some(new_flags),
+                summary: None,
+                thread_count: None,
+            });
+        }
+    }
     Ok(())
 }
 
+/// Adds flags or keywords to messages, e.g. `flagged` to star them or `seen` to mark them read.
+#[tauri::command]
+pub async fn set_flags<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, flags: Vec<String>) -> Result<(), String> {
+    change_flags(&app_handle, &email_ids, &flags, true).await
+}
+
+/// Removes flags or keywords, e.g. `seen` to mark messages unread.
+#[tauri::command]
+pub async fn remove_flags<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, flags: Vec<String>) -> Result<(), String> {
+    change_flags(&app_handle, &email_ids, &flags, false).await
+}
+
 #[tauri::command]
 pub async fn move_to_inbox<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
     let pool = app_handle.state::<SqlitePool>();
+    let mut folders_to_refresh = std::collections::HashSet::new();
 
     for &email_id in &email_ids {
-        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+        let email_info: Option<(i64, i64, bool)> = sqlx::query_as(
+            "SELECT e.account_id, e.folder_id, f.is_local FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
         )
         .bind(email_id)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, source_folder_id, source_folder_path) = match email_info {
+        let (account_id, source_folder_id, source_is_local) = match email_info {
             Some(info) => info,
             None => continue,
         };
@@ -846,47 +972,18 @@ pub async fn move_to_inbox<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, e
             continue;
         }
 
-        // Perform move on server
-        let engine = app_handle.state::<SyncEngine<R>>();
-        if let Ok(backend) = engine.get_backend(account_id).await {
-            let id = email::envelope::Id::single(remote_id);
-            use email::message::r#move::MoveMessages;
-            let _ = backend.move_messages(&source_folder_path, &inbox_folder_path, &id).await.map_err(|e| e.to_string())?;
+        if source_is_local {
+            upload_local_email(&app_handle.state::<SyncEngine>(), email_id, &inbox_folder_path).await?;
+            folders_to_refresh.insert((account_id, inbox_folder_id));
+            continue;
         }
 
-        // Update local DB
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
-
-        // Check if seen to update counts
-        let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?")
-            .bind(email_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE emails SET folder_id = ? WHERE id = ?")
-            .bind(inbox_folder_id)
-            .bind(email_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        // Update counts
-        sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(source_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(inbox_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+        move_email(&app_handle.state::<SyncEngine>(), email_id, inbox_folder_id).await?;
+    }
 
-        tx.commit().await.map_err(|e| e.to_string())?;
+    // Messages uploaded from a local folder get their server ids from a sync
+    for (account_id, folder_id) in folders_to_refresh {
+        app_handle.state::<SyncEngine>().refresh_folder(account_id, folder_id).await?;
     }
 
     if !email_ids.is_empty() {
@@ -901,78 +998,39 @@ pub async fn archive_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>,
     let pool = app_handle.state::<SqlitePool>();
 
     for &email_id in &email_ids {
-        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+        let email_info: Option<(i64, i64, bool)> = sqlx::query_as(
+            "SELECT e.account_id, e.folder_id, f.is_local FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
         )
         .bind(email_id)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, source_folder_id, source_folder_path) = match email_info {
+        let (account_id, source_folder_id, source_is_local) = match email_info {
             Some(info) => info,
             None => continue,
         };
 
         // Find archive folder for this account
-        let archive_folder_info: Option<(i64, String)> = sqlx::query_as(
-            "SELECT id, path FROM folders WHERE account_id = ? AND role = 'archive'"
+        let archive_folder_id: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM folders WHERE account_id = ? AND role = 'archive'"
         )
         .bind(account_id)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (archive_folder_id, archive_folder_path) = match archive_folder_info {
-            Some(info) => info,
+        let archive_folder_id = match archive_folder_id {
+            Some(id) => id,
             None => return Err(format!("Archive folder not found for account {}", account_id)),
         };
         
-        if source_folder_id == archive_folder_id {
+        // A local folder already keeps the message off the server
+        if source_folder_id == archive_folder_id || source_is_local {
             continue;
         }
 
-        // Perform move on server
-        let engine = app_handle.state::<SyncEngine<R>>();
-        if let Ok(backend) = engine.get_backend(account_id).await {
-            let id = email::envelope::Id::single(remote_id);
-            use email::message::r#move::MoveMessages;
-            let _ = backend.move_messages(&source_folder_path, &archive_folder_path, &id).await.map_err(|e| e.to_string())?;
-        }
-
-        // Update local DB
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
-
-        // Check if seen to update counts
-        let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?")
-            .bind(email_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE emails SET folder_id = ? WHERE id = ?")
-            .bind(archive_folder_id)
-            .bind(email_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        // Update counts
-        sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(source_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(archive_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        tx.commit().await.map_err(|e| e.to_string())?;
+        move_email(&app_handle.state::<SyncEngine>(), email_id, archive_folder_id).await?;
     }
 
     if !email_ids.is_empty() {
@@ -985,17 +1043,18 @@ pub async fn archive_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>,
 #[tauri::command]
 pub async fn move_to_trash<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
     let pool = app_handle.state::<SqlitePool>();
+    let mut removed_hashes: Vec<String> = Vec::new();
 
     for &email_id in &email_ids {
-        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+        let email_info: Option<(i64, String, i64, String, bool)> = sqlx::query_as(
+            "SELECT e.account_id, e.remote_id, e.folder_id, f.path, f.is_local FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
         )
         .bind(email_id)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, source_folder_id, source_folder_path) = match email_info {
+        let (account_id, remote_id, source_folder_id, source_folder_path, source_is_local) = match email_info {
             Some(info) => info,
             None => continue,
         };
@@ -1020,8 +1079,20 @@ pub async fn move_to_trash<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, e
             continue;
         }
 
+        // There is no server copy to move, so trashing a local message deletes it
+        if source_is_local {
+            let hashes: Vec<String> = sqlx::query_scalar("SELECT file_hash FROM attachments WHERE email_id = ? AND file_hash IS NOT NULL")
+                .bind(email_id)
+                .fetch_all(&*pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            delete_local_email(&pool, email_id).await?;
+            removed_hashes.extend(hashes);
+            continue;
+        }
+
         // Perform move on server
-        let engine = app_handle.state::<SyncEngine<R>>();
+        let engine = app_handle.state::<SyncEngine>();
         if let Ok(backend) = engine.get_backend(account_id).await {
             let id = email::envelope::Id::single(remote_id);
             use email::message::r#move::MoveMessages;
@@ -1063,6 +1134,12 @@ pub async fn move_to_trash<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, e
         tx.commit().await.map_err(|e| e.to_string())?;
     }
 
+    if !removed_hashes.is_empty() {
+        if let Err(e) = remove_orphaned_attachments(&app_handle, &pool, &removed_hashes).await {
+            log::error!("Failed to clean up attachments of deleted local emails: {}", e);
+        }
+    }
+
     if !email_ids.is_empty() {
         let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: email_ids });
     }
@@ -1070,6 +1147,197 @@ pub async fn move_to_trash<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, e
     Ok(())
 }
 
+/// Removes a message kept in a local folder, keeping the folder's counts right.
+async fn delete_local_email(pool: &SqlitePool, email_id: i64) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        "UPDATE folders SET total_count = MAX(0, total_count - 1),
+            unread_count = MAX(0, unread_count - (SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?))
+         WHERE id = (SELECT folder_id FROM emails WHERE id = ?)"
+    )
+    .bind(email_id)
+    .bind(email_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM emails WHERE id = ?")
+        .bind(email_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Uploads a message from a local folder to a server folder and drops the local copy.
+async fn upload_local_email(engine: &SyncEngine, email_id: i64, folder_path: &str) -> Result<(), String> {
+    let pool = engine.app().pool();
+    let (account_id, flags): (i64, String) = sqlx::query_as("SELECT account_id, flags FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let raw = cached_source(pool, engine.app().data_dir(), email_id).await?;
+    let flags: Vec<String> = serde_json::from_str(&flags).unwrap_or_default();
+    let flags = Flags::from_iter(flags.iter().map(|f| Flag::from(f.as_str())));
+
+    let backend = engine.get_backend(account_id).await?;
+    backend.add_message_with_flags(folder_path, &raw, &flags).await.map_err(|e| e.to_string())?;
+
+    delete_local_email(pool, email_id).await
+}
+
+/// Keeps messages only on this device: their bodies and attachments are stored first,
+/// then they move to a local folder and are deleted from the server.
+#[tauri::command]
+pub async fn move_to_local_archive<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, folder_name: Option<String>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let folder_name = folder_name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| LOCAL_ARCHIVE_NAME.to_string());
+    let engine = app_handle.state::<SyncEngine>();
+    let mut moved_ids = Vec::new();
+    let mut expunge: std::collections::HashSet<(i64, String)> = std::collections::HashSet::new();
+
+    for &email_id in &email_ids {
+        let email_info: Option<(i64, String, i64, String, bool)> = sqlx::query_as(
+            "SELECT e.account_id, e.remote_id, e.folder_id, f.path, f.is_local FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+        )
+        .bind(email_id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let (account_id, remote_id, source_folder_id, source_folder_path, source_is_local) = match email_info {
+            Some(info) => info,
+            None => continue,
+        };
+        if source_is_local {
+            continue;
+        }
+
+        // Nothing is removed from the server unless the full message is stored here
+        let messages = engine.fetch_message(account_id, &source_folder_path, &remote_id).await?;
+        let message = messages.first().ok_or("Email not found on server")?;
+        let raw = message.raw().map_err(|e| e.to_string())?.to_vec();
+        save_message_content(&app_handle, email_id, message).await?;
+
+        let local_folder_id = local_folder_id(&pool, account_id, &folder_name).await?;
+        let local_remote_id = format!("local-{:x}", md5::compute(&raw));
+        let already_archived: Option<i64> = sqlx::query_scalar("SELECT id FROM emails WHERE folder_id = ? AND remote_id = ?")
+            .bind(local_folder_id)
+            .bind(&local_remote_id)
+            .fetch_optional(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // An identical copy is already archived, the next sync drops this row with the server copy
+        if already_archived.is_none() {
+            move_email_to_folder(&pool, email_id, source_folder_id, local_folder_id, &local_remote_id).await?;
+        }
+
+        let backend = engine.get_backend(account_id).await?;
+        let id = Id::single(remote_id.clone());
+        if let Err(e) = backend.remove_messages(&source_folder_path, &id).await {
+            // Put it back so the message is not shown twice once the server copy syncs again
+            if already_archived.is_none() {
+                move_email_to_folder(&pool, email_id, local_folder_id, source_folder_id, &remote_id).await?;
+            }
+            return Err(e.to_string());
+        }
+
+        expunge.insert((account_id, source_folder_path));
+        moved_ids.push(email_id);
+    }
+
+    for (account_id, folder_path) in expunge {
+        let backend = engine.get_backend(account_id).await?;
+        backend.expunge_folder(&folder_path).await.map_err(|e| e.to_string())?;
+    }
+
+    if !moved_ids.is_empty() {
+        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: moved_ids });
+    }
+
+    Ok(())
+}
+
+/// Moves a server message to another server folder of its account. Without a connection
+/// only the cached copy moves and the next sync settles the server side.
+pub(crate) async fn move_email(engine: &SyncEngine, email_id: i64, to_folder_id: i64) -> Result<(), String> {
+    let pool = engine.app().pool();
+    let email_info: Option<(i64, String, i64, String, bool)> = sqlx::query_as(
+        "SELECT e.account_id, e.remote_id, e.folder_id, f.path, f.is_local FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (account_id, remote_id, source_folder_id, source_folder_path, source_is_local) = match email_info {
+        Some(info) => info,
+        None => return Ok(()),
+    };
+    if source_folder_id == to_folder_id {
+        return Ok(());
+    }
+
+    let (target_folder_path, target_is_local): (String, bool) = sqlx::query_as("SELECT path, is_local FROM folders WHERE id = ? AND account_id = ?")
+        .bind(to_folder_id)
+        .bind(account_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Folder {} does not belong to account {}", to_folder_id, account_id))?;
+    if source_is_local || target_is_local {
+        return Err(format!("Email {} cannot move between local and server folders here", email_id));
+    }
+
+    if let Ok(backend) = engine.get_backend(account_id).await {
+        let id = Id::single(remote_id.clone());
+        use email::message::r#move::MoveMessages;
+        backend.move_messages(&source_folder_path, &target_folder_path, &id).await.map_err(|e| e.to_string())?;
+    }
+
+    move_email_to_folder(pool, email_id, source_folder_id, to_folder_id, &remote_id).await
+}
+
+/// Moves an email row between folders, adjusting the counts of both.
+async fn move_email_to_folder(pool: &SqlitePool, email_id: i64, from_folder_id: i64, to_folder_id: i64, remote_id: &str) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("UPDATE emails SET folder_id = ?, remote_id = ? WHERE id = ?")
+        .bind(to_folder_id)
+        .bind(remote_id)
+        .bind(email_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
+        .bind(if is_unread { 1 } else { 0 })
+        .bind(from_folder_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
+        .bind(if is_unread { 1 } else { 0 })
+        .bind(to_folder_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Attachment {
     pub id: i64,
@@ -1127,35 +1395,8 @@ async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::A
     .map_err(|e| e.to_string())?;
 
     let (account_id, remote_id, folder_path) = email_info;
-    let engine = app_handle.state::<SyncEngine<R>>();
-    let context = engine.get_context(account_id).await?;
-
-    let mut client = context.client().await;
-    let id = Id::single(remote_id);
-    
-    use imap_client::imap_next::imap_types::fetch::MessageDataItemName;
-    use imap_client::imap_next::imap_types::fetch::MacroOrMessageDataItemNames;
-    let fetch_items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
-        MessageDataItemName::BodyExt {
-            section: None,
-            partial: None,
-            peek: true,
-        }
-    ]);
-    
-    client.examine_mailbox(&folder_path).await.map_err(|e| e.to_string())?;
-
-    use std::num::NonZeroU32;
-    use imap_client::imap_next::imap_types::sequence::Sequence;
-    let uids: imap_client::imap_next::imap_types::sequence::SequenceSet = id.iter()
-        .filter_map(|s| s.parse::<u32>().ok())
-        .filter_map(|n| NonZeroU32::new(n))
-        .map(Sequence::from)
-        .collect::<Vec<_>>()
-        .try_into()
-        .map_err(|e: imap_client::imap_next::imap_types::error::ValidationError| e.to_string())?;
-
-    let messages = client.fetch_messages_with_items(uids, fetch_items).await.map_err(|e| e.to_string())?;
+    let engine = app_handle.state::<SyncEngine>();
+    let messages = engine.fetch_message(account_id, &folder_path, &remote_id).await?;
     let message = messages.first().ok_or("Email not found on server")?;
 
     if let Ok(attachments) = message.attachments() {
@@ -1239,8 +1480,8 @@ pub async fn send_email<R: tauri::Runtime>(
     attachment_ids: Vec<i64>,
 ) -> Result<(), String> {
     let manager = AccountManager::new(&app_handle).await?;
-    let account = manager.get_account_by_id(account_id).await?;
-    let (account_config, _, smtp_config) = account.get_configs()?;
+    let account = manager.get_authenticated_account(account_id).await?;
+    let transport = Transport::for_account(&account)?;
     let pool = app_handle.state::<SqlitePool>();
 
     let mut builder = MessageBuilder::new();
@@ -1280,67 +1521,9 @@ pub async fn send_email<R: tauri::Runtime>(
 
     let message = builder.write_to_vec().map_err(|e| e.to_string())?;
 
-    let backend_builder = BackendBuilder::new(
-        account_config.clone(),
-        SmtpContextBuilder::new(account_config, smtp_config),
-    );
-
-    let backend = match backend_builder.build().await {
-        Ok(b) => b,
-        Err(e) => {
-            let err_str = e.to_string();
-            if err_str.contains("auth") || err_str.contains("Unauthorized") || err_str.contains("token") || err_str.contains("credentials") {
-                info!("Refreshing token for account {} due to build error: {}", account.email(), err_str);
-                manager.refresh_access_token(account.email()).await?;
-                let account = manager.get_account_by_id(account_id).await?;
-                let (account_config, _, smtp_config) = account.get_configs()?;
-                let backend_builder = BackendBuilder::new(
-                    account_config.clone(),
-                    SmtpContextBuilder::new(account_config, smtp_config),
-                );
-                backend_builder.build().await.map_err(|e| e.to_string())?
-            } else {
-                return Err(err_str);
-            }
-        }
-    };
-
-    if let Err(e) = backend.send_message(&message).await {
-        let err_str = e.to_string();
-        if err_str.contains("auth") || err_str.contains("Unauthorized") || err_str.contains("token") || err_str.contains("credentials") {
-            info!("Refreshing token for account {} due to send error: {}", account.email(), err_str);
-            manager.refresh_access_token(account.email()).await?;
-            let account = manager.get_account_by_id(account_id).await?;
-            let (account_config, _, smtp_config) = account.get_configs()?;
-            let backend_builder = BackendBuilder::new(
-                account_config.clone(),
-                SmtpContextBuilder::new(account_config, smtp_config),
-            );
-            let backend = backend_builder.build().await.map_err(|e| e.to_string())?;
-            backend.send_message(&message).await.map_err(|e| e.to_string())?;
-        } else {
-            return Err(err_str);
-        }
-    }
+    transport.send(&message).await?;
 
-    // Append to Sent Folder
-    let engine = app_handle.state::<SyncEngine<R>>();
-
-    let sent_folder: Option<(i64, String)> = sqlx::query_as("SELECT id, path FROM folders WHERE account_id = ? AND role = 'sent'")
-        .bind(account_id)
-        .fetch_optional(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if let Some((folder_id, path)) = sent_folder {
-         if let Ok(backend) = engine.get_backend(account_id).await {
-            let flags = Flags::from_iter([Flag::Seen]);
-            let _ = backend.add_message_with_flags(&path, &message, &flags).await;
-            
-            // Trigger refresh
-            let _ = SyncEngine::refresh_folder(&app_handle, account_id, folder_id).await;
-         }
-    }
+    save_to_sent(&app_handle.state::<SyncEngine>(), account_id, &message).await?;
 
     // Save recipients as contacts
     let mut all_recipients = Vec::new();
@@ -1358,11 +1541,45 @@ pub async fn send_email<R: tauri::Runtime>(
         .filter(|s| !s.is_empty())
         .collect();
 
-    let _ = crate::email_backend::enrichment::commands::save_recipients_as_contacts(&app_handle, flat_recipients).await;
+    let _ = crate::email_backend::enrichment::commands::save_recipients_as_contacts(&pool, flat_recipients).await;
+
+    Ok(())
+}
+
+/// Appends a sent message to the account's Sent folder, if it has one, and refreshes that folder.
+pub(crate) async fn save_to_sent(engine: &SyncEngine, account_id: i64, message: &[u8]) -> Result<(), String> {
+    let sent_folder: Option<(i64, String)> = sqlx::query_as("SELECT id, path FROM folders WHERE account_id = ? AND role = 'sent'")
+        .bind(account_id)
+        .fetch_optional(engine.app().pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some((folder_id, path)) = sent_folder {
+         if let Ok(backend) = engine.get_backend(account_id).await {
+            let flags = Flags::from_iter([Flag::Seen]);
+            let _ = backend.add_message_with_flags(&path, message, &flags).await;
+            
+            // Trigger refresh
+            let _ = engine.refresh_folder(account_id, folder_id).await;
+         }
+    }
 
     Ok(())
 }
 
+/// Turns what the user typed into an FTS5 match expression.
+pub(crate) fn fts_query(query_text: &str) -> String {
+    // FTS5 works better with a '*' for prefix matching if the user is typing
+    // We wrap the term in double quotes for phrase matching and add * for prefix matching
+    // Example: \"query\"*
+    let fts_query = query_text.trim().replace("\"", "\"\"");
+    if fts_query.contains(' ') {
+        format!("\"{}\"", fts_query)
+    } else {
+        format!("{}*", fts_query)
+    }
+}
+
 #[tauri::command]
 pub async fn search_emails<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
@@ -1374,24 +1591,28 @@ pub async fn search_emails<R: tauri::Runtime>(
     before_id: Option<i64>,
 ) -> Result<Vec<Email>, String> {
     let pool = app_handle.state::<SqlitePool>();
-    
+    find_emails(&pool, &query_text, account_id, view.as_deref(), limit, before_date, before_id).await
+}
+
+/// Full-text search returning the newest match of each thread, shared by the app and the command line.
+pub async fn find_emails(
+    pool: &SqlitePool,
+    query_text: &str,
+    account_id: Option<i64>,
+    view: Option<&str>,
+    limit: Option<u32>,
+    before_date: Option<String>,
+    before_id: Option<i64>,
+) -> Result<Vec<Email>, String> {
     if query_text.trim().is_empty() {
         return Ok(Vec::new());
     }
 
-    // FTS5 works better with a '*' for prefix matching if the user is typing
-    // We wrap the term in double quotes for phrase matching and add * for prefix matching
-    // Example: \"query\"*
-    let fts_query = query_text.trim().replace("\"", "\"\"");
-    let fts_query = if fts_query.contains(' ') {
-        format!("\"{}\"", fts_query)
-    } else {
-        format!("{}*", fts_query)
-    };
+    let fts_query = fts_query(query_text);
 
     let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
         "WITH unique_messages AS (
-            SELECT e.*, f.role as folder_role,
+            SELECT e.*, f.role as folder_role, f.is_local,
             ROW_NUMBER() OVER (
                 PARTITION BY e.account_id, e.message_id 
                 ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC
@@ -1428,14 +1649,17 @@ pub async fn search_emails<R: tauri::Runtime>(
     }
 
     if let Some(v) = view {
-        match v.as_str() {
+        match v {
             "primary" => query_builder.push(" AND e.folder_role = 'inbox'"),
             "spam" => query_builder.push(" AND e.folder_role = 'spam'"),
             "sent" => query_builder.push(" AND e.folder_role = 'sent'"),
             "drafts" => query_builder.push(" AND e.folder_role = 'drafts'"),
             "trash" => query_builder.push(" AND e.folder_role = 'trash'"),
             "archive" => query_builder.push(" AND e.folder_role = 'archive'"),
-            "others" => query_builder.push(" AND (e.folder_role IS NULL OR e.folder_role = '' OR e.folder_role NOT IN ('inbox', 'spam', 'sent', 'drafts', 'trash', 'archive'))"),
+            "local" => query_builder.push(" AND e.is_local = 1"),
+            "starred" => query_builder.push(" AND e.flags LIKE '%\"flagged\"%' AND COALESCE(e.folder_role, '') NOT IN ('trash', 'spam')"),
+            "digest" => query_builder.push(" AND e.folder_role = 'digest'"),
+            "others" => query_builder.push(" AND (e.folder_role IS NULL OR e.folder_role = '' OR e.folder_role NOT IN ('inbox', 'spam', 'sent', 'drafts', 'trash', 'archive', 'digest'))"),
             _ => &mut query_builder,
         };
     }
@@ -1456,7 +1680,7 @@ pub async fn search_emails<R: tauri::Runtime>(
 
     let emails = query_builder
         .build_query_as::<Email>()
-        .fetch_all(&*pool)
+        .fetch_all(pool)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -1477,7 +1701,7 @@ pub async fn get_folders<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, acc
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::test_utils::setup_test_db;
+    use crate::utils::test_utils::{setup_test_db, test_context};
     use tauri::test::mock_builder;
     use chrono::Utc;
 
@@ -1596,6 +1820,7 @@ mod tests {
         let (_, _, email_id) = seed_test_data(&pool).await;
         
         let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(test_context(&pool).0);
         app.manage(pool);
 
         let content = get_email_content(app.handle().clone(), email_id)
@@ -1604,4 +1829,114 @@ mod tests {
 
         assert_eq!(content.body_text, Some("Hello content".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_local_folder_listing_search_and_delete() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (account_id, inbox_id, email_id) = seed_test_data(&pool).await;
+        let local_id = local_folder_id(&pool, account_id, LOCAL_ARCHIVE_NAME).await.unwrap();
+
+        move_email_to_folder(&pool, email_id, inbox_id, local_id, "local-abc").await.unwrap();
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool.clone());
+
+        let local = get_emails(app.handle().clone(), Some(account_id), Some("local".to_string()), None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(local.iter().map(|e| e.id).collect::<Vec<_>>(), vec![email_id]);
+
+        let found = search_emails(app.handle().clone(), "Hello".to_string(), Some(account_id), Some("local".to_string()), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        let total: i64 = sqlx::query_scalar("SELECT total_count FROM folders WHERE id = ?")
+            .bind(local_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+
+        delete_local_email(&pool, email_id).await.unwrap();
+        let total: i64 = sqlx::query_scalar("SELECT total_count FROM folders WHERE id = ?")
+            .bind(local_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_flags_follow_gmail_copies_into_starred_view_and_search() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (account_id, inbox_id, email_id) = seed_test_data(&pool).await;
+        let all_mail_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'All Mail', '[Gmail]/All Mail', 'archive') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let copy_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, sender_address, date, flags)
+             SELECT account_id, ?, 'remote-9', message_id, thread_id, subject, sender_address, date, flags FROM emails WHERE id = ? RETURNING id"
+        )
+        .bind(all_mail_id)
+        .bind(email_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(SyncEngine::new(test_context(&pool).0));
+        app.manage(pool.clone());
+
+        remove_flags(app.handle().clone(), vec![email_id], vec!["seen".to_string()]).await.unwrap();
+        set_flags(app.handle().clone(), vec![email_id], vec!["flagged".to_string(), "receipts".to_string()]).await.unwrap();
+        // Already set, so nothing changes
+        set_flags(app.handle().clone(), vec![email_id], vec!["flagged".to_string()]).await.unwrap();
+
+        let flags: Vec<String> = sqlx::query_scalar("SELECT flags FROM emails WHERE id IN (?, ?) ORDER BY id")
+            .bind(email_id)
+            .bind(copy_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(flags, vec![r#"["flagged","receipts"]"#; 2]);
+
+        let unread: Vec<i64> = sqlx::query_scalar("SELECT unread_count FROM folders WHERE id IN (?, ?) ORDER BY id")
+            .bind(inbox_id)
+            .bind(all_mail_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(unread, vec![1, 1]);
+
+        let starred = get_emails(app.handle().clone(), Some(account_id), Some("starred".to_string()), None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(starred.iter().map(|e| e.id).collect::<Vec<_>>(), vec![email_id]);
+
+        let found = find_emails(&pool, "receipts", Some(account_id), None, None, None, None).await.unwrap();
+        assert_eq!(found.len(), 1);
+        // System flags are not searchable
+        assert!(find_emails(&pool, "flagged", Some(account_id), None, None, None, None).await.unwrap().is_empty());
+
+        remove_flags(app.handle().clone(), vec![copy_id], vec!["flagged".to_string()]).await.unwrap();
+        let starred = get_emails(app.handle().clone(), Some(account_id), Some("starred".to_string()), None, None, None, None)
+            .await
+            .unwrap();
+        assert!(starred.is_empty());
+    }
+
+    #[test]
+    fn test_parse_flags_refuses_keywords_imap_cannot_store() {
+        let flags = parse_flags(&["Flagged".to_string(), " $Work ".to_string(), "".to_string()]).unwrap();
+        assert_eq!(flags, vec![Flag::Flagged, Flag::Custom("$Work".to_string())]);
+
+        for keyword in ["two words", "a(b", "quo\"te", "star*", "caf\u{e9}"] {
+            assert!(parse_flags(&[keyword.to_string()]).is_err(), "{} was accepted", keyword);
+        }
+    }
 }
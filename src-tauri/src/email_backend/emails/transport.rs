@@ -0,0 +1,142 @@
+use std::sync::Arc;
+use email::account::config::AccountConfig;
+use email::backend::BackendBuilder;
+use email::message::send::SendMessage;
+use email::sendmail::config::SendmailConfig;
+use email::sendmail::SendmailContextBuilder;
+use email::smtp::config::SmtpConfig;
+use email::smtp::SmtpContextBuilder;
+use process::Command;
+use crate::email_backend::accounts::manager::Account;
+
+/// How an account hands off outgoing mail.
+pub enum Transport {
+    Smtp(Arc<AccountConfig>, Arc<SmtpConfig>),
+    /// Pipes the raw message to a local command such as msmtp or Postfix's sendmail.
+    Sendmail(Arc<AccountConfig>, Arc<SendmailConfig>),
+}
+
+impl Transport {
+    pub fn for_account(account: &Account) -> Result<Self, String> {
+        if let Some(command) = account.sendmail_command() {
+            let account_config = Arc::new(AccountConfig {
+                name: account.email().to_string(),
+                email: account.email().to_string(),
+                ..Default::default()
+            });
+            let sendmail_config = Arc::new(SendmailConfig { cmd: Some(Command::new(command)) });
+            return Ok(Transport::Sendmail(account_config, sendmail_config));
+        }
+
+        let (account_config, _, smtp_config) = account.get_configs()?;
+        Ok(Transport::Smtp(account_config, smtp_config))
+    }
+
+    pub async fn send(&self, message: &[u8]) -> Result<(), String> {
+        match self {
+            Transport::Smtp(account_config, smtp_config) => {
+                let backend = BackendBuilder::new(
+                    account_config.clone(),
+                    SmtpContextBuilder::new(account_config.clone(), smtp_config.clone()),
+                )
+                .build()
+                .await
+                .map_err(|e| e.to_string())?;
+                backend.send_message(message).await.map_err(|e| e.to_string())
+            }
+            Transport::Sendmail(account_config, sendmail_config) => {
+                let backend = BackendBuilder::new(
+                    account_config.clone(),
+                    SendmailContextBuilder::new(account_config.clone(), sendmail_config.clone()),
+                )
+                .build()
+                .await
+                .map_err(|e| e.to_string())?;
+                backend.send_message(message).await.map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::accounts::maildir::MaildirAccount;
+
+    fn maildir_account(sendmail_command: Option<String>) -> Account {
+        Account::Maildir(MaildirAccount {
+            id: Some(1),
+            email: "local@example.com".to_string(),
+            name: None,
+            root_dir: std::env::temp_dir(),
+            maildirpp: false,
+            sendmail_command,
+        })
+    }
+
+    #[test]
+    fn test_local_account_without_command_cannot_send() {
+        assert!(Transport::for_account(&maildir_account(None)).is_err());
+        assert!(Transport::for_account(&maildir_account(Some("  ".to_string()))).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sendmail_pipes_message_to_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("sent.eml");
+        let script = dir.path().join("fake-sendmail");
+        std::fs::write(&script, format!("#!/bin/sh\ncat > '{}'\n", output.display())).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let account = maildir_account(Some(format!("{} -t", script.display())));
+        let transport = Transport::for_account(&account).unwrap();
+        assert!(matches!(transport, Transport::Sendmail(..)));
+
+        let message = b"From: local@example.com\r\nTo: friend@example.com\r\nSubject: Hi\r\n\r\nHello\r\n";
+        transport.send(message).await.expect("Failed to send through sendmail");
+
+        let sent = std::fs::read_to_string(&output).unwrap();
+        assert!(sent.contains("Subject: Hi"));
+        assert!(sent.contains("Hello"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_delivers_to_server() {
+        use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+        use crate::utils::test_server::FakeSmtpServer;
+
+        let server = FakeSmtpServer::start("me@example.com", "secret").await;
+        let account = |password: &str| Account::ImapSmtp(ImapSmtpAccount {
+            id: Some(1),
+            email: "me@example.com".to_string(),
+            name: None,
+            imap_host: "127.0.0.1".to_string(),
+            imap_port: 0,
+            imap_username: "me@example.com".to_string(),
+            imap_encryption: "none".to_string(),
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: server.port(),
+            smtp_username: "me@example.com".to_string(),
+            smtp_encryption: "none".to_string(),
+            smtp_use_imap_credentials: true,
+            sendmail_command: None,
+            password: Some(password.to_string()),
+            smtp_password: None,
+        });
+
+        let message = b"From: me@example.com\r\nTo: friend@example.com\r\nSubject: Hi\r\n\r\n.Hello\r\n";
+        let rejected = Transport::for_account(&account("wrong")).unwrap().send(message).await;
+        assert!(rejected.is_err());
+        assert!(server.failed_logins() > 0);
+
+        Transport::for_account(&account("secret")).unwrap().send(message).await.expect("Failed to send over SMTP");
+        let sent = server.messages();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].from, "me@example.com");
+        assert_eq!(sent[0].recipients, vec!["friend@example.com"]);
+        assert!(String::from_utf8_lossy(&sent[0].data).contains("\r\n.Hello\r\n"));
+    }
+}
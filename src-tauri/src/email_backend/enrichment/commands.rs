@@ -1,11 +1,11 @@
-use tauri::{Manager, Emitter};
+use tauri::Manager;
 use sqlx::SqlitePool;
 use chrono::Utc;
-use std::collections::HashMap;
+use crate::context::AppContext;
 use crate::email_backend::enrichment::types::{Sender, Domain};
 use crate::email_backend::enrichment::providers::*;
 use crate::email_backend::enrichment::people::*;
-use crate::email_backend::accounts::manager::{AccountManager, Account};
+use crate::email_backend::accounts::manager::Account;
 use crate::email_backend::emails::commands::Email;
 
 #[tauri::command]
@@ -83,44 +83,42 @@ pub async fn search_contacts<R: tauri::Runtime>(
 pub async fn sync_contacts<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
 ) -> Result<(), String> {
-    sync_contacts_internal(&app_handle).await
+    sync_contacts_internal(&app_handle.state::<AppContext>()).await
 }
 
-pub async fn sync_contacts_internal<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
-) -> Result<(), String> {
+pub async fn sync_contacts_internal(app: &AppContext) -> Result<(), String> {
     log::info!("Starting background contact sync");
-    let manager = AccountManager::new(app_handle).await?;
+    let manager = app.accounts().await?;
     let registry = manager.load().await?;
-    let pool = app_handle.state::<SqlitePool>();
 
     for account in registry.accounts {
         if let Account::Google(google) = account {
             let email = google.email.clone();
-            let token = match manager.refresh_access_token(&email).await {
-                Ok(t) => t,
+            let Some(account_id) = google.id else { continue };
+            let token = match manager.get_authenticated_account(account_id).await {
+                Ok(Account::Google(g)) => g.access_token.unwrap_or_default(),
+                Ok(_) => continue,
                 Err(e) => {
                     log::error!("Failed to refresh token for contact sync ({}): {}", email, e);
                     continue;
                 }
             };
 
-            if let Err(e) = sync_google_contacts(app_handle, &email, &token).await {
+            if let Err(e) = sync_google_contacts(app.pool(), &email, &token).await {
                 log::error!("Failed to sync Google contacts for {}: {}", email, e);
             }
         }
     }
 
-    Ok(())
+    crate::email_backend::contacts::carddav::sync_all(app).await
 }
 
-async fn sync_google_contacts<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+async fn sync_google_contacts(
+    pool: &SqlitePool,
     account_email: &str,
     token: &str,
 ) -> Result<(), String> {
     let client = reqwest::Client::new();
-    let pool = app_handle.state::<SqlitePool>();
     
     let mut next_page_token: Option<String> = None;
     let mut total_synced = 0;
@@ -174,7 +172,7 @@ async fn sync_google_contacts<R: tauri::Runtime>(
                             .bind(&name)
                             .bind(&avatar_url)
                             .bind(account_email)
-                            .execute(&*pool)
+                            .execute(pool)
                             .await
                             .map_err(|e| e.to_string())?;
                             
@@ -195,11 +193,10 @@ async fn sync_google_contacts<R: tauri::Runtime>(
     Ok(())
 }
 
-pub async fn save_recipients_as_contacts<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+pub async fn save_recipients_as_contacts(
+    pool: &SqlitePool,
     recipients: Vec<String>,
 ) -> Result<(), String> {
-    let pool = app_handle.state::<SqlitePool>();
     
     for recipient in recipients {
         let recipient = recipient.trim().to_lowercase();
@@ -216,7 +213,7 @@ pub async fn save_recipients_as_contacts<R: tauri::Runtime>(
                 updated_at = CURRENT_TIMESTAMP"
         )
         .bind(&recipient)
-        .execute(&*pool)
+        .execute(pool)
         .await;
     }
     
@@ -286,7 +283,7 @@ pub async fn get_sender_info<R: tauri::Runtime>(
     }
 
     // If not found or needs update, try enrichment
-    let enriched = enrich_sender_internal(&app_handle, address, manual).await?;
+    let enriched = enrich_sender_internal(&app_handle.state::<AppContext>(), address, manual).await?;
     Ok(Some(enriched))
 }
 
@@ -297,7 +294,7 @@ pub async fn regenerate_sender_info<R: tauri::Runtime>(
 ) -> Result<Sender, String> {
     log::info!("regenerate_sender_info called for {}", address);
     // Passing true for manual_trigger forces re-enrichment
-    let enriched = enrich_sender_internal(&app_handle, address, true).await?;
+    let enriched = enrich_sender_internal(&app_handle.state::<AppContext>(), address, true).await?;
     Ok(enriched)
 }
 
@@ -306,8 +303,11 @@ pub async fn update_sender_info<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
     sender: Sender,
 ) -> Result<(), String> {
-    let pool = app_handle.state::<SqlitePool>();
-    
+    save_sender_info(&app_handle.state::<AppContext>(), &sender).await
+}
+
+/// Stores the fields of a sender the user edited by hand.
+async fn save_sender_info(app: &AppContext, sender: &Sender) -> Result<(), String> {
     let is_verified = sender.github_handle.is_some() || sender.twitter_handle.is_some() || sender.linkedin_handle.is_some();
 
     sqlx::query(
@@ -336,11 +336,11 @@ pub async fn update_sender_info<R: tauri::Runtime>(
     .bind(&sender.linkedin_handle)
     .bind(is_verified)
     .bind(&sender.address)
-    .execute(&*pool)
+    .execute(app.pool())
     .await
     .map_err(|e| e.to_string())?;
 
-    let _ = app_handle.emit("sender-updated", &sender.address);
+    app.emit("sender-updated", &sender.address);
     Ok(())
 }
 
@@ -360,13 +360,13 @@ pub async fn get_domain_info<R: tauri::Runtime>(
     Ok(domain_info)
 }
 
-async fn enrich_sender_internal<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+async fn enrich_sender_internal(
+    app: &AppContext,
     address: String,
     manual_trigger: bool,
 ) -> Result<Sender, String> {
     log::info!("Starting enrichment for {} (manual={})", address, manual_trigger);
-    let pool = app_handle.state::<SqlitePool>();
+    let pool = app.pool();
 
     let domain_name = extract_domain(&address);
     let mut avatar_url = None;
@@ -396,7 +396,7 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
     // 0. Collect tokens and info for People API enrichment
     let mut google_accounts = Vec::new();
     let mut own_info = std::collections::HashMap::new(); // email -> (name, picture)
-    if let Ok(manager) = AccountManager::new(app_handle).await {
+    if let Ok(manager) = app.accounts().await {
         if let Ok(registry) = manager.load().await {
             for a in &registry.accounts {
                 match a {
@@ -413,6 +413,15 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
                     crate::email_backend::accounts::manager::Account::ImapSmtp(i) => {
                         own_info.insert(i.email.to_lowercase(), (i.name.clone(), None));
                     }
+                    crate::email_backend::accounts::manager::Account::OAuth2(o) => {
+                        own_info.insert(o.email.to_lowercase(), (o.name.clone(), o.picture.clone()));
+                    }
+                    crate::email_backend::accounts::manager::Account::Maildir(m) => {
+                        own_info.insert(m.email.to_lowercase(), (m.name.clone(), None));
+                    }
+                    crate::email_backend::accounts::manager::Account::Notmuch(n) => {
+                        own_info.insert(n.email.to_lowercase(), (n.name.clone(), None));
+                    }
                 }
             }
         }
@@ -430,7 +439,7 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
         "SELECT sender_name FROM emails WHERE sender_address = ? AND sender_name IS NOT NULL LIMIT 1"
     )
     .bind(&address)
-    .fetch_optional(&*pool)
+    .fetch_optional(pool)
     .await
     .unwrap_or(None);
 
@@ -550,27 +559,21 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
             .bind(&root_domain)
             .bind(logo_url)
             .bind(Utc::now())
-            .execute(&*pool)
+            .execute(pool)
             .await;
         }
     }
 
     // 4. AI Enrichment (optional and sparing)
-    let settings: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM settings WHERE key IN ('aiEnabled', 'aiSenderEnrichmentEnabled')")
-        .fetch_all(&*pool)
-        .await
-        .unwrap_or_default();
-
-    let settings_map: HashMap<String, String> = settings.into_iter().collect();
-    let ai_enabled = settings_map.get("aiEnabled").map(|v| v.as_str()).unwrap_or("false") == "true";
-    let ai_sender_enrichment_enabled = settings_map.get("aiSenderEnrichmentEnabled").map(|v| v.as_str()).unwrap_or("true") == "true";
+    let ai_enabled = app.settings().is_enabled("aiEnabled", false).await;
+    let ai_sender_enrichment_enabled = app.settings().is_enabled("aiSenderEnrichmentEnabled", true).await;
 
     // Check if we already have AI data to avoid redundant calls
     let existing_ai_data: Option<(Option<String>, Option<chrono::DateTime<Utc>>)> = sqlx::query_as(
         "SELECT job_title, ai_last_enriched_at FROM senders WHERE address = ?"
     )
     .bind(&address)
-    .fetch_optional(&*pool)
+    .fetch_optional(pool)
     .await
     .unwrap_or(None);
 
@@ -600,7 +603,7 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
                  )"
             )
             .bind(&address)
-            .fetch_one(&*pool)
+            .fetch_one(pool)
             .await
             .unwrap_or((false,));
 
@@ -618,13 +621,13 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
                 "SELECT snippet FROM emails WHERE sender_address = ? AND snippet IS NOT NULL ORDER BY date DESC LIMIT 5"
             )
             .bind(&address)
-            .fetch_all(&*pool)
+            .fetch_all(pool)
             .await
             .unwrap_or_default();
 
             if !snippets.is_empty() {
                 log::info!("Sparingly triggering AI enrichment for {}", address);
-                if let Ok(ai_data) = crate::email_backend::llm::enrichment::enrich_sender_with_ai(app_handle, &address, snippets).await {
+                if let Ok(ai_data) = crate::email_backend::llm::enrichment::enrich_sender_with_ai(app, &address, snippets).await {
                     if name.is_none() {
                         if let Some(n) = ai_data["name"].as_str() {
                             name = Some(n.to_string());
@@ -740,18 +743,18 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
     .bind(sender.is_automated_mailer)
     .bind(sender.ai_last_enriched_at)
     .bind(sender.last_enriched_at)
-    .execute(&*pool)
+    .execute(pool)
     .await
     .map_err(|e| e.to_string())?;
 
     // Emit event so the frontend can refresh
-    let _ = app_handle.emit("sender-updated", &sender.address);
+    app.emit("sender-updated", &sender.address);
 
     Ok(sender)
 }
 
-pub async fn proactive_enrichment<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
-    let pool = app_handle.state::<SqlitePool>();
+pub async fn proactive_enrichment(app: &AppContext) -> Result<(), String> {
+    let pool = app.pool();
 
     // Find unique senders from emails that are NOT in senders table OR have no avatar OR use the old Clearbit provider
     // AND have at least one email newer than account_creation - 14 days
@@ -766,7 +769,7 @@ pub async fn proactive_enrichment<R: tauri::Runtime>(app_handle: &tauri::AppHand
            AND datetime(e.date) > datetime(a.created_at, '-14 days')
          LIMIT 100" // Process in batches to avoid overwhelming APIs
     )
-    .fetch_all(&*pool)
+    .fetch_all(pool)
     .await
     .map_err(|e| e.to_string())?;
 
@@ -778,7 +781,7 @@ pub async fn proactive_enrichment<R: tauri::Runtime>(app_handle: &tauri::AppHand
 
     for address in addresses {
         // We ignore errors for individual senders to keep the loop going
-        let _ = enrich_sender_internal(app_handle, address, false).await;
+        let _ = enrich_sender_internal(app, address, false).await;
         // Small delay to be polite to APIs
         tokio::time::sleep(std::time::Duration::from_millis(200)).await;
     }
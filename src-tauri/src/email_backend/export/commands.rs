@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use mail_builder::MessageBuilder;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+use log::warn;
+use crate::email_backend::emails::commands::{fts_query, get_thread_emails};
+use crate::email_backend::export::mbox::MboxWriter;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::attachments::read_stored_attachment;
+
+/// Progress is reported every this many messages.
+const PROGRESS_INTERVAL: usize = 25;
+
+/// Which messages go into an mbox export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportSelection {
+    Folder { folder_id: i64 },
+    Search { query_text: String, account_id: Option<i64> },
+    /// Dates are ISO 8601, `until` is exclusive.
+    DateRange { since: String, until: String, account_id: Option<i64> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub path: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Ids of the selected messages, oldest first, with copies of a message in several folders counted once.
+async fn selected_email_ids(pool: &SqlitePool, selection: &ExportSelection) -> Result<Vec<i64>, String> {
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> =
+        sqlx::QueryBuilder::new("SELECT MIN(e.id) FROM emails e ");
+
+    match selection {
+        ExportSelection::Folder { folder_id } => {
+            query_builder.push(" WHERE e.folder_id = ");
+            query_builder.push_bind(*folder_id);
+        }
+        ExportSelection::Search { query_text, account_id } => {
+            if query_text.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            query_builder.push(" JOIN emails_fts fts ON e.id = fts.rowid WHERE emails_fts MATCH ");
+            query_builder.push_bind(fts_query(query_text));
+            if let Some(aid) = account_id {
+                query_builder.push(" AND e.account_id = ");
+                query_builder.push_bind(*aid);
+            }
+        }
+        ExportSelection::DateRange { since, until, account_id } => {
+            query_builder.push(" WHERE e.date >= ");
+            query_builder.push_bind(since.clone());
+            query_builder.push(" AND e.date < ");
+            query_builder.push_bind(until.clone());
+            if let Some(aid) = account_id {
+                query_builder.push(" AND e.account_id = ");
+                query_builder.push_bind(*aid);
+            }
+        }
+    }
+
+    query_builder.push(" GROUP BY e.account_id, COALESCE(e.message_id, e.id) ORDER BY MIN(e.date), MIN(e.id)");
+
+    query_builder
+        .build_query_scalar::<i64>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// The RFC 5322 source of a message. It is fetched from the server when possible and
+/// otherwise rebuilt from the cached headers, body and attachments.
+pub(crate) async fn raw_source(engine: &SyncEngine, email_id: i64) -> Result<Vec<u8>, String> {
+    let pool = engine.app().pool();
+    let (account_id, remote_id, folder_path, is_local): (i64, String, String, bool) = sqlx::query_as(
+        "SELECT e.account_id, e.remote_id, f.path, f.is_local FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if is_local {
+        return cached_source(pool, engine.app().data_dir(), email_id).await;
+    }
+
+    match engine.fetch_message(account_id, &folder_path, &remote_id).await {
+        Ok(messages) => {
+            if let Some(raw) = messages.first().and_then(|m| m.raw().ok()) {
+                return Ok(raw.to_vec());
+            }
+        }
+        Err(e) => warn!("Exporting email {} from cache, fetching it failed: {}", email_id, e),
+    }
+
+    cached_source(pool, engine.app().data_dir(), email_id).await
+}
+
+/// Rebuilds a message from what is stored locally, for messages the server no longer has.
+pub(crate) async fn cached_source(pool: &SqlitePool, data_dir: &Path, email_id: i64) -> Result<Vec<u8>, String> {
+    #[allow(clippy::type_complexity)]
+    let row: (Option<String>, Option<String>, Option<String>, Option<String>, Option<String>, String, Option<String>, Option<String>, String, Option<String>, Option<String>) = sqlx::query_as(
+        "SELECT message_id, in_reply_to, references_header, subject, sender_name, sender_address, recipient_to, recipient_cc, date, body_text, body_html FROM emails WHERE id = ?"
+    )
+    .bind(email_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let (message_id, in_reply_to, references, subject, sender_name, sender_address, to, cc, date, body_text, body_html) = row;
+
+    let strip = |id: &str| id.trim().trim_start_matches('<').trim_end_matches('>').to_string();
+    let addresses = |list: &str| list.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect::<Vec<_>>();
+
+    let mut builder = MessageBuilder::new()
+        .from((sender_name.unwrap_or_default(), sender_address))
+        .subject(subject.unwrap_or_default())
+        .date(parse_date(&date).timestamp());
+
+    if let Some(to) = to.as_deref().map(addresses).filter(|a| !a.is_empty()) {
+        builder = builder.to(to);
+    }
+    if let Some(cc) = cc.as_deref().map(addresses).filter(|a| !a.is_empty()) {
+        builder = builder.cc(cc);
+    }
+    if let Some(id) = message_id {
+        builder = builder.message_id(strip(&id));
+    }
+    if let Some(id) = in_reply_to {
+        builder = builder.in_reply_to(strip(&id));
+    }
+    if let Some(refs) = references {
+        builder = builder.references(refs.split_whitespace().map(strip).collect::<Vec<_>>());
+    }
+    if let Some(text) = body_text {
+        builder = builder.text_body(text);
+    }
+    if let Some(html) = body_html {
+        builder = builder.html_body(html);
+    }
+
+    let attachments: Vec<(Option<String>, Option<String>, String)> = sqlx::query_as(
+        "SELECT filename, mime_type, file_hash FROM attachments WHERE email_id = ? AND file_hash IS NOT NULL"
+    )
+    .bind(email_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (filename, mime_type, hash) in attachments {
+        match read_stored_attachment(data_dir, &hash) {
+            Ok(data) => {
+                builder = builder.attachment(
+                    mime_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+                    filename.unwrap_or_else(|| "attachment".to_string()),
+                    data,
+                );
+            }
+            Err(e) => warn!("Attachment of email {} missing from cache: {}", email_id, e),
+        }
+    }
+
+    builder.write_to_vec().map_err(|e| e.to_string())
+}
+
+fn parse_date(date: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|d| d.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+/// A file name like `2024-03-05-42-quarterly-report.eml` that sorts by date.
+fn eml_file_name(email_id: i64, date: &str, subject: Option<&str>) -> String {
+    let slug: String = subject
+        .unwrap_or("")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .take(8)
+        .collect::<Vec<_>>()
+        .join("-");
+    let day = date.get(..10).unwrap_or("undated");
+    if slug.is_empty() {
+        format!("{}-{}.eml", day, email_id)
+    } else {
+        format!("{}-{}-{}.eml", day, email_id, slug)
+    }
+}
+
+#[tauri::command]
+pub async fn export_email_eml<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, path: String) -> Result<(), String> {
+    let raw = raw_source(&app_handle.state::<SyncEngine>(), email_id).await?;
+    std::fs::write(&path, raw).map_err(|e| e.to_string())
+}
+
+/// Writes every message of the thread as its own `.eml` file into `dir`.
+#[tauri::command]
+pub async fn export_thread_eml<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, dir: String) -> Result<usize, String> {
+    let emails = get_thread_emails(app_handle.clone(), email_id, Some(u32::MAX), None).await?;
+    let dir = PathBuf::from(dir);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let engine = app_handle.state::<SyncEngine>();
+    for email in &emails {
+        let raw = raw_source(&engine, email.id).await?;
+        let path = dir.join(eml_file_name(email.id, &email.date, email.subject.as_deref()));
+        std::fs::write(path, raw).map_err(|e| e.to_string())?;
+    }
+
+    Ok(emails.len())
+}
+
+/// Streams the selected messages into an mbox file and emits `export-progress` while doing so.
+/// Returns the number of messages written.
+#[tauri::command]
+pub async fn export_mbox<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, selection: ExportSelection, path: String) -> Result<usize, String> {
+    let engine = app_handle.state::<SyncEngine>();
+    write_mbox(&engine, &selection, &path, |progress| {
+        let _ = app_handle.emit("export-progress", progress);
+    }).await
+}
+
+/// Writes the selected messages to an mbox file, one message in memory at a time,
+/// reporting progress every few messages.
+pub async fn write_mbox(
+    engine: &SyncEngine,
+    selection: &ExportSelection,
+    path: &str,
+    mut on_progress: impl FnMut(ExportProgress),
+) -> Result<usize, String> {
+    let pool = engine.app().pool();
+    let ids = selected_email_ids(pool, selection).await?;
+    let total = ids.len();
+
+    let file = File::create(Path::new(path)).map_err(|e| e.to_string())?;
+    let mut writer = MboxWriter::new(BufWriter::new(file));
+
+    for (i, email_id) in ids.into_iter().enumerate() {
+        let (sender_address, date): (String, String) = sqlx::query_as("SELECT sender_address, date FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let raw = raw_source(engine, email_id).await?;
+        writer.write_message(&sender_address, parse_date(&date), &raw).map_err(|e| e.to_string())?;
+
+        let done = i + 1;
+        if done % PROGRESS_INTERVAL == 0 || done == total {
+            on_progress(ExportProgress { path: path.to_string(), done, total });
+        }
+    }
+
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    #[test]
+    fn test_eml_file_name() {
+        assert_eq!(
+            eml_file_name(42, "2024-03-05T09:07:01Z", Some("Re: Quarterly report (final)")),
+            "2024-03-05-42-re-quarterly-report-final.eml"
+        );
+        assert_eq!(eml_file_name(7, "2024-03-05T09:07:01Z", None), "2024-03-05-7.eml");
+    }
+
+    #[tokio::test]
+    async fn test_selected_email_ids_dedupes_and_filters_by_date() {
+        let pool = setup_test_db().await;
+        sqlx::query("INSERT INTO accounts (id, email, account_type) VALUES (1, 'a@example.com', 'imap')")
+            .execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO folders (id, account_id, name, path, role) VALUES (1, 1, 'INBOX', 'INBOX', 'inbox'), (2, 1, 'All', 'All', NULL)")
+            .execute(&pool).await.unwrap();
+        for (id, folder_id, remote_id, message_id, date) in [
+            (1, 1, "1", "<m1@x>", "2024-01-10T00:00:00Z"),
+            (2, 2, "2", "<m1@x>", "2024-01-10T00:00:00Z"),
+            (3, 1, "3", "<m2@x>", "2024-02-10T00:00:00Z"),
+            (4, 1, "4", "<m3@x>", "2023-12-31T00:00:00Z"),
+        ] {
+            sqlx::query("INSERT INTO emails (id, account_id, folder_id, remote_id, message_id, sender_address, date, flags) VALUES (?, 1, ?, ?, ?, 's@example.com', ?, '[]')")
+                .bind(id).bind(folder_id).bind(remote_id).bind(message_id).bind(date)
+                .execute(&pool).await.unwrap();
+        }
+
+        let ids = selected_email_ids(&pool, &ExportSelection::DateRange {
+            since: "2024-01-01".to_string(),
+            until: "2025-01-01".to_string(),
+            account_id: Some(1),
+        }).await.unwrap();
+        assert_eq!(ids, vec![1, 3]);
+
+        let ids = selected_email_ids(&pool, &ExportSelection::Folder { folder_id: 1 }).await.unwrap();
+        assert_eq!(ids, vec![4, 1, 3]);
+    }
+}
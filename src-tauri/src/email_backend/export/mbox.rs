@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use std::io::{self, BufRead, Write};
+
+/// Writes messages one at a time in mboxrd format: each message starts with a `From ` line,
+/// and body lines that would look like one get an extra `>`.
+pub struct MboxWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> MboxWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn write_message(&mut self, sender: &str, date: DateTime<Utc>, raw: &[u8]) -> io::Result<()> {
+        let sender = if sender.trim().is_empty() { "MAILER-DAEMON" } else { sender.trim() };
+        writeln!(self.inner, "From {} {}", sender, date.format("%a %b %e %H:%M:%S %Y"))?;
+
+        for line in raw.split(|&b| b == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let quoted = line.iter().position(|&b| b != b'>').unwrap_or(line.len());
+            if line[quoted..].starts_with(b"From ") {
+                self.inner.write_all(b">")?;
+            }
+            self.inner.write_all(line)?;
+            self.inner.write_all(b"\n")?;
+        }
+
+        // Messages are separated by an empty line
+        if !raw.ends_with(b"\n") {
+            self.inner.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads messages one at a time from an mbox file. Both mboxo and mboxrd are accepted, and
+/// messages come out with CRLF line endings, as IMAP servers expect.
+pub struct MboxReader<R: BufRead> {
+    inner: R,
+    started: bool,
+    done: bool,
+}
+
+impl<R: BufRead> MboxReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, started: false, done: false }
+    }
+
+    fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<bool> {
+        line.clear();
+        Ok(self.inner.read_until(b'\n', line)? > 0)
+    }
+}
+
+fn is_separator(line: &[u8]) -> bool {
+    line.starts_with(b"From ")
+}
+
+impl<R: BufRead> Iterator for MboxReader<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut line = Vec::new();
+
+        // Skip anything before the first separator
+        while !self.started {
+            match self.read_line(&mut line) {
+                Ok(true) if is_separator(&line) => self.started = true,
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let mut message = Vec::new();
+        loop {
+            match self.read_line(&mut line) {
+                Ok(true) if is_separator(&line) => break,
+                Ok(true) => {
+                    let content = line.strip_suffix(b"\n").unwrap_or(&line);
+                    let content = content.strip_suffix(b"\r").unwrap_or(content);
+                    let quoted = content.iter().position(|&b| b != b'>').unwrap_or(content.len());
+                    let content = if quoted > 0 && content[quoted..].starts_with(b"From ") {
+                        &content[1..]
+                    } else {
+                        content
+                    };
+                    message.extend_from_slice(content);
+                    message.extend_from_slice(b"\r\n");
+                }
+                Ok(false) => {
+                    self.done = true;
+                    break;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        // Drop the empty line that separates messages
+        if message.ends_with(b"\r\n\r\n") {
+            message.truncate(message.len() - 2);
+        }
+        Some(Ok(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_write_message_escapes_from_lines() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 5, 9, 7, 1).unwrap();
+        let mut writer = MboxWriter::new(Vec::new());
+        writer
+            .write_message("a@example.com", date, b"Subject: One\r\n\r\nFrom here\r\n>From there\r\n")
+            .unwrap();
+        writer.write_message("", date, b"Subject: Two\r\n\r\nBye").unwrap();
+        let out = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(
+            out,
+            "From a@example.com Tue Mar  5 09:07:01 2024\n\
+             Subject: One\n\n>From here\n>>From there\n\n\
+             From MAILER-DAEMON Tue Mar  5 09:07:01 2024\n\
+             Subject: Two\n\nBye\n\n"
+        );
+    }
+
+    #[test]
+    fn test_reader_reverses_writer() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 5, 9, 7, 1).unwrap();
+        let first = b"Subject: One\r\n\r\nFrom here\r\n>From there\r\n".to_vec();
+        let second = b"Subject: Two\r\n\r\nBye\r\n".to_vec();
+
+        let mut writer = MboxWriter::new(Vec::new());
+        writer.write_message("a@example.com", date, &first).unwrap();
+        writer.write_message("b@example.com", date, &second).unwrap();
+        let mbox = writer.finish().unwrap();
+
+        let messages: Vec<Vec<u8>> = MboxReader::new(mbox.as_slice()).collect::<io::Result<_>>().unwrap();
+        assert_eq!(messages, vec![first, second]);
+    }
+}
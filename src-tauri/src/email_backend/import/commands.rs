@@ -0,0 +1,308 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+use email::envelope::{Envelope, Envelopes};
+use email::flag::{Flag, Flags};
+use email::message::add::AddMessage;
+use email::message::Message;
+use crate::email_backend::emails::commands::save_message_content;
+use crate::email_backend::import::source::{ImportSource, SourceMessage};
+use crate::email_backend::sync::SyncEngine;
+
+/// Progress is reported every this many messages.
+const PROGRESS_INTERVAL: usize = 25;
+
+/// Where imported messages go.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImportTarget {
+    /// A folder on the server, messages are uploaded with their flags.
+    Folder { folder_id: i64 },
+    /// A folder kept only in this app, created if it does not exist yet.
+    LocalFolder { account_id: i64, name: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    pub source_path: String,
+    pub done: usize,
+    pub total: usize,
+    pub imported: usize,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ImportSummary {
+    pub total: usize,
+    pub imported: usize,
+    /// Messages already in the folder.
+    pub skipped: usize,
+}
+
+pub(crate) async fn local_folder_id(pool: &SqlitePool, account_id: i64, name: &str) -> Result<i64, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Folder name is required".to_string());
+    }
+
+    sqlx::query_scalar(
+        "INSERT INTO folders (account_id, name, path, is_local) VALUES (?, ?, ?, 1)
+         ON CONFLICT(account_id, path) DO UPDATE SET name = excluded.name
+         RETURNING id"
+    )
+    .bind(account_id)
+    .bind(name)
+    .bind(format!("local:{}", name))
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Imports the messages of `source_path` into a folder. An import that was interrupted carries on
+/// after the last message it handled, and messages whose Message-ID is already in the folder are skipped.
+async fn import_into_folder<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, source_path: &str, folder_id: i64) -> Result<ImportSummary, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+    let (account_id, folder_path, is_local): (i64, String, bool) = sqlx::query_as(
+        "SELECT account_id, path, is_local FROM folders WHERE id = ?"
+    )
+    .bind(folder_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let source = ImportSource::detect(Path::new(source_path))?;
+    let total = source.count()?;
+
+    // A finished import of the same source starts over, only duplicates get skipped then
+    sqlx::query(
+        "INSERT INTO imports (source_path, folder_id) VALUES (?, ?)
+         ON CONFLICT(source_path, folder_id) DO UPDATE SET
+            processed = CASE WHEN completed_at IS NULL THEN processed ELSE 0 END,
+            imported = CASE WHEN completed_at IS NULL THEN imported ELSE 0 END,
+            completed_at = NULL"
+    )
+    .bind(source_path)
+    .bind(folder_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (import_id, processed, mut imported): (i64, i64, i64) = sqlx::query_as(
+        "SELECT id, processed, imported FROM imports WHERE source_path = ? AND folder_id = ?"
+    )
+    .bind(source_path)
+    .bind(folder_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut known: HashSet<String> = sqlx::query_scalar::<_, String>(
+        "SELECT message_id FROM emails WHERE folder_id = ? AND message_id IS NOT NULL AND message_id NOT LIKE '%@generated>'
+         UNION SELECT remote_id FROM emails WHERE folder_id = ? AND remote_id LIKE 'local-%'"
+    )
+    .bind(folder_id)
+    .bind(folder_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .collect();
+
+    let backend = if is_local {
+        None
+    } else {
+        Some(app_handle.state::<SyncEngine>().get_backend(account_id).await?)
+    };
+
+    let mut done = processed as usize;
+    for message in source.messages()?.skip(done) {
+        let SourceMessage { raw, flags } = message?;
+        let message = Message::from(raw.as_slice());
+        let key = dedup_key(&message, &raw);
+        let envelope = Envelope::from_msg(
+            String::new(),
+            Flags::from_iter(flags.iter().map(|f| Flag::from(f.as_str()))),
+            message,
+        );
+
+        if known.insert(key) {
+            match &backend {
+                Some(backend) => {
+                    backend
+                        .add_message_with_flags(&folder_path, &raw, &envelope.flags)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                None => save_local_message(app_handle, account_id, folder_id, envelope, &raw).await?,
+            }
+            imported += 1;
+        }
+
+        done += 1;
+        sqlx::query("UPDATE imports SET processed = ?, imported = ? WHERE id = ?")
+            .bind(done as i64)
+            .bind(imported)
+            .bind(import_id)
+            .execute(&pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if done % PROGRESS_INTERVAL == 0 || done == total {
+            let _ = app_handle.emit("import-progress", ImportProgress {
+                source_path: source_path.to_string(),
+                done,
+                total,
+                imported: imported as usize,
+            });
+        }
+    }
+
+    sqlx::query("UPDATE imports SET completed_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(import_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if is_local {
+        sqlx::query("UPDATE folders SET total_count = (SELECT COUNT(*) FROM emails WHERE folder_id = ?) WHERE id = ?")
+            .bind(folder_id)
+            .bind(folder_id)
+            .execute(&pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let _ = app_handle.emit("emails-updated", "bulk-add");
+    } else {
+        app_handle.state::<SyncEngine>().refresh_folder(account_id, folder_id).await?;
+    }
+
+    Ok(ImportSummary {
+        total: done,
+        imported: imported as usize,
+        skipped: done - imported as usize,
+    })
+}
+
+/// Local messages have no server id, a hash of their source stands in for it.
+fn local_id(raw: &[u8]) -> String {
+    format!("local-{:x}", md5::compute(raw))
+}
+
+/// What tells messages apart when skipping duplicates. email-lib makes up a Message-ID from the
+/// date for messages without one, which distinct messages can share, so those go by their
+/// local id instead.
+fn dedup_key(message: &Message, raw: &[u8]) -> String {
+    match message.parsed().ok().and_then(|parsed| parsed.message_id()) {
+        Some(message_id) => format!("<{}>", message_id),
+        None => local_id(raw),
+    }
+}
+
+async fn save_local_message<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    account_id: i64,
+    folder_id: i64,
+    mut envelope: Envelope,
+    raw: &[u8],
+) -> Result<(), String> {
+    envelope.id = local_id(raw);
+    let envelopes: Envelopes = vec![envelope].into_iter().collect();
+    let ids = app_handle.state::<SyncEngine>().save_envelopes(account_id, folder_id, envelopes, &HashMap::new(), None).await?;
+
+    let message = Message::from(raw);
+    for id in ids {
+        save_message_content(app_handle, id, &message).await?;
+    }
+    Ok(())
+}
+
+/// Imports an mbox file, a Maildir or a directory of `.eml` files and emits `import-progress` as it goes.
+#[tauri::command]
+pub async fn import_messages<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, source_path: String, target: ImportTarget) -> Result<ImportSummary, String> {
+    let folder_id = match target {
+        ImportTarget::Folder { folder_id } => folder_id,
+        ImportTarget::LocalFolder { account_id, name } => {
+            let pool = app_handle.state::<SqlitePool>();
+            local_folder_id(&pool, account_id, &name).await?
+        }
+    };
+
+    import_into_folder(&app_handle, &source_path, folder_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::AppContext;
+    use crate::utils::test_utils::setup_test_db;
+    use tauri::test::mock_builder;
+
+    fn message(n: usize, message_id: &str) -> String {
+        format!(
+            "From: Sender <sender@example.com>\r\nTo: me@example.com\r\nSubject: Message {}\r\nMessage-ID: <{}>\r\nDate: Tue, 14 Nov 2023 22:13:20 +0000\r\n\r\nBody {}\r\n",
+            n, message_id, n
+        )
+    }
+
+    #[tokio::test]
+    async fn test_local_import_skips_duplicates_and_resumes() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let folder_id = local_folder_id(&pool, account_id, "Old mail").await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        for (n, id) in [(1, "m1@example.com"), (2, "m2@example.com"), (3, "m1@example.com")] {
+            std::fs::write(dir.path().join(format!("{}.eml", n)), message(n, id)).unwrap();
+        }
+        let source_path = dir.path().to_string_lossy().to_string();
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool.clone());
+        let context = AppContext::new(pool.clone(), dir.path().to_path_buf(), Arc::new(app.handle().clone()));
+        app.manage(context.clone());
+        app.manage(SyncEngine::new(context));
+
+        // Pretend an earlier run stopped after the first message
+        let first_run = import_into_folder(&app.handle(), &source_path, folder_id).await.unwrap();
+        assert_eq!(first_run, ImportSummary { total: 3, imported: 2, skipped: 1 });
+
+        sqlx::query("UPDATE imports SET processed = 1, imported = 1, completed_at = NULL")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let resumed = import_into_folder(&app.handle(), &source_path, folder_id).await.unwrap();
+        assert_eq!(resumed, ImportSummary { total: 3, imported: 1, skipped: 2 });
+
+        let (count, body): (i64, Option<String>) = sqlx::query_as(
+            "SELECT COUNT(*), MAX(body_text) FROM emails WHERE folder_id = ?"
+        )
+        .bind(folder_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+        assert!(body.unwrap().contains("Body"));
+
+        let is_local: bool = sqlx::query_scalar("SELECT is_local FROM folders WHERE id = ?")
+            .bind(folder_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(is_local);
+    }
+
+    #[test]
+    fn test_messages_without_message_id_go_by_their_source() {
+        let first = "From: a@example.com\r\nDate: Tue, 14 Nov 2023 22:13:20 +0000\r\n\r\nOne\r\n";
+        let second = "From: a@example.com\r\nDate: Tue, 14 Nov 2023 22:13:20 +0000\r\n\r\nTwo\r\n";
+        let key = |raw: &str| dedup_key(&Message::from(raw.as_bytes()), raw.as_bytes());
+
+        assert_ne!(key(first), key(second));
+        assert_eq!(key(first), local_id(first.as_bytes()));
+        assert_eq!(key(&message(1, "m1@example.com")), "<m1@example.com>");
+    }
+}
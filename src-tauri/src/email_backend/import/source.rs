@@ -0,0 +1,182 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use crate::email_backend::export::mbox::MboxReader;
+
+/// A message read from an archive, with the flags the archive recorded for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMessage {
+    pub raw: Vec<u8>,
+    pub flags: Vec<String>,
+}
+
+pub type SourceMessages = Box<dyn Iterator<Item = Result<SourceMessage, String>> + Send>;
+
+/// Where imported messages come from.
+#[derive(Debug, Clone)]
+pub enum ImportSource {
+    Mbox(PathBuf),
+    Eml(Vec<PathBuf>),
+    /// Message files in `cur` and `new`, in a stable order.
+    Maildir(Vec<PathBuf>),
+}
+
+impl ImportSource {
+    /// Picks the format from the path: a directory with `cur` and `new` is a Maildir, any other
+    /// directory is searched for `.eml` files, and a file is mbox unless it ends in `.eml`.
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        if path.is_file() {
+            let is_eml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("eml"));
+            return Ok(if is_eml { ImportSource::Eml(vec![path.to_path_buf()]) } else { ImportSource::Mbox(path.to_path_buf()) });
+        }
+        if !path.is_dir() {
+            return Err(format!("{} does not exist", path.display()));
+        }
+
+        if path.join("cur").is_dir() && path.join("new").is_dir() {
+            let mut files = Vec::new();
+            for sub in ["cur", "new"] {
+                files.extend(list_files(&path.join(sub), false, |_| true)?);
+            }
+            return Ok(ImportSource::Maildir(files));
+        }
+
+        let files = list_files(path, true, |p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("eml")))?;
+        Ok(ImportSource::Eml(files))
+    }
+
+    /// Number of messages in the source, found without keeping them in memory.
+    pub fn count(&self) -> Result<usize, String> {
+        match self {
+            ImportSource::Mbox(path) => {
+                let reader = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+                let mut count = 0;
+                for line in reader.split(b'\n') {
+                    if line.map_err(|e| e.to_string())?.starts_with(b"From ") {
+                        count += 1;
+                    }
+                }
+                Ok(count)
+            }
+            ImportSource::Eml(files) | ImportSource::Maildir(files) => Ok(files.len()),
+        }
+    }
+
+    pub fn messages(&self) -> Result<SourceMessages, String> {
+        match self {
+            ImportSource::Mbox(path) => {
+                let reader = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+                Ok(Box::new(MboxReader::new(reader).map(|raw| {
+                    let raw = raw.map_err(|e| e.to_string())?;
+                    let flags = mbox_flags(&raw);
+                    Ok(SourceMessage { raw, flags })
+                })))
+            }
+            ImportSource::Eml(files) => Ok(Box::new(files.clone().into_iter().map(|path| {
+                let raw = std::fs::read(&path).map_err(|e| e.to_string())?;
+                Ok(SourceMessage { raw, flags: Vec::new() })
+            }))),
+            ImportSource::Maildir(files) => Ok(Box::new(files.clone().into_iter().map(|path| {
+                let raw = std::fs::read(&path).map_err(|e| e.to_string())?;
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                Ok(SourceMessage { raw, flags: maildir_flags(&name) })
+            }))),
+        }
+    }
+}
+
+fn list_files(dir: &Path, recursive: bool, keep: impl Fn(&Path) -> bool + Copy) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            if recursive {
+                files.extend(list_files(&path, true, keep)?);
+            }
+        } else if keep(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Flags from the info part of a Maildir file name, e.g. `1700000000.M1P2.host:2,FS`.
+fn maildir_flags(file_name: &str) -> Vec<String> {
+    let Some((_, info)) = file_name.rsplit_once(":2,") else {
+        return Vec::new();
+    };
+    info.chars()
+        .filter_map(|c| match c {
+            'S' => Some("seen"),
+            'R' => Some("answered"),
+            'F' => Some("flagged"),
+            'T' => Some("deleted"),
+            'D' => Some("draft"),
+            _ => None,
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Flags from the `Status` and `X-Status` headers that mbox mail programs write.
+fn mbox_flags(raw: &[u8]) -> Vec<String> {
+    let mut flags = Vec::new();
+    for line in raw.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            break;
+        }
+        let line = String::from_utf8_lossy(line);
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let mapping: &[(char, &str)] = if name.eq_ignore_ascii_case("Status") {
+            &[('R', "seen")]
+        } else if name.eq_ignore_ascii_case("X-Status") {
+            &[('A', "answered"), ('F', "flagged"), ('D', "deleted"), ('T', "draft")]
+        } else {
+            continue;
+        };
+        for (c, flag) in mapping {
+            if value.trim().contains(*c) && !flags.iter().any(|f| f == flag) {
+                flags.push(flag.to_string());
+            }
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maildir_flags() {
+        assert_eq!(maildir_flags("1700000000.M1P2.host:2,FRS"), vec!["flagged", "answered", "seen"]);
+        assert_eq!(maildir_flags("1700000000.M1P2.host:2,"), Vec::<String>::new());
+        assert_eq!(maildir_flags("1700000000.M1P2.host"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_mbox_flags_stop_at_body() {
+        let raw = b"Subject: Hi\r\nStatus: RO\r\nX-Status: AF\r\n\r\nX-Status: D\r\n";
+        assert_eq!(mbox_flags(raw), vec!["seen", "answered", "flagged"]);
+    }
+
+    #[test]
+    fn test_detect_and_read_maildir() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["cur", "new", "tmp"] {
+            std::fs::create_dir(dir.path().join(sub)).unwrap();
+        }
+        std::fs::write(dir.path().join("cur/1.host:2,S"), b"Subject: One\r\n\r\nBody\r\n").unwrap();
+        std::fs::write(dir.path().join("new/2.host"), b"Subject: Two\r\n\r\nBody\r\n").unwrap();
+
+        let source = ImportSource::detect(dir.path()).unwrap();
+        assert!(matches!(source, ImportSource::Maildir(_)));
+        assert_eq!(source.count().unwrap(), 2);
+
+        let messages: Vec<SourceMessage> = source.messages().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(messages[0].flags, vec!["seen"]);
+        assert!(messages[1].flags.is_empty());
+    }
+}
@@ -1,3 +1,22 @@
-use sqlx::SqlitePool;
-use tauri::Manager;
-use log::info;
+use crate::context::AppContext;
+
+pub struct AiConfig {
+    pub api_key: String,
+    pub base_url: String,
+    pub model: String,
+}
+
+/// Single accessor for the AI provider configuration. The API key is read from the
+/// encrypted secret store, everything else from the `settings` table.
+pub async fn load_ai_config(app: &AppContext) -> Result<AiConfig, String> {
+    let settings = app.settings();
+    let base_url = settings.get("aiBaseUrl").await.unwrap_or_else(|| String::from("https://api.openai.com/v1"));
+    let model = settings.get("aiModel").await.unwrap_or_default();
+    let api_key = app.secrets().await?.get("aiApiKey")?.unwrap_or_default();
+
+    if api_key.is_empty() || model.is_empty() {
+        return Err("AI API Key or Model not configured".to_string());
+    }
+
+    Ok(AiConfig { api_key, base_url, model })
+}
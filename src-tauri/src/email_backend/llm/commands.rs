@@ -1,5 +1,8 @@
 use tauri::command;
 use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use crate::context::AppContext;
+use crate::db::secrets::REDACTED_SECRET;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AIModel {
@@ -12,7 +15,14 @@ struct OpenAIModelsResponse {
 }
 
 #[command]
-pub async fn get_available_models(base_url: String, api_key: String) -> Result<Vec<AIModel>, String> {
+pub async fn get_available_models(app_handle: tauri::AppHandle, base_url: String, api_key: String) -> Result<Vec<AIModel>, String> {
+    // The settings form only ever sees the redacted placeholder for a stored key
+    let api_key = if api_key == REDACTED_SECRET {
+        app_handle.state::<AppContext>().secrets().await?.get("aiApiKey")?.unwrap_or_default()
+    } else {
+        api_key
+    };
+
     let client = reqwest::Client::new();
     let url = if base_url.ends_with("/models") {
         base_url
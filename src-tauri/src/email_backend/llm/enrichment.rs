@@ -1,10 +1,10 @@
 use serde_json::{Value, json};
 use log::{info, error, debug, warn};
-use sqlx::SqlitePool;
-use tauri::Manager;
+use crate::context::AppContext;
+use crate::email_backend::llm::client::{load_ai_config, AiConfig};
 
-pub async fn enrich_sender_with_ai<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+pub async fn enrich_sender_with_ai(
+    app: &AppContext,
     sender_address: &str,
     email_snippets: Vec<String>,
 ) -> Result<Value, String> {
@@ -14,7 +14,7 @@ pub async fn enrich_sender_with_ai<R: tauri::Runtime>(
     let max_retries = 2;
 
     for attempt in 1..=max_retries {
-        match try_enrich_sender_direct(app_handle, sender_address, &email_snippets).await {
+        match try_enrich_sender_direct(app, sender_address, &email_snippets).await {
             Ok(json_val) => {
                 info!("Successfully enriched sender: {} (attempt {})", sender_address, attempt);
                 return Ok(json_val);
@@ -34,35 +34,12 @@ pub async fn enrich_sender_with_ai<R: tauri::Runtime>(
     Err(last_error)
 }
 
-async fn try_enrich_sender_direct<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+async fn try_enrich_sender_direct(
+    app: &AppContext,
     sender_address: &str,
     email_snippets: &[String],
 ) -> Result<Value, String> {
-    let pool = app_handle.state::<SqlitePool>();
-    
-    let rows: Vec<(String, String)> = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM settings WHERE key IN ('aiApiKey', 'aiBaseUrl', 'aiModel')")
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-        
-    let mut api_key = String::new();
-    let mut base_url = String::from("https://api.openai.com/v1");
-    let mut model = String::new();
-
-    for (key, value) in rows {
-        let unquoted = serde_json::from_str::<String>(&value).unwrap_or(value);
-        match key.as_str() {
-            "aiApiKey" => api_key = unquoted,
-            "aiBaseUrl" => base_url = unquoted,
-            "aiModel" => model = unquoted,
-            _ => {}
-        }
-    }
-
-    if api_key.is_empty() || model.is_empty() {
-        return Err("AI API Key or Model not configured".to_string());
-    }
+    let AiConfig { api_key, base_url, model } = load_ai_config(app).await?;
 
     let client = reqwest::Client::new();
     let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
@@ -1,17 +1,17 @@
 use serde_json::{Value, json};
 use log::{info, debug, warn};
-use sqlx::SqlitePool;
-use tauri::Manager;
+use crate::context::AppContext;
+use crate::email_backend::llm::client::{load_ai_config, AiConfig};
 
-pub async fn summarize_email_with_ai<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+pub async fn summarize_email_with_ai(
+    app: &AppContext,
     email_id: i64,
     body_text: &str,
     force: bool,
 ) -> Result<String, String> {
     debug!("Starting AI summarization for email: {} (force: {})", email_id, force);
 
-    let pool = app_handle.state::<SqlitePool>();
+    let pool = app.pool();
     let trimmed_body = body_text.trim();
 
     // 1. Skip if body text is very small (less than 150 characters is usually not worth summarizing)
@@ -37,7 +37,7 @@ pub async fn summarize_email_with_ai<R: tauri::Runtime>(
         if image_indicators.iter().any(|&ind| lower_body.contains(ind)) {
              let has_images: bool = sqlx::query_scalar::<_, i32>("SELECT 1 FROM attachments WHERE email_id = ? AND mime_type LIKE 'image/%' LIMIT 1")
                 .bind(email_id)
-                .fetch_optional(&*pool)
+                .fetch_optional(pool)
                 .await
                 .unwrap_or(None)
                 .is_some();
@@ -53,7 +53,7 @@ pub async fn summarize_email_with_ai<R: tauri::Runtime>(
     if !force {
         let existing_summary: Option<String> = sqlx::query_scalar("SELECT summary FROM emails WHERE body_text = ? AND summary IS NOT NULL AND summary != '' LIMIT 1")
             .bind(body_text)
-            .fetch_optional(&*pool)
+            .fetch_optional(pool)
             .await
             .unwrap_or(None);
 
@@ -63,28 +63,7 @@ pub async fn summarize_email_with_ai<R: tauri::Runtime>(
         }
     }
 
-    let rows: Vec<(String, String)> = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM settings WHERE key IN ('aiApiKey', 'aiBaseUrl', 'aiModel')")
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let mut api_key = String::new();
-    let mut base_url = String::from("https://api.openai.com/v1");
-    let mut model = String::new();
-
-    for (key, value) in rows {
-        let unquoted = serde_json::from_str::<String>(&value).unwrap_or(value);
-        match key.as_str() {
-            "aiApiKey" => api_key = unquoted,
-            "aiBaseUrl" => base_url = unquoted,
-            "aiModel" => model = unquoted,
-            _ => {} // Ignore other keys
-        }
-    }
-
-    if api_key.is_empty() || model.is_empty() {
-        return Err("AI API Key or Model not configured".to_string());
-    }
+    let AiConfig { api_key, base_url, model } = load_ai_config(app).await?;
 
     let client = reqwest::Client::new();
     let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
@@ -2,4 +2,13 @@ pub mod accounts;
 pub mod sync;
 pub mod emails;
 pub mod enrichment;
-pub mod llm;
\ No newline at end of file
+pub mod llm;
+pub mod export;
+pub mod import;
+pub mod rules;
+pub mod sieve;
+pub mod timers;
+pub mod subscriptions;
+pub mod notifications;
+pub mod calendar;
+pub mod contacts;
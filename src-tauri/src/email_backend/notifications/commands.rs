@@ -0,0 +1,74 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::timers::run::thread_of;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AccountNotifications {
+    pub account_id: i64,
+    pub enabled: bool,
+}
+
+/// Stops notifications for new mail in the messages' threads.
+#[tauri::command]
+pub async fn mute_threads<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    for email_id in email_ids.into_iter().filter(|&id| id >= 0) {
+        let (account_id, thread_id) = thread_of(&pool, email_id).await?;
+        sqlx::query("INSERT OR IGNORE INTO muted_threads (account_id, thread_id) VALUES (?, ?)")
+            .bind(account_id)
+            .bind(thread_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unmute_threads<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    for email_id in email_ids.into_iter().filter(|&id| id >= 0) {
+        let (account_id, thread_id) = thread_of(&pool, email_id).await?;
+        sqlx::query("DELETE FROM muted_threads WHERE account_id = ? AND thread_id = ?")
+            .bind(account_id)
+            .bind(thread_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_account_notifications<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<AccountNotifications>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT id AS account_id, notifications_enabled AS enabled FROM accounts")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_account_notifications<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, enabled: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("UPDATE accounts SET notifications_enabled = ? WHERE id = ?")
+        .bind(enabled)
+        .bind(account_id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_folder_notifications<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, folder_id: i64, enabled: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("UPDATE folders SET notifications_enabled = ? WHERE id = ?")
+        .bind(enabled)
+        .bind(folder_id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
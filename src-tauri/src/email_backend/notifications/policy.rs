@@ -0,0 +1,294 @@
+//! Decides which new mail reaches the desktop: VIPs, quiet hours, muted threads, per-account and
+//! per-folder switches, and batching so a bulk sync does not flood the screen.
+
+use std::collections::HashMap;
+use chrono::{Local, Timelike};
+use sqlx::SqlitePool;
+use crate::context::AppContext;
+use crate::email_backend::sync::worker::SyncWorker;
+use crate::email_backend::timers::run::thread_of;
+
+/// More separate notifications than this collapse into one.
+const MAX_NOTIFICATIONS: usize = 5;
+/// Subjects listed in a batched notification.
+const BATCH_SUBJECTS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub enabled: bool,
+    /// Only contacts notify.
+    pub vips_only: bool,
+    /// Start and end hour in local time; the range may wrap past midnight.
+    pub quiet_hours: Option<(u32, u32)>,
+    pub quiet_hours_allow_vips: bool,
+}
+
+impl Policy {
+    pub async fn load(app: &AppContext) -> Self {
+        let settings = app.settings();
+        let quiet_hours = if settings.is_enabled("quietHoursEnabled", false).await {
+            let start = settings.number("quietHoursStart", 22).await.clamp(0, 23) as u32;
+            let end = settings.number("quietHoursEnd", 7).await.clamp(0, 23) as u32;
+            Some((start, end))
+        } else {
+            None
+        };
+        Self {
+            enabled: settings.is_enabled("notificationsEnabled", true).await,
+            vips_only: settings.is_enabled("notifyVipsOnly", false).await,
+            quiet_hours,
+            quiet_hours_allow_vips: settings.is_enabled("quietHoursAllowVips", true).await,
+        }
+    }
+
+    fn is_quiet(&self, hour: u32) -> bool {
+        match self.quiet_hours {
+            Some((start, end)) if start <= end => hour >= start && hour < end,
+            Some((start, end)) => hour >= start || hour < end,
+            None => false,
+        }
+    }
+}
+
+/// A new message up for a notification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub email_id: i64,
+    pub subject: String,
+    pub sender: String,
+    /// The sender is a contact.
+    pub is_vip: bool,
+    /// Its account and folder notify and its thread is not muted.
+    pub allowed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Planned {
+    Single { email_id: i64, subject: String, sender: String },
+    Batch { title: String, body: String },
+}
+
+fn subjects(candidates: &[&Candidate]) -> String {
+    let mut listed: Vec<&str> = candidates.iter().take(BATCH_SUBJECTS).map(|c| c.subject.as_str()).collect();
+    if candidates.len() > BATCH_SUBJECTS {
+        listed.push("…");
+    }
+    listed.join(", ")
+}
+
+/// Plans the notifications for a batch of new mail at `hour` local time. VIPs always get their
+/// own notification, other senders with several messages get one each, and when that is still
+/// too many everything collapses into a single notification.
+pub fn plan(policy: &Policy, candidates: &[Candidate], hour: u32) -> Vec<Planned> {
+    if !policy.enabled {
+        return Vec::new();
+    }
+    let quiet = policy.is_quiet(hour);
+    let notifying: Vec<&Candidate> = candidates.iter()
+        .filter(|c| c.allowed)
+        .filter(|c| c.is_vip || !policy.vips_only)
+        .filter(|c| !quiet || (c.is_vip && policy.quiet_hours_allow_vips))
+        .collect();
+
+    let mut planned = Vec::new();
+    let mut by_sender: Vec<(&str, Vec<&Candidate>)> = Vec::new();
+    for &candidate in &notifying {
+        if candidate.is_vip {
+            planned.push(Planned::Single {
+                email_id: candidate.email_id,
+                subject: candidate.subject.clone(),
+                sender: candidate.sender.clone(),
+            });
+            continue;
+        }
+        match by_sender.iter_mut().find(|(sender, _)| *sender == candidate.sender) {
+            Some((_, group)) => group.push(candidate),
+            None => by_sender.push((candidate.sender.as_str(), vec![candidate])),
+        }
+    }
+    for (sender, group) in by_sender {
+        planned.push(match group.as_slice() {
+            [only] => Planned::Single { email_id: only.email_id, subject: only.subject.clone(), sender: sender.to_string() },
+            _ => Planned::Batch { title: format!("{} new emails from {}", group.len(), sender), body: subjects(&group) },
+        });
+    }
+
+    if planned.len() > MAX_NOTIFICATIONS {
+        let mut senders: Vec<&str> = Vec::new();
+        for candidate in &notifying {
+            if !senders.contains(&candidate.sender.as_str()) {
+                senders.push(candidate.sender.as_str());
+            }
+        }
+        let body = match senders.len() {
+            n if n > BATCH_SUBJECTS => format!("From {} and {} others", senders[..BATCH_SUBJECTS].join(", "), n - BATCH_SUBJECTS),
+            _ => format!("From {}", senders.join(", ")),
+        };
+        return vec![Planned::Batch { title: format!("{} new emails", notifying.len()), body }];
+    }
+    planned
+}
+
+/// Looks up VIP status, switches and muted threads for new mail given as id, subject and sender.
+pub async fn load_candidates(pool: &SqlitePool, mail: &[(i64, String, String)]) -> Result<Vec<Candidate>, String> {
+    let mut muted: HashMap<(i64, String), bool> = HashMap::new();
+    let mut candidates = Vec::new();
+
+    for (email_id, subject, sender) in mail {
+        let info: Option<(bool, bool, bool)> = sqlx::query_as(
+            "SELECT COALESCE(s.is_contact, 0), a.notifications_enabled, f.notifications_enabled
+             FROM emails e
+             JOIN accounts a ON a.id = e.account_id
+             JOIN folders f ON f.id = e.folder_id
+             LEFT JOIN senders s ON s.address = e.sender_address
+             WHERE e.id = ?"
+        )
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some((is_vip, account_on, folder_on)) = info else {
+            continue;
+        };
+
+        // New mail is saved as a thread of its own, so replies are linked before the lookup
+        SyncWorker::link_reply(pool, *email_id).await?;
+        let thread = thread_of(pool, *email_id).await?;
+        let thread_muted = match muted.get(&thread) {
+            Some(&value) => value,
+            None => {
+                let value: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM muted_threads WHERE account_id = ? AND thread_id = ?)")
+                    .bind(thread.0)
+                    .bind(&thread.1)
+                    .fetch_one(pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                muted.insert(thread, value);
+                value
+            }
+        };
+
+        candidates.push(Candidate {
+            email_id: *email_id,
+            subject: subject.clone(),
+            sender: sender.clone(),
+            is_vip,
+            allowed: account_on && folder_on && !thread_muted,
+        });
+    }
+    Ok(candidates)
+}
+
+/// The notifications to show for new mail right now.
+pub async fn notification_plan(app: &AppContext, mail: &[(i64, String, String)]) -> Result<Vec<Planned>, String> {
+    let policy = Policy::load(app).await;
+    if !policy.enabled || mail.is_empty() {
+        return Ok(Vec::new());
+    }
+    let candidates = load_candidates(app.pool(), mail).await?;
+    Ok(plan(&policy, &candidates, Local::now().hour()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    fn candidate(email_id: i64, sender: &str, is_vip: bool) -> Candidate {
+        Candidate { email_id, subject: format!("Subject {}", email_id), sender: sender.to_string(), is_vip, allowed: true }
+    }
+
+    fn policy() -> Policy {
+        Policy { enabled: true, vips_only: false, quiet_hours: Some((22, 7)), quiet_hours_allow_vips: true }
+    }
+
+    #[test]
+    fn test_plan_batches_senders_and_keeps_quiet_hours() {
+        let mail = vec![
+            candidate(1, "GitHub", false),
+            candidate(2, "Ann", true),
+            candidate(3, "GitHub", false),
+            candidate(4, "Shop", false),
+        ];
+        assert_eq!(plan(&policy(), &mail, 12), vec![
+            Planned::Single { email_id: 2, subject: "Subject 2".to_string(), sender: "Ann".to_string() },
+            Planned::Batch { title: "2 new emails from GitHub".to_string(), body: "Subject 1, Subject 3".to_string() },
+            Planned::Single { email_id: 4, subject: "Subject 4".to_string(), sender: "Shop".to_string() },
+        ]);
+
+        // Quiet hours wrap past midnight and only let VIPs through
+        assert_eq!(plan(&policy(), &mail, 23).len(), 1);
+        assert_eq!(plan(&policy(), &mail, 3).len(), 1);
+        assert_eq!(plan(&Policy { quiet_hours_allow_vips: false, ..policy() }, &mail, 3), vec![]);
+        assert_eq!(plan(&Policy { vips_only: true, ..policy() }, &mail, 12).len(), 1);
+
+        let muted = vec![Candidate { allowed: false, ..candidate(5, "Ann", true) }];
+        assert!(plan(&policy(), &muted, 12).is_empty());
+
+        let flood: Vec<Candidate> = (1..=8).map(|n| candidate(n, &format!("Sender {}", n), false)).collect();
+        assert_eq!(plan(&policy(), &flood, 12), vec![Planned::Batch {
+            title: "8 new emails".to_string(),
+            body: "From Sender 1, Sender 2, Sender 3 and 5 others".to_string(),
+        }]);
+    }
+
+    #[tokio::test]
+    async fn test_candidates_follow_switches_contacts_and_muted_threads() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let inbox_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let lists_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, notifications_enabled) VALUES (?, 'Lists', 'Lists', 0) RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO senders (address, is_contact) VALUES ('ann@example.com', 1)").execute(&pool).await.unwrap();
+
+        let mut ids = Vec::new();
+        for (folder_id, sender, thread) in [(inbox_id, "ann@example.com", "t1"), (inbox_id, "bob@example.com", "t2"), (lists_id, "list@example.com", "t3")] {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, sender_address, date, flags)
+                 VALUES (?, ?, ?, ?, ?, ?, '2024-01-01T10:00:00Z', '[]') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(thread)
+            .bind(thread)
+            .bind(thread)
+            .bind(sender)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push((id, "Hello".to_string(), sender.to_string()));
+        }
+        sqlx::query("INSERT INTO muted_threads (account_id, thread_id) VALUES (?, 't2')").bind(account_id).execute(&pool).await.unwrap();
+
+        let candidates = load_candidates(&pool, &ids).await.unwrap();
+        let summary: Vec<(bool, bool)> = candidates.iter().map(|c| (c.is_vip, c.allowed)).collect();
+        assert_eq!(summary, vec![(true, true), (false, false), (false, false)]);
+
+        // A reply arrives from sync as its own thread and still follows the mute
+        let reply_id: i64 = sqlx::query_scalar(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, in_reply_to, references_header, sender_address, date, flags)
+             VALUES (?, ?, 'r1', '<r1>', '<r1>', '<other>', 't2 <other>', 'bob@example.com', '2024-01-02T10:00:00Z', '[]') RETURNING id"
+        )
+        .bind(account_id)
+        .bind(inbox_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let reply = vec![(reply_id, "Re: Hello".to_string(), "bob@example.com".to_string())];
+        assert!(!load_candidates(&pool, &reply).await.unwrap()[0].allowed);
+        assert_eq!(thread_of(&pool, reply_id).await.unwrap().1, "t2");
+
+        sqlx::query("UPDATE accounts SET notifications_enabled = 0").execute(&pool).await.unwrap();
+        assert!(load_candidates(&pool, &ids).await.unwrap().iter().all(|c| !c.allowed));
+    }
+}
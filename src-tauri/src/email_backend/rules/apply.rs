@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+use email::flag::Flag;
+use log::{info, warn};
+use mail_builder::MessageBuilder;
+use sqlx::SqlitePool;
+use crate::email_backend::emails::commands::{move_email, update_email_flags};
+use crate::email_backend::emails::transport::Transport;
+use crate::email_backend::export::commands::raw_source;
+use crate::email_backend::rules::types::{parse_headers, Action, MailFacts, Rule, SenderCategory};
+use crate::email_backend::sync::SyncEngine;
+
+type RuleRow = (i64, Option<i64>, String, bool, bool, String, String, bool, i64);
+
+const RULE_COLUMNS: &str = "id, account_id, name, enabled, match_all, conditions, actions, stop_processing, position";
+
+fn rule_from_row(row: RuleRow) -> Result<Rule, String> {
+    let (id, account_id, name, enabled, match_all, conditions, actions, stop_processing, position) = row;
+    Ok(Rule {
+        id: Some(id),
+        account_id,
+        name,
+        enabled,
+        match_all,
+        conditions: serde_json::from_str(&conditions).map_err(|e| e.to_string())?,
+        actions: serde_json::from_str(&actions).map_err(|e| e.to_string())?,
+        stop_processing,
+        position,
+    })
+}
+
+/// All rules in the order they run. Rows this version cannot read are skipped.
+pub async fn load_rules(pool: &SqlitePool) -> Result<Vec<Rule>, String> {
+    let rows: Vec<RuleRow> = sqlx::query_as(&format!("SELECT {} FROM rules ORDER BY position, id", RULE_COLUMNS))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter()
+        .filter_map(|row| {
+            let id = row.0;
+            rule_from_row(row).map_err(|e| warn!("Skipping unreadable rule {}: {}", id, e)).ok()
+        })
+        .collect())
+}
+
+pub async fn load_rule(pool: &SqlitePool, rule_id: i64) -> Result<Rule, String> {
+    let row: RuleRow = sqlx::query_as(&format!("SELECT {} FROM rules WHERE id = ?", RULE_COLUMNS))
+        .bind(rule_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Rule {} not found", rule_id))?;
+    rule_from_row(row)
+}
+
+/// What a run of the rules did.
+#[derive(Debug, Default)]
+pub struct RulesOutcome {
+    /// Messages at least one rule matched.
+    pub matched: Vec<i64>,
+    /// Messages that should not notify.
+    pub silenced: HashSet<i64>,
+}
+
+/// Runs the stored rules over newly arrived messages.
+pub async fn apply_rules(engine: &SyncEngine, email_ids: &[i64]) -> Result<RulesOutcome, String> {
+    let rules: Vec<Rule> = load_rules(engine.app().pool()).await?
+        .into_iter()
+        .filter(|rule| rule.enabled)
+        .collect();
+    apply_rule_set(engine, &rules, email_ids).await
+}
+
+/// Runs `rules` over the messages. A failing action is logged and the others still run.
+pub async fn apply_rule_set(engine: &SyncEngine, rules: &[Rule], email_ids: &[i64]) -> Result<RulesOutcome, String> {
+    let mut outcome = RulesOutcome::default();
+    if rules.is_empty() {
+        return Ok(outcome);
+    }
+    let with_headers = rules.iter().any(Rule::needs_headers);
+
+    for &email_id in email_ids {
+        let Some(mail) = mail_facts(engine, email_id, with_headers).await? else {
+            continue;
+        };
+
+        let mut actions = Vec::new();
+        for rule in rules {
+            if !rule.matches(&mail) {
+                continue;
+            }
+            info!("Rule \"{}\" matched email {}", rule.name, email_id);
+            actions.extend(rule.actions.iter().cloned());
+            if rule.stop_processing {
+                break;
+            }
+        }
+
+        if actions.is_empty() {
+            continue;
+        }
+        outcome.matched.push(email_id);
+        run_actions(engine, email_id, &mail, &actions, &mut outcome.silenced).await;
+    }
+
+    Ok(outcome)
+}
+
+/// Loads what rules look at. Headers cost a fetch, so they are only read when a rule needs them.
+async fn mail_facts(engine: &SyncEngine, email_id: i64, with_headers: bool) -> Result<Option<MailFacts>, String> {
+    #[allow(clippy::type_complexity)]
+    let row: Option<(i64, Option<String>, String, Option<String>, Option<String>, Option<String>, Option<bool>, Option<bool>, Option<bool>)> = sqlx::query_as(
+        "SELECT e.account_id, e.sender_name, e.sender_address, e.recipient_to, e.recipient_cc, e.subject, e.has_attachments, s.is_personal_email, s.is_automated_mailer
+         FROM emails e LEFT JOIN senders s ON s.address = e.sender_address WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(engine.app().pool())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some((account_id, sender_name, sender_address, recipient_to, recipient_cc, subject, has_attachments, is_personal, is_automated)) = row else {
+        return Ok(None);
+    };
+
+    let recipients = [recipient_to, recipient_cc].into_iter()
+        .flatten()
+        .flat_map(|list| list.split(',').map(|a| a.trim().to_string()).collect::<Vec<_>>())
+        .filter(|a| !a.is_empty())
+        .collect();
+
+    let category = match (is_automated, is_personal) {
+        (Some(true), _) => Some(SenderCategory::Automated),
+        (_, Some(true)) => Some(SenderCategory::Personal),
+        _ => None,
+    };
+
+    let headers = if with_headers {
+        match raw_source(engine, email_id).await {
+            Ok(raw) => parse_headers(&raw),
+            Err(e) => {
+                warn!("Matching email {} without its headers: {}", email_id, e);
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(MailFacts {
+        account_id,
+        sender_name,
+        sender_address,
+        recipients,
+        subject: subject.unwrap_or_default(),
+        has_attachments: has_attachments.unwrap_or(false),
+        category,
+        headers,
+    }))
+}
+
+/// Runs the actions in order, except that the message moves last, once it no longer has to be found
+/// in its old folder. Of several moves the last one wins.
+async fn run_actions(engine: &SyncEngine, email_id: i64, mail: &MailFacts, actions: &[Action], silenced: &mut HashSet<i64>) {
+    let mut target_folder = None;
+
+    for action in actions {
+        let result = match action {
+            Action::Move { folder_id } => {
+                target_folder = Some(*folder_id);
+                Ok(())
+            }
+            Action::Label { label } if label.trim().is_empty() => Ok(()),
+            Action::Label { label } => update_email_flags(engine, email_id, &[Flag::custom(label.trim())], true).await.map(|_| ()),
+            Action::MarkRead => {
+                silenced.insert(email_id);
+                update_email_flags(engine, email_id, &[Flag::Seen], true).await.map(|_| ())
+            }
+            Action::Star => update_email_flags(engine, email_id, &[Flag::Flagged], true).await.map(|_| ()),
+            Action::SkipNotification => {
+                silenced.insert(email_id);
+                Ok(())
+            }
+            Action::Forward { to } => forward(engine, email_id, mail, to).await,
+        };
+
+        if let Err(e) = result {
+            warn!("Rule action {:?} failed for email {}: {}", action, email_id, e);
+        }
+    }
+
+    if let Some(folder_id) = target_folder {
+        if let Err(e) = move_email(engine, email_id, folder_id).await {
+            warn!("Rule could not move email {} to folder {}: {}", email_id, folder_id, e);
+        }
+    }
+}
+
+/// Forwards a message as an attachment. Mail the account sent itself is not forwarded,
+/// so two rules cannot bounce a message back and forth.
+async fn forward(engine: &SyncEngine, email_id: i64, mail: &MailFacts, to: &str) -> Result<(), String> {
+    let account = engine.app().accounts().await?.get_authenticated_account(mail.account_id).await?;
+    if mail.sender_address.eq_ignore_ascii_case(account.email()) {
+        return Ok(());
+    }
+
+    let recipients: Vec<String> = to.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let raw = raw_source(engine, email_id).await?;
+    let message = MessageBuilder::new()
+        .from(account.email())
+        .to(recipients)
+        .subject(format!("Fwd: {}", mail.subject))
+        .text_body("Forwarded by a mail rule.")
+        .attachment("message/rfc822", "forwarded.eml", raw)
+        .write_to_vec()
+        .map_err(|e| e.to_string())?;
+
+    Transport::for_account(&account)?.send(&message).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::rules::types::{Condition, TextOp};
+    use crate::utils::test_utils::{setup_test_db, test_context};
+
+    #[tokio::test]
+    async fn test_rules_flag_move_and_silence_new_mail() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let inbox_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role, unread_count, total_count) VALUES (?, 'INBOX', 'INBOX', 'inbox', 2, 2) RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let receipts_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path) VALUES (?, 'Receipts', 'Receipts') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let mut ids = Vec::new();
+        for (uid, subject) in [("1", "Your invoice"), ("2", "Lunch?")] {
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, date, flags) VALUES (?, ?, ?, ?, 'shop@example.com', '2024-01-01T00:00:00Z', '[]') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(inbox_id)
+            .bind(uid)
+            .bind(subject)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let rules = vec![Rule {
+            id: None,
+            account_id: Some(account_id),
+            name: "Receipts".to_string(),
+            enabled: true,
+            match_all: true,
+            conditions: vec![Condition::Subject { op: TextOp::Contains, value: "invoice".to_string() }],
+            actions: vec![
+                Action::Move { folder_id: receipts_id },
+                Action::Label { label: "finance".to_string() },
+                Action::MarkRead,
+            ],
+            stop_processing: true,
+            position: 0,
+        }];
+
+        let engine = SyncEngine::new(test_context(&pool).0);
+        let outcome = apply_rule_set(&engine, &rules, &ids).await.unwrap();
+        assert_eq!(outcome.matched, vec![ids[0]]);
+        assert!(outcome.silenced.contains(&ids[0]) && !outcome.silenced.contains(&ids[1]));
+
+        let (folder_id, flags): (i64, String) = sqlx::query_as("SELECT folder_id, flags FROM emails WHERE id = ?")
+            .bind(ids[0])
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(folder_id, receipts_id);
+        assert_eq!(flags, r#"["finance","seen"]"#);
+
+        let counts: Vec<(i64, i64)> = sqlx::query_as("SELECT unread_count, total_count FROM folders WHERE id IN (?, ?) ORDER BY id")
+            .bind(inbox_id)
+            .bind(receipts_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(counts, vec![(1, 1), (0, 1)]);
+    }
+}
@@ -0,0 +1,121 @@
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+use crate::email_backend::emails::commands::parse_flags;
+use crate::email_backend::rules::apply::{apply_rule_set, load_rule, load_rules};
+use crate::email_backend::rules::types::{Action, Rule};
+use crate::email_backend::sync::SyncEngine;
+
+#[tauri::command]
+pub async fn get_rules<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<Rule>, String> {
+    load_rules(&app_handle.state::<SqlitePool>()).await
+}
+
+/// Creates a rule, or updates it when it has an id. New rules run after the existing ones.
+#[tauri::command]
+pub async fn save_rule<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, rule: Rule) -> Result<Rule, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    if rule.name.trim().is_empty() {
+        return Err("Rule name is required".to_string());
+    }
+    if rule.actions.is_empty() {
+        return Err("A rule needs at least one action".to_string());
+    }
+    for action in &rule.actions {
+        if let Action::Label { label } = action {
+            parse_flags(std::slice::from_ref(label))?;
+        }
+    }
+
+    let conditions = serde_json::to_string(&rule.conditions).map_err(|e| e.to_string())?;
+    let actions = serde_json::to_string(&rule.actions).map_err(|e| e.to_string())?;
+
+    let id: i64 = match rule.id {
+        Some(id) => sqlx::query_scalar(
+            "UPDATE rules SET account_id = ?, name = ?, enabled = ?, match_all = ?, conditions = ?, actions = ?, stop_processing = ?, position = ?
+             WHERE id = ? RETURNING id"
+        )
+        .bind(rule.account_id)
+        .bind(rule.name.trim())
+        .bind(rule.enabled)
+        .bind(rule.match_all)
+        .bind(&conditions)
+        .bind(&actions)
+        .bind(rule.stop_processing)
+        .bind(rule.position)
+        .bind(id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Rule {} not found", id))?,
+        None => sqlx::query_scalar(
+            "INSERT INTO rules (account_id, name, enabled, match_all, conditions, actions, stop_processing, position)
+             VALUES (?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), -1) + 1 FROM rules))
+             RETURNING id"
+        )
+        .bind(rule.account_id)
+        .bind(rule.name.trim())
+        .bind(rule.enabled)
+        .bind(rule.match_all)
+        .bind(&conditions)
+        .bind(&actions)
+        .bind(rule.stop_processing)
+        .fetch_one(&*pool)
+        .await
+        .map_err(|e| e.to_string())?,
+    };
+
+    load_rule(&pool, id).await
+}
+
+#[tauri::command]
+pub async fn delete_rule<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, id: i64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("DELETE FROM rules WHERE id = ?")
+        .bind(id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Runs a rule over mail that is already here: one folder, or by default the inboxes the rule
+/// covers. Disabled rules run too, so a rule can be tried before it is switched on.
+/// Returns how many messages matched.
+#[tauri::command]
+pub async fn apply_rule_to_existing<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, rule_id: i64, folder_id: Option<i64>) -> Result<usize, String> {
+    let engine = app_handle.state::<SyncEngine>();
+    let pool = engine.app().pool();
+    let mut rule = load_rule(pool, rule_id).await?;
+    rule.enabled = true;
+
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+        "SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id WHERE f.is_local = 0"
+    );
+    match folder_id {
+        Some(folder_id) => {
+            query_builder.push(" AND f.id = ");
+            query_builder.push_bind(folder_id);
+        }
+        None => {
+            query_builder.push(" AND f.role = 'inbox'");
+        }
+    }
+    if let Some(account_id) = rule.account_id {
+        query_builder.push(" AND e.account_id = ");
+        query_builder.push_bind(account_id);
+    }
+    query_builder.push(" ORDER BY e.date DESC");
+
+    let email_ids: Vec<i64> = query_builder
+        .build_query_scalar()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let outcome = apply_rule_set(&engine, &[rule], &email_ids).await?;
+    if !outcome.matched.is_empty() {
+        let _ = app_handle.emit("emails-updated", ());
+    }
+
+    Ok(outcome.matched.len())
+}
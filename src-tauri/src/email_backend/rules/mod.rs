@@ -0,0 +1,5 @@
+pub mod apply;
+pub mod commands;
+pub mod types;
+
+pub use types::*;
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+/// A mail rule: when its conditions match a new message, its actions run in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: Option<i64>,
+    /// `None` applies the rule to every account.
+    pub account_id: Option<i64>,
+    pub name: String,
+    pub enabled: bool,
+    /// Whether every condition has to match, or any one of them.
+    pub match_all: bool,
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+    /// Skip the rules after this one once it matched.
+    pub stop_processing: bool,
+    pub position: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextOp {
+    Contains,
+    Equals,
+    StartsWith,
+    EndsWith,
+}
+
+impl TextOp {
+    /// Compares case-insensitively, as mail clients do for addresses and subjects.
+    fn test(self, text: &str, value: &str) -> bool {
+        let text = text.to_lowercase();
+        let value = value.to_lowercase();
+        match self {
+            TextOp::Contains => text.contains(&value),
+            TextOp::Equals => text.trim() == value.trim(),
+            TextOp::StartsWith => text.starts_with(&value),
+            TextOp::EndsWith => text.trim_end().ends_with(&value),
+        }
+    }
+}
+
+/// What enrichment learned about the sender.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderCategory {
+    Personal,
+    Automated,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// Matches the sender's name or address.
+    Sender { op: TextOp, value: String },
+    /// Matches any To or Cc address.
+    Recipient { op: TextOp, value: String },
+    Subject { op: TextOp, value: String },
+    Header { name: String, op: TextOp, value: String },
+    ListId { op: TextOp, value: String },
+    HasAttachment { value: bool },
+    AiCategory { category: SenderCategory },
+}
+
+impl Condition {
+    /// Whether the condition looks at headers the envelope does not carry.
+    pub fn needs_headers(&self) -> bool {
+        matches!(self, Condition::Header { .. } | Condition::ListId { .. })
+    }
+
+    pub fn matches(&self, mail: &MailFacts) -> bool {
+        match self {
+            Condition::Sender { op, value } => {
+                op.test(&mail.sender_address, value)
+                    || mail.sender_name.as_deref().is_some_and(|name| op.test(name, value))
+            }
+            Condition::Recipient { op, value } => mail.recipients.iter().any(|r| op.test(r, value)),
+            Condition::Subject { op, value } => op.test(&mail.subject, value),
+            Condition::Header { name, op, value } => mail.header_values(name).any(|h| op.test(h, value)),
+            Condition::ListId { op, value } => mail.header_values("List-Id").any(|h| op.test(h, value)),
+            Condition::HasAttachment { value } => mail.has_attachments == *value,
+            Condition::AiCategory { category } => mail.category == Some(*category),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    Move { folder_id: i64 },
+    /// Adds a keyword, which IMAP servers and notmuch keep as a label.
+    Label { label: String },
+    MarkRead,
+    Star,
+    SkipNotification,
+    /// Forwards the message as an attachment to comma separated addresses.
+    Forward { to: String },
+}
+
+impl Rule {
+    pub fn needs_headers(&self) -> bool {
+        self.conditions.iter().any(Condition::needs_headers)
+    }
+
+    /// Whether the rule applies to the message. A rule without conditions matches everything.
+    pub fn matches(&self, mail: &MailFacts) -> bool {
+        if !self.enabled || self.account_id.is_some_and(|id| id != mail.account_id) {
+            return false;
+        }
+        if self.match_all {
+            self.conditions.iter().all(|c| c.matches(mail))
+        } else {
+            self.conditions.is_empty() || self.conditions.iter().any(|c| c.matches(mail))
+        }
+    }
+}
+
+/// What rules can see of a message.
+#[derive(Debug, Clone, Default)]
+pub struct MailFacts {
+    pub account_id: i64,
+    pub sender_name: Option<String>,
+    pub sender_address: String,
+    pub recipients: Vec<String>,
+    pub subject: String,
+    pub has_attachments: bool,
+    pub category: Option<SenderCategory>,
+    /// Only loaded when a rule looks at headers.
+    pub headers: Vec<(String, String)>,
+}
+
+impl MailFacts {
+    fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers.iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Splits the header block of an RFC 5322 message into unfolded name/value pairs.
+pub fn parse_headers(raw: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(raw);
+    let mut headers: Vec<(String, String)> = Vec::new();
+
+    for line in text.split('\n') {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn newsletter() -> MailFacts {
+        MailFacts {
+            account_id: 1,
+            sender_name: Some("Weekly News".to_string()),
+            sender_address: "news@lists.example.com".to_string(),
+            recipients: vec!["me@example.com".to_string()],
+            subject: "Your weekly digest".to_string(),
+            has_attachments: false,
+            category: Some(SenderCategory::Automated),
+            headers: parse_headers(b"From: news@lists.example.com\r\nList-Id: Weekly News\r\n <weekly.lists.example.com>\r\nSubject: Your weekly digest\r\n\r\nX-Not-A-Header: body\r\n"),
+        }
+    }
+
+    fn rule(match_all: bool, conditions: Vec<Condition>) -> Rule {
+        Rule {
+            id: None,
+            account_id: None,
+            name: "Test".to_string(),
+            enabled: true,
+            match_all,
+            conditions,
+            actions: vec![Action::MarkRead],
+            stop_processing: false,
+            position: 0,
+        }
+    }
+
+    #[test]
+    fn test_conditions_match_envelope_and_headers() {
+        let mail = newsletter();
+
+        assert!(Condition::Sender { op: TextOp::EndsWith, value: "@LISTS.example.com".to_string() }.matches(&mail));
+        assert!(Condition::Sender { op: TextOp::StartsWith, value: "weekly".to_string() }.matches(&mail));
+        assert!(Condition::ListId { op: TextOp::Contains, value: "<weekly.lists.example.com>".to_string() }.matches(&mail));
+        assert!(Condition::AiCategory { category: SenderCategory::Automated }.matches(&mail));
+        assert!(!Condition::Header { name: "X-Not-A-Header".to_string(), op: TextOp::Contains, value: "body".to_string() }.matches(&mail));
+        assert!(!Condition::HasAttachment { value: true }.matches(&mail));
+    }
+
+    #[test]
+    fn test_match_all_any_and_account_scope() {
+        let mail = newsletter();
+        let subject = Condition::Subject { op: TextOp::Contains, value: "digest".to_string() };
+        let recipient = Condition::Recipient { op: TextOp::Equals, value: "other@example.com".to_string() };
+
+        assert!(!rule(true, vec![subject.clone(), recipient.clone()]).matches(&mail));
+        assert!(rule(false, vec![subject.clone(), recipient]).matches(&mail));
+        assert!(rule(false, vec![]).matches(&mail));
+
+        let mut scoped = rule(true, vec![subject]);
+        scoped.account_id = Some(2);
+        assert!(!scoped.matches(&mail));
+    }
+}
@@ -0,0 +1,434 @@
+//! A ManageSieve (RFC 5804) client for the filter scripts a server runs on delivery.
+
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::TlsConnector;
+use rustls_platform_verifier::ConfigVerifierExt;
+use crate::email_backend::accounts::manager::Account;
+
+/// The port RFC 5804 assigns to ManageSieve.
+pub const MANAGESIEVE_PORT: u16 = 4190;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SieveEncryption {
+    /// Upgrades the connection with STARTTLS, the usual setup on port 4190.
+    StartTls,
+    Tls,
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct SieveConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub encryption: SieveEncryption,
+}
+
+impl SieveConfig {
+    /// Most servers run ManageSieve next to IMAP with the same credentials.
+    pub fn for_account(account: &Account) -> Result<Self, String> {
+        match account {
+            Account::ImapSmtp(imap) => Ok(Self {
+                host: imap.imap_host.clone(),
+                port: MANAGESIEVE_PORT,
+                username: imap.imap_username.clone(),
+                password: imap.password.clone().unwrap_or_default(),
+                encryption: match imap.imap_encryption.as_str() {
+                    "none" => SieveEncryption::None,
+                    _ => SieveEncryption::StartTls,
+                },
+            }),
+            _ => Err(format!("Server filters need an IMAP account with a password, {} accounts are not supported", account.account_type())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Capabilities {
+    pub implementation: Option<String>,
+    pub sasl: Vec<String>,
+    /// Sieve extensions the server supports, e.g. `fileinto` or `vacation`.
+    pub sieve: Vec<String>,
+    pub starttls: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SieveScript {
+    pub name: String,
+    pub active: bool,
+}
+
+/// The server's verdict on a script, with its warnings or errors.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScriptCheck {
+    pub valid: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Atom(String),
+    Str(String),
+    /// A response code such as `(NONEXISTENT)`, without the parentheses.
+    Code(String),
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Token::Atom(s) | Token::Str(s) | Token::Code(s) => s,
+        }
+    }
+}
+
+/// The data lines of a response and the message of its closing OK, NO or BYE.
+struct Response {
+    lines: Vec<Vec<Token>>,
+    ok: bool,
+    message: Option<String>,
+}
+
+/// Splits one response line into tokens. A trailing `{n}` announces a literal of n bytes
+/// that follows on the next line.
+fn tokenize(line: &str) -> Result<(Vec<Token>, Option<usize>), String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            ' ' => i += 1,
+            '"' => {
+                let mut value = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        i += 1;
+                    }
+                    value.push(chars[i]);
+                    i += 1;
+                }
+                tokens.push(Token::Str(value));
+                i += 1;
+            }
+            '(' => {
+                let start = i + 1;
+                let mut in_quotes = false;
+                while i < chars.len() && (in_quotes || chars[i] != ')') {
+                    if chars[i] == '"' {
+                        in_quotes = !in_quotes;
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::Code(chars[start..i.min(chars.len())].iter().collect()));
+                i += 1;
+            }
+            '{' => {
+                let end = chars[i..].iter().position(|&c| c == '}').map(|p| i + p)
+                    .ok_or_else(|| format!("Malformed literal in ManageSieve response: {}", line))?;
+                let length: String = chars[i + 1..end].iter().filter(|c| c.is_ascii_digit()).collect();
+                let length = length.parse().map_err(|_| format!("Malformed literal in ManageSieve response: {}", line))?;
+                return Ok((tokens, Some(length)));
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && chars[i] != ' ' {
+                    i += 1;
+                }
+                tokens.push(Token::Atom(chars[start..i].iter().collect()));
+            }
+        }
+    }
+
+    Ok((tokens, None))
+}
+
+/// A string argument: quoted when short and single-line, otherwise a non-synchronizing literal.
+fn sieve_string(value: &str) -> String {
+    if value.len() <= 1024 && !value.contains(['\r', '\n']) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("{{{}+}}\r\n{}", value.len(), value)
+    }
+}
+
+/// The stream a session runs over once it is connected: plain TCP or TLS.
+pub trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+pub struct SieveClient<S = Box<dyn Connection>> {
+    stream: BufReader<S>,
+    capabilities: Capabilities,
+}
+
+impl SieveClient {
+    /// Connects, secures the connection as configured and logs in.
+    pub async fn connect(config: &SieveConfig) -> Result<Self, String> {
+        let tcp = TcpStream::connect((config.host.as_str(), config.port))
+            .await
+            .map_err(|e| format!("Cannot reach ManageSieve server {}:{}: {}", config.host, config.port, e))?;
+
+        let stream: Box<dyn Connection> = match config.encryption {
+            SieveEncryption::None => Box::new(tcp),
+            SieveEncryption::Tls => Box::new(tls(&config.host, tcp).await?),
+            SieveEncryption::StartTls => {
+                let mut plain = SieveClient::greet(tcp).await?;
+                if !plain.capabilities.starttls {
+                    return Err(format!("{} does not offer STARTTLS for ManageSieve", config.host));
+                }
+                plain.command("STARTTLS").await?;
+                Box::new(tls(&config.host, plain.stream.into_inner()).await?)
+            }
+        };
+
+        // After STARTTLS the server greets again with its capabilities
+        let mut client = SieveClient::greet(stream).await?;
+        client.authenticate(&config.username, &config.password).await?;
+        Ok(client)
+    }
+}
+
+async fn tls(host: &str, tcp: TcpStream) -> Result<tokio_rustls::client::TlsStream<TcpStream>, String> {
+    let config = ClientConfig::with_platform_verifier().map_err(|e| e.to_string())?;
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .map_err(|e| format!("TLS with {} failed: {}", host, e))
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SieveClient<S> {
+    /// Reads the server greeting, which lists its capabilities.
+    async fn greet(stream: S) -> Result<Self, String> {
+        let mut client = Self { stream: BufReader::new(stream), capabilities: Capabilities::default() };
+        client.read_capabilities().await?;
+        Ok(client)
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    async fn read_capabilities(&mut self) -> Result<(), String> {
+        let response = self.read_response().await?;
+        if !response.ok {
+            return Err(format!("ManageSieve server is not available: {}", response.message.unwrap_or_default()));
+        }
+
+        let mut capabilities = Capabilities::default();
+        for line in response.lines {
+            let name = line.first().map(|t| t.text().to_uppercase()).unwrap_or_default();
+            let value = line.get(1).map(|t| t.text().to_string()).unwrap_or_default();
+            match name.as_str() {
+                "IMPLEMENTATION" => capabilities.implementation = Some(value),
+                "SASL" => capabilities.sasl = value.split_whitespace().map(|s| s.to_uppercase()).collect(),
+                "SIEVE" => capabilities.sieve = value.split_whitespace().map(|s| s.to_string()).collect(),
+                "STARTTLS" => capabilities.starttls = true,
+                _ => {}
+            }
+        }
+        self.capabilities = capabilities;
+        Ok(())
+    }
+
+    async fn authenticate(&mut self, username: &str, password: &str) -> Result<(), String> {
+        if !self.capabilities.sasl.iter().any(|m| m == "PLAIN") {
+            return Err("ManageSieve server does not accept PLAIN logins".to_string());
+        }
+        let credentials = BASE64.encode(format!("\0{}\0{}", username, password));
+        self.command(&format!("AUTHENTICATE \"PLAIN\" \"{}\"", credentials))
+            .await
+            .map_err(|e| format!("ManageSieve login failed: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn list_scripts(&mut self) -> Result<Vec<SieveScript>, String> {
+        let lines = self.command("LISTSCRIPTS").await?;
+        Ok(lines.into_iter()
+            .filter_map(|line| {
+                let name = line.first()?.text().to_string();
+                let active = line.get(1).is_some_and(|t| t.text().eq_ignore_ascii_case("ACTIVE"));
+                Some(SieveScript { name, active })
+            })
+            .collect())
+    }
+
+    pub async fn get_script(&mut self, name: &str) -> Result<String, String> {
+        let lines = self.command(&format!("GETSCRIPT {}", sieve_string(name))).await?;
+        lines.into_iter()
+            .flatten()
+            .find_map(|token| match token {
+                Token::Str(script) => Some(script),
+                _ => None,
+            })
+            .ok_or_else(|| format!("Server sent no script for {}", name))
+    }
+
+    /// Uploads a script, replacing one with the same name. The server rejects invalid scripts.
+    pub async fn put_script(&mut self, name: &str, content: &str) -> Result<(), String> {
+        self.command(&format!("PUTSCRIPT {} {}", sieve_string(name), sieve_string(content))).await?;
+        Ok(())
+    }
+
+    /// Makes `name` the script the server runs, or runs none when `name` is empty.
+    pub async fn set_active(&mut self, name: &str) -> Result<(), String> {
+        self.command(&format!("SETACTIVE {}", sieve_string(name))).await?;
+        Ok(())
+    }
+
+    pub async fn check_script(&mut self, content: &str) -> Result<ScriptCheck, String> {
+        self.send(&format!("CHECKSCRIPT {}", sieve_string(content))).await?;
+        let response = self.read_response().await?;
+        Ok(ScriptCheck { valid: response.ok, message: response.message })
+    }
+
+    pub async fn delete_script(&mut self, name: &str) -> Result<(), String> {
+        self.command(&format!("DELETESCRIPT {}", sieve_string(name))).await?;
+        Ok(())
+    }
+
+    pub async fn logout(mut self) -> Result<(), String> {
+        self.command("LOGOUT").await?;
+        Ok(())
+    }
+
+    async fn send(&mut self, command: &str) -> Result<(), String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes()).await.map_err(|e| e.to_string())?;
+        stream.write_all(b"\r\n").await.map_err(|e| e.to_string())?;
+        stream.flush().await.map_err(|e| e.to_string())
+    }
+
+    /// Sends a command and returns its data lines, or the server's message when it answers NO.
+    async fn command(&mut self, command: &str) -> Result<Vec<Vec<Token>>, String> {
+        self.send(command).await?;
+        let response = self.read_response().await?;
+        if response.ok {
+            Ok(response.lines)
+        } else {
+            Err(response.message.unwrap_or_else(|| "ManageSieve command failed".to_string()))
+        }
+    }
+
+    async fn read_response(&mut self) -> Result<Response, String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_tokens().await?;
+            let status = line.first().and_then(|t| match t {
+                Token::Atom(atom) => Some(atom.to_uppercase()),
+                _ => None,
+            });
+            match status.as_deref() {
+                Some("OK") | Some("NO") | Some("BYE") => {
+                    let message = line.iter()
+                        .skip(1)
+                        .find_map(|t| match t {
+                            Token::Str(s) => Some(s.clone()),
+                            _ => None,
+                        });
+                    return Ok(Response { lines, ok: status.as_deref() == Some("OK"), message });
+                }
+                _ => lines.push(line),
+            }
+        }
+    }
+
+    /// Reads one logical response line, including the literals it announces.
+    async fn read_tokens(&mut self) -> Result<Vec<Token>, String> {
+        let mut tokens = Vec::new();
+        loop {
+            let mut raw = Vec::new();
+            if self.stream.read_until(b'\n', &mut raw).await.map_err(|e| e.to_string())? == 0 {
+                return Err("ManageSieve server closed the connection".to_string());
+            }
+            let line = String::from_utf8_lossy(&raw);
+            let (mut parsed, literal) = tokenize(line.trim_end_matches(['\r', '\n']))?;
+            tokens.append(&mut parsed);
+
+            match literal {
+                Some(length) => {
+                    let mut data = vec![0u8; length];
+                    self.stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+                    tokens.push(Token::Str(String::from_utf8_lossy(&data).to_string()));
+                }
+                None => return Ok(tokens),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::sieve::script::{rules_to_sieve, Vacation};
+    use std::collections::HashMap;
+    use crate::utils::test_server::FakeSieveServer;
+
+    fn config(server: &FakeSieveServer, password: &str) -> SieveConfig {
+        SieveConfig {
+            host: "127.0.0.1".to_string(),
+            port: server.port(),
+            username: "me@example.com".to_string(),
+            password: password.to_string(),
+            encryption: SieveEncryption::None,
+        }
+    }
+
+    #[test]
+    fn test_tokenize_response_codes_and_literals() {
+        let (tokens, literal) = tokenize("NO (NONEXISTENT) \"There is no \\\"away\\\" script\"").unwrap();
+        assert_eq!(tokens, vec![
+            Token::Atom("NO".to_string()),
+            Token::Code("NONEXISTENT".to_string()),
+            Token::Str("There is no \"away\" script".to_string()),
+        ]);
+        assert_eq!(literal, None);
+        assert_eq!(tokenize("{42}").unwrap(), (vec![], Some(42)));
+    }
+
+    #[tokio::test]
+    async fn test_upload_check_and_activate_against_server() {
+        let server = FakeSieveServer::start("me@example.com", "secret").await;
+        assert!(SieveClient::connect(&config(&server, "wrong")).await.is_err());
+
+        let mut client = SieveClient::connect(&config(&server, "secret")).await.unwrap();
+        assert!(client.capabilities().sieve.iter().any(|e| e == "vacation"));
+        assert!(client.list_scripts().await.unwrap().is_empty());
+
+        let vacation = Vacation {
+            subject: "Out of office".to_string(),
+            body: "I am away until Monday.\nFor \"urgent\" things call the office.".to_string(),
+            days: Some(7),
+            addresses: vec!["me@example.com".to_string()],
+            start_date: None,
+            end_date: None,
+        };
+        let script = rules_to_sieve(&[], &HashMap::new(), Some(&vacation));
+        assert!(client.check_script(&script).await.unwrap().valid);
+
+        let check = client.check_script("require \"notify\";\nkeep;\n").await.unwrap();
+        assert!(!check.valid);
+        assert!(check.message.unwrap().contains("notify"));
+        assert!(client.put_script("broken", "if true {").await.is_err());
+
+        client.put_script("dueam", &script).await.unwrap();
+        client.set_active("dueam").await.unwrap();
+        assert_eq!(client.list_scripts().await.unwrap(), vec![SieveScript { name: "dueam".to_string(), active: true }]);
+        assert_eq!(client.get_script("dueam").await.unwrap(), script);
+        assert_eq!(server.script("dueam").as_deref(), Some(script.as_str()));
+
+        assert!(client.delete_script("dueam").await.is_err());
+        client.set_active("").await.unwrap();
+        assert_eq!(server.active(), None);
+        client.delete_script("dueam").await.unwrap();
+        client.logout().await.unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use tauri::Manager;
+use crate::context::AppContext;
+use crate::email_backend::rules::apply::load_rules;
+use crate::email_backend::sieve::client::{ScriptCheck, SieveClient, SieveConfig, SieveScript};
+use crate::email_backend::sieve::script::{rules_to_sieve, Vacation};
+
+async fn connect(app: &AppContext, account_id: i64) -> Result<SieveClient, String> {
+    let account = app.accounts().await?.get_authenticated_account(account_id).await?;
+    SieveClient::connect(&SieveConfig::for_account(&account)?).await
+}
+
+#[tauri::command]
+pub async fn list_sieve_scripts<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<Vec<SieveScript>, String> {
+    let mut client = connect(&app_handle.state::<AppContext>(), account_id).await?;
+    let scripts = client.list_scripts().await?;
+    client.logout().await?;
+    Ok(scripts)
+}
+
+#[tauri::command]
+pub async fn get_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: String) -> Result<String, String> {
+    let mut client = connect(&app_handle.state::<AppContext>(), account_id).await?;
+    let script = client.get_script(&name).await?;
+    client.logout().await?;
+    Ok(script)
+}
+
+/// Uploads a script and, with `activate`, makes it the one the server runs.
+#[tauri::command]
+pub async fn put_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: String, content: String, activate: bool) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Script name is required".to_string());
+    }
+    let mut client = connect(&app_handle.state::<AppContext>(), account_id).await?;
+    client.put_script(name.trim(), &content).await?;
+    if activate {
+        client.set_active(name.trim()).await?;
+    }
+    client.logout().await
+}
+
+/// Activates a script, or switches server-side filtering off when `name` is `None`.
+#[tauri::command]
+pub async fn activate_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: Option<String>) -> Result<(), String> {
+    let mut client = connect(&app_handle.state::<AppContext>(), account_id).await?;
+    client.set_active(name.as_deref().unwrap_or_default()).await?;
+    client.logout().await
+}
+
+#[tauri::command]
+pub async fn check_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, content: String) -> Result<ScriptCheck, String> {
+    let mut client = connect(&app_handle.state::<AppContext>(), account_id).await?;
+    let check = client.check_script(&content).await?;
+    client.logout().await?;
+    Ok(check)
+}
+
+/// Generates a script from the account's rules and the global ones, for review before it is uploaded.
+#[tauri::command]
+pub async fn generate_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, vacation: Option<Vacation>) -> Result<String, String> {
+    let app = app_handle.state::<AppContext>();
+    let rules: Vec<_> = load_rules(app.pool()).await?
+        .into_iter()
+        .filter(|rule| rule.account_id.is_none_or(|id| id == account_id))
+        .collect();
+
+    // Local folders only exist in the app, so moves to them are left out
+    let folder_paths: HashMap<i64, String> = sqlx::query_as::<_, (i64, String)>("SELECT id, path FROM folders WHERE account_id = ? AND is_local = 0")
+        .bind(account_id)
+        .fetch_all(app.pool())
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+
+    Ok(rules_to_sieve(&rules, &folder_paths, vacation.as_ref()))
+}
@@ -0,0 +1,262 @@
+//! Turns mail rules and a vacation reply into a Sieve script (RFC 5228) the server runs on delivery.
+
+use std::collections::{BTreeSet, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::email_backend::rules::types::{Action, Condition, Rule, TextOp};
+
+/// An automatic reply while away, sent at most once every `days` to each sender.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vacation {
+    pub subject: String,
+    pub body: String,
+    pub days: Option<u32>,
+    /// Other addresses of the account, so mail to them is answered too.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// First and last day of the absence as `YYYY-MM-DD`.
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// Quotes a Sieve string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn string_list(values: &[String]) -> String {
+    match values {
+        [value] => quote(value),
+        _ => format!("[{}]", values.iter().map(|v| quote(v)).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// The match type and key for a text comparison. Sieve compares case-insensitively by default.
+fn match_key(op: TextOp, value: &str) -> (&'static str, String) {
+    let escaped = || value.replace('\\', "\\\\").replace('*', "\\*").replace('?', "\\?");
+    match op {
+        TextOp::Contains => (":contains", value.to_string()),
+        TextOp::Equals => (":is", value.trim().to_string()),
+        TextOp::StartsWith => (":matches", format!("{}*", escaped())),
+        TextOp::EndsWith => (":matches", format!("*{}", escaped())),
+    }
+}
+
+fn header_test(headers: &[&str], op: TextOp, value: &str) -> String {
+    let (match_type, key) = match_key(op, value);
+    let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    format!("header {} {} {}", match_type, string_list(&headers), quote(&key))
+}
+
+/// The Sieve test for a condition, or `None` when only the app can evaluate it.
+fn condition_test(condition: &Condition) -> Option<String> {
+    Some(match condition {
+        // Equality is about the address itself, the other comparisons also see the display name
+        Condition::Sender { op: TextOp::Equals, value } => format!("address :is \"from\" {}", quote(value.trim())),
+        Condition::Sender { op, value } => header_test(&["from"], *op, value),
+        Condition::Recipient { op: TextOp::Equals, value } => format!("address :is [\"to\", \"cc\"] {}", quote(value.trim())),
+        Condition::Recipient { op, value } => header_test(&["to", "cc"], *op, value),
+        Condition::Subject { op, value } => header_test(&["subject"], *op, value),
+        Condition::Header { name, op, value } => header_test(&[name.trim()], *op, value),
+        Condition::ListId { op, value } => header_test(&["list-id"], *op, value),
+        // Mail with attachments is multipart/mixed at the top level, which is close enough on the server
+        Condition::HasAttachment { value } => {
+            let test = "header :contains \"content-type\" \"multipart/mixed\"".to_string();
+            if *value { test } else { format!("not {}", test) }
+        }
+        Condition::AiCategory { .. } => return None,
+    })
+}
+
+/// Writes the commands for a rule's actions. As in the app, flags are set before the message is
+/// filed, and of several moves the last one wins.
+fn action_commands(actions: &[Action], folder_paths: &HashMap<i64, String>, requires: &mut BTreeSet<&'static str>) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut target = None;
+
+    for action in actions {
+        match action {
+            Action::Move { folder_id } => target = folder_paths.get(folder_id),
+            Action::Label { label } if label.trim().is_empty() => {}
+            Action::Label { label } => {
+                requires.insert("imap4flags");
+                commands.push(format!("addflag {};", quote(label.trim())));
+            }
+            Action::MarkRead => {
+                requires.insert("imap4flags");
+                commands.push(format!("addflag {};", quote("\\Seen")));
+            }
+            Action::Star => {
+                requires.insert("imap4flags");
+                commands.push(format!("addflag {};", quote("\\Flagged")));
+            }
+            // Notifications are the app's business
+            Action::SkipNotification => {}
+            Action::Forward { to } => {
+                for address in to.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+                    requires.insert("copy");
+                    commands.push(format!("redirect :copy {};", quote(address)));
+                }
+            }
+        }
+    }
+
+    if let Some(path) = target {
+        requires.insert("fileinto");
+        commands.push(format!("fileinto {};", quote(path)));
+    }
+    commands
+}
+
+fn vacation_block(vacation: &Vacation, requires: &mut BTreeSet<&'static str>) -> String {
+    requires.insert("vacation");
+
+    let mut command = String::from("vacation");
+    if let Some(days) = vacation.days {
+        command.push_str(&format!(" :days {}", days.max(1)));
+    }
+    if !vacation.subject.trim().is_empty() {
+        command.push_str(&format!(" :subject {}", quote(vacation.subject.trim())));
+    }
+    let addresses: Vec<String> = vacation.addresses.iter()
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .collect();
+    if !addresses.is_empty() {
+        command.push_str(&format!(" :addresses {}", string_list(&addresses)));
+    }
+    command.push_str(&format!(" {};", quote(&vacation.body)));
+
+    let mut dates = Vec::new();
+    if let Some(start) = vacation.start_date.as_deref().filter(|d| !d.is_empty()) {
+        dates.push(format!("currentdate :value \"ge\" \"date\" {}", quote(start)));
+    }
+    if let Some(end) = vacation.end_date.as_deref().filter(|d| !d.is_empty()) {
+        dates.push(format!("currentdate :value \"le\" \"date\" {}", quote(end)));
+    }
+
+    match dates.len() {
+        0 => format!("{}\n", command),
+        _ => {
+            requires.insert("date");
+            requires.insert("relational");
+            let test = if dates.len() == 1 { dates.remove(0) } else { format!("allof ({})", dates.join(", ")) };
+            format!("if {} {{\n    {}\n}}\n", test, command)
+        }
+    }
+}
+
+/// Generates a script from the enabled rules, in order, with the vacation reply first so it also
+/// answers mail that a rule files away. Rules that need the app, like AI categories, are left out
+/// with a comment, and moves to folders without a known path are dropped.
+pub fn rules_to_sieve(rules: &[Rule], folder_paths: &HashMap<i64, String>, vacation: Option<&Vacation>) -> String {
+    let mut requires = BTreeSet::new();
+    let mut body = String::new();
+
+    if let Some(vacation) = vacation {
+        body.push_str(&vacation_block(vacation, &mut requires));
+    }
+
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        body.push_str(&format!("\n# {}\n", rule.name.replace('\n', " ")));
+
+        let tests: Option<Vec<String>> = rule.conditions.iter().map(condition_test).collect();
+        let Some(tests) = tests else {
+            body.push_str("# Uses conditions only the app can check, so it runs there\n");
+            continue;
+        };
+
+        let mut commands = action_commands(&rule.actions, folder_paths, &mut requires);
+        if rule.stop_processing {
+            commands.push("stop;".to_string());
+        }
+        if commands.is_empty() {
+            body.push_str("# Has no actions the server can run\n");
+            continue;
+        }
+
+        let test = match tests.len() {
+            0 => "true".to_string(),
+            1 => tests[0].clone(),
+            _ => format!("{} ({})", if rule.match_all { "allof" } else { "anyof" }, tests.join(", ")),
+        };
+        body.push_str(&format!("if {} {{\n", test));
+        for command in commands {
+            body.push_str(&format!("    {}\n", command));
+        }
+        body.push_str("}\n");
+    }
+
+    let mut script = String::from("# Generated by Dueam\n");
+    if !requires.is_empty() {
+        let requires: Vec<String> = requires.into_iter().map(String::from).collect();
+        script.push_str(&format!("require {};\n", string_list(&requires)));
+    }
+    script.push_str(&body);
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::rules::types::SenderCategory;
+
+    fn rule(name: &str, conditions: Vec<Condition>, actions: Vec<Action>) -> Rule {
+        Rule {
+            id: None,
+            account_id: None,
+            name: name.to_string(),
+            enabled: true,
+            match_all: true,
+            conditions,
+            actions,
+            stop_processing: false,
+            position: 0,
+        }
+    }
+
+    #[test]
+    fn test_rules_and_vacation_to_sieve() {
+        let mut receipts = rule(
+            "Receipts",
+            vec![
+                Condition::Sender { op: TextOp::EndsWith, value: "@shop.example".to_string() },
+                Condition::Subject { op: TextOp::Contains, value: "\"invoice\"".to_string() },
+            ],
+            vec![Action::Move { folder_id: 7 }, Action::MarkRead, Action::Forward { to: "books@example.com".to_string() }],
+        );
+        receipts.stop_processing = true;
+        let personal = rule(
+            "Personal",
+            vec![Condition::AiCategory { category: SenderCategory::Personal }],
+            vec![Action::Star],
+        );
+        let vacation = Vacation {
+            subject: "Away".to_string(),
+            body: "Back on Monday.".to_string(),
+            days: Some(3),
+            addresses: vec![],
+            start_date: Some("2024-07-01".to_string()),
+            end_date: Some("2024-07-14".to_string()),
+        };
+
+        let folder_paths = HashMap::from([(7, "Receipts".to_string())]);
+        let script = rules_to_sieve(&[receipts, personal], &folder_paths, Some(&vacation));
+
+        assert_eq!(script, concat!(
+            "# Generated by Dueam\n",
+            "require [\"copy\", \"date\", \"fileinto\", \"imap4flags\", \"relational\", \"vacation\"];\n",
+            "if allof (currentdate :value \"ge\" \"date\" \"2024-07-01\", currentdate :value \"le\" \"date\" \"2024-07-14\") {\n",
+            "    vacation :days 3 :subject \"Away\" \"Back on Monday.\";\n",
+            "}\n",
+            "\n# Receipts\n",
+            "if allof (header :matches \"from\" \"*@shop.example\", header :contains \"subject\" \"\\\"invoice\\\"\") {\n",
+            "    addflag \"\\\\Seen\";\n",
+            "    redirect :copy \"books@example.com\";\n",
+            "    fileinto \"Receipts\";\n",
+            "    stop;\n",
+            "}\n",
+            "\n# Personal\n",
+            "# Uses conditions only the app can check, so it runs there\n",
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::emails::commands::archive_emails;
+use crate::email_backend::subscriptions::digest;
+use crate::email_backend::subscriptions::manage::{self, Subscription};
+use crate::email_backend::subscriptions::unsubscribe::{self, UnsubscribeResult};
+use crate::email_backend::sync::SyncEngine;
+
+/// Unsubscribes from the list an email came from. When the list only offers a web page,
+/// the result carries its URL for the frontend to open.
+#[tauri::command]
+pub async fn unsubscribe<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<UnsubscribeResult, String> {
+    let engine = app_handle.state::<SyncEngine>();
+    unsubscribe::unsubscribe(&engine, email_id).await
+}
+
+#[tauri::command]
+pub async fn get_subscriptions<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: Option<i64>) -> Result<Vec<Subscription>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    manage::list_subscriptions(&pool, account_id).await
+}
+
+/// Archives everything a list or sender has in the inbox and returns how many messages moved.
+#[tauri::command]
+pub async fn archive_subscription<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, key: String) -> Result<usize, String> {
+    let email_ids = manage::subscription_emails(&app_handle.state::<SqlitePool>(), account_id, &key, true).await?;
+    let count = email_ids.len();
+    archive_emails(app_handle, email_ids).await?;
+    Ok(count)
+}
+
+/// Sets how new mail from a list is handled: "muted", "digest", or `None` for the inbox as usual.
+#[tauri::command]
+pub async fn set_subscription_mode<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, key: String, mode: Option<String>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    manage::set_mode(&pool, account_id, &key, mode.as_deref()).await
+}
+
+/// Turns archiving of new mail from a list the user unsubscribed from on or off.
+#[tauri::command]
+pub async fn set_unsubscribe_auto_archive<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, key: String, enabled: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    unsubscribe::set_auto_archive(&pool, account_id, &key, enabled).await
+}
+
+/// Builds a digest of the last 24 hours right away. Returns the digest message, or `None` when
+/// there was nothing to cover.
+#[tauri::command]
+pub async fn build_digest_now<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<Option<i64>, String> {
+    let engine = app_handle.state::<SyncEngine>();
+    digest::build_digest(&engine, account_id, Utc::now() - chrono::Duration::hours(24)).await
+}
@@ -0,0 +1,368 @@
+//! A daily digest of newsletters and notifications, built from their cached summaries and stored
+//! as a local message in the account's Digest folder.
+
+use std::collections::HashSet;
+use chrono::{DateTime, Local, Utc};
+use log::{info, warn};
+use sqlx::SqlitePool;
+use crate::email_backend::emails::commands::move_email;
+use crate::email_backend::import::commands::local_folder_id;
+use crate::email_backend::subscriptions::manage::LIST_KEY;
+use crate::email_backend::sync::SyncEngine;
+use crate::email_backend::timers::run::utc_string;
+
+pub const DIGEST_FOLDER: &str = "Digest";
+
+/// Mail the digest covers: automated senders and groups routed to the digest, outside sent,
+/// drafts, trash and spam.
+const DIGEST_MAIL: &str = "(s.is_automated_mailer = 1 OR m.mode = 'digest')
+    AND COALESCE(f.role, '') NOT IN ('sent', 'drafts', 'trash', 'spam', 'digest')";
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DigestItem {
+    pub email_id: i64,
+    pub sender: String,
+    pub subject: Option<String>,
+    /// The cached summary, or the snippet when there is none.
+    pub summary: Option<String>,
+    pub in_inbox: bool,
+}
+
+/// New mail the digest will cover, which does not need a notification of its own.
+pub async fn digest_silenced(engine: &SyncEngine, email_ids: &[i64]) -> Result<HashSet<i64>, String> {
+    let app = engine.app();
+    let mut silenced = HashSet::new();
+    if !app.settings().is_enabled("digestEnabled", false).await {
+        return Ok(silenced);
+    }
+
+    for &email_id in email_ids {
+        let covered: Option<i64> = sqlx::query_scalar(&format!(
+            "SELECT e.id FROM emails e
+             JOIN folders f ON f.id = e.folder_id
+             LEFT JOIN senders s ON s.address = e.sender_address
+             LEFT JOIN subscription_modes m ON m.account_id = e.account_id AND m.list_key = {LIST_KEY}
+             WHERE e.id = ? AND {DIGEST_MAIL}"
+        ))
+        .bind(email_id)
+        .fetch_optional(app.pool())
+        .await
+        .map_err(|e| e.to_string())?;
+        silenced.extend(covered);
+    }
+    Ok(silenced)
+}
+
+async fn collect_items(pool: &SqlitePool, account_id: i64, since: &str) -> Result<Vec<DigestItem>, String> {
+    sqlx::query_as(&format!(
+        "SELECT e.id AS email_id, COALESCE(MAX(e.sender_name) OVER (PARTITION BY {LIST_KEY}), e.sender_address) AS sender,
+                e.subject, COALESCE(NULLIF(e.summary, ''), e.snippet) AS summary, COALESCE(f.role, '') = 'inbox' AS in_inbox
+         FROM emails e
+         JOIN folders f ON f.id = e.folder_id
+         LEFT JOIN senders s ON s.address = e.sender_address
+         LEFT JOIN subscription_modes m ON m.account_id = e.account_id AND m.list_key = {LIST_KEY}
+         WHERE e.account_id = ? AND e.date > ? AND {DIGEST_MAIL}
+         ORDER BY COUNT(*) OVER (PARTITION BY {LIST_KEY}) DESC, {LIST_KEY}, e.date DESC"
+    ))
+    .bind(account_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders the subject, plain text and HTML of a digest, one section per sender.
+pub fn render(items: &[DigestItem]) -> (String, String, String) {
+    let mut sections: Vec<(&str, Vec<&DigestItem>)> = Vec::new();
+    for item in items {
+        match sections.last_mut() {
+            Some((sender, entries)) if *sender == item.sender => entries.push(item),
+            _ => sections.push((&item.sender, vec![item])),
+        }
+    }
+
+    let subject = format!(
+        "Daily digest: {} update{} from {} sender{}",
+        items.len(),
+        if items.len() == 1 { "" } else { "s" },
+        sections.len(),
+        if sections.len() == 1 { "" } else { "s" },
+    );
+    let mut text = String::new();
+    let mut html = String::from("<div>");
+
+    for (sender, entries) in &sections {
+        text.push_str(&format!("{} ({})\n", sender, entries.len()));
+        html.push_str(&format!("<h3>{} ({})</h3><ul>", escape(sender), entries.len()));
+        for entry in entries {
+            let subject = entry.subject.as_deref().unwrap_or("(No subject)");
+            match entry.summary.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+                Some(summary) => {
+                    text.push_str(&format!("- {}: {}\n", subject, summary));
+                    html.push_str(&format!("<li><b>{}</b>: {}</li>", escape(subject), escape(summary)));
+                }
+                None => {
+                    text.push_str(&format!("- {}\n", subject));
+                    html.push_str(&format!("<li><b>{}</b></li>", escape(subject)));
+                }
+            }
+        }
+        text.push('\n');
+        html.push_str("</ul>");
+    }
+    html.push_str("</div>");
+
+    (subject, text.trim_end().to_string(), html)
+}
+
+/// Builds the digest of everything since `since` into the account's Digest folder and, with
+/// `digestAutoArchive`, archives the covered mail still in the inbox. Returns the digest message,
+/// or `None` when there was nothing to cover.
+pub async fn build_digest(engine: &SyncEngine, account_id: i64, since: DateTime<Utc>) -> Result<Option<i64>, String> {
+    let app = engine.app();
+    let pool = app.pool();
+    let items = collect_items(pool, account_id, &utc_string(since)).await?;
+    if items.is_empty() {
+        return Ok(None);
+    }
+
+    let (subject, text, html) = render(&items);
+    let own_address: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+        .bind(account_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let folder_id = local_folder_id(pool, account_id, DIGEST_FOLDER).await?;
+    let now = Utc::now();
+    let message_id = format!("<digest-{}-{}@dueam.local>", account_id, now.timestamp());
+    let snippet: String = text.chars().take(200).collect();
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE folders SET role = 'digest', total_count = total_count + 1, unread_count = unread_count + 1 WHERE id = ?")
+        .bind(folder_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let email_id: i64 = sqlx::query_scalar(
+        "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, sender_name, sender_address, recipient_to, date, flags, snippet, body_text, body_html)
+         VALUES (?, ?, ?, ?, ?, ?, 'Daily digest', ?, ?, ?, '[]', ?, ?, ?)
+         RETURNING id"
+    )
+    .bind(account_id)
+    .bind(folder_id)
+    .bind(format!("digest-{}", now.timestamp()))
+    .bind(&message_id)
+    .bind(&message_id)
+    .bind(&subject)
+    .bind(&own_address)
+    .bind(&own_address)
+    .bind(utc_string(now))
+    .bind(&snippet)
+    .bind(&text)
+    .bind(&html)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query("INSERT INTO digests (account_id, email_id, covered, created_at) VALUES (?, ?, ?, ?)")
+        .bind(account_id)
+        .bind(email_id)
+        .bind(items.len() as i64)
+        .bind(utc_string(now))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    info!("Built a digest of {} messages for account {}", items.len(), account_id);
+
+    if app.settings().is_enabled("digestAutoArchive", false).await {
+        let archive_id: Option<i64> = sqlx::query_scalar("SELECT id FROM folders WHERE account_id = ? AND role = 'archive' AND is_local = 0")
+            .bind(account_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(archive_id) = archive_id {
+            for item in items.iter().filter(|item| item.in_inbox) {
+                if let Err(e) = move_email(engine, item.email_id, archive_id).await {
+                    warn!("Could not archive email {} after the digest: {}", item.email_id, e);
+                }
+            }
+        }
+    }
+
+    app.emit("emails-updated", "bulk-add");
+    if app.can_notify() && app.settings().is_enabled("notificationsEnabled", true).await {
+        app.notify("Your daily digest", &subject);
+    }
+    Ok(Some(email_id))
+}
+
+/// Builds each account's digest once a day, after the `digestHour` in local time.
+pub async fn run_daily_digest(engine: &SyncEngine) -> Result<(), String> {
+    let app = engine.app();
+    if !app.settings().is_enabled("digestEnabled", false).await {
+        return Ok(());
+    }
+
+    let hour = app.settings().number("digestHour", 8).await.clamp(0, 23) as u32;
+    let Some(due) = Local::now().date_naive().and_hms_opt(hour, 0, 0).and_then(|t| t.and_local_timezone(Local).earliest()) else {
+        return Ok(());
+    };
+    let due = due.with_timezone(&Utc);
+    let now = Utc::now();
+    if now < due {
+        return Ok(());
+    }
+
+    let accounts: Vec<(i64, Option<String>)> = sqlx::query_as(
+        "SELECT a.id, (SELECT MAX(created_at) FROM digests WHERE account_id = a.id) FROM accounts a"
+    )
+    .fetch_all(app.pool())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (account_id, last) in accounts {
+        if last.as_deref().is_some_and(|last| last >= utc_string(due).as_str()) {
+            continue;
+        }
+        let day_ago = now - chrono::Duration::hours(24);
+        let since = last
+            .and_then(|last| DateTime::parse_from_rfc3339(&last).ok())
+            .map(|last| last.with_timezone(&Utc).max(day_ago))
+            .unwrap_or(day_ago);
+        match build_digest(engine, account_id, since).await {
+            // An empty day still counts as today's run, so the mail is not searched again until tomorrow
+            Ok(None) => {
+                sqlx::query("INSERT INTO digests (account_id, email_id, covered, created_at) VALUES (?, NULL, 0, ?)")
+                    .bind(account_id)
+                    .bind(utc_string(now))
+                    .execute(app.pool())
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(Some(_)) => {}
+            Err(e) => warn!("Daily digest for account {} failed: {}", account_id, e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::subscriptions::manage::{route_new_mail, DIGEST};
+    use crate::utils::test_utils::{setup_test_db, test_context};
+
+    async fn insert_email(pool: &SqlitePool, folder_id: i64, sender: &str, subject: &str, summary: Option<&str>, date: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_name, sender_address, summary, date, flags)
+             SELECT account_id, id, ?, ?, ?, ?, ?, ?, '[]' FROM folders WHERE id = ? RETURNING id"
+        )
+        .bind(format!("{}-{}", sender, subject))
+        .bind(subject)
+        .bind(sender.split('@').next())
+        .bind(sender)
+        .bind(summary)
+        .bind(date)
+        .bind(folder_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_digest_covers_automated_and_routed_mail() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let inbox_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO senders (address, is_automated_mailer) VALUES ('shop@store.example', 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO subscription_modes (account_id, list_key, mode) VALUES (?, 'news@paper.example', ?)")
+            .bind(account_id)
+            .bind(DIGEST)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let recent = utc_string(Utc::now() - chrono::Duration::hours(2));
+        let newer = utc_string(Utc::now() - chrono::Duration::hours(1));
+        let shipped = insert_email(&pool, inbox_id, "shop@store.example", "Shipped", Some("Your order ships today."), &newer).await;
+        insert_email(&pool, inbox_id, "shop@store.example", "Sale", None, &recent).await;
+        let headlines = insert_email(&pool, inbox_id, "news@paper.example", "Headlines", Some("Rates <held> steady."), &recent).await;
+        insert_email(&pool, inbox_id, "ann@example.com", "Lunch?", Some("Lunch on Friday."), &recent).await;
+        insert_email(&pool, inbox_id, "shop@store.example", "Old", None, "2020-01-01T00:00:00Z").await;
+
+        let (context, _events) = test_context(&pool);
+        let engine = SyncEngine::new(context);
+
+        // Mail routed to the digest is only archived while digests are on
+        let archive_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'Archive', 'Archive', 'archive') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let routed = route_new_mail(&engine, &[headlines]).await.unwrap();
+        assert!(routed.changed.is_empty() && routed.silenced.is_empty());
+        sqlx::query("UPDATE settings SET value = 'true' WHERE key = 'digestEnabled'").execute(&pool).await.unwrap();
+        assert_eq!(route_new_mail(&engine, &[headlines]).await.unwrap().changed, vec![headlines]);
+        let folder_id: i64 = sqlx::query_scalar("SELECT folder_id FROM emails WHERE id = ?").bind(headlines).fetch_one(&pool).await.unwrap();
+        assert_eq!(folder_id, archive_id);
+        sqlx::query("UPDATE settings SET value = 'false' WHERE key = 'digestEnabled'").execute(&pool).await.unwrap();
+
+        let email_id = build_digest(&engine, account_id, Utc::now() - chrono::Duration::hours(24)).await.unwrap().unwrap();
+
+        let (subject, text, html, role): (String, String, String, String) = sqlx::query_as(
+            "SELECT e.subject, e.body_text, e.body_html, f.role FROM emails e JOIN folders f ON f.id = e.folder_id WHERE e.id = ?"
+        )
+        .bind(email_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(role, "digest");
+        assert_eq!(subject, "Daily digest: 3 updates from 2 senders");
+        assert_eq!(text, "shop (2)\n- Shipped: Your order ships today.\n- Sale\n\nnews (1)\n- Headlines: Rates <held> steady.");
+        assert!(html.contains("Rates &lt;held&gt; steady."));
+        assert!(!text.contains("Lunch") && !text.contains("Old"));
+
+        // Digests are off by default, so new mail keeps notifying
+        assert!(digest_silenced(&engine, &[shipped]).await.unwrap().is_empty());
+        sqlx::query("UPDATE settings SET value = 'true' WHERE key = 'digestEnabled'").execute(&pool).await.unwrap();
+        assert!(digest_silenced(&engine, &[shipped]).await.unwrap().contains(&shipped));
+    }
+
+    #[tokio::test]
+    async fn test_empty_daily_digest_runs_once_a_day() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE settings SET value = 'true' WHERE key = 'digestEnabled'").execute(&pool).await.unwrap();
+        sqlx::query("UPDATE settings SET value = '0' WHERE key = 'digestHour'").execute(&pool).await.unwrap();
+
+        let (context, _events) = test_context(&pool);
+        let engine = SyncEngine::new(context);
+        run_daily_digest(&engine).await.unwrap();
+        run_daily_digest(&engine).await.unwrap();
+
+        let runs: Vec<(Option<i64>, i64)> = sqlx::query_as("SELECT email_id, covered FROM digests WHERE account_id = ?")
+            .bind(account_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(runs, vec![(None, 0)]);
+    }
+}
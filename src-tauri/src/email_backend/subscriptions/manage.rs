@@ -0,0 +1,264 @@
+//! Mailing lists and automated senders grouped for the subscriptions view, and the modes that
+//! decide what happens to their new mail.
+
+use std::collections::HashSet;
+use log::warn;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use email::flag::Flag;
+use crate::email_backend::emails::commands::{move_email, update_email_flags};
+use crate::email_backend::subscriptions::unsubscribe::UNSUBSCRIBE_MATCH;
+use crate::email_backend::sync::SyncEngine;
+
+/// Groups a message under its List-Id, or its sender when the list has none.
+pub const LIST_KEY: &str = "COALESCE(e.list_id, LOWER(e.sender_address))";
+
+pub const MUTED: &str = "muted";
+pub const DIGEST: &str = "digest";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Subscription {
+    pub account_id: i64,
+    pub key: String,
+    pub list_id: Option<String>,
+    pub sender_name: Option<String>,
+    pub sender_address: String,
+    pub total: i64,
+    /// Messages per week between the first and the last one received.
+    pub per_week: f64,
+    pub last_received: String,
+    /// Share of the messages that were read, from 0 to 1.
+    pub read_rate: f64,
+    pub unsubscribe_status: Option<String>,
+    /// Whether new mail is archived after unsubscribing.
+    pub auto_archive: Option<bool>,
+    pub mode: Option<String>,
+}
+
+/// Lists and automated senders with at least one message outside sent, drafts, trash and spam,
+/// the busiest first.
+pub async fn list_subscriptions(pool: &SqlitePool, account_id: Option<i64>) -> Result<Vec<Subscription>, String> {
+    #[allow(clippy::type_complexity)]
+    let rows: Vec<(i64, String, Option<String>, Option<String>, String, i64, i64, String, f64, Option<String>, Option<bool>, Option<String>)> = sqlx::query_as(&format!(
+        "SELECT e.account_id, {LIST_KEY} AS list_key, MAX(e.list_id), MAX(e.sender_name), LOWER(MAX(e.sender_address)),
+                COUNT(DISTINCT e.id), COUNT(DISTINCT CASE WHEN e.flags LIKE '%\"seen\"%' THEN e.id END), MAX(e.date),
+                COALESCE(julianday(MAX(e.date)) - julianday(MIN(e.date)), 0), MAX(u.status), MAX(u.auto_archive), MAX(m.mode)
+         FROM emails e
+         JOIN folders f ON f.id = e.folder_id
+         LEFT JOIN senders s ON s.address = e.sender_address
+         LEFT JOIN unsubscribes u ON {UNSUBSCRIBE_MATCH}
+         LEFT JOIN subscription_modes m ON m.account_id = e.account_id AND m.list_key = {LIST_KEY}
+         WHERE COALESCE(f.role, '') NOT IN ('sent', 'drafts', 'trash', 'spam')
+           AND (e.list_id IS NOT NULL OR s.is_automated_mailer = 1)
+           AND (? IS NULL OR e.account_id = ?)
+         GROUP BY e.account_id, list_key
+         ORDER BY COUNT(DISTINCT e.id) DESC, MAX(e.date) DESC"
+    ))
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter()
+        .map(|(account_id, key, list_id, sender_name, sender_address, total, read, last_received, span_days, unsubscribe_status, auto_archive, mode)| {
+            // Anything received within a week counts as one week
+            let weeks = (span_days / 7.0).max(1.0);
+            Subscription {
+                account_id,
+                key,
+                list_id,
+                sender_name,
+                sender_address,
+                total,
+                per_week: total as f64 / weeks,
+                last_received,
+                read_rate: if total > 0 { read as f64 / total as f64 } else { 0.0 },
+                unsubscribe_status,
+                auto_archive,
+                mode,
+            }
+        })
+        .collect())
+}
+
+/// Ids of a group's messages, limited to the inbox with `inbox_only`.
+pub async fn subscription_emails(pool: &SqlitePool, account_id: i64, key: &str, inbox_only: bool) -> Result<Vec<i64>, String> {
+    sqlx::query_scalar(&format!(
+        "SELECT e.id FROM emails e JOIN folders f ON f.id = e.folder_id
+         WHERE e.account_id = ? AND {LIST_KEY} = ? AND (? = 0 OR f.role = 'inbox')
+         ORDER BY e.date DESC"
+    ))
+    .bind(account_id)
+    .bind(key)
+    .bind(inbox_only)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Mutes a group or routes it to the digest, or with `None` lets its mail in as usual again.
+pub async fn set_mode(pool: &SqlitePool, account_id: i64, key: &str, mode: Option<&str>) -> Result<(), String> {
+    match mode {
+        Some(mode) if mode == MUTED || mode == DIGEST => {
+            sqlx::query(
+                "INSERT INTO subscription_modes (account_id, list_key, mode) VALUES (?, ?, ?)
+                 ON CONFLICT (account_id, list_key) DO UPDATE SET mode = excluded.mode"
+            )
+            .bind(account_id)
+            .bind(key)
+            .bind(mode)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+        Some(mode) => return Err(format!("Unknown subscription mode: {}", mode)),
+        None => {
+            sqlx::query("DELETE FROM subscription_modes WHERE account_id = ? AND list_key = ?")
+                .bind(account_id)
+                .bind(key)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// What `route_new_mail` did.
+#[derive(Debug, Default)]
+pub struct RoutedMail {
+    /// Messages that were changed.
+    pub changed: Vec<i64>,
+    /// Messages that should not notify.
+    pub silenced: HashSet<i64>,
+}
+
+/// Marks new mail from muted groups as read and, while the digest is on, archives mail routed
+/// to it. Neither notifies.
+pub async fn route_new_mail(engine: &SyncEngine, email_ids: &[i64]) -> Result<RoutedMail, String> {
+    let pool = engine.app().pool();
+    let mut routed = RoutedMail::default();
+    let digest_enabled = engine.app().settings().is_enabled("digestEnabled", false).await;
+
+    for &email_id in email_ids {
+        let target: Option<(String, Option<i64>)> = sqlx::query_as(&format!(
+            "SELECT m.mode, (SELECT id FROM folders WHERE account_id = e.account_id AND role = 'archive' AND is_local = 0 AND id != e.folder_id)
+             FROM emails e JOIN subscription_modes m ON m.account_id = e.account_id AND m.list_key = {LIST_KEY}
+             WHERE e.id = ?"
+        ))
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let Some((mode, archive_id)) = target else {
+            continue;
+        };
+        // No digest would ever show it, so it stays in the inbox like any other mail
+        if mode == DIGEST && !digest_enabled {
+            continue;
+        }
+        routed.silenced.insert(email_id);
+        if mode == MUTED {
+            update_email_flags(engine, email_id, &[Flag::Seen], true).await?;
+            routed.changed.push(email_id);
+        } else if let Some(archive_id) = archive_id {
+            match move_email(engine, email_id, archive_id).await {
+                Ok(()) => routed.changed.push(email_id),
+                Err(e) => warn!("Could not route email {} to the digest: {}", email_id, e),
+            }
+        }
+    }
+
+    Ok(routed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use email::envelope::{Address, Envelope};
+    use crate::utils::test_utils::{setup_test_db, test_context};
+
+    async fn insert_email(pool: &SqlitePool, folder_id: i64, sender: &str, list_id: Option<&str>, date: &str, flags: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, list_id, date, flags)
+             SELECT account_id, id, ?, 'News', ?, ?, ?, ? FROM folders WHERE id = ? RETURNING id"
+        )
+        .bind(format!("{}-{}", sender, date))
+        .bind(sender)
+        .bind(list_id)
+        .bind(date)
+        .bind(flags)
+        .bind(folder_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_groups_lists_and_automated_senders_and_mutes_new_mail() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let inbox_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO senders (address, is_automated_mailer) VALUES ('alerts@bank.example', 1), ('ann@example.com', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let list = Some("<weekly.news.example>");
+        insert_email(&pool, inbox_id, "news@news.example", list, "2024-01-01T08:00:00Z", r#"["seen"]"#).await;
+        insert_email(&pool, inbox_id, "editor@news.example", list, "2024-01-15T08:00:00Z", "[]").await;
+        insert_email(&pool, inbox_id, "news@news.example", list, "2024-01-29T08:00:00Z", r#"["seen"]"#).await;
+        insert_email(&pool, inbox_id, "alerts@bank.example", None, "2024-01-10T08:00:00Z", "[]").await;
+        insert_email(&pool, inbox_id, "ann@example.com", None, "2024-01-10T09:00:00Z", "[]").await;
+
+        let subscriptions = list_subscriptions(&pool, None).await.unwrap();
+        assert_eq!(subscriptions.len(), 2);
+        let news = &subscriptions[0];
+        assert_eq!(news.key, "<weekly.news.example>");
+        assert_eq!(news.total, 3);
+        assert!((news.per_week - 0.75).abs() < 1e-9);
+        assert!((news.read_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(news.last_received, "2024-01-29T08:00:00Z");
+        assert_eq!(subscriptions[1].key, "alerts@bank.example");
+
+        set_mode(&pool, account_id, &news.key, Some(MUTED)).await.unwrap();
+        assert!(set_mode(&pool, account_id, &news.key, Some("loud")).await.is_err());
+        assert_eq!(list_subscriptions(&pool, Some(account_id)).await.unwrap()[0].mode.as_deref(), Some(MUTED));
+
+        let (context, _events) = test_context(&pool);
+        let engine = SyncEngine::new(context);
+        let fresh = insert_email(&pool, inbox_id, "news@news.example", list, "2024-02-05T08:00:00Z", "[]").await;
+        let personal = insert_email(&pool, inbox_id, "ann@example.com", None, "2024-02-05T09:00:00Z", "[]").await;
+        let routed = route_new_mail(&engine, &[fresh, personal]).await.unwrap();
+        assert_eq!(routed.changed, vec![fresh]);
+        assert!(routed.silenced.contains(&fresh) && !routed.silenced.contains(&personal));
+
+        let flags: String = sqlx::query_scalar("SELECT flags FROM emails WHERE id = ?").bind(fresh).fetch_one(&pool).await.unwrap();
+        assert!(flags.contains("seen"));
+        assert_eq!(subscription_emails(&pool, account_id, &news.key, true).await.unwrap().len(), 4);
+
+        // List headers captured at sync time group mail from a new sender of the list with it
+        let mut envelope = Envelope::default();
+        envelope.id = "issue-6".to_string();
+        envelope.message_id = "<issue-6@news.example>".to_string();
+        envelope.from = Address::new(None, "bounce-6@news.example".to_string());
+        envelope.date = "2024-02-12T08:00:00Z".parse().unwrap();
+        let headers = HashMap::from([(
+            "issue-6".to_string(),
+            vec![("List-Id".to_string(), "<weekly.news.example>".to_string())],
+        )]);
+        let synced = engine.save_envelopes(account_id, inbox_id, vec![envelope].into_iter().collect(), &headers, None).await.unwrap();
+        let routed = route_new_mail(&engine, &synced).await.unwrap();
+        assert_eq!(routed.changed, synced);
+    }
+}
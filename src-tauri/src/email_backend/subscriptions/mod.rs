@@ -0,0 +1,4 @@
+pub mod commands;
+pub mod digest;
+pub mod manage;
+pub mod unsubscribe;
@@ -0,0 +1,390 @@
+//! Unsubscribing from mailing lists through their List-Unsubscribe header (RFC 2369), with the
+//! one-click POST of RFC 8058 where the list offers it.
+
+use std::time::Duration;
+use log::{info, warn};
+use mail_builder::MessageBuilder;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use url::Url;
+use crate::email_backend::emails::commands::move_email;
+use crate::email_backend::emails::transport::Transport;
+use crate::email_backend::export::commands::raw_source;
+use crate::email_backend::rules::types::parse_headers;
+use crate::email_backend::sync::SyncEngine;
+
+/// Joins `unsubscribes u` to the message `e` it covers: by List-Id when one was recorded, since
+/// lists often send from changing addresses, and by sender otherwise.
+pub const UNSUBSCRIBE_MATCH: &str = "u.account_id = e.account_id AND CASE WHEN u.list_id IS NOT NULL
+    THEN u.list_id = e.list_id ELSE u.sender_address = LOWER(e.sender_address) END";
+
+/// How a list lets subscribers leave.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnsubscribeMethod {
+    /// An HTTPS POST the list handles without further questions.
+    OneClick { url: String },
+    Mailto { address: String, subject: Option<String>, body: Option<String> },
+    /// A web page the user has to visit.
+    Link { url: String },
+}
+
+impl UnsubscribeMethod {
+    fn name(&self) -> &'static str {
+        match self {
+            UnsubscribeMethod::OneClick { .. } => "one_click",
+            UnsubscribeMethod::Mailto { .. } => "mailto",
+            UnsubscribeMethod::Link { .. } => "link",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsubscribeResult {
+    pub method: String,
+    pub status: String,
+    /// The page to open when the list only offers a link.
+    pub url: Option<String>,
+}
+
+/// The `<...>` URIs of a List-Unsubscribe header, in the order the list prefers them.
+pub fn unsubscribe_uris(header: &str) -> Vec<String> {
+    header.split(',')
+        .filter_map(|part| {
+            let part = part.trim();
+            let start = part.find('<')?;
+            let end = part[start..].find('>')? + start;
+            Some(part[start + 1..end].trim().to_string())
+        })
+        .filter(|uri| !uri.is_empty())
+        .collect()
+}
+
+/// Picks the best way out: one-click when the list announces it for an HTTPS URI, then mail,
+/// then a plain link.
+pub fn choose_method(list_unsubscribe: &str, list_unsubscribe_post: Option<&str>) -> Option<UnsubscribeMethod> {
+    let uris: Vec<Url> = unsubscribe_uris(list_unsubscribe).iter().filter_map(|uri| Url::parse(uri).ok()).collect();
+    let one_click = list_unsubscribe_post
+        .is_some_and(|post| post.replace(' ', "").eq_ignore_ascii_case("List-Unsubscribe=One-Click"));
+    let https = uris.iter().find(|uri| uri.scheme() == "https");
+
+    if let (true, Some(url)) = (one_click, https) {
+        return Some(UnsubscribeMethod::OneClick { url: url.to_string() });
+    }
+    if let Some(mailto) = uris.iter().find(|uri| uri.scheme() == "mailto") {
+        let query = |name: &str| mailto.query_pairs().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.to_string());
+        let address = percent_decode(mailto.path());
+        if !address.is_empty() {
+            return Some(UnsubscribeMethod::Mailto { address, subject: query("subject"), body: query("body") });
+        }
+    }
+    uris.iter()
+        .find(|uri| uri.scheme() == "https" || uri.scheme() == "http")
+        .map(|url| UnsubscribeMethod::Link { url: url.to_string() })
+}
+
+fn percent_decode(value: &str) -> String {
+    url::form_urlencoded::parse(format!("v={}", value.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, v)| v.to_string())
+        .unwrap_or_default()
+}
+
+/// The `<...>` identifier of a List-Id header (RFC 2919) in lowercase, without the display phrase
+/// in front of it, so one list always groups and matches the same way.
+pub fn normalize_list_id(header: &str) -> Option<String> {
+    let id = match header.rfind('<') {
+        Some(start) => header[start + 1..].split('>').next().unwrap_or_default(),
+        None => header,
+    };
+    let id: String = id.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+    (!id.is_empty()).then(|| format!("<{}>", id))
+}
+
+/// Stores the list headers of an indexed message.
+pub async fn save_list_headers(pool: &SqlitePool, email_id: i64, headers: &[(String, String)]) -> Result<(), String> {
+    let header = |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone());
+    let list_id = header("List-Id").as_deref().and_then(normalize_list_id);
+    let (unsubscribe, post) = (header("List-Unsubscribe"), header("List-Unsubscribe-Post"));
+    if list_id.is_none() && unsubscribe.is_none() {
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE emails SET list_id = ?, list_unsubscribe = ?, list_unsubscribe_post = ? WHERE id = ?")
+        .bind(list_id)
+        .bind(unsubscribe)
+        .bind(post)
+        .bind(email_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn one_click(url: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.post(url)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("The list answered {}", response.status()))
+    }
+}
+
+async fn send_unsubscribe_mail(engine: &SyncEngine, account_id: i64, address: &str, subject: Option<&str>, body: Option<&str>) -> Result<(), String> {
+    let account = engine.app().accounts().await?.get_authenticated_account(account_id).await?;
+    let message = MessageBuilder::new()
+        .from(account.email())
+        .to(address)
+        .subject(subject.unwrap_or("unsubscribe"))
+        .text_body(body.unwrap_or("unsubscribe"))
+        .write_to_vec()
+        .map_err(|e| e.to_string())?;
+    Transport::for_account(&account)?.send(&message).await
+}
+
+/// Leaves the list a message came from and remembers it, so later mail from the list is archived.
+/// Lists that only offer a web page come back as a link for the user to open; whether they finish
+/// the form is unknown, so their mail is only archived once the user turns that on.
+pub async fn unsubscribe(engine: &SyncEngine, email_id: i64) -> Result<UnsubscribeResult, String> {
+    let pool = engine.app().pool();
+    #[allow(clippy::type_complexity)]
+    let (account_id, sender_address, mut list_id, mut list_unsubscribe, mut post): (i64, String, Option<String>, Option<String>, Option<String>) = sqlx::query_as(
+        "SELECT account_id, sender_address, list_id, list_unsubscribe, list_unsubscribe_post FROM emails WHERE id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Email {} not found", email_id))?;
+
+    // Not indexed yet, so read the headers now
+    if list_unsubscribe.is_none() {
+        let headers = parse_headers(&raw_source(engine, email_id).await?);
+        save_list_headers(pool, email_id, &headers).await?;
+        let header = |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone());
+        list_id = header("List-Id").as_deref().and_then(normalize_list_id);
+        list_unsubscribe = header("List-Unsubscribe");
+        post = header("List-Unsubscribe-Post");
+    }
+
+    let method = list_unsubscribe.as_deref()
+        .and_then(|header| choose_method(header, post.as_deref()))
+        .ok_or("This message does not say how to unsubscribe")?;
+
+    let (status, detail, url) = match &method {
+        UnsubscribeMethod::OneClick { url } => match one_click(url).await {
+            Ok(()) => ("done", None, None),
+            Err(e) => {
+                warn!("One-click unsubscribe from {} failed: {}", sender_address, e);
+                ("failed", Some(e), None)
+            }
+        },
+        UnsubscribeMethod::Mailto { address, subject, body } => {
+            match send_unsubscribe_mail(engine, account_id, address, subject.as_deref(), body.as_deref()).await {
+                Ok(()) => ("done", None, None),
+                Err(e) => ("failed", Some(e), None),
+            }
+        }
+        UnsubscribeMethod::Link { url } => ("link_opened", None, Some(url.clone())),
+    };
+    info!("Unsubscribed from {} by {}: {}", sender_address, method.name(), status);
+
+    sqlx::query(
+        "INSERT INTO unsubscribes (account_id, list_id, sender_address, method, status, detail, auto_archive) VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT (account_id, sender_address) DO UPDATE SET
+            list_id = excluded.list_id, method = excluded.method, status = excluded.status, detail = excluded.detail,
+            auto_archive = excluded.auto_archive, created_at = CURRENT_TIMESTAMP"
+    )
+    .bind(account_id)
+    .bind(&list_id)
+    .bind(sender_address.to_lowercase())
+    .bind(method.name())
+    .bind(status)
+    .bind(&detail)
+    .bind(status == "done")
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if status == "failed" {
+        return Err(detail.unwrap_or_else(|| "Unsubscribing failed".to_string()));
+    }
+    Ok(UnsubscribeResult { method: method.name().to_string(), status: status.to_string(), url })
+}
+
+/// The archive folder a message goes to when it comes from a list the user left.
+async fn unsubscribed_archive(pool: &SqlitePool, email_id: i64) -> Result<Option<i64>, String> {
+    sqlx::query_scalar(&format!(
+        "SELECT f.id FROM emails e
+         JOIN unsubscribes u ON {UNSUBSCRIBE_MATCH}
+         JOIN folders f ON f.account_id = e.account_id AND f.role = 'archive' AND f.is_local = 0
+         WHERE e.id = ? AND u.auto_archive = 1 AND e.folder_id != f.id
+         LIMIT 1"
+    ))
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Turns archiving of new mail on or off for a list or sender the user unsubscribed from; `key`
+/// is the subscription's List-Id or sender address.
+pub async fn set_auto_archive(pool: &SqlitePool, account_id: i64, key: &str, enabled: bool) -> Result<(), String> {
+    sqlx::query("UPDATE unsubscribes SET auto_archive = ? WHERE account_id = ? AND COALESCE(list_id, sender_address) = ?")
+        .bind(enabled)
+        .bind(account_id)
+        .bind(key)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Archives new messages from lists the user left, and returns their ids.
+pub async fn archive_unsubscribed(engine: &SyncEngine, email_ids: &[i64]) -> Result<Vec<i64>, String> {
+    let pool = engine.app().pool();
+    let mut archived = Vec::new();
+
+    for &email_id in email_ids {
+        let Some(archive_id) = unsubscribed_archive(pool, email_id).await? else {
+            continue;
+        };
+        match move_email(engine, email_id, archive_id).await {
+            Ok(()) => archived.push(email_id),
+            Err(e) => warn!("Could not archive email {} from a list the user left: {}", email_id, e),
+        }
+    }
+
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use crate::utils::test_utils::setup_test_db;
+
+    #[test]
+    fn test_choose_method() {
+        let header = "<mailto:leave-123@lists.example.com?subject=Unsubscribe%20me>, <https://lists.example.com/u/123>";
+        assert_eq!(
+            choose_method(header, Some("List-Unsubscribe=One-Click")),
+            Some(UnsubscribeMethod::OneClick { url: "https://lists.example.com/u/123".to_string() })
+        );
+        assert_eq!(
+            choose_method(header, None),
+            Some(UnsubscribeMethod::Mailto {
+                address: "leave-123@lists.example.com".to_string(),
+                subject: Some("Unsubscribe me".to_string()),
+                body: None,
+            })
+        );
+        assert_eq!(
+            choose_method("<http://example.com/leave>", Some("List-Unsubscribe=One-Click")),
+            Some(UnsubscribeMethod::Link { url: "http://example.com/leave".to_string() })
+        );
+        assert_eq!(choose_method("not a uri", None), None);
+    }
+
+    #[test]
+    fn test_normalize_list_id() {
+        assert_eq!(normalize_list_id("Weekly News <Weekly.News.Example>").as_deref(), Some("<weekly.news.example>"));
+        assert_eq!(normalize_list_id("\"Weekly\r\n News\" <weekly.news.example >").as_deref(), Some("<weekly.news.example>"));
+        assert_eq!(normalize_list_id("weekly.news.example").as_deref(), Some("<weekly.news.example>"));
+        assert_eq!(normalize_list_id("  "), None);
+    }
+
+    #[tokio::test]
+    async fn test_one_click_posts_the_form_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).ends_with("List-Unsubscribe=One-Click") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        one_click(&format!("http://127.0.0.1:{}/u/123", port)).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /u/123 HTTP/1.1"));
+        assert!(request.to_lowercase().contains("content-type: application/x-www-form-urlencoded"));
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribed_lists_match_by_list_id_then_sender() {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let inbox_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let archive_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'Archive', 'Archive', 'archive') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO unsubscribes (account_id, list_id, sender_address, method, status, auto_archive) VALUES
+                (?, '<news.example.com>', 'news@example.com', 'one_click', 'done', 1),
+                (?, NULL, 'deals@shop.example', 'mailto', 'done', 1),
+                (?, '<promo.example.com>', 'promo@example.com', 'link', 'link_opened', 0)"
+        )
+        .bind(account_id)
+        .bind(account_id)
+        .bind(account_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut archives = Vec::new();
+        for (remote_id, sender, list_id) in [
+            ("1", "Bounce-123@example.com", Some("<news.example.com>")),
+            ("2", "news@example.com", Some("<other.example.com>")),
+            ("3", "Deals@shop.example", None),
+            ("4", "promo@example.com", Some("<promo.example.com>")),
+        ] {
+            let email_id: i64 = sqlx::query_scalar(
+                "INSERT INTO emails (account_id, folder_id, remote_id, sender_address, list_id, date, flags)
+                 VALUES (?, ?, ?, ?, ?, '2024-01-01T10:00:00Z', '[]') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(inbox_id)
+            .bind(remote_id)
+            .bind(sender)
+            .bind(list_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            archives.push(unsubscribed_archive(&pool, email_id).await.unwrap());
+        }
+        assert_eq!(archives, vec![Some(archive_id), None, Some(archive_id), None]);
+
+        // A link may never have been followed through, so archiving waits for the user
+        set_auto_archive(&pool, account_id, "<promo.example.com>", true).await.unwrap();
+        let promo: i64 = sqlx::query_scalar("SELECT id FROM emails WHERE remote_id = '4'").fetch_one(&pool).await.unwrap();
+        assert_eq!(unsubscribed_archive(&pool, promo).await.unwrap(), Some(archive_id));
+    }
+}
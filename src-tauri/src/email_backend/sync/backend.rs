@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use email::account::config::AccountConfig;
+use email::backend::{Backend, context::{BackendContext, BackendContextBuilder}, feature::BackendFeature};
+use email::backend::mapper::SomeBackendContextBuilderMapper;
+use email::envelope::{get::GetEnvelope, list::ListEnvelopes, watch::WatchEnvelopes, thread::ThreadEnvelopes};
+use email::flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags};
+use email::folder::{add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders, purge::PurgeFolder};
+use email::imap::{ImapContext, ImapContextBuilder};
+use email::maildir::{MaildirContextBuilder, MaildirContextSync};
+use email::notmuch::{NotmuchContextBuilder, NotmuchContextSync};
+use email::message::{
+    add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+    peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages,
+};
+use email::AnyResult;
+use crate::email_backend::accounts::manager::Account;
+
+/// Where an account's mail lives, which decides how it is synced and watched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Imap,
+    Maildir,
+    Notmuch,
+}
+
+/// Backend context of any account kind. Only the field matching the account is set.
+#[derive(Clone, Default)]
+pub struct AccountContext {
+    pub imap: Option<ImapContext>,
+    pub maildir: Option<MaildirContextSync>,
+    pub notmuch: Option<NotmuchContextSync>,
+}
+
+impl BackendContext for AccountContext {}
+
+impl AsRef<Option<ImapContext>> for AccountContext {
+    fn as_ref(&self) -> &Option<ImapContext> {
+        &self.imap
+    }
+}
+
+impl AsRef<Option<MaildirContextSync>> for AccountContext {
+    fn as_ref(&self) -> &Option<MaildirContextSync> {
+        &self.maildir
+    }
+}
+
+impl AsRef<Option<NotmuchContextSync>> for AccountContext {
+    fn as_ref(&self) -> &Option<NotmuchContextSync> {
+        &self.notmuch
+    }
+}
+
+#[derive(Clone)]
+pub struct AccountContextBuilder {
+    pub account_config: Arc<AccountConfig>,
+    imap: Option<ImapContextBuilder>,
+    maildir: Option<MaildirContextBuilder>,
+    notmuch: Option<NotmuchContextBuilder>,
+}
+
+impl AccountContextBuilder {
+    pub fn new(account: &Account) -> Result<Self, String> {
+        match account {
+            Account::Maildir(maildir) => {
+                let (account_config, maildir_config) = maildir.get_configs();
+                Ok(Self {
+                    account_config: account_config.clone(),
+                    imap: None,
+                    maildir: Some(MaildirContextBuilder::new(account_config, maildir_config)),
+                    notmuch: None,
+                })
+            }
+            Account::Notmuch(notmuch) => {
+                let (account_config, notmuch_config) = notmuch.get_configs();
+                Ok(Self {
+                    account_config: account_config.clone(),
+                    imap: None,
+                    maildir: None,
+                    notmuch: Some(NotmuchContextBuilder::new(account_config, notmuch_config)),
+                })
+            }
+            _ => {
+                let (account_config, imap_config, _) = account.get_configs()?;
+                // Use pool size 2 to allow IDLE and one concurrent request
+                let imap = ImapContextBuilder::new(account_config.clone(), imap_config).with_pool_size(2);
+                Ok(Self { account_config, imap: Some(imap), maildir: None, notmuch: None })
+            }
+        }
+    }
+
+    /// Wraps an already built context with this builder's features.
+    pub fn backend(&self, context: AccountContext) -> Backend<AccountContext> {
+        Backend {
+            account_config: self.account_config.clone(),
+            context: Arc::new(context),
+            add_folder: self.add_folder(),
+            list_folders: self.list_folders(),
+            expunge_folder: self.expunge_folder(),
+            purge_folder: self.purge_folder(),
+            delete_folder: self.delete_folder(),
+            get_envelope: self.get_envelope(),
+            list_envelopes: self.list_envelopes(),
+            thread_envelopes: self.thread_envelopes(),
+            watch_envelopes: self.watch_envelopes(),
+            add_flags: self.add_flags(),
+            set_flags: self.set_flags(),
+            remove_flags: self.remove_flags(),
+            add_message: self.add_message(),
+            send_message: None,
+            peek_messages: self.peek_messages(),
+            get_messages: self.get_messages(),
+            copy_messages: self.copy_messages(),
+            move_messages: self.move_messages(),
+            delete_messages: self.delete_messages(),
+            remove_messages: self.remove_messages(),
+        }
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for AccountContextBuilder {
+    type Context = AccountContext;
+
+    fn add_folder(&self) -> Option<BackendFeature<Self::Context, dyn AddFolder>> {
+        self.add_folder_with_some(&self.imap).or_else(|| self.add_folder_with_some(&self.maildir))
+            .or_else(|| self.add_folder_with_some(&self.notmuch))
+    }
+
+    fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>> {
+        self.list_folders_with_some(&self.imap).or_else(|| self.list_folders_with_some(&self.maildir))
+            .or_else(|| self.list_folders_with_some(&self.notmuch))
+    }
+
+    fn expunge_folder(&self) -> Option<BackendFeature<Self::Context, dyn ExpungeFolder>> {
+        self.expunge_folder_with_some(&self.imap).or_else(|| self.expunge_folder_with_some(&self.maildir))
+            .or_else(|| self.expunge_folder_with_some(&self.notmuch))
+    }
+
+    fn purge_folder(&self) -> Option<BackendFeature<Self::Context, dyn PurgeFolder>> {
+        self.purge_folder_with_some(&self.imap).or_else(|| self.purge_folder_with_some(&self.maildir))
+            .or_else(|| self.purge_folder_with_some(&self.notmuch))
+    }
+
+    fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder>> {
+        self.delete_folder_with_some(&self.imap).or_else(|| self.delete_folder_with_some(&self.maildir))
+            .or_else(|| self.delete_folder_with_some(&self.notmuch))
+    }
+
+    fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
+        self.get_envelope_with_some(&self.imap).or_else(|| self.get_envelope_with_some(&self.maildir))
+            .or_else(|| self.get_envelope_with_some(&self.notmuch))
+    }
+
+    fn list_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ListEnvelopes>> {
+        self.list_envelopes_with_some(&self.imap).or_else(|| self.list_envelopes_with_some(&self.maildir))
+            .or_else(|| self.list_envelopes_with_some(&self.notmuch))
+    }
+
+    fn thread_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ThreadEnvelopes>> {
+        self.thread_envelopes_with_some(&self.imap).or_else(|| self.thread_envelopes_with_some(&self.maildir))
+            .or_else(|| self.thread_envelopes_with_some(&self.notmuch))
+    }
+
+    fn watch_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn WatchEnvelopes>> {
+        self.watch_envelopes_with_some(&self.imap).or_else(|| self.watch_envelopes_with_some(&self.maildir))
+            .or_else(|| self.watch_envelopes_with_some(&self.notmuch))
+    }
+
+    fn add_flags(&self) -> Option<BackendFeature<Self::Context, dyn AddFlags>> {
+        self.add_flags_with_some(&self.imap).or_else(|| self.add_flags_with_some(&self.maildir))
+            .or_else(|| self.add_flags_with_some(&self.notmuch))
+    }
+
+    fn set_flags(&self) -> Option<BackendFeature<Self::Context, dyn SetFlags>> {
+        self.set_flags_with_some(&self.imap).or_else(|| self.set_flags_with_some(&self.maildir))
+            .or_else(|| self.set_flags_with_some(&self.notmuch))
+    }
+
+    fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>> {
+        self.remove_flags_with_some(&self.imap).or_else(|| self.remove_flags_with_some(&self.maildir))
+            .or_else(|| self.remove_flags_with_some(&self.notmuch))
+    }
+
+    fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
+        self.add_message_with_some(&self.imap).or_else(|| self.add_message_with_some(&self.maildir))
+            .or_else(|| self.add_message_with_some(&self.notmuch))
+    }
+
+    fn peek_messages(&self) -> Option<BackendFeature<Self::Context, dyn PeekMessages>> {
+        self.peek_messages_with_some(&self.imap).or_else(|| self.peek_messages_with_some(&self.maildir))
+            .or_else(|| self.peek_messages_with_some(&self.notmuch))
+    }
+
+    fn get_messages(&self) -> Option<BackendFeature<Self::Context, dyn GetMessages>> {
+        self.get_messages_with_some(&self.imap).or_else(|| self.get_messages_with_some(&self.maildir))
+            .or_else(|| self.get_messages_with_some(&self.notmuch))
+    }
+
+    fn copy_messages(&self) -> Option<BackendFeature<Self::Context, dyn CopyMessages>> {
+        self.copy_messages_with_some(&self.imap).or_else(|| self.copy_messages_with_some(&self.maildir))
+            .or_else(|| self.copy_messages_with_some(&self.notmuch))
+    }
+
+    fn move_messages(&self) -> Option<BackendFeature<Self::Context, dyn MoveMessages>> {
+        self.move_messages_with_some(&self.imap).or_else(|| self.move_messages_with_some(&self.maildir))
+            .or_else(|| self.move_messages_with_some(&self.notmuch))
+    }
+
+    fn delete_messages(&self) -> Option<BackendFeature<Self::Context, dyn DeleteMessages>> {
+        self.delete_messages_with_some(&self.imap).or_else(|| self.delete_messages_with_some(&self.maildir))
+            .or_else(|| self.delete_messages_with_some(&self.notmuch))
+    }
+
+    fn remove_messages(&self) -> Option<BackendFeature<Self::Context, dyn RemoveMessages>> {
+        self.remove_messages_with_some(&self.imap).or_else(|| self.remove_messages_with_some(&self.maildir))
+            .or_else(|| self.remove_messages_with_some(&self.notmuch))
+    }
+
+    async fn build(self) -> AnyResult<Self::Context> {
+        let mut context = AccountContext::default();
+
+        if let Some(imap) = self.imap {
+            context.imap = Some(imap.build().await?);
+        }
+
+        if let Some(mut maildir) = self.maildir {
+            maildir.configure().await?;
+            context.maildir = Some(maildir.build().await?);
+        }
+
+        if let Some(notmuch) = self.notmuch {
+            context.notmuch = Some(notmuch.build().await?);
+        }
+
+        Ok(context)
+    }
+}
@@ -1,39 +1,99 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use std::sync::Arc;
 use std::num::NonZeroU32;
-use tauri::{Manager, Emitter};
+use crate::context::AppContext;
 use crate::email_backend::accounts::manager::{AccountManager, Account};
+use crate::email_backend::accounts::error::AccountError;
+use crate::email_backend::accounts::events::ReauthRequiredEvent;
+use crate::email_backend::notifications::policy::{notification_plan, Planned};
+use crate::email_backend::rules::apply::{apply_rules, RulesOutcome};
+use crate::email_backend::rules::types::parse_headers;
+use crate::email_backend::subscriptions::digest::digest_silenced;
+use crate::email_backend::subscriptions::manage::route_new_mail;
+use crate::email_backend::subscriptions::unsubscribe::{archive_unsubscribed, save_list_headers};
+use crate::email_backend::timers::run::restore_snoozes;
+use crate::email_backend::sync::backend::{AccountContext, AccountContextBuilder, BackendKind};
 use tokio::time::sleep;
-use tokio::sync::{oneshot, Mutex};
-use log::{info, error};
-use email::imap::{ImapContext, ImapContextBuilder, ImapClient};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use log::{info, warn, error};
+use email::imap::{ImapContext, ImapClient};
 use email::backend::{Backend, context::BackendContextBuilder};
-use email::folder::list::ListFolders;
-use email::envelope::Envelopes;
+use email::folder::{Folder, list::ListFolders};
+use email::envelope::{Envelopes, Id};
+use email::envelope::list::{ListEnvelopes, ListEnvelopesOptions};
+use email::message::{Messages, peek::PeekMessages};
+use notify::{RecursiveMode, Watcher};
 use imap_client::tasks::tasks::select::SelectDataUnvalidated;
+use imap_client::imap_next::imap_types::search::SearchKey;
 use sqlx::SqlitePool;
 
-pub struct SyncEngine<R: tauri::Runtime = tauri::Wry> {
-    app_handle: tauri::AppHandle<R>,
+#[derive(Clone)]
+pub struct SyncEngine {
+    app: AppContext,
     idle_senders: Arc<Mutex<HashMap<i64, oneshot::Sender<()>>>>,
-    contexts: Arc<Mutex<HashMap<i64, ImapContext>>>,
+    contexts: Arc<Mutex<HashMap<i64, AccountContext>>>,
 }
 
-impl<R: tauri::Runtime> Clone for SyncEngine<R> {
-    fn clone(&self) -> Self {
-        Self {
-            app_handle: self.app_handle.clone(),
-            idle_senders: self.idle_senders.clone(),
-            contexts: self.contexts.clone(),
-        }
-    }
+/// A message saved by a sync that brought it in for the first time.
+pub(crate) struct NewMail {
+    email_id: i64,
+    subject: String,
+    sender: String,
+    seen: bool,
 }
 
 const SYNC_BATCH_SIZE: u32 = 100;
 const MAX_SYNC_MESSAGES_PER_FOLDER: u32 = 500;
 
-use tauri_plugin_notification::NotificationExt;
+/// The list headers of a batch of messages keyed by UID. They come with the envelopes so that
+/// rules, subscriptions and notifications see new list mail as such; on failure the body
+/// indexer fills them in later.
+async fn fetch_list_headers(client: &mut ImapClient, envelopes: &Envelopes) -> HashMap<String, Vec<(String, String)>> {
+    use imap_client::imap_next::imap_types::core::{AString, Vec1};
+    use imap_client::imap_next::imap_types::fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName, Section};
+    use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
+
+    let uids: Vec<Sequence> = envelopes.iter()
+        .filter_map(|e| e.id.parse::<u32>().ok())
+        .filter_map(NonZeroU32::new)
+        .map(Sequence::from)
+        .collect();
+    let Ok(uids) = SequenceSet::try_from(uids) else {
+        return HashMap::new();
+    };
+    let fields = ["List-Id", "List-Unsubscribe", "List-Unsubscribe-Post"]
+        .into_iter()
+        .filter_map(|name| AString::try_from(name).ok())
+        .collect::<Vec<_>>();
+    let Ok(fields) = Vec1::try_from(fields) else {
+        return HashMap::new();
+    };
+    let items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+        MessageDataItemName::BodyExt {
+            section: Some(Section::HeaderFields(None, fields)),
+            partial: None,
+            peek: true,
+        }
+    ]);
+
+    let fetches = match client.fetch_items(uids, items).await {
+        Ok(fetches) => fetches,
+        Err(e) => {
+            warn!("Failed to fetch list headers: {}", e);
+            return HashMap::new();
+        }
+    };
+    fetches.into_iter()
+        .filter_map(|(uid, items)| {
+            items.as_ref().iter().find_map(|item| match item {
+                MessageDataItem::BodyExt { data, .. } => data.0.as_ref().map(|data| (uid.to_string(), parse_headers(data.as_ref()))),
+                _ => None,
+            })
+        })
+        .filter(|(_, headers)| !headers.is_empty())
+        .collect()
+}
 
 fn normalize_subject(subject: &str) -> String {
     let mut s = subject.trim().to_lowercase();
@@ -59,112 +119,136 @@ fn normalize_subject(subject: &str) -> String {
     s
 }
 
-impl<R: tauri::Runtime> SyncEngine<R> {
-    pub fn new(app_handle: tauri::AppHandle<R>) -> Self {
+/// Maps a folder to the role it plays in the revamped inbox, or `None` for folders we do not sync.
+fn folder_role(folder: &Folder) -> Option<String> {
+    let name_lower = folder.name.to_lowercase();
+    if folder.is_inbox() {
+        Some("inbox".to_string())
+    } else if folder.is_sent() {
+        Some("sent".to_string())
+    } else if folder.is_drafts() || name_lower.contains("drafts") {
+        Some("drafts".to_string())
+    } else if name_lower.contains("spam") || name_lower.contains("junk") {
+        Some("spam".to_string())
+    } else if name_lower.contains("trash") || name_lower.contains("bin") || name_lower.contains("deleted") {
+        Some("trash".to_string())
+    } else if name_lower.contains("archive") || name_lower.contains("all mail") {
+        Some("archive".to_string())
+    } else {
+        None
+    }
+}
+
+impl SyncEngine {
+    pub fn new(app: AppContext) -> Self {
         Self {
-            app_handle,
+            app,
             idle_senders: Arc::new(Mutex::new(HashMap::new())),
             contexts: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    pub async fn get_context(&self, account_id: i64) -> Result<ImapContext, String> {
+    pub fn app(&self) -> &AppContext {
+        &self.app
+    }
+
+    pub async fn get_context(&self, account_id: i64) -> Result<AccountContext, AccountError> {
         let mut contexts = self.contexts.lock().await;
         if let Some(ctx) = contexts.get(&account_id) {
             return Ok(ctx.clone());
         }
 
-        let manager = AccountManager::new(&self.app_handle).await?;
-        let account = manager.get_account_by_id(account_id).await?;
-        let (account_config, imap_config, _) = account.get_configs()?;
-
-        // Use pool size 2 to allow IDLE and one concurrent request
-        let ctx_builder = ImapContextBuilder::new(account_config.clone(), imap_config)
-            .with_pool_size(2);
+        let manager = self.app.accounts().await?;
+        let account = manager.get_authenticated_account(account_id).await?;
+        let ctx_builder = AccountContextBuilder::new(&account)?;
 
-        let context: ImapContext = match BackendContextBuilder::build(ctx_builder).await {
-            Ok(ctx) => ctx,
-            Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("auth") || err_str.contains("Unauthorized") || err_str.contains("token") || err_str.contains("credentials") {
-                    info!("Refreshing token for account {} due to context build error: {}", account.email(), err_str);
-                    manager.refresh_access_token(account.email()).await?;
-
-                    // Reload account and configs
-                    let account = manager.get_account_by_id(account_id).await?;
-                    let (account_config, imap_config, _) = account.get_configs()?;
-                    let ctx_builder = ImapContextBuilder::new(account_config, imap_config)
-                        .with_pool_size(2);
-
-                    BackendContextBuilder::build(ctx_builder)
-                        .await
-                        .map_err(|e| e.to_string())?
-                } else {
-                    return Err(err_str);
-                }
-            }
-        };
+        let context = BackendContextBuilder::build(ctx_builder)
+            .await
+            .map_err(|e| AccountError::Other(e.to_string()))?;
 
         contexts.insert(account_id, context.clone());
         Ok(context)
     }
 
-    pub async fn get_backend(&self, account_id: i64) -> Result<Backend<ImapContext>, String> {
+    /// The IMAP connection pool of an account, for work the generic backend features do not cover.
+    pub async fn get_imap_context(&self, account_id: i64) -> Result<ImapContext, AccountError> {
+        self.get_context(account_id).await?
+            .imap
+            .ok_or_else(|| AccountError::Other(format!("Account {} is not an IMAP account", account_id)))
+    }
+
+    pub async fn get_backend(&self, account_id: i64) -> Result<Backend<AccountContext>, String> {
         let context = self.get_context(account_id).await?;
-        let manager = AccountManager::new(&self.app_handle).await?;
+        let manager = self.app.accounts().await?;
         let account = manager.get_account_by_id(account_id).await?;
-        let (account_config, imap_config, _) = account.get_configs()?;
-
-        let ctx_builder = ImapContextBuilder::new(account_config.clone(), imap_config);
-
-        Ok(Backend {
-            account_config,
-            context: Arc::new(context),
-            add_folder: ctx_builder.add_folder(),
-            list_folders: ctx_builder.list_folders(),
-            expunge_folder: ctx_builder.expunge_folder(),
-            purge_folder: ctx_builder.purge_folder(),
-            delete_folder: ctx_builder.delete_folder(),
-            get_envelope: ctx_builder.get_envelope(),
-            list_envelopes: ctx_builder.list_envelopes(),
-            thread_envelopes: ctx_builder.thread_envelopes(),
-            watch_envelopes: ctx_builder.watch_envelopes(),
-            add_flags: ctx_builder.add_flags(),
-            set_flags: ctx_builder.set_flags(),
-            remove_flags: ctx_builder.remove_flags(),
-            add_message: ctx_builder.add_message(),
-            send_message: None,
-            peek_messages: ctx_builder.peek_messages(),
-            get_messages: ctx_builder.get_messages(),
-            copy_messages: ctx_builder.copy_messages(),
-            move_messages: ctx_builder.move_messages(),
-            delete_messages: ctx_builder.delete_messages(),
-            remove_messages: ctx_builder.remove_messages(),
-        })
+
+        Ok(AccountContextBuilder::new(&account)?.backend(context))
+    }
+
+    /// Fetches a full message without marking it as seen.
+    pub async fn fetch_message(&self, account_id: i64, folder_path: &str, remote_id: &str) -> Result<Messages, String> {
+        let context = self.get_context(account_id).await?;
+
+        let Some(imap) = context.imap else {
+            let backend = self.get_backend(account_id).await?;
+            return backend.peek_messages(folder_path, &Id::single(remote_id)).await.map_err(|e| e.to_string());
+        };
+
+        use imap_client::imap_next::imap_types::fetch::{MacroOrMessageDataItemNames, MessageDataItemName};
+        use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
+        use imap_client::imap_next::imap_types::error::ValidationError;
+
+        let fetch_items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+            MessageDataItemName::BodyExt {
+                section: None,
+                partial: None,
+                peek: true,
+            }
+        ]);
+
+        let mut client = imap.client().await;
+        client.examine_mailbox(folder_path).await.map_err(|e| e.to_string())?;
+
+        let uids: SequenceSet = Id::single(remote_id).iter()
+            .filter_map(|s| s.parse::<u32>().ok())
+            .filter_map(NonZeroU32::new)
+            .map(Sequence::from)
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|e: ValidationError| e.to_string())?;
+
+        client.fetch_messages_with_items(uids, fetch_items).await.map_err(|e| e.to_string())
     }
 
     pub async fn start(&self) {
         info!("Starting Sync Engine...");
-        let app_handle = self.app_handle.clone();
-
         // Initial sync of all accounts
-        if let Err(e) = Self::sync_all_accounts(&app_handle).await {
+        if let Err(e) = self.sync_all_accounts().await {
             error!("Initial sync failed: {}", e);
         }
 
         // Start background periodic sync
-        let app_handle_periodic = app_handle.clone();
+        let engine = self.clone();
         tauri::async_runtime::spawn(async move {
             loop {
                 sleep(Duration::from_secs(300)).await;
-                if let Err(e) = Self::sync_all_accounts(&app_handle_periodic).await {
+                if let Err(e) = engine.sync_all_accounts().await {
                     error!("Error during periodic sync: {}", e);
                 }
             }
         });
 
+        // Refresh OAuth2 tokens ahead of expiry so reconnects never present a stale one
+        let engine = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                engine.refresh_expiring_tokens().await;
+                sleep(Duration::from_secs(60)).await;
+            }
+        });
+
         // Start IDLE for all accounts
-        if let Ok(manager) = AccountManager::new(&app_handle).await {
+        if let Ok(manager) = self.app.accounts().await {
             if let Ok(registry) = manager.load().await {
                 for account in registry.accounts {
                     let engine = self.clone();
@@ -181,7 +265,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
         tauri::async_runtime::spawn(async move {
             // 1. Initial sync
-            if let Err(e) = Self::sync_account(&engine.app_handle, &account).await {
+            if let Err(e) = engine.sync_account(&account).await {
                 error!("Initial sync failed for {}: {}", account.email(), e);
             }
 
@@ -190,20 +274,74 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         });
     }
 
-    pub async fn refresh_folder(app_handle: &tauri::AppHandle<R>, account_id: i64, folder_id: i64) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
-        let folder_info: (String, Option<String>) = sqlx::query_as("SELECT path, role FROM folders WHERE id = ?")
+    /// Stops IDLE for an account and drops its cached IMAP context, e.g. after the
+    /// account was removed or its credentials changed.
+    pub async fn stop_account(&self, account_id: i64) {
+        if let Some(tx) = self.idle_senders.lock().await.remove(&account_id) {
+            let _ = tx.send(());
+        }
+        self.contexts.lock().await.remove(&account_id);
+    }
+
+    async fn refresh_expiring_tokens(&self) {
+        let Ok(manager) = self.app.accounts().await else { return };
+        let Ok(registry) = manager.load().await else { return };
+
+        for account in registry.accounts {
+            let Some(account_id) = account.id() else { continue };
+            if !account.needs_token_refresh() {
+                continue;
+            }
+
+            match manager.refresh_access_token(account.email()).await {
+                Ok(_) => {
+                    // The cached context holds the old token for its reconnects
+                    self.contexts.lock().await.remove(&account_id);
+                }
+                Err(e) if e.is_reauth_required() => self.report_reauth_required(account_id, &e).await,
+                Err(e) => warn!("Token refresh for {} failed, retrying later: {}", account.email(), e),
+            }
+        }
+    }
+
+    /// Tells the frontend that an account has to be signed in again and stops its IDLE loop.
+    async fn report_reauth_required(&self, account_id: i64, err: &AccountError) {
+        let AccountError::ReauthRequired { email, reason } = err else { return };
+        warn!("Account {} needs to be signed in again: {}", email, reason);
+
+        self.stop_account(account_id).await;
+        self.app.emit("account-needs-reauth", ReauthRequiredEvent {
+            account_id,
+            email: email.clone(),
+            reason: reason.clone(),
+        });
+    }
+
+    pub async fn refresh_folder(&self, account_id: i64, folder_id: i64) -> Result<(), String> {
+        let pool = self.app.pool();
+        let folder_info: (String, Option<String>, bool) = sqlx::query_as("SELECT path, role, is_local FROM folders WHERE id = ?")
             .bind(folder_id)
-            .fetch_one(&*pool)
+            .fetch_one(pool)
             .await
             .map_err(|e| e.to_string())?;
 
-        let (folder_path, folder_role) = folder_info;
+        let (folder_path, folder_role, is_local) = folder_info;
+
+        // Local folders have nothing on the server to sync with
+        if is_local {
+            return Ok(());
+        }
+
+        let account = self.app.accounts().await?.get_account_by_id(account_id).await?;
 
-        let engine = app_handle.state::<SyncEngine<R>>();
-        let context = engine.get_context(account_id).await?;
-        let account = AccountManager::new(app_handle).await?.get_account_by_id(account_id).await?;
+        if account.backend_kind() != BackendKind::Imap {
+            let backend = self.get_backend(account_id).await?;
+            self.sync_local_folder(&backend, &account, &folder_path, folder_role).await?;
+            self.app.emit("emails-updated", account_id);
+            return Ok(());
+        }
 
+        let context = self.get_imap_context(account_id).await?;
         let mut client = context.client().await;
 
         let folder_data = match client.examine_mailbox(&folder_path).await {
@@ -216,54 +354,23 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             }
         };
 
-        Self::sync_folder(app_handle, &mut *client, &account, &folder_path, folder_role, &folder_data).await?;
+        let new_mail = self.sync_folder(&mut *client, &account, &folder_path, folder_role, &folder_data).await?;
+        drop(client);
+        if !new_mail.is_empty() {
+            self.process_new_mail(new_mail).await;
+        }
 
-        let _ = app_handle.emit("emails-updated", account_id);
+        self.app.emit("emails-updated", account_id);
 
         Ok(())
     }
 
-    async fn is_ai_summary_enabled(app_handle: &tauri::AppHandle<R>) -> bool {
-        let pool = app_handle.state::<SqlitePool>();
-        let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-
-        let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-
-        ai_enabled.0 == "true" && ai_summarization_enabled.0 == "true"
-    }
-
-    async fn is_notifications_enabled(app_handle: &tauri::AppHandle<R>) -> bool {
-        let pool = app_handle.state::<SqlitePool>();
-        let notifications_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'notificationsEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("true".to_string(),));
-
-        notifications_enabled.0 == "true"
-    }
-
-    async fn handle_notification(
-        app_handle: tauri::AppHandle<R>,
-        email_id: i64,
-        subject: String,
-        sender: String,
-    ) {
-        if !Self::is_notifications_enabled(&app_handle).await {
-            return;
-        }
-
-        if !Self::is_ai_summary_enabled(&app_handle).await {
-            let _ = app_handle.notification()
-                .builder()
-                .title(format!("New Email: {}", subject))
-                .body(format!("From: {}", sender))
-                .show();
+    /// Notifies for one message the policy let through, with its AI summary when one arrives in time.
+    async fn handle_notification(&self, email_id: i64, subject: String, sender: String) {
+        let settings = self.app.settings();
+        let title = format!("New Email: {}", subject);
+        if !settings.ai_summaries_enabled().await {
+            self.app.notify(&title, &format!("From: {}", sender));
             return;
         }
 
@@ -272,32 +379,27 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         let timeout = std::time::Duration::from_secs(10);
 
         // Trigger indexing and summarization immediately for this email
-        let app_handle_worker = app_handle.clone();
+        let engine = self.clone();
         tauri::async_runtime::spawn(async move {
             use crate::email_backend::sync::worker::SyncWorker;
-            if let Err(e) = SyncWorker::index_specific_email(&app_handle_worker, email_id).await {
+            if let Err(e) = SyncWorker::index_specific_email(&engine, email_id).await {
                 error!("Failed to index email {} for notification: {}", email_id, e);
                 return;
             }
-            if let Err(e) = SyncWorker::summarize_specific_email(&app_handle_worker, email_id).await {
+            if let Err(e) = SyncWorker::summarize_specific_email(engine.app(), email_id).await {
                 error!("Failed to summarize email {} for notification: {}", email_id, e);
             }
         });
 
         while start.elapsed() < timeout {
-             let pool = app_handle.state::<SqlitePool>();
              let summary: Option<Option<String>> = sqlx::query_scalar("SELECT summary FROM emails WHERE id = ?")
                  .bind(email_id)
-                 .fetch_optional(&*pool)
+                 .fetch_optional(self.app.pool())
                  .await
                  .unwrap_or(None);
 
              if let Some(Some(s)) = summary {
-                 let _ = app_handle.notification()
-                     .builder()
-                     .title(format!("New Email: {}", subject))
-                     .body(format!("{}", s))
-                     .show();
+                 self.app.notify(&title, &s);
                  return;
              }
 
@@ -305,21 +407,83 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         }
 
         // Timeout reached, send default notification
-        let _ = app_handle.notification()
-            .builder()
-            .title(format!("New Email: {}", subject))
-            .body(format!("From: {}", sender))
-            .show();
+        self.app.notify(&title, &format!("From: {}", sender));
     }
 
-    async fn save_envelopes(
-        app_handle: &tauri::AppHandle<R>,
+    /// Runs the rules over newly arrived mail, archives mail from lists the user left and routes
+    /// muted and digest lists, then notifies for what is left and the daily digest will not cover.
+    /// Rules may move or flag the messages, so callers run it once the folder's connection is free.
+    async fn process_new_mail(&self, new_mail: Vec<NewMail>) {
+        let ids: Vec<i64> = new_mail.iter().map(|mail| mail.email_id).collect();
+        let mut outcome = match apply_rules(self, &ids).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                error!("Failed to apply rules to new mail: {}", e);
+                RulesOutcome::default()
+            }
+        };
+        let unmatched: Vec<i64> = ids.iter().copied().filter(|id| !outcome.matched.contains(id)).collect();
+        match archive_unsubscribed(self, &unmatched).await {
+            Ok(archived) => {
+                outcome.matched.extend(archived.iter().copied());
+                outcome.silenced.extend(archived);
+            }
+            Err(e) => error!("Failed to archive mail from unsubscribed lists: {}", e),
+        }
+        let unmatched: Vec<i64> = unmatched.into_iter().filter(|id| !outcome.matched.contains(id)).collect();
+        match route_new_mail(self, &unmatched).await {
+            Ok(routed) => {
+                outcome.matched.extend(routed.changed);
+                outcome.silenced.extend(routed.silenced);
+            }
+            Err(e) => error!("Failed to route mail from muted and digest lists: {}", e),
+        }
+        match digest_silenced(self, &ids).await {
+            Ok(covered) => outcome.silenced.extend(covered),
+            Err(e) => error!("Failed to check new mail against the digest: {}", e),
+        }
+        if !outcome.matched.is_empty() {
+            self.app.emit("emails-updated", "bulk-add");
+        }
+
+        // Hosts without notifications do not need the summary work behind them
+        if !self.app.can_notify() {
+            return;
+        }
+        let mail: Vec<(i64, String, String)> = new_mail.into_iter()
+            .filter(|mail| !mail.seen && !outcome.silenced.contains(&mail.email_id))
+            .map(|mail| (mail.email_id, mail.subject, mail.sender))
+            .collect();
+        let planned = match notification_plan(&self.app, &mail).await {
+            Ok(planned) => planned,
+            Err(e) => {
+                error!("Failed to plan notifications: {}", e);
+                return;
+            }
+        };
+        for notification in planned {
+            match notification {
+                Planned::Single { email_id, subject, sender } => {
+                    info!("Scheduling notification for email: {}", subject);
+                    let engine = self.clone();
+                    tauri::async_runtime::spawn(async move {
+                        engine.handle_notification(email_id, subject, sender).await;
+                    });
+                }
+                Planned::Batch { title, body } => self.app.notify(&title, &body),
+            }
+        }
+    }
+
+    pub(crate) async fn save_envelopes(
+        &self,
         account_id: i64,
         folder_id: i64,
         envelopes: Envelopes,
-        notify: bool,
+        list_headers: &HashMap<String, Vec<(String, String)>>,
+        mut new_mail: Option<&mut Vec<NewMail>>,
     ) -> Result<Vec<i64>, String> {
-        let pool = app_handle.state::<SqlitePool>();
+        let pool = self.app.pool();
         let mut saved_ids = Vec::new();
         let mut success_count = 0;
         let mut failure_count = 0;
@@ -356,21 +520,24 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             .bind(&date_str)
             .bind(serde_json::to_string(&flags).unwrap_or_default())
             .bind(env.has_attachment)
-            .fetch_one(&*pool)
+            .fetch_one(pool)
             .await;
 
             match res {
                 Ok((email_id,)) => {
                     success_count += 1;
                     saved_ids.push(email_id);
-                    if notify && !flags.contains(&"seen".to_string()) {
-                        info!("Scheduling notification for email: {}", env.subject);
-                        let app_handle_clone = app_handle.clone();
-                        let subject = env.subject.clone();
-                        let sender = env.from.name.as_deref().unwrap_or(&env.from.addr).to_string();
-
-                        tauri::async_runtime::spawn(async move {
-                            Self::handle_notification(app_handle_clone, email_id, subject, sender).await;
+                    if let Some(headers) = list_headers.get(&env.id) {
+                        if let Err(e) = save_list_headers(pool, email_id, headers).await {
+                            warn!("Failed to save list headers of email {}: {}", email_id, e);
+                        }
+                    }
+                    if let Some(new_mail) = new_mail.as_deref_mut() {
+                        new_mail.push(NewMail {
+                            email_id,
+                            subject: env.subject.clone(),
+                            sender: env.from.name.as_deref().unwrap_or(&env.from.addr).to_string(),
+                            seen: flags.contains(&"seen".to_string()),
                         });
                     }
                 }
@@ -393,7 +560,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         )
         .bind(folder_id)
         .bind(folder_id)
-        .execute(&*pool)
+        .execute(pool)
         .await;
 
         if failure_count > 0 && success_count == 0 {
@@ -420,19 +587,36 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                     info!("Stopping IDLE for account: {}", account.email());
                     break;
                 }
-                res = self.run_idle_loop(&account) => res,
+                res = self.watch_account(&account) => res,
             };
 
-            if let Err(e) = res {
-                error!("IDLE loop error for {}: {}. Retrying in 30s...", account.email(), e);
-                sleep(Duration::from_secs(30)).await;
+            match res {
+                // Retrying cannot fix a revoked grant, wait for the user to log in again
+                Err(e) if e.is_reauth_required() => {
+                    self.report_reauth_required(account_id, &e).await;
+                    break;
+                }
+                Err(e) => {
+                    error!("IDLE loop error for {}: {}. Retrying in 30s...", account.email(), e);
+                    sleep(Duration::from_secs(30)).await;
+                }
+                Ok(()) => {}
             }
         }
     }
 
-    async fn run_idle_loop(&self, account: &Account) -> Result<(), String> {
-        let account_id = account.id().ok_or("Account ID missing")?;
-        let context = self.get_context(account_id).await?;
+    /// Waits for changes to the account's inbox and syncs them, returning when the watch has to restart.
+    async fn watch_account(&self, account: &Account) -> Result<(), AccountError> {
+        match account.backend_kind() {
+            BackendKind::Imap => self.run_idle_loop(account).await,
+            BackendKind::Maildir => self.run_maildir_watch(account).await,
+            BackendKind::Notmuch => self.run_notmuch_poll(account).await,
+        }
+    }
+
+    async fn run_idle_loop(&self, account: &Account) -> Result<(), AccountError> {
+        let account_id = account.id().ok_or("Account ID missing".to_string())?;
+        let context = self.get_imap_context(account_id).await?;
 
         let mut client = context.client().await;
 
@@ -442,8 +626,11 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             // Select INBOX and get current state
             let folder_data = client.select_mailbox("INBOX").await.map_err(|e| e.to_string())?;
 
-            // Sync current state
-            Self::sync_folder(&self.app_handle, &mut *client, account, "INBOX", Some("inbox".to_string()), &folder_data).await?;
+            // Sync current state, the pool's other connection is free for what rules do to new mail
+            let new_mail = self.sync_folder(&mut *client, account, "INBOX", Some("inbox".to_string()), &folder_data).await?;
+            if !new_mail.is_empty() {
+                self.process_new_mail(new_mail).await;
+            }
 
             let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
 
@@ -461,21 +648,17 @@ impl<R: tauri::Runtime> SyncEngine<R> {
     }
 
     async fn sync_folder(
-        app_handle: &tauri::AppHandle<R>,
+        &self,
         client: &mut ImapClient,
         account: &Account,
         folder_name: &str,
         role: Option<String>,
         folder_data: &SelectDataUnvalidated
-    ) -> Result<(), String> {
+    ) -> Result<Vec<NewMail>, String> {
         let account_id = account.id().ok_or("Account ID missing")?;
-        let pool = app_handle.state::<SqlitePool>();
+        let pool = self.app.pool();
 
-        let sync_months_setting: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'syncMonths'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("3".to_string(),));
-        let sync_months = sync_months_setting.0.parse::<i32>().unwrap_or(3);
+        let sync_months = self.app.settings().number("syncMonths", 3).await;
 
         info!("Syncing folder {} for {}. Role: {:?}. SyncMonths: {}", folder_name, account.email(), role, sync_months);
 
@@ -487,11 +670,11 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
         // 1. Get stored folder info
         let stored_folder: Option<(i64, i64, i64, Option<String>)> = sqlx::query_as(
-            "SELECT id, uid_validity, uid_next, role FROM folders WHERE account_id = ? AND path = ?"
+            "SELECT id, uid_validity, uid_next, role FROM folders WHERE account_id = ? AND path = ? AND is_local = 0"
         )
         .bind(account_id)
         .bind(folder_name)
-        .fetch_optional(&*pool)
+        .fetch_optional(pool)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -504,7 +687,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                         sqlx::query("UPDATE folders SET role = ? WHERE id = ?")
                             .bind(new_role)
                             .bind(id)
-                            .execute(&*pool)
+                            .execute(pool)
                             .await
                             .map_err(|e| e.to_string())?;
                     }
@@ -526,7 +709,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 .bind(current_uid_validity)
                 .bind(current_uid_next)
                 .bind(total_count)
-                .fetch_one(&*pool)
+                .fetch_one(pool)
                 .await
                 .map_err(|e| e.to_string())?;
                 info!("Created folder entry {} with id {}", folder_name, row.0);
@@ -539,11 +722,13 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             info!("UID validity changed for folder {} of {}, clearing local cache", folder_name, account.email());
             sqlx::query("DELETE FROM emails WHERE folder_id = ?")
                 .bind(folder_id)
-                .execute(&*pool)
+                .execute(pool)
                 .await
                 .map_err(|e| e.to_string())?;
         }
 
+        let mut new_mail = Vec::new();
+        // A first sync is a backfill and a UIDVALIDITY change refills the folder, neither is new mail
         if stored_uid_validity != current_uid_validity || stored_uid_next == 0 {
             info!("Performing full sync for folder {} of {} (total={})", folder_name, account.email(), total_count);
             let mut end = total_count as u32;
@@ -570,8 +755,8 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 let batch_len = envelopes.len() as u32;
                 info!("Fetched {} envelopes for sequence {}:{} in folder {}", batch_len, start, end, folder_name);
 
-                let is_initial = stored_uid_next == 0;
-                let _saved_ids = match Self::save_envelopes(app_handle, account_id, folder_id, envelopes, !is_initial).await {
+                let list_headers = fetch_list_headers(client, &envelopes).await;
+                let _saved_ids = match self.save_envelopes(account_id, folder_id, envelopes, &list_headers, None).await {
                     Ok(ids) => ids,
                     Err(e) => {
                         error!("Critical failure saving envelopes for {}: {}. Aborting folder sync.", folder_name, e);
@@ -581,7 +766,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
                 synced_count += batch_len;
                 // Signal that new emails are available without spamming granular events
-                let _ = app_handle.emit("emails-updated", "bulk-add");
+                self.app.emit("emails-updated", "bulk-add");
 
                 end = if start > 1 { start - 1 } else { 0 };
             }
@@ -590,27 +775,84 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
             let start_uid = NonZeroU32::new(stored_uid_next as u32).unwrap_or(NonZeroU32::new(1).unwrap());
             let uids = (start_uid..).into();
-            let mut envelopes = client.fetch_envelopes(uids).await.map_err(|e| {
+            let envelopes = client.fetch_envelopes(uids).await.map_err(|e| {
                 error!("Failed to fetch envelopes incremental UID {}:* for {}: {}", stored_uid_next, folder_name, e);
                 e.to_string()
             })?;
 
             if !envelopes.is_empty() {
                 info!("Fetched {} new envelopes incrementally for folder {}", envelopes.len(), folder_name);
-                let _saved_ids = match Self::save_envelopes(app_handle, account_id, folder_id, envelopes, true).await {
-                    Ok(ids) => ids,
-                    Err(e) => {
+                let list_headers = fetch_list_headers(client, &envelopes).await;
+                // `n:*` always returns the highest UID, even when it is below n
+                let (new, known): (Vec<_>, Vec<_>) = envelopes.into_iter()
+                    .partition(|e| e.id.parse::<i64>().is_ok_and(|uid| uid >= stored_uid_next));
+                for (batch, is_new) in [(known, false), (new, true)] {
+                    if batch.is_empty() {
+                        continue;
+                    }
+                    let collect = if is_new { Some(&mut new_mail) } else { None };
+                    if let Err(e) = self.save_envelopes(account_id, folder_id, batch.into_iter().collect(), &list_headers, collect).await {
                         error!("Critical failure saving incremental envelopes for {}: {}. Aborting folder sync.", folder_name, e);
                         return Err(e);
                     }
-                };
+                }
 
-                let _ = app_handle.emit("emails-updated", "bulk-add");
+                self.app.emit("emails-updated", "bulk-add");
             }
         } else {
             info!("Folder {} of {} is up to date", folder_name, account.email());
         }
 
+        // Messages expunged by another client leave the counts out of step
+        let stored_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE folder_id = ?")
+            .bind(folder_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if stored_count != total_count {
+            let remote_ids: HashSet<String> = client.search_uids(vec![SearchKey::All]).await
+                .map_err(|e| {
+                    error!("Failed to search UIDs in folder {}: {}", folder_name, e);
+                    e.to_string()
+                })?
+                .into_iter()
+                .map(|uid| uid.to_string())
+                .collect();
+
+            let known_ids: Vec<String> = sqlx::query_scalar("SELECT remote_id FROM emails WHERE folder_id = ?")
+                .bind(folder_id)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let removed: Vec<&String> = known_ids.iter().filter(|id| !remote_ids.contains(*id)).collect();
+            if !removed.is_empty() {
+                info!("Removing {} expunged messages from folder {} of {}", removed.len(), folder_name, account.email());
+                for remote_id in &removed {
+                    sqlx::query("DELETE FROM emails WHERE folder_id = ? AND remote_id = ?")
+                        .bind(folder_id)
+                        .bind(remote_id)
+                        .execute(pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+
+                sqlx::query(
+                    "UPDATE folders SET unread_count = (
+                        SELECT COUNT(*) FROM emails WHERE folder_id = ? AND flags NOT LIKE '%seen%'
+                    ) WHERE id = ?"
+                )
+                .bind(folder_id)
+                .bind(folder_id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+                self.app.emit("emails-updated", "bulk-add");
+            }
+        }
+
         // Update folder info with latest state from server
         info!("Updating folder {} entry with new UIDNext={}", folder_name, current_uid_next);
         sqlx::query(
@@ -620,62 +862,57 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         .bind(current_uid_next)
         .bind(total_count)
         .bind(folder_id)
-        .execute(&*pool)
+        .execute(pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        Ok(())
+        Ok(new_mail)
     }
 
-    pub async fn sync_all_accounts(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
-        let manager = AccountManager::new(app_handle).await?;
+    pub async fn sync_all_accounts(&self) -> Result<(), String> {
+        let manager = self.app.accounts().await?;
         let registry = manager.load().await?;
 
         for account in registry.accounts {
-            if let Err(e) = Self::sync_account(app_handle, &account).await {
+            if let Err(e) = self.sync_account(&account).await {
                 error!("Failed to sync account {}: {}", account.email(), e);
             }
         }
 
+        // Snooze keywords synced from the server bring back timers a reinstall lost
+        if let Err(e) = restore_snoozes(self.app.pool()).await {
+            error!("Failed to restore snoozes: {}", e);
+        }
+
         Ok(())
     }
 
-    pub async fn sync_account(app_handle: &tauri::AppHandle<R>, account: &Account) -> Result<(), String> {
+    pub async fn sync_account(&self, account: &Account) -> Result<(), String> {
         // Ensure we have the latest account info with ID from DB
-        let manager = AccountManager::new(app_handle).await?;
+        let manager = self.app.accounts().await?;
         let account = manager.get_account_by_id(account.id().ok_or("Account ID missing before sync")?).await?;
 
-        Self::sync_imap_account(app_handle, &account).await
+        match account.backend_kind() {
+            BackendKind::Imap => self.sync_imap_account(&account).await,
+            BackendKind::Maildir | BackendKind::Notmuch => self.sync_local_account(&account).await,
+        }
     }
 
-    async fn sync_imap_account(app_handle: &tauri::AppHandle<R>, account: &Account) -> Result<(), String> {
+    async fn sync_imap_account(&self, account: &Account) -> Result<(), String> {
         info!("Syncing IMAP account: {}", account.email());
         let account_id = account.id().ok_or("Account ID missing")?;
 
-        let engine = app_handle.state::<SyncEngine<R>>();
-        let backend = engine.get_backend(account_id).await?;
+        let backend = self.get_backend(account_id).await?;
         let folders = backend.list_folders().await.map_err(|e| e.to_string())?;
 
-        let context = (*backend.context).clone();
+        let context = self.get_imap_context(account_id).await?;
 
         for folder in folders {
-            let name_lower = folder.name.to_lowercase();
-            let role = if folder.is_inbox() {
-                Some("inbox".to_string())
-            } else if folder.is_sent() {
-                Some("sent".to_string())
-            } else if folder.is_drafts() || name_lower.contains("drafts") {
-                Some("drafts".to_string())
-            } else if name_lower.contains("spam") || name_lower.contains("junk") {
-                Some("spam".to_string())
-            } else if name_lower.contains("trash") || name_lower.contains("bin") || name_lower.contains("deleted") {
-                Some("trash".to_string())
-            } else if name_lower.contains("archive") || name_lower.contains("all mail") {
-                Some("archive".to_string())
-            } else {
+            let role = folder_role(&folder);
+            if role.is_none() {
                 // Ignore all other folders for the revamped inbox
                 continue;
-            };
+            }
 
             let mut client = context.client().await;
             info!("Syncing revamped folder: {} as {:?} for {}", folder.name, role, account.email());
@@ -683,21 +920,234 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 error!("Failed to select mailbox {}: {}", folder.name, e);
                 e.to_string()
             })?;
-            Self::sync_folder(app_handle, &mut *client, account, &folder.name, role, &folder_data).await?;
+            let new_mail = self.sync_folder(&mut *client, account, &folder.name, role, &folder_data).await?;
+            drop(client);
+            if !new_mail.is_empty() {
+                self.process_new_mail(new_mail).await;
+            }
         }
 
         Ok(())
     }
+
+    async fn sync_local_account(&self, account: &Account) -> Result<(), String> {
+        info!("Syncing local account: {}", account.email());
+        let account_id = account.id().ok_or("Account ID missing")?;
+
+        let backend = self.get_backend(account_id).await?;
+        let folders = backend.list_folders().await.map_err(|e| e.to_string())?;
+
+        // A Maildir++ inbox is the root directory itself, which is not listed as a folder
+        let maildirpp = match account {
+            Account::Maildir(m) => m.maildirpp,
+            Account::Notmuch(n) => n.maildirpp,
+            _ => false,
+        };
+        if maildirpp && !folders.iter().any(|f| f.is_inbox()) {
+            self.sync_local_folder(&backend, account, "INBOX", Some("inbox".to_string())).await?;
+        }
+
+        for folder in folders.iter() {
+            let role = folder_role(folder);
+            if role.is_none() {
+                continue;
+            }
+            self.sync_local_folder(&backend, account, &folder.name, role).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Maildir and notmuch have no UIDs to sync incrementally from, so the whole folder is
+    /// listed and diffed against the local cache by message id.
+    async fn sync_local_folder(
+        &self,
+        backend: &Backend<AccountContext>,
+        account: &Account,
+        folder_name: &str,
+        role: Option<String>,
+    ) -> Result<(), String> {
+        let account_id = account.id().ok_or("Account ID missing")?;
+        let pool = self.app.pool();
+
+        let stored_folder: Option<(i64, Option<String>)> = sqlx::query_as(
+            "SELECT id, role FROM folders WHERE account_id = ? AND path = ? AND is_local = 0"
+        )
+        .bind(account_id)
+        .bind(folder_name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let (folder_id, is_initial) = match stored_folder {
+            Some((id, stored_role)) => {
+                if role.is_some() && stored_role != role {
+                    sqlx::query("UPDATE folders SET role = ? WHERE id = ?")
+                        .bind(&role)
+                        .bind(id)
+                        .execute(pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                (id, false)
+            }
+            None => {
+                let row: (i64,) = sqlx::query_as(
+                    "INSERT INTO folders (account_id, name, path, role) VALUES (?, ?, ?, ?) RETURNING id"
+                )
+                .bind(account_id)
+                .bind(folder_name)
+                .bind(folder_name)
+                .bind(role.clone().unwrap_or_default())
+                .fetch_one(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+                (row.0, true)
+            }
+        };
+
+        let envelopes = backend.list_envelopes(folder_name, ListEnvelopesOptions::default()).await.map_err(|e| {
+            error!("Failed to list local folder {} for {}: {}", folder_name, account.email(), e);
+            e.to_string()
+        })?;
+        let total_count = envelopes.len() as i64;
+
+        let known_ids: HashSet<String> = sqlx::query_scalar::<_, String>("SELECT remote_id FROM emails WHERE folder_id = ?")
+            .bind(folder_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect();
+
+        let remote_ids: HashSet<String> = envelopes.iter().map(|e| e.id.clone()).collect();
+        let (new, existing): (Vec<_>, Vec<_>) = envelopes.into_iter().partition(|e| !known_ids.contains(&e.id));
+        let has_new = !new.is_empty();
+
+        info!("Local folder {} of {}: {} messages, {} new", folder_name, account.email(), total_count, new.len());
+
+        if !existing.is_empty() {
+            self.save_envelopes(account_id, folder_id, existing.into_iter().collect(), &HashMap::new(), None).await?;
+        }
+        let mut new_mail = Vec::new();
+        if has_new {
+            // Reading files is cheap, but a first sync leaves the list headers to the body indexer
+            let mut list_headers = HashMap::new();
+            if !is_initial {
+                for envelope in &new {
+                    match backend.peek_messages(folder_name, &Id::single(envelope.id.as_str())).await {
+                        Ok(messages) => {
+                            if let Some(raw) = messages.first().and_then(|m| m.raw().ok()) {
+                                list_headers.insert(envelope.id.clone(), parse_headers(raw));
+                            }
+                        }
+                        Err(e) => warn!("Failed to read headers of {} in {}: {}", envelope.id, folder_name, e),
+                    }
+                }
+            }
+            let collect = if is_initial { None } else { Some(&mut new_mail) };
+            self.save_envelopes(account_id, folder_id, new.into_iter().collect(), &list_headers, collect).await?;
+        }
+
+        // Messages deleted or moved away by another client
+        let removed: Vec<&String> = known_ids.difference(&remote_ids).collect();
+        for remote_id in &removed {
+            sqlx::query("DELETE FROM emails WHERE folder_id = ? AND remote_id = ?")
+                .bind(folder_id)
+                .bind(remote_id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        sqlx::query(
+            "UPDATE folders SET total_count = ?, unread_count = (
+                SELECT COUNT(*) FROM emails WHERE folder_id = ? AND flags NOT LIKE '%seen%'
+            ) WHERE id = ?"
+        )
+        .bind(total_count)
+        .bind(folder_id)
+        .bind(folder_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if has_new || !removed.is_empty() {
+            self.app.emit("emails-updated", "bulk-add");
+        }
+        if !new_mail.is_empty() {
+            self.process_new_mail(new_mail).await;
+        }
+
+        Ok(())
+    }
+
+    /// Live updates for a Maildir inbox from filesystem events. The watcher is owned by this
+    /// future, so stopping or restarting the account drops it and ends the watch.
+    async fn run_maildir_watch(&self, account: &Account) -> Result<(), AccountError> {
+        let account_id = account.id().ok_or("Account ID missing".to_string())?;
+        let backend = self.get_backend(account_id).await?;
+        let maildir = backend.context.maildir.as_ref().ok_or("Maildir context missing".to_string())?;
+        let inbox = maildir.lock().await
+            .get_maildir_from_folder_alias("INBOX")
+            .map_err(|e| AccountError::Other(e.to_string()))?;
+
+        // Deliveries, removals and flag changes (renames) all need a sync; reads do not
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let account_email = account.email().to_string();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) if !event.kind.is_access() => {
+                let _ = tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Maildir watcher error for {}: {}", account_email, e),
+        })
+        .map_err(|e| AccountError::Other(e.to_string()))?;
+        watcher
+            .watch(inbox.path(), RecursiveMode::Recursive)
+            .map_err(|e| AccountError::Other(e.to_string()))?;
+        info!("Watching Maildir inbox of {}", account.email());
+
+        loop {
+            self.sync_local_folder(&backend, account, "INBOX", Some("inbox".to_string())).await?;
+
+            if rx.recv().await.is_none() {
+                return Err(AccountError::Other(format!("Maildir watcher for {} stopped", account.email())));
+            }
+            // Deliveries often come in bursts, sync once for all of them
+            sleep(Duration::from_millis(500)).await;
+            while rx.try_recv().is_ok() {}
+        }
+    }
+
+    /// email-lib has no notmuch watcher, so the inbox is rescanned on a timer. This also
+    /// picks up tags changed by other notmuch clients.
+    async fn run_notmuch_poll(&self, account: &Account) -> Result<(), AccountError> {
+        let account_id = account.id().ok_or("Account ID missing".to_string())?;
+        let backend = self.get_backend(account_id).await?;
+        info!("Polling notmuch inbox of {}", account.email());
+
+        loop {
+            self.sync_local_folder(&backend, account, "INBOX", Some("inbox".to_string())).await?;
+            sleep(Duration::from_secs(60)).await;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::test_utils::setup_test_db;
-    use tauri::test::mock_builder;
+    use crate::utils::test_utils::{setup_test_db, test_context};
     use email::envelope::{Envelope, Envelopes, Address};
     use chrono::Utc;
-    use tauri::Manager;
+    use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+    use crate::utils::test_server::FakeImapServer;
+    use email::flag::{Flag, add::AddFlags};
+    use email::message::r#move::MoveMessages;
+
+    fn engine(pool: &SqlitePool) -> SyncEngine {
+        SyncEngine::new(test_context(pool).0)
+    }
 
     #[tokio::test]
     async fn test_save_envelopes_saves_has_attachments() {
@@ -732,10 +1182,7 @@ mod tests {
 
         let envelopes: Envelopes = vec![envelope].into_iter().collect();
 
-        let app = mock_builder().build(tauri::generate_context!()).unwrap();
-        app.manage(pool.clone());
-
-        SyncEngine::save_envelopes(&app.handle(), account_id, folder_id, envelopes, false)
+        engine(&pool).save_envelopes(account_id, folder_id, envelopes, &HashMap::new(), None)
             .await
             .expect("Failed to save envelopes");
 
@@ -746,4 +1193,330 @@ mod tests {
 
         assert!(has_attachments, "has_attachments should be true");
     }
+
+    #[tokio::test]
+    async fn test_sync_local_folder_tracks_added_and_removed_messages() {
+        use crate::email_backend::accounts::maildir::MaildirAccount;
+
+        let pool = setup_test_db().await;
+        let row: (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type) VALUES (?, ?) RETURNING id")
+            .bind("local@example.com")
+            .bind("maildir")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["cur", "new", "tmp"] {
+            std::fs::create_dir(root.path().join(dir)).unwrap();
+        }
+        let message_path = root.path().join("new").join("1700000000.1.localhost");
+        std::fs::write(
+            &message_path,
+            "From: Sender <sender@example.com>\r\nTo: local@example.com\r\nSubject: Hello\r\nMessage-ID: <m1@example.com>\r\nDate: Tue, 14 Nov 2023 22:13:20 +0000\r\n\r\nBody\r\n",
+        ).unwrap();
+
+        let account = Account::Maildir(MaildirAccount {
+            id: Some(row.0),
+            email: "local@example.com".to_string(),
+            name: None,
+            root_dir: root.path().to_path_buf(),
+            maildirpp: true,
+            sendmail_command: None,
+        });
+        let ctx_builder = AccountContextBuilder::new(&account).unwrap();
+        let context = BackendContextBuilder::build(ctx_builder.clone()).await.unwrap();
+        let backend = ctx_builder.backend(context);
+
+        let engine = engine(&pool);
+        engine.sync_local_folder(&backend, &account, "INBOX", Some("inbox".to_string()))
+            .await
+            .expect("Failed to sync Maildir folder");
+
+        let subjects: Vec<String> = sqlx::query_scalar("SELECT subject FROM emails")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(subjects, vec!["Hello".to_string()]);
+
+        std::fs::remove_file(&message_path).unwrap();
+        engine.sync_local_folder(&backend, &account, "INBOX", Some("inbox".to_string()))
+            .await
+            .expect("Failed to resync Maildir folder");
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+
+    fn message(n: u32) -> Vec<u8> {
+        format!(
+            "From: Sender <sender@example.com>\r\nTo: me@example.com\r\nSubject: Message {}\r\nMessage-ID: <m{}@example.com>\r\nDate: Tue, 14 Nov 2023 22:13:20 +0000\r\n\r\nBody {}\r\n",
+            n, n, n
+        ).into_bytes()
+    }
+
+    async fn setup(server: &FakeImapServer, password: &str) -> (SqlitePool, Account) {
+        let pool = setup_test_db().await;
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let account = Account::ImapSmtp(ImapSmtpAccount {
+            id: Some(account_id),
+            email: "me@example.com".to_string(),
+            name: None,
+            imap_host: "127.0.0.1".to_string(),
+            imap_port: server.port(),
+            imap_username: "me@example.com".to_string(),
+            imap_encryption: "none".to_string(),
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: 0,
+            smtp_username: "me@example.com".to_string(),
+            smtp_encryption: "none".to_string(),
+            smtp_use_imap_credentials: true,
+            sendmail_command: None,
+            password: Some(password.to_string()),
+            smtp_password: None,
+        });
+        (pool, account)
+    }
+
+    async fn connect(account: &Account) -> Result<AccountContext, String> {
+        let ctx_builder = AccountContextBuilder::new(account)?;
+        BackendContextBuilder::build(ctx_builder).await.map_err(|e| e.to_string())
+    }
+
+    async fn sync_inbox(engine: &SyncEngine, context: &AccountContext, account: &Account) {
+        let imap = context.imap.clone().unwrap();
+        let mut client = imap.client().await;
+        let folder_data = client.select_mailbox("INBOX").await.unwrap();
+        let new_mail = engine.sync_folder(&mut *client, account, "INBOX", Some("inbox".to_string()), &folder_data)
+            .await
+            .expect("Failed to sync INBOX");
+        drop(client);
+        engine.process_new_mail(new_mail).await;
+    }
+
+    async fn remote_ids(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("SELECT remote_id FROM emails ORDER BY CAST(remote_id AS INTEGER)")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Polls until the inbox holds `expected`, failing after a few seconds.
+    async fn wait_for_ids(pool: &SqlitePool, expected: &[&str]) {
+        for _ in 0..100 {
+            if remote_ids(pool).await == expected {
+                return;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(remote_ids(pool).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_full_incremental_and_expunge_sync() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        server.add_message("INBOX", &message(1), &["\\Seen"]);
+        server.add_message("INBOX", &message(2), &[]);
+        let (pool, account) = setup(&server, "secret").await;
+        let context = connect(&account).await.unwrap();
+        let engine = engine(&pool);
+
+        sync_inbox(&engine, &context, &account).await;
+        assert_eq!(remote_ids(&pool).await, vec!["1", "2"]);
+        let (uid_next, unread): (i64, i64) = sqlx::query_as("SELECT uid_next, unread_count FROM folders WHERE path = 'INBOX'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((uid_next, unread), (3, 1));
+
+        server.add_message("INBOX", &message(3), &[]);
+        server.expunge("INBOX", 1);
+        sync_inbox(&engine, &context, &account).await;
+        assert_eq!(remote_ids(&pool).await, vec!["2", "3"]);
+        assert!(server.commands().iter().any(|c| c.starts_with("UID FETCH 3:*")));
+    }
+
+    #[tokio::test]
+    async fn test_new_mail_reaches_event_sink() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        server.add_message("INBOX", &message(1), &[]);
+        let (pool, account) = setup(&server, "secret").await;
+        let context = connect(&account).await.unwrap();
+        let (app, events) = test_context(&pool);
+        let engine = SyncEngine::new(app);
+
+        // The first sync of a folder is a backfill and stays quiet
+        sync_inbox(&engine, &context, &account).await;
+        assert!(!events.named("emails-updated").is_empty());
+        assert!(events.notifications().is_empty());
+
+        server.add_message("INBOX", &message(2), &[]);
+        sync_inbox(&engine, &context, &account).await;
+        for _ in 0..100 {
+            if !events.notifications().is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(
+            events.notifications(),
+            vec![("New Email: Message 2".to_string(), "From: Sender".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rules_silence_new_mail() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        server.add_message("INBOX", &message(1), &[]);
+        let (pool, account) = setup(&server, "secret").await;
+        sqlx::query("INSERT INTO rules (name, conditions, actions) VALUES ('Quiet', ?, ?)")
+            .bind(r#"[{"type":"subject","op":"equals","value":"Message 3"}]"#)
+            .bind(r#"[{"type":"skip_notification"}]"#)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let context = connect(&account).await.unwrap();
+        let (app, events) = test_context(&pool);
+        let engine = SyncEngine::new(app);
+        sync_inbox(&engine, &context, &account).await;
+
+        server.add_message("INBOX", &message(2), &[]);
+        server.add_message("INBOX", &message(3), &[]);
+        sync_inbox(&engine, &context, &account).await;
+        for _ in 0..100 {
+            if !events.notifications().is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        // Both messages went through the rules before either notified
+        assert_eq!(
+            events.notifications(),
+            vec![("New Email: Message 2".to_string(), "From: Sender".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_new_mail_notifies_once_per_sender() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        server.add_message("INBOX", &message(1), &[]);
+        let (pool, account) = setup(&server, "secret").await;
+        let context = connect(&account).await.unwrap();
+        let (app, events) = test_context(&pool);
+        let engine = SyncEngine::new(app);
+        sync_inbox(&engine, &context, &account).await;
+
+        for n in 2..=4 {
+            server.add_message("INBOX", &message(n), &[]);
+        }
+        sync_inbox(&engine, &context, &account).await;
+        for _ in 0..100 {
+            if !events.notifications().is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(
+            events.notifications(),
+            vec![("3 new emails from Sender".to_string(), "Message 2, Message 3, Message 4".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_uid_validity_change_refetches_folder() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        for n in 1..=3 {
+            server.add_message("INBOX", &message(n), &[]);
+        }
+        server.expunge("INBOX", 1);
+        let (pool, account) = setup(&server, "secret").await;
+        let context = connect(&account).await.unwrap();
+        let (app, events) = test_context(&pool);
+        let engine = SyncEngine::new(app);
+
+        sync_inbox(&engine, &context, &account).await;
+        assert_eq!(remote_ids(&pool).await, vec!["2", "3"]);
+
+        server.reset_uid_validity("INBOX");
+        sync_inbox(&engine, &context, &account).await;
+        assert_eq!(remote_ids(&pool).await, vec!["1", "2"]);
+        // The refilled folder is old mail under new UIDs
+        assert!(events.notifications().is_empty());
+
+        let subjects: Vec<String> = sqlx::query_scalar("SELECT subject FROM emails ORDER BY CAST(remote_id AS INTEGER)")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(subjects, vec!["Message 2", "Message 3"]);
+        let uid_validity: i64 = sqlx::query_scalar("SELECT uid_validity FROM folders WHERE path = 'INBOX'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(uid_validity, 2);
+    }
+
+    #[tokio::test]
+    async fn test_idle_pushes_trigger_sync() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        server.add_message("INBOX", &message(1), &[]);
+        let (pool, account) = setup(&server, "secret").await;
+        let context = connect(&account).await.unwrap();
+
+        let engine = engine(&pool);
+        engine.contexts.lock().await.insert(account.id().unwrap(), context);
+
+        let idle = tokio::spawn({
+            let engine = engine.clone();
+            let account = account.clone();
+            async move { engine.run_idle_loop(&account).await }
+        });
+
+        wait_for_ids(&pool, &["1"]).await;
+        server.add_message("INBOX", &message(2), &[]);
+        wait_for_ids(&pool, &["1", "2"]).await;
+        server.expunge("INBOX", 1);
+        wait_for_ids(&pool, &["2"]).await;
+
+        assert!(server.commands().iter().filter(|c| *c == "IDLE").count() >= 2);
+        idle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_is_rejected() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        let (_pool, account) = setup(&server, "wrong").await;
+
+        assert!(connect(&account).await.is_err());
+        assert!(server.failed_logins() > 0);
+
+        server.set_password("wrong");
+        assert!(connect(&account).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_flags_and_moves_reach_server() {
+        let server = FakeImapServer::start("me@example.com", "secret").await;
+        server.add_message("INBOX", &message(1), &[]);
+        server.add_message("INBOX", &message(2), &[]);
+        let (_pool, account) = setup(&server, "secret").await;
+        let context = connect(&account).await.unwrap();
+        let backend = AccountContextBuilder::new(&account).unwrap().backend(context);
+
+        backend.add_flag("INBOX", &Id::single("1"), Flag::Seen).await.unwrap();
+        assert_eq!(server.messages("INBOX")[0].flags, vec!["\\Seen"]);
+
+        backend.move_messages("INBOX", "Trash", &Id::single("2")).await.unwrap();
+        assert_eq!(server.messages("INBOX").len(), 1);
+        let trash = server.messages("Trash");
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].raw, message(2));
+    }
 }
@@ -1,101 +1,118 @@
 use std::collections::HashMap;
 use std::time::Duration;
-use tauri::{Manager, Emitter};
+use crate::context::AppContext;
 use crate::email_backend::emails::events::EmailEvent;
 use log::{info, error};
 use sqlx::SqlitePool;
 use tokio::time::sleep;
 
+use crate::email_backend::rules::types::parse_headers;
+use crate::email_backend::subscriptions::unsubscribe::save_list_headers;
+use crate::email_backend::calendar::invites::save_invites;
 use crate::email_backend::sync::SyncEngine;
 use email::envelope::Id;
 use email::message::get::GetMessages;
 
-pub struct SyncWorker<R: tauri::Runtime> {
-    app_handle: tauri::AppHandle<R>,
-    pool: SqlitePool,
+pub struct SyncWorker {
+    engine: SyncEngine,
 }
 
-impl<R: tauri::Runtime> SyncWorker<R> {
-    pub fn new(app_handle: tauri::AppHandle<R>) -> Self {
-        let pool = app_handle.state::<SqlitePool>().inner().clone();
-        Self { app_handle, pool }
+impl SyncWorker {
+    pub fn new(engine: SyncEngine) -> Self {
+        Self { engine }
     }
 
     pub async fn start(&self) {
         info!("Starting Sync Worker...");
 
-        let app_handle = self.app_handle.clone();
+        let engine = self.engine.clone();
         tokio::spawn(async move {
             loop {
                 // Indexing
-                if let Err(e) = Self::index_pending_emails(&app_handle).await {
+                if let Err(e) = Self::index_pending_emails(&engine).await {
                     error!("Error during background indexing: {}", e);
                 }
                 sleep(Duration::from_secs(10)).await;
 
                 // Thread Resolution
-                let app_handle_threading = app_handle.clone();
+                let pool = engine.app().pool().clone();
                 tokio::spawn(async move {
-                    let pool = app_handle_threading.state::<SqlitePool>();
                     let backlog_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE thread_id = message_id AND normalized_subject IS NOT NULL AND normalized_subject != ''")
-                        .fetch_one(&*pool)
+                        .fetch_one(&pool)
                         .await
                         .unwrap_or(0);
 
                     let sleep_time = if backlog_count > 1000 { 5 } else { 30 };
                     let batch_size = if backlog_count > 1000 { 2000 } else { 100 };
 
-                    if let Err(e) = Self::resolve_threads(&app_handle_threading, batch_size).await {
+                    if let Err(e) = Self::resolve_threads(&pool, batch_size).await {
                         error!("Error during background threading: {}", e);
                     }
                     sleep(Duration::from_secs(sleep_time)).await;
                 });
 
                 // Proactive Enrichment
-                let app_handle_enrichment = app_handle.clone();
+                let app = engine.app().clone();
                 tokio::spawn(async move {
-                    if let Err(e) = crate::email_backend::enrichment::commands::proactive_enrichment(&app_handle_enrichment).await {
+                    if let Err(e) = crate::email_backend::enrichment::commands::proactive_enrichment(&app).await {
                         error!("Error during background enrichment: {}", e);
                     }
                     sleep(Duration::from_secs(120)).await;
                 });
 
                 // Proactive Summarization
-                let app_handle_summarization = app_handle.clone();
+                let app = engine.app().clone();
                 tokio::spawn(async move {
-                    if let Err(e) = Self::proactive_summarization(&app_handle_summarization).await {
+                    if let Err(e) = Self::proactive_summarization(&app).await {
                         error!("Error during background summarization: {}", e);
                     }
                     sleep(Duration::from_secs(120)).await;
                 });
+            }
+        });
 
-                // Contact Sync
-                let app_handle_contacts = app_handle.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = crate::email_backend::enrichment::commands::sync_contacts_internal(&app_handle_contacts).await {
-                        error!("Error during background contact sync: {}", e);
-                    }
-                    sleep(Duration::from_secs(1800)).await; // Sync every 30 minutes
-                });
+        // The daily digest only needs to notice that its hour has come
+        let engine = self.engine.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = crate::email_backend::subscriptions::digest::run_daily_digest(&engine).await {
+                    error!("Error while building the daily digest: {}", e);
+                }
+                sleep(Duration::from_secs(300)).await;
+            }
+        });
+
+        // Snoozes and reminders run one check at a time, so a slow server cannot fire a timer twice
+        let engine = self.engine.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::email_backend::timers::run::restore_snoozes(engine.app().pool()).await {
+                error!("Error while restoring snoozes: {}", e);
+            }
+            loop {
+                if let Err(e) = crate::email_backend::timers::run::run_due_timers(&engine).await {
+                    error!("Error while running timers: {}", e);
+                }
+                sleep(Duration::from_secs(30)).await;
+            }
+        });
+
+        // Contact Sync talks to Google and CardDAV servers, so it keeps its own slower schedule
+        let app = self.engine.app().clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = crate::email_backend::enrichment::commands::sync_contacts_internal(&app).await {
+                    error!("Error during background contact sync: {}", e);
+                }
+                sleep(Duration::from_secs(1800)).await; // Sync every 30 minutes
             }
         });
     }
 
-    async fn proactive_summarization(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
+    async fn proactive_summarization(app: &AppContext) -> Result<(), String> {
+        let pool = app.pool();
 
         // Check if enabled
-        let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-        
-        let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-
-        if ai_enabled.0 != "true" || ai_summarization_enabled.0 != "true" {
+        if !app.settings().ai_summaries_enabled().await {
             return Ok(());
         }
 
@@ -117,7 +134,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
              ORDER BY e.date DESC
              LIMIT 10" // Process in small batches
         )
-        .fetch_all(&*pool)
+        .fetch_all(pool)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -130,19 +147,19 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         for (id, body_text) in pending_summaries {
             let sender_address: Option<String> = sqlx::query_scalar("SELECT sender_address FROM emails WHERE id = ?")
                 .bind(id)
-                .fetch_one(&*pool)
+                .fetch_one(pool)
                 .await
                 .ok();
 
-            match crate::email_backend::llm::summarization::summarize_email_with_ai(app_handle, id, &body_text, false).await {
+            match crate::email_backend::llm::summarization::summarize_email_with_ai(app, id, &body_text, false).await {
                 Ok(summary) => {
                     let _ = sqlx::query("UPDATE emails SET summary = ? WHERE id = ?")
                         .bind(&summary)
                         .bind(id)
-                        .execute(&*pool)
+                        .execute(pool)
                         .await;
                     
-                    let _ = app_handle.emit("emails-updated", EmailEvent::Updated {
+                    app.emit("emails-updated", EmailEvent::Updated {
                         id,
                         address: sender_address,
                         flags: None,
@@ -161,21 +178,21 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         Ok(())
     }
 
-    pub async fn summarize_specific_email(app_handle: &tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
+    pub async fn summarize_specific_email(app: &AppContext, email_id: i64) -> Result<(), String> {
+        let pool = app.pool();
         let body_text: Option<String> = sqlx::query_scalar("SELECT body_text FROM emails WHERE id = ?")
             .bind(email_id)
-            .fetch_one(&*pool)
+            .fetch_one(pool)
             .await
             .map_err(|e| e.to_string())?;
 
         if let Some(text) = body_text {
-            match crate::email_backend::llm::summarization::summarize_email_with_ai(app_handle, email_id, &text, false).await {
+            match crate::email_backend::llm::summarization::summarize_email_with_ai(app, email_id, &text, false).await {
                 Ok(summary) => {
                     let _ = sqlx::query("UPDATE emails SET summary = ? WHERE id = ?")
                         .bind(summary)
                         .bind(email_id)
-                        .execute(&*pool)
+                        .execute(pool)
                         .await;
                     return Ok(());
                 }
@@ -185,14 +202,9 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         Err("No body text found for summarization".to_string())
     }
 
-    async fn index_pending_emails(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
-
-        let sync_months_setting: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'syncMonths'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("3".to_string(),));
-        let sync_months = sync_months_setting.0.parse::<i32>().unwrap_or(3);
+    async fn index_pending_emails(engine: &SyncEngine) -> Result<(), String> {
+        let pool = engine.app().pool();
+        let sync_months = engine.app().settings().number("syncMonths", 3).await;
 
         let mut query = "SELECT e.id, e.account_id, e.remote_id, f.path
              FROM emails e
@@ -206,7 +218,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         query.push_str(" ORDER BY e.date DESC LIMIT 20");
 
         let pending_emails: Vec<(i64, i64, String, String)> = sqlx::query_as(&query)
-            .fetch_all(&*pool)
+            .fetch_all(pool)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -222,7 +234,6 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         }
 
         for (account_id, emails) in by_account {
-            let engine = app_handle.state::<SyncEngine<R>>();
             let backend = match engine.get_backend(account_id).await {
                 Ok(b) => b,
                 Err(e) => {
@@ -237,7 +248,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                 match backend.get_messages(&folder_path, &uids).await {
                     Ok(messages) => {
                         for message in messages.to_vec() {
-                            Self::save_message_parts(app_handle, email_id, message).await?;
+                            Self::save_message_parts(pool, email_id, message).await?;
                         }
                     }
                     Err(e) => {
@@ -251,8 +262,8 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         Ok(())
     }
 
-    pub async fn index_specific_email(app_handle: &tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
+    pub async fn index_specific_email(engine: &SyncEngine, email_id: i64) -> Result<(), String> {
+        let pool = engine.app().pool();
         let email_info: Option<(i64, String, String)> = sqlx::query_as(
             "SELECT e.account_id, e.remote_id, f.path 
              FROM emails e 
@@ -260,19 +271,18 @@ impl<R: tauri::Runtime> SyncWorker<R> {
              WHERE e.id = ?"
         )
         .bind(email_id)
-        .fetch_optional(&*pool)
+        .fetch_optional(pool)
         .await
         .map_err(|e| e.to_string())?;
 
         if let Some((account_id, remote_id, folder_path)) = email_info {
-            let engine = app_handle.state::<SyncEngine<R>>();
             let backend = engine.get_backend(account_id).await?;
             let uids = Id::single(remote_id.clone());
             
             match backend.get_messages(&folder_path, &uids).await {
                 Ok(messages) => {
                     for message in messages.to_vec() {
-                        Self::save_message_parts(app_handle, email_id, message).await?;
+                        Self::save_message_parts(pool, email_id, message).await?;
                     }
                 }
                 Err(e) => return Err(e.to_string()),
@@ -281,9 +291,8 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         Ok(())
     }
 
-    async fn save_message_parts(app_handle: &tauri::AppHandle<R>, email_id: i64, message: &email::message::Message<'_>) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
-        
+    async fn save_message_parts(pool: &SqlitePool, email_id: i64, message: &email::message::Message<'_>) -> Result<(), String> {
+
         // Save attachments if any
         if let Ok(attachments) = message.attachments() {
             for att in attachments {
@@ -295,7 +304,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                 .bind(&att.filename)
                 .bind(&att.mime)
                 .bind(att.body.len() as i64)
-                .execute(&*pool)
+                .execute(pool)
                 .await
                 .map_err(|e| error!("Failed to save attachment for email {}: {}", email_id, e));
             }
@@ -315,23 +324,74 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                 .bind(body_html)
                 .bind(snippet)
                 .bind(email_id)
-                .execute(&*pool)
+                .execute(pool)
                 .await
                 .map_err(|e| e.to_string())?;
         }
+
+        if let Ok(raw) = message.raw() {
+            save_list_headers(pool, email_id, &parse_headers(raw)).await?;
+            save_invites(pool, email_id, raw).await?;
+        }
         Ok(())
     }
 
-    async fn resolve_threads(app_handle: &tauri::AppHandle<R>, limit: i64) -> Result<(), String> {
-        let pool = app_handle.state::<SqlitePool>();
-        
+    /// Links one newly saved reply to its parent's thread right away, for callers that cannot
+    /// wait for the background pass. Returns the thread it ends up in.
+    pub(crate) async fn link_reply(pool: &SqlitePool, email_id: i64) -> Result<Option<String>, String> {
+        let row: Option<(Option<String>, Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT thread_id, message_id, in_reply_to, references_header FROM emails WHERE id = ?"
+        )
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some((thread_id, message_id, in_reply_to, refs)) = row else {
+            return Ok(None);
+        };
+        if thread_id != message_id {
+            return Ok(thread_id);
+        }
+
+        // The direct parent first, then the references from the newest back
+        let mut parents: Vec<&str> = in_reply_to.as_deref().into_iter().collect();
+        if let Some(refs) = refs.as_deref() {
+            parents.extend(refs.split(|c| c == ' ' || c == ',').map(|s| s.trim()).filter(|s| !s.is_empty()).rev());
+        }
+        for parent in parents {
+            if Some(parent) == message_id.as_deref() {
+                continue;
+            }
+            let parent_thread: Option<(String,)> = sqlx::query_as(
+                "SELECT thread_id FROM emails WHERE message_id = ? AND thread_id IS NOT NULL LIMIT 1"
+            )
+            .bind(parent)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if let Some((parent_thread_id,)) = parent_thread {
+                sqlx::query("UPDATE emails SET thread_id = ? WHERE id = ?")
+                    .bind(&parent_thread_id)
+                    .bind(email_id)
+                    .execute(pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                return Ok(Some(parent_thread_id));
+            }
+        }
+        Ok(thread_id)
+    }
+
+    async fn resolve_threads(pool: &SqlitePool, limit: i64) -> Result<(), String> {
+
         let unlinked_replies: Vec<(i64, String, String)> = sqlx::query_as(
             "SELECT id, message_id, in_reply_to FROM emails 
              WHERE in_reply_to IS NOT NULL AND thread_id = message_id 
              LIMIT ?"
         )
         .bind(limit)
-        .fetch_all(&*pool)
+        .fetch_all(pool)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -340,7 +400,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                 "SELECT thread_id FROM emails WHERE message_id = ? LIMIT 1"
             )
             .bind(&in_reply_to)
-            .fetch_optional(&*pool)
+            .fetch_optional(pool)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -348,7 +408,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                 let _ = sqlx::query("UPDATE emails SET thread_id = ? WHERE id = ?")
                     .bind(parent_thread_id)
                     .bind(id)
-                    .execute(&*pool)
+                    .execute(pool)
                     .await
                     .map_err(|e| e.to_string());
             }
@@ -360,7 +420,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
              LIMIT ?"
         )
         .bind(limit)
-        .fetch_all(&*pool)
+        .fetch_all(pool)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -371,7 +431,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                     "SELECT thread_id FROM emails WHERE message_id = ? LIMIT 1"
                 )
                 .bind(ref_id)
-                .fetch_optional(&*pool)
+                .fetch_optional(pool)
                 .await
                 .map_err(|e| e.to_string())?;
 
@@ -379,7 +439,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                     let _ = sqlx::query("UPDATE emails SET thread_id = ? WHERE id = ?")
                         .bind(parent_thread_id)
                         .bind(id)
-                        .execute(&*pool)
+                        .execute(pool)
                         .await
                         .map_err(|e| e.to_string());
                     break;
@@ -405,7 +465,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
                AND id IN (SELECT id FROM emails WHERE thread_id = message_id LIMIT ?)"
         )
         .bind(limit)
-        .execute(&*pool)
+        .execute(pool)
         .await;
         
         Ok(())
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+use crate::email_backend::sync::SyncEngine;
+use crate::email_backend::timers::run::{set_follow_up, snooze, thread_of, unsnooze};
+
+/// Parses a time from the frontend, which has to lie ahead.
+fn future_time(value: &str) -> Result<DateTime<Utc>, String> {
+    let time = DateTime::parse_from_rfc3339(value)
+        .map_err(|e| format!("Invalid time {}: {}", value, e))?
+        .with_timezone(&Utc);
+    if time <= Utc::now() {
+        return Err("The time has to be in the future".to_string());
+    }
+    Ok(time)
+}
+
+/// Hides the messages' threads until `until`, an RFC 3339 time.
+#[tauri::command]
+pub async fn snooze_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, until: String) -> Result<(), String> {
+    let until = future_time(&until)?;
+    let engine = app_handle.state::<SyncEngine>();
+    for email_id in email_ids.into_iter().filter(|&id| id >= 0) {
+        snooze(&engine, email_id, until).await?;
+    }
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+/// Ends the snooze of the messages' threads now, without marking them unread.
+#[tauri::command]
+pub async fn unsnooze_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
+    let engine = app_handle.state::<SyncEngine>();
+    for email_id in email_ids.into_iter().filter(|&id| id >= 0) {
+        let (account_id, thread_id) = thread_of(engine.app().pool(), email_id).await?;
+        unsnooze(&engine, account_id, &thread_id, false).await?;
+    }
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+/// Reminds at `by` unless someone has replied to the sent message by then.
+#[tauri::command]
+pub async fn remind_if_no_reply<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, by: String) -> Result<(), String> {
+    let by = future_time(&by)?;
+    set_follow_up(&app_handle.state::<SqlitePool>(), email_id, by).await
+}
+
+#[tauri::command]
+pub async fn cancel_reminder<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let (account_id, thread_id) = thread_of(&pool, email_id).await?;
+    sqlx::query("DELETE FROM timers WHERE kind = 'follow_up' AND account_id = ? AND thread_id = ?")
+        .bind(account_id)
+        .bind(thread_id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
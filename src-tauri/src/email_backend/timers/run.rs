@@ -0,0 +1,439 @@
+use chrono::{DateTime, TimeZone, Utc};
+use email::flag::Flag;
+use log::{info, warn};
+use serde_json::json;
+use sqlx::SqlitePool;
+use crate::email_backend::emails::commands::{move_email, update_email_flags};
+use crate::email_backend::sync::SyncEngine;
+
+/// A snoozed message carries this keyword followed by the Unix time it comes back, so the snooze
+/// lives on the server and survives a reinstall.
+pub const SNOOZE_KEYWORD_PREFIX: &str = "$SnoozedUntil-";
+
+/// The thread a message belongs to, as timers key it. Messages without ids are their own thread.
+const THREAD_KEY: &str = "COALESCE(thread_id, message_id, 'email-' || id)";
+
+pub fn snooze_keyword(until: DateTime<Utc>) -> String {
+    format!("{}{}", SNOOZE_KEYWORD_PREFIX, until.timestamp())
+}
+
+pub fn parse_snooze_keyword(flag: &str) -> Option<DateTime<Utc>> {
+    let prefix = flag.get(..SNOOZE_KEYWORD_PREFIX.len())?;
+    if !prefix.eq_ignore_ascii_case(SNOOZE_KEYWORD_PREFIX) {
+        return None;
+    }
+    let seconds = flag[SNOOZE_KEYWORD_PREFIX.len()..].parse().ok()?;
+    Utc.timestamp_opt(seconds, 0).single()
+}
+
+/// Times are stored like message dates, so they compare as strings.
+pub fn utc_string(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// The account and thread of a message.
+pub async fn thread_of(pool: &SqlitePool, email_id: i64) -> Result<(i64, String), String> {
+    sqlx::query_as(&format!("SELECT account_id, {} FROM emails WHERE id = ?", THREAD_KEY))
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Email {} not found", email_id))
+}
+
+/// The thread's messages outside the bin and spam with their flags, those in the inbox first,
+/// then newest first.
+async fn thread_emails(pool: &SqlitePool, account_id: i64, thread_id: &str) -> Result<Vec<(i64, String)>, String> {
+    sqlx::query_as(&format!(
+        "SELECT e.id, e.flags FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE e.id IN (SELECT id FROM emails WHERE account_id = ? AND {} = ?)
+           AND COALESCE(f.role, '') NOT IN ('trash', 'spam')
+         ORDER BY CASE WHEN f.role = 'inbox' THEN 0 ELSE 1 END, e.date DESC, e.id DESC",
+        THREAD_KEY
+    ))
+    .bind(account_id)
+    .bind(thread_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn snooze_flags(flags: &str) -> Vec<Flag> {
+    serde_json::from_str::<Vec<String>>(flags)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| parse_snooze_keyword(f).is_some())
+        .map(Flag::custom)
+        .collect()
+}
+
+/// Hides a message's thread until `until`. Snoozing again moves the time.
+pub async fn snooze(engine: &SyncEngine, email_id: i64, until: DateTime<Utc>) -> Result<(), String> {
+    let pool = engine.app().pool();
+    let (account_id, thread_id) = thread_of(pool, email_id).await?;
+    let keyword = Flag::custom(snooze_keyword(until));
+
+    for (id, flags) in thread_emails(pool, account_id, &thread_id).await? {
+        let earlier = snooze_flags(&flags);
+        if !earlier.is_empty() {
+            update_email_flags(engine, id, &earlier, false).await?;
+        }
+        update_email_flags(engine, id, std::slice::from_ref(&keyword), true).await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO timers (kind, account_id, thread_id, due_at) VALUES ('snooze', ?, ?, ?)
+         ON CONFLICT (kind, account_id, thread_id) DO UPDATE SET due_at = excluded.due_at"
+    )
+    .bind(account_id)
+    .bind(&thread_id)
+    .bind(utc_string(until))
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Ends a snooze. With `resurface`, the thread's newest message in the inbox comes back unread at
+/// the top, moved back to the inbox if the thread was archived meanwhile; its id is returned.
+pub async fn unsnooze(engine: &SyncEngine, account_id: i64, thread_id: &str, resurface: bool) -> Result<Option<i64>, String> {
+    let pool = engine.app().pool();
+    let emails = thread_emails(pool, account_id, thread_id).await?;
+
+    for (id, flags) in &emails {
+        let keywords = snooze_flags(flags);
+        if !keywords.is_empty() {
+            update_email_flags(engine, *id, &keywords, false).await?;
+        }
+    }
+
+    sqlx::query("DELETE FROM timers WHERE kind = 'snooze' AND account_id = ? AND thread_id = ?")
+        .bind(account_id)
+        .bind(thread_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let Some(&(latest_id, _)) = emails.first().filter(|_| resurface) else {
+        return Ok(None);
+    };
+    update_email_flags(engine, latest_id, &[Flag::Seen], false).await?;
+
+    // Local folders cannot move to the server inbox, they come back where they are
+    let inbox_id: Option<i64> = sqlx::query_scalar(
+        "SELECT i.id FROM emails e
+         JOIN folders f ON f.id = e.folder_id
+         JOIN folders i ON i.account_id = e.account_id AND i.role = 'inbox' AND i.is_local = 0
+         WHERE e.id = ? AND f.is_local = 0 AND COALESCE(f.role, '') != 'inbox'
+         LIMIT 1"
+    )
+    .bind(latest_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if let Some(inbox_id) = inbox_id {
+        if let Err(e) = move_email(engine, latest_id, inbox_id).await {
+            warn!("Could not move snoozed email {} back to the inbox: {}", latest_id, e);
+        }
+    }
+
+    sqlx::query("UPDATE emails SET resurfaced_at = ? WHERE id = ?")
+        .bind(utc_string(Utc::now()))
+        .bind(latest_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(latest_id))
+}
+
+/// Asks to be reminded at `by` if nobody has answered a sent message by then.
+pub async fn set_follow_up(pool: &SqlitePool, email_id: i64, by: DateTime<Utc>) -> Result<(), String> {
+    let message_id: Option<String> = sqlx::query_scalar("SELECT message_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten();
+    let message_id = message_id.ok_or("Replies can only be tracked for messages with a Message-ID")?;
+    let (account_id, thread_id) = thread_of(pool, email_id).await?;
+
+    sqlx::query(
+        "INSERT INTO timers (kind, account_id, thread_id, message_id, due_at) VALUES ('follow_up', ?, ?, ?, ?)
+         ON CONFLICT (kind, account_id, thread_id) DO UPDATE SET message_id = excluded.message_id, due_at = excluded.due_at"
+    )
+    .bind(account_id)
+    .bind(&thread_id)
+    .bind(&message_id)
+    .bind(utc_string(by))
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Whether someone other than the account answered the message: a reply to it, or a later
+/// message in its thread.
+pub async fn has_reply(pool: &SqlitePool, account_id: i64, thread_id: &str, message_id: &str) -> Result<bool, String> {
+    let replies: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM emails e JOIN accounts a ON e.account_id = a.id
+         WHERE e.account_id = ?
+           AND (e.in_reply_to = ? OR e.thread_id = ?)
+           AND e.message_id IS NOT ?
+           AND LOWER(e.sender_address) != LOWER(a.email)
+           AND e.date > (SELECT MIN(date) FROM emails WHERE account_id = ? AND message_id = ?)"
+    )
+    .bind(account_id)
+    .bind(message_id)
+    .bind(thread_id)
+    .bind(message_id)
+    .bind(account_id)
+    .bind(message_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(replies > 0)
+}
+
+/// Recreates snooze timers from the keywords of synced messages, e.g. after a reinstall. This
+/// scans every message, so it runs at startup and after syncs rather than with each timer check.
+pub async fn restore_snoozes(pool: &SqlitePool) -> Result<(), String> {
+    let snoozed: Vec<(i64, String, String)> = sqlx::query_as(&format!(
+        "SELECT account_id, {}, flags FROM emails WHERE flags LIKE '%$SnoozedUntil-%'",
+        THREAD_KEY
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (account_id, thread_id, flags) in snoozed {
+        let until = serde_json::from_str::<Vec<String>>(&flags)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|f| parse_snooze_keyword(f))
+            .max();
+        let Some(until) = until else {
+            continue;
+        };
+
+        sqlx::query("INSERT OR IGNORE INTO timers (kind, account_id, thread_id, due_at) VALUES ('snooze', ?, ?, ?)")
+            .bind(account_id)
+            .bind(&thread_id)
+            .bind(utc_string(until))
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+async fn notify(engine: &SyncEngine, title: &str, email_id: i64) {
+    let app = engine.app();
+    if !app.can_notify() || !app.settings().is_enabled("notificationsEnabled", true).await {
+        return;
+    }
+    let subject: Option<String> = sqlx::query_scalar("SELECT subject FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_optional(app.pool())
+        .await
+        .ok()
+        .flatten()
+        .flatten();
+    app.notify(title, subject.as_deref().unwrap_or("(No Subject)"));
+}
+
+/// Brings back snoozed threads and fires reminders whose time has come.
+pub async fn run_due_timers(engine: &SyncEngine) -> Result<(), String> {
+    let app = engine.app();
+    let pool = app.pool();
+
+    let due: Vec<(i64, String, i64, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, kind, account_id, thread_id, message_id FROM timers WHERE due_at <= ? ORDER BY due_at"
+    )
+    .bind(utc_string(Utc::now()))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (timer_id, kind, account_id, thread_id, message_id) in due {
+        // Claimed before acting, so a run that overlaps this one cannot fire it twice. A snooze
+        // that fails to come back keeps its keyword, which brings the timer back.
+        let claimed: Option<i64> = sqlx::query_scalar("DELETE FROM timers WHERE id = ? RETURNING id")
+            .bind(timer_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        if claimed.is_none() {
+            continue;
+        }
+
+        match kind.as_str() {
+            "snooze" => match unsnooze(engine, account_id, &thread_id, true).await {
+                Ok(Some(email_id)) => {
+                    info!("Snoozed email {} is back", email_id);
+                    app.emit("emails-updated", ());
+                    notify(engine, "Snoozed email is back", email_id).await;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to bring back snoozed thread {}: {}", thread_id, e),
+            },
+            "follow_up" => {
+                let message_id = message_id.unwrap_or_default();
+                if !has_reply(pool, account_id, &thread_id, &message_id).await? {
+                    let email_id: Option<i64> = sqlx::query_scalar("SELECT id FROM emails WHERE account_id = ? AND message_id = ? ORDER BY id LIMIT 1")
+                        .bind(account_id)
+                        .bind(&message_id)
+                        .fetch_optional(pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    if let Some(email_id) = email_id {
+                        app.emit("follow-up-due", json!({ "email_id": email_id }));
+                        notify(engine, "No reply yet", email_id).await;
+                    }
+                }
+            }
+            _ => warn!("Dropping timer {} of unknown kind {}", timer_id, kind),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{setup_test_db, test_context};
+
+    async fn insert_email(pool: &SqlitePool, folder_id: i64, message_id: &str, in_reply_to: Option<&str>, sender: &str, date: &str, flags: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, in_reply_to, subject, sender_address, date, flags)
+             SELECT account_id, id, ?, ?, 'thread-1', ?, 'Plans', ?, ?, ? FROM folders WHERE id = ? RETURNING id"
+        )
+        .bind(message_id)
+        .bind(message_id)
+        .bind(in_reply_to)
+        .bind(sender)
+        .bind(date)
+        .bind(flags)
+        .bind(folder_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn setup(pool: &SqlitePool) -> (i64, i64) {
+        let account_id: i64 = sqlx::query_scalar("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap') RETURNING id")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let inbox_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(account_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let sent_id: i64 = sqlx::query_scalar("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'Sent', 'Sent', 'sent') RETURNING id")
+            .bind(account_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (inbox_id, sent_id)
+    }
+
+    #[tokio::test]
+    async fn test_snooze_hides_thread_and_brings_it_back_unread() {
+        let pool = setup_test_db().await;
+        let (inbox_id, sent_id) = setup(&pool).await;
+        let first = insert_email(&pool, inbox_id, "m1", None, "ann@example.com", "2024-01-01T10:00:00Z", r#"["seen"]"#).await;
+        let reply = insert_email(&pool, sent_id, "m2", Some("m1"), "me@example.com", "2024-01-01T11:00:00Z", r#"["seen"]"#).await;
+
+        let (context, events) = test_context(&pool);
+        let engine = SyncEngine::new(context);
+        let until = Utc::now() + chrono::Duration::hours(2);
+        snooze(&engine, first, until).await.unwrap();
+
+        let keyword = snooze_keyword(until);
+        for id in [first, reply] {
+            let flags: String = sqlx::query_scalar("SELECT flags FROM emails WHERE id = ?").bind(id).fetch_one(&pool).await.unwrap();
+            assert!(flags.contains(&keyword));
+        }
+
+        // A reinstall keeps the keywords on the server but loses the timers
+        sqlx::query("DELETE FROM timers").execute(&pool).await.unwrap();
+        restore_snoozes(&pool).await.unwrap();
+        run_due_timers(&engine).await.unwrap();
+        let due_at: String = sqlx::query_scalar("SELECT due_at FROM timers WHERE kind = 'snooze'").fetch_one(&pool).await.unwrap();
+        assert_eq!(due_at, utc_string(until));
+        assert!(events.notifications().is_empty());
+
+        sqlx::query("UPDATE timers SET due_at = '2000-01-01T00:00:00Z'").execute(&pool).await.unwrap();
+        sqlx::query("UPDATE folders SET unread_count = 0").execute(&pool).await.unwrap();
+        run_due_timers(&engine).await.unwrap();
+
+        let rows: Vec<(i64, String, Option<String>)> = sqlx::query_as("SELECT id, flags, resurfaced_at FROM emails ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        // The inbox message comes back unread and on top, the sent reply just loses the keyword
+        assert_eq!(rows[0].1, "[]");
+        assert!(rows[0].2.is_some());
+        assert_eq!((rows[1].1.as_str(), rows[1].2.as_deref()), (r#"["seen"]"#, None));
+
+        let timers: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timers").fetch_one(&pool).await.unwrap();
+        assert_eq!(timers, 0);
+        assert_eq!(events.notifications(), vec![("Snoozed email is back".to_string(), "Plans".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_thread_archived_while_snoozed_returns_to_the_inbox() {
+        let pool = setup_test_db().await;
+        let (inbox_id, _) = setup(&pool).await;
+        let archive_id: i64 = sqlx::query_scalar(
+            "INSERT INTO folders (account_id, name, path, role) SELECT account_id, 'Archive', 'Archive', 'archive' FROM folders WHERE id = ? RETURNING id"
+        )
+        .bind(inbox_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let email = insert_email(&pool, archive_id, "m1", None, "ann@example.com", "2024-01-01T10:00:00Z", r#"["seen"]"#).await;
+
+        let engine = SyncEngine::new(test_context(&pool).0);
+        let (account_id, thread_id) = thread_of(&pool, email).await.unwrap();
+        assert_eq!(unsnooze(&engine, account_id, &thread_id, true).await.unwrap(), Some(email));
+
+        let (folder_id, flags): (i64, String) = sqlx::query_as("SELECT folder_id, flags FROM emails WHERE id = ?")
+            .bind(email)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((folder_id, flags.as_str()), (inbox_id, "[]"));
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_only_reminds_without_a_reply() {
+        let pool = setup_test_db().await;
+        let (inbox_id, sent_id) = setup(&pool).await;
+        let sent = insert_email(&pool, sent_id, "s1", None, "me@example.com", "2024-01-02T09:00:00Z", r#"["seen"]"#).await;
+
+        let (context, events) = test_context(&pool);
+        let engine = SyncEngine::new(context);
+        set_follow_up(&pool, sent, Utc::now() + chrono::Duration::days(3)).await.unwrap();
+        sqlx::query("UPDATE timers SET due_at = '2000-01-01T00:00:00Z'").execute(&pool).await.unwrap();
+        // Overlapping runs fire the reminder once
+        let (first, second) = tokio::join!(run_due_timers(&engine), run_due_timers(&engine));
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(events.named("follow-up-due"), vec![json!({ "email_id": sent })]);
+
+        // Once someone answers, the reminder stays quiet
+        insert_email(&pool, inbox_id, "r1", Some("s1"), "bob@example.com", "2024-01-02T12:00:00Z", "[]").await;
+        set_follow_up(&pool, sent, Utc::now() + chrono::Duration::days(3)).await.unwrap();
+        sqlx::query("UPDATE timers SET due_at = '2000-01-01T00:00:00Z'").execute(&pool).await.unwrap();
+        run_due_timers(&engine).await.unwrap();
+        assert_eq!(events.named("follow-up-due").len(), 1);
+        assert_eq!(events.notifications().len(), 1);
+    }
+}
@@ -1,14 +1,28 @@
-use crate::email_backend::accounts::commands::{login_with_google, login_with_microsoft, add_imap_smtp_account, get_accounts, remove_account, verify_imap_smtp_credentials};
-use crate::email_backend::emails::commands::{get_emails, get_folders, refresh_folder, get_unified_counts, get_email_content, regenerate_summary, get_attachments, get_attachment_data, save_attachment_to_path, open_attachment, mark_as_read, move_to_trash, archive_emails, move_to_inbox, get_email_by_id, get_thread_emails, send_email, save_draft, get_drafts, delete_draft, get_draft_by_id, search_emails};
+use crate::email_backend::accounts::commands::{login_with_google, login_with_microsoft, login_with_oauth2, get_oauth2_providers, add_imap_smtp_account, add_maildir_account, add_notmuch_account, discover_account_settings, get_accounts, remove_account, reorder_accounts, update_account, verify_imap_smtp_credentials};
+use crate::email_backend::emails::commands::{get_emails, get_folders, refresh_folder, get_unified_counts, get_email_content, regenerate_summary, get_attachments, get_attachment_data, save_attachment_to_path, open_attachment, mark_as_read, set_flags, remove_flags, move_to_trash, archive_emails, move_to_inbox, get_email_by_id, get_thread_emails, send_email, save_draft, get_drafts, delete_draft, get_draft_by_id, search_emails, move_to_local_archive};
+use crate::email_backend::export::commands::{export_email_eml, export_thread_eml, export_mbox};
+use crate::email_backend::import::commands::import_messages;
 use crate::email_backend::enrichment::commands::{get_sender_info, get_domain_info, get_emails_by_sender, regenerate_sender_info, update_sender_info, search_contacts, sync_contacts};
 use crate::email_backend::llm::commands::get_available_models;
+use crate::email_backend::rules::commands::{get_rules, save_rule, delete_rule, apply_rule_to_existing};
+use crate::email_backend::timers::commands::{snooze_emails, unsnooze_emails, remind_if_no_reply, cancel_reminder};
+use crate::email_backend::notifications::commands::{mute_threads, unmute_threads, get_account_notifications, set_account_notifications, set_folder_notifications};
+use crate::email_backend::calendar::commands::respond_to_invite;
+use crate::email_backend::contacts::commands::{import_vcards, export_vcards, get_carddav_accounts, set_carddav_account, sync_carddav};
+use crate::email_backend::subscriptions::commands::{unsubscribe, get_subscriptions, archive_subscription, set_subscription_mode, set_unsubscribe_auto_archive, build_digest_now};
+use crate::email_backend::sieve::commands::{list_sieve_scripts, get_sieve_script, put_sieve_script, activate_sieve_script, check_sieve_script, generate_sieve_script};
 use crate::db::settings::{get_settings, update_setting};
+use crate::db::backup::{create_backup, restore_backup};
 use crate::email_backend::sync::{SyncEngine, SyncWorker};
 use crate::db::setup::setup_database;
+use crate::context::AppContext;
+use std::sync::Arc;
 use tauri::Manager;
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 
+pub mod cli;
+mod context;
 mod email_backend;
 mod utils;
 mod db;
@@ -90,16 +104,18 @@ pub fn run() {
                 setup_database(&handle).await
             }).expect("Failed to setup database");
 
+            let data_dir = handle.path().app_data_dir().expect("Failed to get app data dir");
+            let app_context = AppContext::new(pool.clone(), data_dir, Arc::new(handle.clone()));
+            let sync_engine = SyncEngine::new(app_context.clone());
             app.manage(pool);
-
-            let sync_engine = SyncEngine::new(handle.clone());
+            app.manage(app_context);
             app.manage(sync_engine.clone());
 
+            let sync_worker = SyncWorker::new(sync_engine.clone());
             tauri::async_runtime::spawn(async move {
                 sync_engine.start().await;
             });
 
-            let sync_worker = SyncWorker::new(handle.clone());
             tauri::async_runtime::spawn(async move {
                 sync_worker.start().await;
             });
@@ -109,10 +125,17 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             login_with_google,
             login_with_microsoft,
+            login_with_oauth2,
+            get_oauth2_providers,
             add_imap_smtp_account,
+            add_maildir_account,
+            add_notmuch_account,
             verify_imap_smtp_credentials,
+            discover_account_settings,
             get_accounts,
             remove_account,
+            reorder_accounts,
+            update_account,
             get_emails,
             get_folders,
             refresh_folder,
@@ -124,6 +147,8 @@ pub fn run() {
             save_attachment_to_path,
             open_attachment,
             mark_as_read,
+            set_flags,
+            remove_flags,
             move_to_trash,
             archive_emails,
             move_to_inbox,
@@ -135,8 +160,15 @@ pub fn run() {
             delete_draft,
             get_draft_by_id,
             search_emails,
+            move_to_local_archive,
+            export_email_eml,
+            export_thread_eml,
+            export_mbox,
+            import_messages,
             get_settings,
             update_setting,
+            create_backup,
+            restore_backup,
             get_sender_info,
             regenerate_sender_info,
             update_sender_info,
@@ -144,7 +176,38 @@ pub fn run() {
             get_emails_by_sender,
             get_available_models,
             search_contacts,
-            sync_contacts
+            sync_contacts,
+            get_rules,
+            save_rule,
+            delete_rule,
+            apply_rule_to_existing,
+            list_sieve_scripts,
+            get_sieve_script,
+            put_sieve_script,
+            activate_sieve_script,
+            check_sieve_script,
+            generate_sieve_script,
+            snooze_emails,
+            unsnooze_emails,
+            remind_if_no_reply,
+            cancel_reminder,
+            unsubscribe,
+            get_subscriptions,
+            archive_subscription,
+            set_subscription_mode,
+            set_unsubscribe_auto_archive,
+            build_digest_now,
+            mute_threads,
+            unmute_threads,
+            get_account_notifications,
+            set_account_notifications,
+            set_folder_notifications,
+            respond_to_invite,
+            import_vcards,
+            export_vcards,
+            get_carddav_accounts,
+            set_carddav_account,
+            sync_carddav
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
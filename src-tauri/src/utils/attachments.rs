@@ -1,12 +1,18 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager, Runtime};
 use std::fs;
 use sha2::{Sha256, Digest};
-use log::error;
+use log::{error, info};
+use sqlx::SqlitePool;
 
 pub fn get_attachments_dir<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf, String> {
-    let mut path = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
-    path.push("attachments");
+    let data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    attachments_dir(&data_dir)
+}
+
+/// The attachment cache inside `data_dir`, created if missing.
+pub fn attachments_dir(data_dir: &Path) -> Result<PathBuf, String> {
+    let path = data_dir.join("attachments");
     
     if !path.exists() {
         fs::create_dir_all(&path).map_err(|e| e.to_string())?;
@@ -45,3 +51,39 @@ pub fn read_attachment_data<R: Runtime>(app_handle: &AppHandle<R>, hash: &str) -
     let path = get_attachment_path(app_handle, hash)?;
     fs::read(path).map_err(|e| e.to_string())
 }
+
+/// Reads a cached attachment without a running app.
+pub fn read_stored_attachment(data_dir: &Path, hash: &str) -> Result<Vec<u8>, String> {
+    let file_path = attachments_dir(data_dir)?.join(hash);
+    if !file_path.exists() {
+        return Err("Attachment file not found on disk".to_string());
+    }
+    fs::read(file_path).map_err(|e| e.to_string())
+}
+
+/// Deletes the files of `hashes` that no row in `attachments` references any more.
+/// Files are content-addressed and shared, so callers collect the hashes of the rows they
+/// delete and pass them here once the rows are gone. Files of other rows, including ones
+/// whose row is still being saved, are left alone.
+pub async fn remove_orphaned_attachments<R: Runtime>(app_handle: &AppHandle<R>, pool: &SqlitePool, hashes: &[String]) -> Result<usize, String> {
+    let dir = get_attachments_dir(app_handle)?;
+    let mut removed = 0;
+    for hash in hashes {
+        let referenced: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM attachments WHERE file_hash = ?)")
+            .bind(hash)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let path = dir.join(hash);
+        if referenced || !path.exists() {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(_) => removed += 1,
+            Err(e) => error!("Failed to remove orphaned attachment {}: {}", hash, e),
+        }
+    }
+
+    info!("Removed {} orphaned attachment files", removed);
+    Ok(removed)
+}
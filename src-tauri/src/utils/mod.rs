@@ -2,3 +2,5 @@ pub mod security;
 pub mod attachments;
 #[cfg(test)]
 pub mod test_utils;
+#[cfg(test)]
+pub mod test_server;
@@ -0,0 +1,1293 @@
+//! Scripted in-memory IMAP, SMTP and ManageSieve servers for driving the sync engine, the transports
+//! and the filter client end to end.
+//!
+//! They speak just enough of the protocols for the clients under test, over plain TCP on `127.0.0.1`.
+//! Tests change the IMAP server's mailboxes while clients are connected; idling clients are told about
+//! new and expunged messages like a real server would.
+
+use std::sync::{Arc, Mutex};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+const CAPABILITIES: &str = "IMAP4rev1 AUTH=PLAIN SASL-IR IDLE MOVE UIDPLUS";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeMessage {
+    pub uid: u32,
+    /// IMAP flags such as `\Seen`.
+    pub flags: Vec<String>,
+    pub raw: Vec<u8>,
+}
+
+struct Mailbox {
+    name: String,
+    special_use: Option<&'static str>,
+    uid_validity: u32,
+    uid_next: u32,
+    messages: Vec<FakeMessage>,
+}
+
+impl Mailbox {
+    fn new(name: &str, special_use: Option<&'static str>) -> Self {
+        Self { name: name.to_string(), special_use, uid_validity: 1, uid_next: 1, messages: Vec::new() }
+    }
+
+    fn append(&mut self, raw: Vec<u8>, flags: Vec<String>) -> u32 {
+        let uid = self.uid_next;
+        self.uid_next += 1;
+        self.messages.push(FakeMessage { uid, flags, raw });
+        uid
+    }
+
+    fn max_uid(&self) -> u32 {
+        self.messages.last().map(|m| m.uid).unwrap_or(0)
+    }
+}
+
+struct ImapState {
+    username: String,
+    password: String,
+    mailboxes: Vec<Mailbox>,
+    commands: Vec<String>,
+    failed_logins: usize,
+}
+
+impl ImapState {
+    fn mailbox(&self, name: &str) -> Option<&Mailbox> {
+        self.mailboxes.iter().find(|m| same_mailbox(&m.name, name))
+    }
+
+    fn mailbox_mut(&mut self, name: &str) -> Option<&mut Mailbox> {
+        self.mailboxes.iter_mut().find(|m| same_mailbox(&m.name, name))
+    }
+}
+
+fn same_mailbox(a: &str, b: &str) -> bool {
+    a == b || (a.eq_ignore_ascii_case("INBOX") && b.eq_ignore_ascii_case("INBOX"))
+}
+
+/// An IMAP server with an INBOX, Sent and Trash mailbox and a single user.
+pub struct FakeImapServer {
+    port: u16,
+    state: Arc<Mutex<ImapState>>,
+    changes: broadcast::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl FakeImapServer {
+    pub async fn start(username: &str, password: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind fake IMAP server");
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(ImapState {
+            username: username.to_string(),
+            password: password.to_string(),
+            mailboxes: vec![
+                Mailbox::new("INBOX", None),
+                Mailbox::new("Sent", Some("\\Sent")),
+                Mailbox::new("Trash", Some("\\Trash")),
+            ],
+            commands: Vec::new(),
+            failed_logins: 0,
+        }));
+        let (changes, _) = broadcast::channel(64);
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            let changes = changes.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let session = ImapSession::new(stream, state.clone(), changes.clone());
+                    tokio::spawn(session.run());
+                }
+            }
+        });
+
+        Self { port, state, changes, task }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Delivers a message and returns its UID.
+    pub fn add_message(&self, mailbox: &str, raw: &[u8], flags: &[&str]) -> u32 {
+        let uid = self.with_mailbox(mailbox, |m| m.append(raw.to_vec(), flags.iter().map(|f| f.to_string()).collect()));
+        let _ = self.changes.send(());
+        uid
+    }
+
+    /// Removes a message as if another client had expunged it.
+    pub fn expunge(&self, mailbox: &str, uid: u32) {
+        self.with_mailbox(mailbox, |m| m.messages.retain(|msg| msg.uid != uid));
+        let _ = self.changes.send(());
+    }
+
+    /// Starts a new UIDVALIDITY epoch and numbers the messages from 1 again.
+    pub fn reset_uid_validity(&self, mailbox: &str) {
+        self.with_mailbox(mailbox, |m| {
+            m.uid_validity += 1;
+            for (i, message) in m.messages.iter_mut().enumerate() {
+                message.uid = i as u32 + 1;
+            }
+            m.uid_next = m.messages.len() as u32 + 1;
+        });
+    }
+
+    pub fn set_password(&self, password: &str) {
+        self.state.lock().unwrap().password = password.to_string();
+    }
+
+    pub fn messages(&self, mailbox: &str) -> Vec<FakeMessage> {
+        self.with_mailbox(mailbox, |m| m.messages.clone())
+    }
+
+    /// Every command received so far, without tags and literals, e.g. `UID FETCH 1:* (...)`.
+    pub fn commands(&self) -> Vec<String> {
+        self.state.lock().unwrap().commands.clone()
+    }
+
+    pub fn failed_logins(&self) -> usize {
+        self.state.lock().unwrap().failed_logins
+    }
+
+    fn with_mailbox<T>(&self, name: &str, f: impl FnOnce(&mut Mailbox) -> T) -> T {
+        let mut state = self.state.lock().unwrap();
+        f(state.mailbox_mut(name).unwrap_or_else(|| panic!("No mailbox {}", name)))
+    }
+}
+
+impl Drop for FakeImapServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Atom(String),
+    Str(Vec<u8>),
+    List(Vec<Token>),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Atom(s) => s.clone(),
+            Token::Str(bytes) => String::from_utf8_lossy(bytes).to_string(),
+            Token::List(items) => format!("({})", items.iter().map(Token::text).collect::<Vec<_>>().join(" ")),
+        }
+    }
+
+    /// The atoms of a list, or the token itself when it is not a list.
+    fn items(&self) -> Vec<String> {
+        match self {
+            Token::List(items) => items.iter().map(Token::text).collect(),
+            other => vec![other.text()],
+        }
+    }
+}
+
+/// Splits a command, including the literals read along with it, into tokens.
+fn tokenize(input: &[u8]) -> Vec<Token> {
+    fn parse(input: &[u8], pos: &mut usize, in_list: bool) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        while *pos < input.len() {
+            match input[*pos] {
+                b' ' | b'\r' | b'\n' => *pos += 1,
+                b')' if in_list => {
+                    *pos += 1;
+                    return tokens;
+                }
+                b'(' => {
+                    *pos += 1;
+                    tokens.push(Token::List(parse(input, pos, true)));
+                }
+                b'"' => {
+                    *pos += 1;
+                    let mut value = Vec::new();
+                    while *pos < input.len() && input[*pos] != b'"' {
+                        if input[*pos] == b'\\' {
+                            *pos += 1;
+                        }
+                        if *pos < input.len() {
+                            value.push(input[*pos]);
+                        }
+                        *pos += 1;
+                    }
+                    *pos += 1;
+                    tokens.push(Token::Str(value));
+                }
+                b'{' => {
+                    let end = *pos + input[*pos..].iter().position(|&b| b == b'}').unwrap_or(0);
+                    let len: usize = String::from_utf8_lossy(&input[*pos + 1..end]).trim_end_matches('+').parse().unwrap_or(0);
+                    // Skip `}\r\n` to the literal data
+                    *pos = end + 3;
+                    let data_end = (*pos + len).min(input.len());
+                    tokens.push(Token::Str(input[*pos..data_end].to_vec()));
+                    *pos = data_end;
+                }
+                _ => {
+                    let start = *pos;
+                    let mut depth = 0;
+                    while *pos < input.len() {
+                        match input[*pos] {
+                            b'[' => depth += 1,
+                            b']' => depth -= 1,
+                            b' ' | b'(' | b')' | b'\r' | b'\n' if depth == 0 => break,
+                            _ => {}
+                        }
+                        *pos += 1;
+                    }
+                    if *pos == start {
+                        // A stray `)`
+                        *pos += 1;
+                        continue;
+                    }
+                    tokens.push(Token::Atom(String::from_utf8_lossy(&input[start..*pos]).to_string()));
+                }
+            }
+        }
+        tokens
+    }
+
+    parse(input, &mut 0, false)
+}
+
+/// The length of the literal a command line announces at its end, and whether the client waits for `+`.
+fn literal_at_end(line: &[u8]) -> Option<(usize, bool)> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end();
+    let inner = line.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    let spec = &inner[start + 1..];
+    let (digits, non_sync) = match spec.strip_suffix('+') {
+        Some(digits) => (digits, true),
+        None => (spec, false),
+    };
+    digits.parse().ok().map(|len| (len, !non_sync))
+}
+
+/// Parses a sequence set like `1:3,5,7:*` into inclusive ranges, `*` standing for `max`.
+fn parse_set(set: &str, max: u32) -> Vec<(u32, u32)> {
+    let value = |s: &str| if s == "*" { max } else { s.parse().unwrap_or(0) };
+    set.split(',')
+        .map(|part| match part.split_once(':') {
+            Some((a, b)) => {
+                let (a, b) = (value(a), value(b));
+                (a.min(b), a.max(b))
+            }
+            None => (value(part), value(part)),
+        })
+        .collect()
+}
+
+fn in_set(set: &[(u32, u32)], n: u32) -> bool {
+    set.iter().any(|&(a, b)| a <= n && n <= b)
+}
+
+fn is_set(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || matches!(c, ':' | ',' | '*'))
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn literal(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(format!("{{{}}}\r\n", data.len()).as_bytes());
+    out.extend_from_slice(data);
+}
+
+fn nstring(value: Option<&str>) -> String {
+    value.map(quote).unwrap_or_else(|| "NIL".to_string())
+}
+
+fn split_message(raw: &[u8]) -> (&[u8], &[u8]) {
+    match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(i) => (&raw[..i + 4], &raw[i + 4..]),
+        None => (raw, &[]),
+    }
+}
+
+/// The unfolded value of a header.
+fn header(raw: &[u8], name: &str) -> Option<String> {
+    let (head, _) = split_message(raw);
+    let head = String::from_utf8_lossy(head);
+    let mut value: Option<String> = None;
+    for line in head.split("\r\n") {
+        if line.starts_with([' ', '\t']) {
+            if let Some(v) = value.as_mut() {
+                v.push(' ');
+                v.push_str(line.trim());
+            }
+            continue;
+        }
+        if value.is_some() {
+            break;
+        }
+        if let Some((key, v)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case(name) {
+                value = Some(v.trim().to_string());
+            }
+        }
+    }
+    value
+}
+
+/// An envelope address list for a header like `Name <user@host>, other@host`.
+fn addresses(raw: &[u8], name: &str) -> String {
+    let Some(value) = header(raw, name) else {
+        return "NIL".to_string();
+    };
+    let parts: Vec<String> = value
+        .split(',')
+        .filter(|part| part.contains('@'))
+        .map(|part| {
+            let (display, addr) = match (part.find('<'), part.find('>')) {
+                (Some(start), Some(end)) => (Some(part[..start].trim().trim_matches('"')), &part[start + 1..end]),
+                _ => (None, part.trim()),
+            };
+            let (mailbox, host) = addr.split_once('@').unwrap_or((addr, ""));
+            format!("({} NIL {} {})", nstring(display.filter(|d| !d.is_empty())), quote(mailbox), quote(host))
+        })
+        .collect();
+    if parts.is_empty() { "NIL".to_string() } else { format!("({})", parts.join("")) }
+}
+
+fn envelope(raw: &[u8]) -> String {
+    let from = addresses(raw, "From");
+    let sender = if header(raw, "Sender").is_some() { addresses(raw, "Sender") } else { from.clone() };
+    let reply_to = if header(raw, "Reply-To").is_some() { addresses(raw, "Reply-To") } else { from.clone() };
+    format!(
+        "({} {} {} {} {} {} {} {} {} {})",
+        nstring(header(raw, "Date").as_deref()),
+        nstring(header(raw, "Subject").as_deref()),
+        from,
+        sender,
+        reply_to,
+        addresses(raw, "To"),
+        addresses(raw, "Cc"),
+        addresses(raw, "Bcc"),
+        nstring(header(raw, "In-Reply-To").as_deref()),
+        nstring(header(raw, "Message-ID").as_deref()),
+    )
+}
+
+/// Every message is described as a single text/plain part.
+fn body_structure(raw: &[u8]) -> String {
+    let (_, body) = split_message(raw);
+    let lines = body.iter().filter(|&&b| b == b'\n').count();
+    format!("(\"TEXT\" \"PLAIN\" (\"CHARSET\" \"utf-8\") NIL NIL \"7BIT\" {} {})", body.len(), lines)
+}
+
+struct ImapSession {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    state: Arc<Mutex<ImapState>>,
+    notify: broadcast::Sender<()>,
+    changes: broadcast::Receiver<()>,
+    selected: Option<String>,
+    /// UIDs of the selected mailbox as this client last heard of them.
+    known_uids: Vec<u32>,
+}
+
+impl ImapSession {
+    fn new(stream: TcpStream, state: Arc<Mutex<ImapState>>, notify: broadcast::Sender<()>) -> Self {
+        let (read, writer) = stream.into_split();
+        let changes = notify.subscribe();
+        Self { reader: BufReader::new(read), writer, state, notify, changes, selected: None, known_uids: Vec::new() }
+    }
+
+    async fn run(mut self) {
+        let greeting = format!("* OK [CAPABILITY {}] Fake IMAP server ready\r\n", CAPABILITIES);
+        if self.writer.write_all(greeting.as_bytes()).await.is_err() {
+            return;
+        }
+
+        let mut pending: Option<Vec<u8>> = None;
+        loop {
+            let command = match self.read_command(pending.take()).await {
+                Ok(Some(command)) => command,
+                _ => return,
+            };
+            match self.handle(&command).await {
+                Ok(Some(next)) => pending = Some(next),
+                Ok(None) => {}
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Reads a command line and any literals it announces, starting from `first` if a line was already read.
+    async fn read_command(&mut self, first: Option<Vec<u8>>) -> std::io::Result<Option<Vec<u8>>> {
+        let mut command = Vec::new();
+        let mut line = first.unwrap_or_default();
+        loop {
+            if line.is_empty() && self.reader.read_until(b'\n', &mut line).await? == 0 {
+                return Ok(None);
+            }
+            command.extend_from_slice(&line);
+            let Some((len, sync)) = literal_at_end(&line) else {
+                return Ok(Some(command));
+            };
+            if sync {
+                self.writer.write_all(b"+ Ready for literal data\r\n").await?;
+            }
+            let mut data = vec![0; len];
+            self.reader.read_exact(&mut data).await?;
+            command.extend_from_slice(&data);
+            line.clear();
+        }
+    }
+
+    async fn read_line(&mut self) -> std::io::Result<String> {
+        let mut line = Vec::new();
+        self.reader.read_until(b'\n', &mut line).await?;
+        Ok(String::from_utf8_lossy(&line).trim().to_string())
+    }
+
+    /// Handles one command. IDLE can end with the client's next command, which is returned to be handled next.
+    async fn handle(&mut self, command: &[u8]) -> std::io::Result<Option<Vec<u8>>> {
+        let tokens = tokenize(command);
+        let Some(Token::Atom(tag)) = tokens.first() else {
+            self.writer.write_all(b"* BAD Missing tag\r\n").await?;
+            return Ok(None);
+        };
+        let tag = tag.clone();
+        let mut name = tokens.get(1).map(Token::text).unwrap_or_default().to_uppercase();
+        let mut args = tokens.get(2..).unwrap_or_default().to_vec();
+        if name == "UID" && !args.is_empty() {
+            name = format!("UID {}", args.remove(0).text().to_uppercase());
+        }
+
+        let logged = std::iter::once(name.clone())
+            .chain(args.iter().map(|a| match a {
+                Token::Str(bytes) if bytes.len() > 64 => "{literal}".to_string(),
+                other => other.text(),
+            }))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.state.lock().unwrap().commands.push(logged);
+
+        let mut out = Vec::new();
+        match name.as_str() {
+            "CAPABILITY" => {
+                out.extend_from_slice(format!("* CAPABILITY {}\r\n", CAPABILITIES).as_bytes());
+                out.extend_from_slice(format!("{} OK CAPABILITY completed\r\n", tag).as_bytes());
+            }
+            "LOGIN" => {
+                let user = args.first().map(Token::text).unwrap_or_default();
+                let pass = args.get(1).map(Token::text).unwrap_or_default();
+                out.extend_from_slice(self.login(&tag, &user, &pass).as_bytes());
+            }
+            "AUTHENTICATE" => {
+                let mechanism = args.first().map(Token::text).unwrap_or_default().to_uppercase();
+                if mechanism != "PLAIN" {
+                    out.extend_from_slice(format!("{} NO Unsupported mechanism\r\n", tag).as_bytes());
+                } else {
+                    let response = match args.get(1) {
+                        Some(initial) => initial.text(),
+                        None => {
+                            self.writer.write_all(b"+ \r\n").await?;
+                            self.read_line().await?
+                        }
+                    };
+                    let decoded = BASE64.decode(response.trim()).unwrap_or_default();
+                    let parts: Vec<String> = decoded.split(|&b| b == 0).map(|p| String::from_utf8_lossy(p).to_string()).collect();
+                    let (user, pass) = match parts.as_slice() {
+                        [_, user, pass] => (user.clone(), pass.clone()),
+                        _ => (String::new(), String::new()),
+                    };
+                    out.extend_from_slice(self.login(&tag, &user, &pass).as_bytes());
+                }
+            }
+            "LIST" | "LSUB" => {
+                let state = self.state.lock().unwrap();
+                for mailbox in &state.mailboxes {
+                    let attributes = match mailbox.special_use {
+                        Some(special) => format!("\\HasNoChildren {}", special),
+                        None => "\\HasNoChildren".to_string(),
+                    };
+                    out.extend_from_slice(format!("* {} ({}) \"/\" {}\r\n", name, attributes, quote(&mailbox.name)).as_bytes());
+                }
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+            }
+            "SELECT" | "EXAMINE" => {
+                let requested = args.first().map(Token::text).unwrap_or_default();
+                let state = self.state.lock().unwrap();
+                match state.mailbox(&requested) {
+                    Some(mailbox) => {
+                        out.extend_from_slice(b"* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n");
+                        out.extend_from_slice(b"* OK [PERMANENTFLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft \\*)] Flags permitted\r\n");
+                        out.extend_from_slice(format!("* {} EXISTS\r\n", mailbox.messages.len()).as_bytes());
+                        out.extend_from_slice(b"* 0 RECENT\r\n");
+                        out.extend_from_slice(format!("* OK [UIDVALIDITY {}] UIDs valid\r\n", mailbox.uid_validity).as_bytes());
+                        out.extend_from_slice(format!("* OK [UIDNEXT {}] Predicted next UID\r\n", mailbox.uid_next).as_bytes());
+                        let access = if name == "SELECT" { "READ-WRITE" } else { "READ-ONLY" };
+                        out.extend_from_slice(format!("{} OK [{}] {} completed\r\n", tag, access, name).as_bytes());
+                        self.known_uids = mailbox.messages.iter().map(|m| m.uid).collect();
+                        self.selected = Some(mailbox.name.clone());
+                    }
+                    None => {
+                        out.extend_from_slice(format!("{} NO Mailbox does not exist\r\n", tag).as_bytes());
+                        self.selected = None;
+                    }
+                }
+            }
+            "CREATE" => {
+                let requested = args.first().map(Token::text).unwrap_or_default();
+                let mut state = self.state.lock().unwrap();
+                if state.mailbox(&requested).is_none() {
+                    state.mailboxes.push(Mailbox::new(&requested, None));
+                }
+                out.extend_from_slice(format!("{} OK CREATE completed\r\n", tag).as_bytes());
+            }
+            "FETCH" | "UID FETCH" => {
+                let by_uid = name.starts_with("UID");
+                let set = args.first().map(Token::text).unwrap_or_default();
+                let items: Vec<String> = args.get(1).map(Token::items).unwrap_or_default().into_iter().map(|i| i.to_uppercase()).collect();
+                self.fetch(&mut out, by_uid, &set, &items);
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+            }
+            "STORE" | "UID STORE" => {
+                let by_uid = name.starts_with("UID");
+                let set = args.first().map(Token::text).unwrap_or_default();
+                let operation = args.get(1).map(Token::text).unwrap_or_default().to_uppercase();
+                let flags = args.get(2).map(Token::items).unwrap_or_default();
+                self.store(&mut out, by_uid, &set, &operation, &flags);
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+            }
+            "COPY" | "UID COPY" | "MOVE" | "UID MOVE" => {
+                let by_uid = name.starts_with("UID");
+                let set = args.first().map(Token::text).unwrap_or_default();
+                let target = args.get(1).map(Token::text).unwrap_or_default();
+                match self.copy(&mut out, by_uid, &set, &target, name.ends_with("MOVE")) {
+                    Some(code) => out.extend_from_slice(format!("{} OK {}{} completed\r\n", tag, code, name).as_bytes()),
+                    None => out.extend_from_slice(format!("{} NO [TRYCREATE] No such mailbox\r\n", tag).as_bytes()),
+                }
+                let _ = self.notify.send(());
+            }
+            "EXPUNGE" | "UID EXPUNGE" => {
+                let only = if name == "UID EXPUNGE" { args.first().map(Token::text) } else { None };
+                self.expunge(&mut out, only.as_deref());
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+                let _ = self.notify.send(());
+            }
+            "CLOSE" | "UNSELECT" => {
+                if name == "CLOSE" {
+                    self.expunge(&mut Vec::new(), None);
+                }
+                self.selected = None;
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+            }
+            "APPEND" => {
+                let requested = args.first().map(Token::text).unwrap_or_default();
+                let flags = args.iter().find(|a| matches!(a, Token::List(_))).map(Token::items).unwrap_or_default();
+                let data = match args.last() {
+                    Some(Token::Str(data)) if args.len() > 1 => data.clone(),
+                    _ => Vec::new(),
+                };
+                let mut state = self.state.lock().unwrap();
+                match state.mailbox_mut(&requested) {
+                    Some(mailbox) => {
+                        let uid = mailbox.append(data, flags);
+                        out.extend_from_slice(format!("{} OK [APPENDUID {} {}] APPEND completed\r\n", tag, mailbox.uid_validity, uid).as_bytes());
+                    }
+                    None => out.extend_from_slice(format!("{} NO [TRYCREATE] No such mailbox\r\n", tag).as_bytes()),
+                }
+                drop(state);
+                let _ = self.notify.send(());
+            }
+            "SEARCH" | "UID SEARCH" => {
+                let by_uid = name.starts_with("UID");
+                let matches = self.search(by_uid, &args);
+                let found: Vec<String> = matches.iter().map(u32::to_string).collect();
+                out.extend_from_slice(format!("* SEARCH{}{}\r\n", if found.is_empty() { "" } else { " " }, found.join(" ")).as_bytes());
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+            }
+            "NOOP" | "CHECK" => {
+                self.pending_updates(&mut out);
+                out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes());
+            }
+            "IDLE" => return self.idle(&tag).await,
+            "LOGOUT" => {
+                self.writer.write_all(format!("* BYE Logging out\r\n{} OK LOGOUT completed\r\n", tag).as_bytes()).await?;
+                return Err(std::io::ErrorKind::ConnectionAborted.into());
+            }
+            _ => out.extend_from_slice(format!("{} OK {} completed\r\n", tag, name).as_bytes()),
+        }
+
+        self.writer.write_all(&out).await?;
+        Ok(None)
+    }
+
+    fn login(&self, tag: &str, user: &str, pass: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        if user == state.username && pass == state.password {
+            format!("{} OK Logged in\r\n", tag)
+        } else {
+            state.failed_logins += 1;
+            format!("{} NO [AUTHENTICATIONFAILED] Invalid credentials\r\n", tag)
+        }
+    }
+
+    /// Indexes (0-based) of the selected mailbox's messages matching a set of UIDs or sequence numbers.
+    fn matching(mailbox: &Mailbox, by_uid: bool, set: &str) -> Vec<usize> {
+        let max = if by_uid { mailbox.max_uid() } else { mailbox.messages.len() as u32 };
+        if max == 0 {
+            return Vec::new();
+        }
+        let ranges = parse_set(set, max);
+        mailbox.messages.iter().enumerate()
+            .filter(|(i, m)| in_set(&ranges, if by_uid { m.uid } else { *i as u32 + 1 }))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn fetch(&mut self, out: &mut Vec<u8>, by_uid: bool, set: &str, items: &[String]) {
+        let Some(selected) = self.selected.clone() else { return };
+        let mut state = self.state.lock().unwrap();
+        let Some(mailbox) = state.mailbox_mut(&selected) else { return };
+
+        for index in Self::matching(mailbox, by_uid, set) {
+            let message = &mut mailbox.messages[index];
+            let mut parts: Vec<Vec<u8>> = Vec::new();
+            if by_uid && !items.iter().any(|i| i == "UID") {
+                parts.push(format!("UID {}", message.uid).into_bytes());
+            }
+            for item in items {
+                let (head, body) = split_message(&message.raw);
+                let section = |name: &str, data: &[u8]| {
+                    let mut part = format!("{} ", name).into_bytes();
+                    literal(&mut part, data);
+                    part
+                };
+                match item.as_str() {
+                    "UID" => parts.push(format!("UID {}", message.uid).into_bytes()),
+                    "FLAGS" => parts.push(format!("FLAGS ({})", message.flags.join(" ")).into_bytes()),
+                    "ENVELOPE" => parts.push(format!("ENVELOPE {}", envelope(&message.raw)).into_bytes()),
+                    "BODYSTRUCTURE" | "BODY" => parts.push(format!("{} {}", item, body_structure(&message.raw)).into_bytes()),
+                    "RFC822.SIZE" => parts.push(format!("RFC822.SIZE {}", message.raw.len()).into_bytes()),
+                    "INTERNALDATE" => parts.push(b"INTERNALDATE \"14-Nov-2023 22:13:20 +0000\"".to_vec()),
+                    "RFC822" => parts.push(section("RFC822", &message.raw)),
+                    "RFC822.HEADER" => parts.push(section("RFC822.HEADER", head)),
+                    "RFC822.TEXT" => parts.push(section("RFC822.TEXT", body)),
+                    item if item.starts_with("BODY[") || item.starts_with("BODY.PEEK[") => {
+                        let peek = item.starts_with("BODY.PEEK[");
+                        let spec = &item[item.find('[').unwrap() + 1..item.rfind(']').unwrap_or(item.len())];
+                        let data = if spec.is_empty() {
+                            &message.raw[..]
+                        } else if spec.starts_with("TEXT") {
+                            body
+                        } else {
+                            head
+                        };
+                        parts.push(section(&format!("BODY[{}]", spec), data));
+                        if !peek && !message.flags.iter().any(|f| f == "\\Seen") {
+                            message.flags.push("\\Seen".to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            out.extend_from_slice(format!("* {} FETCH (", index + 1).as_bytes());
+            out.extend_from_slice(&parts.join(&b' '));
+            out.extend_from_slice(b")\r\n");
+        }
+    }
+
+    fn store(&mut self, out: &mut Vec<u8>, by_uid: bool, set: &str, operation: &str, flags: &[String]) {
+        let Some(selected) = self.selected.clone() else { return };
+        let mut state = self.state.lock().unwrap();
+        let Some(mailbox) = state.mailbox_mut(&selected) else { return };
+
+        for index in Self::matching(mailbox, by_uid, set) {
+            let message = &mut mailbox.messages[index];
+            let has = |current: &[String], flag: &str| current.iter().any(|f| f.eq_ignore_ascii_case(flag));
+            if operation.starts_with('+') {
+                for flag in flags {
+                    if !has(&message.flags, flag) {
+                        message.flags.push(flag.clone());
+                    }
+                }
+            } else if operation.starts_with('-') {
+                message.flags.retain(|f| !has(flags, f));
+            } else {
+                message.flags = flags.to_vec();
+            }
+
+            if !operation.ends_with(".SILENT") {
+                let uid = if by_uid { format!("UID {} ", message.uid) } else { String::new() };
+                out.extend_from_slice(format!("* {} FETCH ({}FLAGS ({}))\r\n", index + 1, uid, message.flags.join(" ")).as_bytes());
+            }
+        }
+    }
+
+    /// Copies or moves messages and returns the COPYUID response code, or `None` if the target does not exist.
+    fn copy(&mut self, out: &mut Vec<u8>, by_uid: bool, set: &str, target: &str, remove: bool) -> Option<String> {
+        let selected = self.selected.clone()?;
+        let mut state = self.state.lock().unwrap();
+        state.mailbox(target)?;
+
+        let source = state.mailbox_mut(&selected)?;
+        let indexes = Self::matching(source, by_uid, set);
+        let copied: Vec<FakeMessage> = indexes.iter().map(|&i| source.messages[i].clone()).collect();
+        if remove {
+            for &index in indexes.iter().rev() {
+                source.messages.remove(index);
+            }
+        }
+
+        let destination = state.mailbox_mut(target)?;
+        let mut source_uids = Vec::new();
+        let mut target_uids = Vec::new();
+        for message in copied {
+            source_uids.push(message.uid.to_string());
+            target_uids.push(destination.append(message.raw, message.flags).to_string());
+        }
+        let code = if source_uids.is_empty() {
+            String::new()
+        } else {
+            format!("[COPYUID {} {} {}] ", destination.uid_validity, source_uids.join(","), target_uids.join(","))
+        };
+        drop(state);
+
+        if remove {
+            if !code.is_empty() {
+                out.extend_from_slice(format!("* OK {}Moved\r\n", code).as_bytes());
+            }
+            for &index in indexes.iter().rev() {
+                out.extend_from_slice(format!("* {} EXPUNGE\r\n", index + 1).as_bytes());
+                if index < self.known_uids.len() {
+                    self.known_uids.remove(index);
+                }
+            }
+            return Some(String::new());
+        }
+        Some(code)
+    }
+
+    fn expunge(&mut self, out: &mut Vec<u8>, only: Option<&str>) {
+        let Some(selected) = self.selected.clone() else { return };
+        let mut state = self.state.lock().unwrap();
+        let Some(mailbox) = state.mailbox_mut(&selected) else { return };
+
+        let limit = only.map(|set| Self::matching(mailbox, true, set));
+        let removed: Vec<usize> = mailbox.messages.iter().enumerate()
+            .filter(|(i, m)| m.flags.iter().any(|f| f == "\\Deleted") && limit.as_ref().is_none_or(|l| l.contains(i)))
+            .map(|(i, _)| i)
+            .collect();
+        for &index in removed.iter().rev() {
+            mailbox.messages.remove(index);
+            out.extend_from_slice(format!("* {} EXPUNGE\r\n", index + 1).as_bytes());
+            if index < self.known_uids.len() {
+                self.known_uids.remove(index);
+            }
+        }
+    }
+
+    /// Matches UIDs or sequence numbers against a handful of search keys; keys it does not know match everything.
+    fn search(&self, by_uid: bool, args: &[Token]) -> Vec<u32> {
+        let Some(selected) = self.selected.clone() else { return Vec::new() };
+        let state = self.state.lock().unwrap();
+        let Some(mailbox) = state.mailbox(&selected) else { return Vec::new() };
+
+        let keys: Vec<String> = args.iter().map(Token::text).collect();
+        mailbox.messages.iter().enumerate()
+            .filter(|(i, message)| {
+                let has = |flag: &str| message.flags.iter().any(|f| f.eq_ignore_ascii_case(flag));
+                let mut keys = keys.iter();
+                while let Some(key) = keys.next() {
+                    let matched = match key.to_uppercase().as_str() {
+                        "SEEN" => has("\\Seen"),
+                        "UNSEEN" => !has("\\Seen"),
+                        "FLAGGED" => has("\\Flagged"),
+                        "UNFLAGGED" => !has("\\Flagged"),
+                        "DELETED" => has("\\Deleted"),
+                        "UNDELETED" => !has("\\Deleted"),
+                        "ANSWERED" => has("\\Answered"),
+                        "UID" => keys.next().is_some_and(|set| in_set(&parse_set(set, mailbox.max_uid()), message.uid)),
+                        "CHARSET" => {
+                            keys.next();
+                            true
+                        }
+                        set if is_set(set) => in_set(&parse_set(set, mailbox.messages.len() as u32), *i as u32 + 1),
+                        _ => true,
+                    };
+                    if !matched {
+                        return false;
+                    }
+                }
+                true
+            })
+            .map(|(i, message)| if by_uid { message.uid } else { i as u32 + 1 })
+            .collect()
+    }
+
+    /// Tells the client about messages that arrived or were expunged since it last heard.
+    fn pending_updates(&mut self, out: &mut Vec<u8>) {
+        let Some(selected) = self.selected.clone() else { return };
+        let state = self.state.lock().unwrap();
+        let Some(mailbox) = state.mailbox(&selected) else { return };
+        let current: Vec<u32> = mailbox.messages.iter().map(|m| m.uid).collect();
+
+        for index in (0..self.known_uids.len()).rev() {
+            if !current.contains(&self.known_uids[index]) {
+                out.extend_from_slice(format!("* {} EXPUNGE\r\n", index + 1).as_bytes());
+                self.known_uids.remove(index);
+            }
+        }
+        if current.len() > self.known_uids.len() {
+            out.extend_from_slice(format!("* {} EXISTS\r\n", current.len()).as_bytes());
+        }
+        self.known_uids = current;
+    }
+
+    async fn idle(&mut self, tag: &str) -> std::io::Result<Option<Vec<u8>>> {
+        // Changes made before IDLE started are reported right away
+        while self.changes.try_recv().is_ok() {}
+        self.writer.write_all(b"+ idling\r\n").await?;
+        let mut out = Vec::new();
+        self.pending_updates(&mut out);
+        self.writer.write_all(&out).await?;
+
+        let mut line = Vec::new();
+        loop {
+            let read = tokio::select! {
+                read = self.reader.read_until(b'\n', &mut line) => Some(read?),
+                _ = self.changes.recv() => None,
+            };
+            match read {
+                Some(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Some(_) => {
+                    self.writer.write_all(format!("{} OK IDLE terminated\r\n", tag).as_bytes()).await?;
+                    if String::from_utf8_lossy(&line).trim().eq_ignore_ascii_case("DONE") {
+                        return Ok(None);
+                    }
+                    // The client moved on without DONE, its line is the next command
+                    return Ok(Some(line));
+                }
+                None => {
+                    let mut out = Vec::new();
+                    self.pending_updates(&mut out);
+                    self.writer.write_all(&out).await?;
+                }
+            }
+        }
+    }
+}
+
+/// A message accepted by the fake SMTP server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentMail {
+    pub from: String,
+    pub recipients: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+struct SmtpState {
+    username: String,
+    password: String,
+    messages: Vec<SentMail>,
+    failed_logins: usize,
+}
+
+/// An SMTP server that requires AUTH PLAIN or LOGIN and keeps whatever it is sent.
+pub struct FakeSmtpServer {
+    port: u16,
+    state: Arc<Mutex<SmtpState>>,
+    task: JoinHandle<()>,
+}
+
+impl FakeSmtpServer {
+    pub async fn start(username: &str, password: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind fake SMTP server");
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(SmtpState {
+            username: username.to_string(),
+            password: password.to_string(),
+            messages: Vec::new(),
+            failed_logins: 0,
+        }));
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(smtp_session(stream, state.clone()));
+                }
+            }
+        });
+
+        Self { port, state, task }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn messages(&self) -> Vec<SentMail> {
+        self.state.lock().unwrap().messages.clone()
+    }
+
+    pub fn failed_logins(&self) -> usize {
+        self.state.lock().unwrap().failed_logins
+    }
+}
+
+impl Drop for FakeSmtpServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn angle_address(arg: &str) -> String {
+    match (arg.find('<'), arg.find('>')) {
+        (Some(start), Some(end)) => arg[start + 1..end].to_string(),
+        _ => arg.split_whitespace().next().unwrap_or_default().to_string(),
+    }
+}
+
+async fn smtp_session(stream: TcpStream, state: Arc<Mutex<SmtpState>>) -> std::io::Result<()> {
+    let (read, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let mut authenticated = false;
+    let mut from = String::new();
+    let mut recipients = Vec::new();
+
+    async fn read_line(reader: &mut BufReader<OwnedReadHalf>) -> std::io::Result<Option<String>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    let check = |user: &str, pass: &str| {
+        let mut state = state.lock().unwrap();
+        let ok = user == state.username && pass == state.password;
+        if !ok {
+            state.failed_logins += 1;
+        }
+        ok
+    };
+    let decode = |value: &str| String::from_utf8_lossy(&BASE64.decode(value.trim()).unwrap_or_default()).to_string();
+
+    writer.write_all(b"220 localhost ESMTP fake server ready\r\n").await?;
+    while let Some(line) = read_line(&mut reader).await? {
+        let (verb, rest) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+        let reply = match verb.to_uppercase().as_str() {
+            "EHLO" => "250-localhost\r\n250-AUTH PLAIN LOGIN\r\n250-8BITMIME\r\n250 SIZE 10485760\r\n".to_string(),
+            "HELO" => "250 localhost\r\n".to_string(),
+            "AUTH" => {
+                let (mechanism, initial) = rest.split_once(' ').unwrap_or((rest, ""));
+                let (user, pass) = match mechanism.to_uppercase().as_str() {
+                    "PLAIN" => {
+                        let response = if initial.is_empty() {
+                            writer.write_all(b"334 \r\n").await?;
+                            read_line(&mut reader).await?.unwrap_or_default()
+                        } else {
+                            initial.to_string()
+                        };
+                        let decoded = decode(&response);
+                        let mut parts = decoded.split('\0').skip(1);
+                        (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string())
+                    }
+                    "LOGIN" => {
+                        let user = if initial.is_empty() {
+                            writer.write_all(b"334 VXNlcm5hbWU6\r\n").await?;
+                            read_line(&mut reader).await?.unwrap_or_default()
+                        } else {
+                            initial.to_string()
+                        };
+                        writer.write_all(b"334 UGFzc3dvcmQ6\r\n").await?;
+                        let pass = read_line(&mut reader).await?.unwrap_or_default();
+                        (decode(&user), decode(&pass))
+                    }
+                    _ => {
+                        writer.write_all(b"504 5.5.4 Unrecognized authentication type\r\n").await?;
+                        continue;
+                    }
+                };
+                authenticated = check(&user, &pass);
+                if authenticated {
+                    "235 2.7.0 Authentication successful\r\n".to_string()
+                } else {
+                    "535 5.7.8 Authentication credentials invalid\r\n".to_string()
+                }
+            }
+            "MAIL" if !authenticated => "530 5.7.0 Authentication required\r\n".to_string(),
+            "MAIL" => {
+                from = angle_address(rest);
+                recipients.clear();
+                "250 2.1.0 OK\r\n".to_string()
+            }
+            "RCPT" => {
+                recipients.push(angle_address(rest));
+                "250 2.1.5 OK\r\n".to_string()
+            }
+            "DATA" => {
+                writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await?;
+                let mut data = Vec::new();
+                loop {
+                    let mut line = Vec::new();
+                    if reader.read_until(b'\n', &mut line).await? == 0 {
+                        return Ok(());
+                    }
+                    if line == b".\r\n" || line == b".\n" {
+                        break;
+                    }
+                    // Undo dot-stuffing
+                    let line = if line.starts_with(b"..") { &line[1..] } else { &line[..] };
+                    data.extend_from_slice(line);
+                }
+                state.lock().unwrap().messages.push(SentMail { from: from.clone(), recipients: recipients.clone(), data });
+                "250 2.0.0 Queued\r\n".to_string()
+            }
+            "RSET" => {
+                from.clear();
+                recipients.clear();
+                "250 2.0.0 OK\r\n".to_string()
+            }
+            "NOOP" => "250 2.0.0 OK\r\n".to_string(),
+            "QUIT" => {
+                writer.write_all(b"221 2.0.0 Bye\r\n").await?;
+                return Ok(());
+            }
+            _ => "502 5.5.2 Command not recognized\r\n".to_string(),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+const SIEVE_EXTENSIONS: &str = "fileinto imap4flags copy vacation date relational";
+
+struct SieveState {
+    username: String,
+    password: String,
+    scripts: Vec<(String, String)>,
+    active: Option<String>,
+}
+
+/// A ManageSieve server that keeps scripts in memory. It only checks that braces balance and that
+/// every required extension is one it announces, which catches what a generator gets wrong.
+pub struct FakeSieveServer {
+    port: u16,
+    state: Arc<Mutex<SieveState>>,
+    task: JoinHandle<()>,
+}
+
+impl FakeSieveServer {
+    pub async fn start(username: &str, password: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind fake ManageSieve server");
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(SieveState {
+            username: username.to_string(),
+            password: password.to_string(),
+            scripts: Vec::new(),
+            active: None,
+        }));
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(sieve_session(stream, state.clone()));
+                }
+            }
+        });
+
+        Self { port, state, task }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn script(&self, name: &str) -> Option<String> {
+        self.state.lock().unwrap().scripts.iter().find(|(n, _)| n == name).map(|(_, s)| s.clone())
+    }
+
+    pub fn active(&self) -> Option<String> {
+        self.state.lock().unwrap().active.clone()
+    }
+}
+
+impl Drop for FakeSieveServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Why the server would refuse a script, if it would.
+fn sieve_errors(script: &str) -> Option<String> {
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_comment = false;
+    for c in script.chars() {
+        match c {
+            '\n' if in_comment => in_comment = false,
+            _ if in_comment => {}
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => in_comment = true,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return Some("line 1: unexpected '}'".to_string());
+        }
+    }
+    if depth != 0 || in_string {
+        return Some("unexpected end of script".to_string());
+    }
+
+    for line in script.lines().filter(|l| l.trim_start().starts_with("require")) {
+        for extension in line.split('"').skip(1).step_by(2) {
+            if !SIEVE_EXTENSIONS.split(' ').any(|e| e == extension) {
+                return Some(format!("require: unknown Sieve capability '{}'", extension));
+            }
+        }
+    }
+    None
+}
+
+async fn sieve_session(stream: TcpStream, state: Arc<Mutex<SieveState>>) -> std::io::Result<()> {
+    let (read, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let mut authenticated = false;
+
+    let capabilities = format!(
+        "\"IMPLEMENTATION\" \"Fake ManageSieve\"\r\n\"SASL\" \"PLAIN\"\r\n\"SIEVE\" \"{}\"\r\n\"VERSION\" \"1.0\"\r\nOK \"Ready\"\r\n",
+        SIEVE_EXTENSIONS
+    );
+    writer.write_all(capabilities.as_bytes()).await?;
+
+    loop {
+        // Clients send scripts as non-synchronizing literals, so the data simply follows the line
+        let mut command = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line).await? == 0 {
+                return Ok(());
+            }
+            command.extend_from_slice(&line);
+            let Some((len, _)) = literal_at_end(&line) else {
+                break;
+            };
+            let mut data = vec![0; len];
+            reader.read_exact(&mut data).await?;
+            command.extend_from_slice(&data);
+        }
+
+        let tokens = tokenize(&command);
+        let Some(verb) = tokens.first().map(|t| t.text().to_uppercase()) else {
+            continue;
+        };
+        let arg = |i: usize| tokens.get(i).map(Token::text).unwrap_or_default();
+
+        let reply = match verb.as_str() {
+            "AUTHENTICATE" => {
+                let decoded = String::from_utf8_lossy(&BASE64.decode(arg(2)).unwrap_or_default()).to_string();
+                let mut parts = decoded.split('\0').skip(1);
+                let state = state.lock().unwrap();
+                authenticated = parts.next() == Some(state.username.as_str()) && parts.next() == Some(state.password.as_str());
+                if authenticated { "OK \"Logged in\"\r\n".to_string() } else { "NO \"Authentication failed\"\r\n".to_string() }
+            }
+            "CAPABILITY" => capabilities.clone(),
+            "LOGOUT" => {
+                writer.write_all(b"OK \"Bye\"\r\n").await?;
+                return Ok(());
+            }
+            _ if !authenticated => "NO \"Authenticate first\"\r\n".to_string(),
+            "LISTSCRIPTS" => {
+                let state = state.lock().unwrap();
+                let mut reply = String::new();
+                for (name, _) in &state.scripts {
+                    let active = if state.active.as_deref() == Some(name) { " ACTIVE" } else { "" };
+                    reply.push_str(&format!("{}{}\r\n", quote(name), active));
+                }
+                reply + "OK \"Listscripts completed\"\r\n"
+            }
+            "GETSCRIPT" => match state.lock().unwrap().scripts.iter().find(|(n, _)| *n == arg(1)) {
+                Some((_, script)) => format!("{{{}}}\r\n{}\r\nOK \"Getscript completed\"\r\n", script.len(), script),
+                None => "NO (NONEXISTENT) \"There is no script by that name\"\r\n".to_string(),
+            },
+            "PUTSCRIPT" | "CHECKSCRIPT" => {
+                let script = if verb == "PUTSCRIPT" { arg(2) } else { arg(1) };
+                match sieve_errors(&script) {
+                    Some(error) => format!("NO {}\r\n", quote(&error)),
+                    None if verb == "PUTSCRIPT" => {
+                        let mut state = state.lock().unwrap();
+                        let name = arg(1);
+                        state.scripts.retain(|(n, _)| *n != name);
+                        state.scripts.push((name, script));
+                        "OK \"Putscript completed\"\r\n".to_string()
+                    }
+                    None => "OK \"Script is valid\"\r\n".to_string(),
+                }
+            }
+            "SETACTIVE" => {
+                let mut state = state.lock().unwrap();
+                let name = arg(1);
+                if name.is_empty() {
+                    state.active = None;
+                    "OK \"No script is active\"\r\n".to_string()
+                } else if state.scripts.iter().any(|(n, _)| *n == name) {
+                    state.active = Some(name);
+                    "OK \"Setactive completed\"\r\n".to_string()
+                } else {
+                    "NO (NONEXISTENT) \"There is no script by that name\"\r\n".to_string()
+                }
+            }
+            "DELETESCRIPT" => {
+                let mut state = state.lock().unwrap();
+                let name = arg(1);
+                if state.active.as_deref() == Some(name.as_str()) {
+                    "NO (ACTIVE) \"You may not delete an active script\"\r\n".to_string()
+                } else {
+                    state.scripts.retain(|(n, _)| *n != name);
+                    "OK \"Deletescript completed\"\r\n".to_string()
+                }
+            }
+            _ => "NO \"Unknown command\"\r\n".to_string(),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_command_with_literal_and_section() {
+        let tokens = tokenize(b"a1 UID FETCH 1:* (UID BODY.PEEK[HEADER.FIELDS (DATE)])\r\n");
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[4].items(), vec!["UID", "BODY.PEEK[HEADER.FIELDS (DATE)]"]);
+
+        let tokens = tokenize(b"a2 APPEND \"Sent\" (\\Seen) {5}\r\nHello\r\n");
+        assert_eq!(tokens[3].items(), vec!["\\Seen"]);
+        assert!(matches!(&tokens[4], Token::Str(data) if data == b"Hello"));
+    }
+
+    #[test]
+    fn test_envelope_from_headers() {
+        let raw = b"From: \"Ann\" <ann@example.com>\r\nTo: bob@example.com\r\nSubject: Hi\r\nMessage-ID: <m1@example.com>\r\n\r\nBody\r\n";
+        assert_eq!(
+            envelope(raw),
+            "(NIL \"Hi\" ((\"Ann\" NIL \"ann\" \"example.com\")) ((\"Ann\" NIL \"ann\" \"example.com\")) ((\"Ann\" NIL \"ann\" \"example.com\")) ((NIL NIL \"bob\" \"example.com\")) NIL NIL NIL \"<m1@example.com>\")"
+        );
+        assert_eq!(parse_set("2:*,5", 3), vec![(2, 3), (5, 5)]);
+    }
+}
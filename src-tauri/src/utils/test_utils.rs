@@ -1,5 +1,7 @@
 use sqlx::sqlite::{SqlitePool, SqliteConnectOptions};
 use std::env;
+use std::sync::Arc;
+use crate::context::{AppContext, EventCollector};
 
 pub async fn setup_test_db() -> SqlitePool {
     let mut temp_db = env::temp_dir();
@@ -21,3 +23,9 @@ pub async fn setup_test_db() -> SqlitePool {
     pool
 }
 
+
+/// A context over `pool` whose events land in the returned collector.
+pub fn test_context(pool: &SqlitePool) -> (AppContext, Arc<EventCollector>) {
+    let events = Arc::new(EventCollector::default());
+    (AppContext::new(pool.clone(), env::temp_dir(), events.clone()), events)
+}
@@ -17,6 +17,10 @@ import {
   Send,
   ShieldAlert,
   FilePen,
+  HardDrive,
+  Star,
+  Clock,
+  Newspaper,
 } from "lucide-react";
 // @ts-ignore
 import DueamIcon from "@/assets/dueam-icon.svg?react"
@@ -144,6 +148,51 @@ export function AppSidebar() {
                   </Link>
                 </SidebarMenuButton>
               </SidebarMenuItem>
+              <SidebarMenuItem>
+                <SidebarMenuButton asChild isActive={search.view === "starred"}>
+                  <Link
+                    to="/"
+                    search={{
+                      account_id: search.account_id,
+                      view: "starred",
+                      filter: undefined,
+                    }}
+                  >
+                    <Star className="w-4 h-4" />
+                    <span>Starred</span>
+                  </Link>
+                </SidebarMenuButton>
+              </SidebarMenuItem>
+              <SidebarMenuItem>
+                <SidebarMenuButton asChild isActive={search.view === "snoozed"}>
+                  <Link
+                    to="/"
+                    search={{
+                      account_id: search.account_id,
+                      view: "snoozed",
+                      filter: undefined,
+                    }}
+                  >
+                    <Clock className="w-4 h-4" />
+                    <span>Snoozed</span>
+                  </Link>
+                </SidebarMenuButton>
+              </SidebarMenuItem>
+              <SidebarMenuItem>
+                <SidebarMenuButton asChild isActive={search.view === "digest"}>
+                  <Link
+                    to="/"
+                    search={{
+                      account_id: search.account_id,
+                      view: "digest",
+                      filter: undefined,
+                    }}
+                  >
+                    <Newspaper className="w-4 h-4" />
+                    <span>Digest</span>
+                  </Link>
+                </SidebarMenuButton>
+              </SidebarMenuItem>
               <SidebarMenuItem>
                 <SidebarMenuButton asChild isActive={search.view === "spam"}>
                   <Link
@@ -164,6 +213,21 @@ export function AppSidebar() {
                   </Link>
                 </SidebarMenuButton>
               </SidebarMenuItem>
+              <SidebarMenuItem>
+                <SidebarMenuButton asChild isActive={search.view === "local"}>
+                  <Link
+                    to="/"
+                    search={{
+                      account_id: search.account_id,
+                      view: "local",
+                      filter: undefined,
+                    }}
+                  >
+                    <HardDrive className="w-4 h-4" />
+                    <span>On This Device</span>
+                  </Link>
+                </SidebarMenuButton>
+              </SidebarMenuItem>
             </SidebarMenu>
           </SidebarGroupContent>
         </SidebarGroup>
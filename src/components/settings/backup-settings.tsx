@@ -0,0 +1,129 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { ArchiveRestore, DatabaseBackup, Loader2 } from "lucide-react";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import {
+  AlertDialog,
+  AlertDialogAction,
+  AlertDialogCancel,
+  AlertDialogContent,
+  AlertDialogDescription,
+  AlertDialogFooter,
+  AlertDialogHeader,
+  AlertDialogTitle,
+} from "@/components/ui/alert-dialog";
+
+export function BackupSettings() {
+  const [passphrase, setPassphrase] = useState("");
+  const [restorePath, setRestorePath] = useState<string | null>(null);
+  const [isWorking, setIsWorking] = useState(false);
+
+  const handleBackup = async () => {
+    const date = new Date().toISOString().slice(0, 10);
+    const path = await save({
+      defaultPath: `dueam-${date}.dueambackup`,
+      filters: [{ name: "Dueam backup", extensions: ["dueambackup"] }],
+    });
+    if (!path) return;
+
+    setIsWorking(true);
+    try {
+      await invoke("create_backup", { path, passphrase });
+      toast.success("Backup saved");
+    } catch (err) {
+      console.error("Backup failed:", err);
+      toast.error(typeof err === "string" ? err : "Backup failed");
+    } finally {
+      setIsWorking(false);
+    }
+  };
+
+  const chooseRestore = async () => {
+    const path = await open({
+      multiple: false,
+      filters: [{ name: "Dueam backup", extensions: ["dueambackup"] }],
+    });
+    if (typeof path === "string") setRestorePath(path);
+  };
+
+  const handleRestore = async () => {
+    if (!restorePath) return;
+    const path = restorePath;
+    setRestorePath(null);
+
+    setIsWorking(true);
+    try {
+      // The app restarts once the backup is in place
+      await invoke("restore_backup", { path, passphrase });
+    } catch (err) {
+      console.error("Restore failed:", err);
+      toast.error(typeof err === "string" ? err : "Restore failed");
+      setIsWorking(false);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <DatabaseBackup className="h-5 w-5" /> Backup
+        </CardTitle>
+        <CardDescription>
+          Save accounts, drafts, summaries, contacts, settings and attachments to one encrypted file.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="space-y-2">
+          <Label>Passphrase</Label>
+          <Input
+            type="password"
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+            placeholder="Needed again to restore the backup"
+          />
+        </div>
+
+        <div className="flex justify-end gap-2">
+          <Button variant="outline" onClick={chooseRestore} disabled={isWorking || !passphrase}>
+            <ArchiveRestore className="mr-2 h-4 w-4" />
+            Restore
+          </Button>
+          <Button onClick={handleBackup} disabled={isWorking || !passphrase}>
+            {isWorking ? (
+              <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+            ) : (
+              <DatabaseBackup className="mr-2 h-4 w-4" />
+            )}
+            Back up
+          </Button>
+        </div>
+      </CardContent>
+
+      <AlertDialog open={restorePath !== null} onOpenChange={(open) => !open && setRestorePath(null)}>
+        <AlertDialogContent>
+          <AlertDialogHeader>
+            <AlertDialogTitle>Restore this backup?</AlertDialogTitle>
+            <AlertDialogDescription>
+              Everything stored in Dueam now will be replaced by the backup, and the app will restart.
+            </AlertDialogDescription>
+          </AlertDialogHeader>
+          <AlertDialogFooter>
+            <AlertDialogCancel>Cancel</AlertDialogCancel>
+            <AlertDialogAction onClick={handleRestore}>Restore</AlertDialogAction>
+          </AlertDialogFooter>
+        </AlertDialogContent>
+      </AlertDialog>
+    </Card>
+  );
+}
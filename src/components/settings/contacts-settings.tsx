@@ -0,0 +1,203 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { formatDistanceToNow } from "date-fns";
+import { toast } from "sonner";
+import { Contact, Download, Loader2, RefreshCw, Upload } from "lucide-react";
+import { useEmailStore } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type CardDavAccount = {
+  account_id: number;
+  url: string;
+  username: string | null;
+  last_synced_at: string | null;
+  last_error: string | null;
+};
+
+type SyncSummary = {
+  address_books: number;
+  fetched: number;
+  removed: number;
+};
+
+type Server = { url: string; username: string };
+
+export function ContactsSettings() {
+  const accounts = useEmailStore((state) => state.accounts);
+  const imapAccounts = accounts.filter((account) => account.type === "imap_smtp" && account.data.id);
+  const [version, setVersion] = useState<"3.0" | "4.0">("3.0");
+  const [servers, setServers] = useState<Record<number, Server>>({});
+  const [status, setStatus] = useState<Record<number, CardDavAccount>>({});
+  const [syncing, setSyncing] = useState<number | null>(null);
+
+  const loadServers = async () => {
+    try {
+      const rows = await invoke<CardDavAccount[]>("get_carddav_accounts");
+      setStatus(Object.fromEntries(rows.map((row) => [row.account_id, row])));
+      setServers(Object.fromEntries(rows.map((row) => [row.account_id, { url: row.url, username: row.username || "" }])));
+    } catch (err) {
+      console.error("Failed to load CardDAV accounts:", err);
+    }
+  };
+
+  useEffect(() => {
+    loadServers();
+  }, []);
+
+  const handleImport = async () => {
+    const path = await open({ multiple: false, filters: [{ name: "vCard", extensions: ["vcf", "vcard"] }] });
+    if (typeof path !== "string") return;
+    try {
+      const count = await invoke<number>("import_vcards", { path });
+      toast.success(`Imported ${count} contacts`);
+    } catch (err) {
+      console.error("vCard import failed:", err);
+      toast.error(typeof err === "string" ? err : "Import failed");
+    }
+  };
+
+  const handleExport = async () => {
+    const path = await save({ defaultPath: "contacts.vcf", filters: [{ name: "vCard", extensions: ["vcf"] }] });
+    if (!path) return;
+    try {
+      const count = await invoke<number>("export_vcards", { path, version });
+      toast.success(`Exported ${count} contacts`);
+    } catch (err) {
+      console.error("vCard export failed:", err);
+      toast.error(typeof err === "string" ? err : "Export failed");
+    }
+  };
+
+  const updateServer = (accountId: number, change: Partial<Server>) => {
+    setServers((current) => ({ ...current, [accountId]: { url: "", username: "", ...current[accountId], ...change } }));
+  };
+
+  const handleSave = async (accountId: number) => {
+    const server = servers[accountId] || { url: "", username: "" };
+    try {
+      await invoke("set_carddav_account", { accountId, url: server.url, username: server.username || null });
+      await loadServers();
+      if (server.url.trim()) {
+        await handleSync(accountId);
+      } else {
+        toast.success("CardDAV sync turned off");
+      }
+    } catch (err) {
+      console.error("Failed to save CardDAV server:", err);
+      toast.error(typeof err === "string" ? err : "Failed to save the server");
+    }
+  };
+
+  const handleSync = async (accountId: number) => {
+    setSyncing(accountId);
+    try {
+      const summary = await invoke<SyncSummary>("sync_carddav", { accountId });
+      toast.success(`Synced ${summary.address_books} address books: ${summary.fetched} updated, ${summary.removed} removed`);
+    } catch (err) {
+      console.error("CardDAV sync failed:", err);
+      toast.error(typeof err === "string" ? err : "CardDAV sync failed");
+    } finally {
+      setSyncing(null);
+      loadServers();
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Contact className="h-5 w-5" /> Contacts
+        </CardTitle>
+        <CardDescription>
+          Move contacts in and out as vCard files, and keep them in step with a CardDAV address book such as Fastmail or Nextcloud.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-6">
+        <div className="flex items-center justify-between gap-2">
+          <Button variant="outline" onClick={handleImport}>
+            <Upload className="mr-2 h-4 w-4" />
+            Import .vcf
+          </Button>
+          <div className="flex items-center gap-2">
+            <Select value={version} onValueChange={(v) => setVersion(v as "3.0" | "4.0")}>
+              <SelectTrigger className="w-[120px]">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="3.0">vCard 3.0</SelectItem>
+                <SelectItem value="4.0">vCard 4.0</SelectItem>
+              </SelectContent>
+            </Select>
+            <Button variant="outline" onClick={handleExport}>
+              <Download className="mr-2 h-4 w-4" />
+              Export
+            </Button>
+          </div>
+        </div>
+
+        {imapAccounts.map((account) => {
+          const accountId = account.data.id!;
+          const server = servers[accountId] || { url: "", username: "" };
+          const state = status[accountId];
+          return (
+            <div key={accountId} className="space-y-3 rounded-lg border p-3">
+              <Label>{account.data.email}</Label>
+              <div className="grid grid-cols-2 gap-2">
+                <Input
+                  placeholder="https://carddav.fastmail.com/"
+                  value={server.url}
+                  onChange={(e) => updateServer(accountId, { url: e.target.value })}
+                />
+                <Input
+                  placeholder="Username (IMAP username if empty)"
+                  value={server.username}
+                  onChange={(e) => updateServer(accountId, { username: e.target.value })}
+                />
+              </div>
+              <div className="flex items-center justify-between gap-2">
+                <p className="text-sm text-muted-foreground truncate">
+                  {state?.last_error
+                    ? state.last_error
+                    : state?.last_synced_at
+                      ? `Synced ${formatDistanceToNow(new Date(`${state.last_synced_at.replace(" ", "T")}Z`), { addSuffix: true })}`
+                      : "Signs in with the account's password."}
+                </p>
+                <div className="flex gap-2">
+                  {state && (
+                    <Button variant="ghost" size="sm" onClick={() => handleSync(accountId)} disabled={syncing !== null}>
+                      {syncing === accountId ? (
+                        <Loader2 className="h-4 w-4 animate-spin" />
+                      ) : (
+                        <RefreshCw className="h-4 w-4" />
+                      )}
+                    </Button>
+                  )}
+                  <Button size="sm" onClick={() => handleSave(accountId)} disabled={syncing !== null}>
+                    Save
+                  </Button>
+                </div>
+              </div>
+            </div>
+          );
+        })}
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,111 @@
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
+import { Newspaper } from "lucide-react";
+import { useSettingsStore } from "@/lib/settings-store";
+import { useEmailStore } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Switch } from "@/components/ui/switch";
+import { Button } from "@/components/ui/button";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+const HOURS = Array.from({ length: 24 }, (_, hour) => hour);
+
+export function DigestSettings() {
+  const settings = useSettingsStore((state) => state.settings);
+  const updateSetting = useSettingsStore((state) => state.updateSetting);
+  const accounts = useEmailStore((state) => state.accounts);
+
+  const handleBuildNow = async () => {
+    let built = 0;
+    try {
+      for (const account of accounts) {
+        const id = await invoke<number | null>("build_digest_now", { accountId: account.data.id });
+        if (id !== null) built++;
+      }
+      if (built > 0) {
+        toast.success("Digest ready");
+      } else {
+        toast.info("Nothing to digest from the last 24 hours");
+      }
+    } catch (err) {
+      console.error("Failed to build digest:", err);
+      toast.error(typeof err === "string" ? err : "Failed to build the digest");
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Newspaper className="h-5 w-5" /> Daily Digest
+        </CardTitle>
+        <CardDescription>
+          Collect newsletters and notifications into one summarized message a day instead of notifying for each.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-6">
+        <div className="flex items-center justify-between">
+          <Label htmlFor="digest-enabled">Daily digest</Label>
+          <Switch
+            id="digest-enabled"
+            checked={settings.digestEnabled}
+            onCheckedChange={(checked) => updateSetting("digestEnabled", checked)}
+          />
+        </div>
+        {settings.digestEnabled && (
+          <>
+            <div className="flex items-center justify-between">
+              <Label>Deliver at</Label>
+              <Select
+                value={settings.digestHour.toString()}
+                onValueChange={(v) => updateSetting("digestHour", parseInt(v))}
+              >
+                <SelectTrigger className="w-[180px]">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  {HOURS.map((hour) => (
+                    <SelectItem key={hour} value={hour.toString()}>
+                      {`${hour.toString().padStart(2, "0")}:00`}
+                    </SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+            </div>
+            <div className="flex items-center justify-between">
+              <div className="space-y-0.5">
+                <Label htmlFor="digest-archive">Archive covered mail</Label>
+                <p className="text-sm text-muted-foreground">
+                  Move the messages a digest covers out of the inbox.
+                </p>
+              </div>
+              <Switch
+                id="digest-archive"
+                checked={settings.digestAutoArchive}
+                onCheckedChange={(checked) => updateSetting("digestAutoArchive", checked)}
+              />
+            </div>
+            <div className="flex justify-end">
+              <Button variant="outline" onClick={handleBuildNow}>
+                Build now
+              </Button>
+            </div>
+          </>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
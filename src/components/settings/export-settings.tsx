@@ -0,0 +1,181 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { save } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { Download, Loader2 } from "lucide-react";
+import { useEmailStore } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import { Progress } from "@/components/ui/progress";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type Source = "folder" | "search" | "date_range";
+
+// The picked end date is inclusive, the backend's bound is not
+const dayAfter = (date: string) => {
+  const d = new Date(`${date}T00:00:00Z`);
+  d.setUTCDate(d.getUTCDate() + 1);
+  return d.toISOString().slice(0, 10);
+};
+
+type ExportProgress = {
+  path: string;
+  done: number;
+  total: number;
+};
+
+export function ExportSettings() {
+  const accounts = useEmailStore((state) => state.accounts);
+  const accountFolders = useEmailStore((state) => state.accountFolders);
+  const [source, setSource] = useState<Source>("folder");
+  const [folderId, setFolderId] = useState<string>("");
+  const [queryText, setQueryText] = useState("");
+  const [since, setSince] = useState("");
+  const [until, setUntil] = useState("");
+  const [progress, setProgress] = useState<ExportProgress | null>(null);
+  const [isExporting, setIsExporting] = useState(false);
+
+  useEffect(() => {
+    const unlistenPromise = listen<ExportProgress>("export-progress", (event) => {
+      setProgress(event.payload);
+    });
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  const selection = () => {
+    switch (source) {
+      case "folder":
+        return folderId ? { type: "folder", folder_id: parseInt(folderId) } : null;
+      case "search":
+        return queryText.trim() ? { type: "search", query_text: queryText, account_id: null } : null;
+      case "date_range":
+        return since && until ? { type: "date_range", since, until: dayAfter(until), account_id: null } : null;
+    }
+  };
+
+  const handleExport = async () => {
+    const selected = selection();
+    if (!selected) return;
+
+    const path = await save({
+      defaultPath: "export.mbox",
+      filters: [{ name: "Mailbox", extensions: ["mbox"] }],
+    });
+    if (!path) return;
+
+    setIsExporting(true);
+    setProgress(null);
+    try {
+      const count = await invoke<number>("export_mbox", { selection: selected, path });
+      toast.success(`Exported ${count} messages`);
+    } catch (err) {
+      console.error("Export failed:", err);
+      toast.error(typeof err === "string" ? err : "Export failed");
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Download className="h-5 w-5" /> Export
+        </CardTitle>
+        <CardDescription>
+          Save messages to an mbox file that other mail programs can import.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="flex items-center justify-between">
+          <Label>Messages</Label>
+          <Select value={source} onValueChange={(v) => setSource(v as Source)}>
+            <SelectTrigger className="w-[180px]">
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value="folder">From a folder</SelectItem>
+              <SelectItem value="search">Matching a search</SelectItem>
+              <SelectItem value="date_range">In a date range</SelectItem>
+            </SelectContent>
+          </Select>
+        </div>
+
+        {source === "folder" && (
+          <Select value={folderId} onValueChange={setFolderId}>
+            <SelectTrigger className="w-full">
+              <SelectValue placeholder="Choose a folder" />
+            </SelectTrigger>
+            <SelectContent>
+              {accounts.flatMap((account) =>
+                (accountFolders[account.data.id!] || []).map((folder) => (
+                  <SelectItem key={folder.id} value={folder.id.toString()}>
+                    {account.data.email} / {folder.name}
+                  </SelectItem>
+                ))
+              )}
+            </SelectContent>
+          </Select>
+        )}
+
+        {source === "search" && (
+          <Input
+            placeholder="Search text"
+            value={queryText}
+            onChange={(e) => setQueryText(e.target.value)}
+          />
+        )}
+
+        {source === "date_range" && (
+          <div className="grid grid-cols-2 gap-4">
+            <div className="space-y-2">
+              <Label>From</Label>
+              <Input type="date" value={since} onChange={(e) => setSince(e.target.value)} />
+            </div>
+            <div className="space-y-2">
+              <Label>Until</Label>
+              <Input type="date" value={until} onChange={(e) => setUntil(e.target.value)} />
+            </div>
+          </div>
+        )}
+
+        {isExporting && progress && progress.total > 0 && (
+          <div className="space-y-1">
+            <Progress value={(progress.done / progress.total) * 100} />
+            <p className="text-sm text-muted-foreground">
+              {progress.done} of {progress.total} messages
+            </p>
+          </div>
+        )}
+
+        <div className="flex justify-end">
+          <Button onClick={handleExport} disabled={isExporting || !selection()}>
+            {isExporting ? (
+              <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+            ) : (
+              <Download className="mr-2 h-4 w-4" />
+            )}
+            Export to mbox
+          </Button>
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
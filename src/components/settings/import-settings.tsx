@@ -0,0 +1,204 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { open } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { FileUp, FolderOpen, Loader2, Upload } from "lucide-react";
+import { useEmailStore } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import { Progress } from "@/components/ui/progress";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type Destination = "folder" | "local_folder";
+
+type ImportProgress = {
+  source_path: string;
+  done: number;
+  total: number;
+  imported: number;
+};
+
+type ImportSummary = {
+  total: number;
+  imported: number;
+  skipped: number;
+};
+
+export function ImportSettings() {
+  const accounts = useEmailStore((state) => state.accounts);
+  const accountFolders = useEmailStore((state) => state.accountFolders);
+  const [sourcePath, setSourcePath] = useState<string | null>(null);
+  const [destination, setDestination] = useState<Destination>("folder");
+  const [folderId, setFolderId] = useState<string>("");
+  const [accountId, setAccountId] = useState<string>("");
+  const [localName, setLocalName] = useState("");
+  const [progress, setProgress] = useState<ImportProgress | null>(null);
+  const [isImporting, setIsImporting] = useState(false);
+
+  useEffect(() => {
+    const unlistenPromise = listen<ImportProgress>("import-progress", (event) => {
+      setProgress(event.payload);
+    });
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  const pickSource = async (directory: boolean) => {
+    const path = await open({ directory, multiple: false });
+    if (typeof path === "string") setSourcePath(path);
+  };
+
+  const target = () => {
+    switch (destination) {
+      case "folder":
+        return folderId ? { type: "folder", folder_id: parseInt(folderId) } : null;
+      case "local_folder":
+        return accountId && localName.trim()
+          ? { type: "local_folder", account_id: parseInt(accountId), name: localName.trim() }
+          : null;
+    }
+  };
+
+  const handleImport = async () => {
+    const selected = target();
+    if (!sourcePath || !selected) return;
+
+    setIsImporting(true);
+    setProgress(null);
+    try {
+      const summary = await invoke<ImportSummary>("import_messages", {
+        sourcePath,
+        target: selected,
+      });
+      toast.success(
+        summary.skipped > 0
+          ? `Imported ${summary.imported} messages, ${summary.skipped} were already there`
+          : `Imported ${summary.imported} messages`
+      );
+    } catch (err) {
+      console.error("Import failed:", err);
+      toast.error(typeof err === "string" ? err : "Import failed");
+    } finally {
+      setIsImporting(false);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Upload className="h-5 w-5" /> Import
+        </CardTitle>
+        <CardDescription>
+          Bring in messages from an mbox file, a Maildir or a folder of .eml files.
+          An interrupted import picks up where it stopped.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="flex items-center justify-between gap-2">
+          <p className="truncate text-sm text-muted-foreground">
+            {sourcePath ?? "No source chosen"}
+          </p>
+          <div className="flex shrink-0 gap-2">
+            <Button variant="outline" size="sm" onClick={() => pickSource(false)}>
+              <FileUp className="mr-2 h-4 w-4" /> File
+            </Button>
+            <Button variant="outline" size="sm" onClick={() => pickSource(true)}>
+              <FolderOpen className="mr-2 h-4 w-4" /> Directory
+            </Button>
+          </div>
+        </div>
+
+        <div className="flex items-center justify-between">
+          <Label>Import into</Label>
+          <Select value={destination} onValueChange={(v) => setDestination(v as Destination)}>
+            <SelectTrigger className="w-[180px]">
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value="folder">A server folder</SelectItem>
+              <SelectItem value="local_folder">A local folder</SelectItem>
+            </SelectContent>
+          </Select>
+        </div>
+
+        {destination === "folder" && (
+          <Select value={folderId} onValueChange={setFolderId}>
+            <SelectTrigger className="w-full">
+              <SelectValue placeholder="Choose a folder" />
+            </SelectTrigger>
+            <SelectContent>
+              {accounts.flatMap((account) =>
+                (accountFolders[account.data.id!] || [])
+                  .filter((folder) => !folder.is_local)
+                  .map((folder) => (
+                    <SelectItem key={folder.id} value={folder.id.toString()}>
+                      {account.data.email} / {folder.name}
+                    </SelectItem>
+                  ))
+              )}
+            </SelectContent>
+          </Select>
+        )}
+
+        {destination === "local_folder" && (
+          <div className="grid grid-cols-2 gap-4">
+            <Select value={accountId} onValueChange={setAccountId}>
+              <SelectTrigger className="w-full">
+                <SelectValue placeholder="Choose an account" />
+              </SelectTrigger>
+              <SelectContent>
+                {accounts.map((account) => (
+                  <SelectItem key={account.data.id} value={account.data.id!.toString()}>
+                    {account.data.email}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Input
+              placeholder="Folder name"
+              value={localName}
+              onChange={(e) => setLocalName(e.target.value)}
+            />
+          </div>
+        )}
+
+        {isImporting && progress && progress.total > 0 && (
+          <div className="space-y-1">
+            <Progress value={(progress.done / progress.total) * 100} />
+            <p className="text-sm text-muted-foreground">
+              {progress.done} of {progress.total} messages, {progress.imported} imported
+            </p>
+          </div>
+        )}
+
+        <div className="flex justify-end">
+          <Button onClick={handleImport} disabled={isImporting || !sourcePath || !target()}>
+            {isImporting ? (
+              <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+            ) : (
+              <Upload className="mr-2 h-4 w-4" />
+            )}
+            Import
+          </Button>
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
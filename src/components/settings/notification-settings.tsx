@@ -0,0 +1,178 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { useSettingsStore } from "@/lib/settings-store";
+import { useEmailStore, Folder } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Switch } from "@/components/ui/switch";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type AccountNotifications = { account_id: number; enabled: boolean };
+
+const HOURS = Array.from({ length: 24 }, (_, hour) => hour);
+
+function HourSelect({ value, onChange }: { value: number; onChange: (hour: number) => void }) {
+  return (
+    <Select value={value.toString()} onValueChange={(v) => onChange(parseInt(v))}>
+      <SelectTrigger className="w-[100px]">
+        <SelectValue />
+      </SelectTrigger>
+      <SelectContent>
+        {HOURS.map((hour) => (
+          <SelectItem key={hour} value={hour.toString()}>
+            {`${hour.toString().padStart(2, "0")}:00`}
+          </SelectItem>
+        ))}
+      </SelectContent>
+    </Select>
+  );
+}
+
+export function NotificationSettings() {
+  const settings = useSettingsStore((state) => state.settings);
+  const updateSetting = useSettingsStore((state) => state.updateSetting);
+  const accounts = useEmailStore((state) => state.accounts);
+  const [accountSwitches, setAccountSwitches] = useState<Record<number, boolean>>({});
+  const [folders, setFolders] = useState<Record<number, Folder[]>>({});
+
+  useEffect(() => {
+    invoke<AccountNotifications[]>("get_account_notifications")
+      .then((rows) => setAccountSwitches(Object.fromEntries(rows.map((r) => [r.account_id, r.enabled]))))
+      .catch((err) => console.error("Failed to load account notifications:", err));
+
+    Promise.all(
+      accounts
+        .filter((account) => account.data.id)
+        .map(async (account) => [account.data.id!, await invoke<Folder[]>("get_folders", { account_id: account.data.id })] as const),
+    )
+      .then((entries) => setFolders(Object.fromEntries(entries)))
+      .catch((err) => console.error("Failed to load folders:", err));
+  }, [accounts]);
+
+  const toggleAccount = async (accountId: number, enabled: boolean) => {
+    setAccountSwitches((current) => ({ ...current, [accountId]: enabled }));
+    try {
+      await invoke("set_account_notifications", { accountId, enabled });
+    } catch (err) {
+      console.error("Failed to update account notifications:", err);
+    }
+  };
+
+  const toggleFolder = async (accountId: number, folderId: number, enabled: boolean) => {
+    setFolders((current) => ({
+      ...current,
+      [accountId]: current[accountId].map((f) => (f.id === folderId ? { ...f, notifications_enabled: enabled } : f)),
+    }));
+    try {
+      await invoke("set_folder_notifications", { folderId, enabled });
+    } catch (err) {
+      console.error("Failed to update folder notifications:", err);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle>Notifications</CardTitle>
+        <CardDescription>
+          Manage how you receive notifications.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="flex items-center justify-between">
+          <div className="space-y-0.5">
+            <Label>Desktop Notifications</Label>
+            <p className="text-sm text-muted-foreground">
+              Show notifications for new emails.
+            </p>
+          </div>
+          <Switch
+            checked={settings.notificationsEnabled}
+            onCheckedChange={(checked) =>
+              updateSetting("notificationsEnabled", checked)
+            }
+          />
+        </div>
+
+        {settings.notificationsEnabled && (
+          <>
+            <div className="flex items-center justify-between">
+              <div className="space-y-0.5">
+                <Label>Contacts only</Label>
+                <p className="text-sm text-muted-foreground">
+                  Only notify for mail from people in your contacts.
+                </p>
+              </div>
+              <Switch
+                checked={settings.notifyVipsOnly}
+                onCheckedChange={(checked) => updateSetting("notifyVipsOnly", checked)}
+              />
+            </div>
+
+            <div className="flex items-center justify-between">
+              <Label>Quiet hours</Label>
+              <Switch
+                checked={settings.quietHoursEnabled}
+                onCheckedChange={(checked) => updateSetting("quietHoursEnabled", checked)}
+              />
+            </div>
+            {settings.quietHoursEnabled && (
+              <>
+                <div className="flex items-center justify-end gap-2 text-sm text-muted-foreground">
+                  <span>From</span>
+                  <HourSelect value={settings.quietHoursStart} onChange={(hour) => updateSetting("quietHoursStart", hour)} />
+                  <span>to</span>
+                  <HourSelect value={settings.quietHoursEnd} onChange={(hour) => updateSetting("quietHoursEnd", hour)} />
+                </div>
+                <div className="flex items-center justify-between">
+                  <Label>Let contacts through during quiet hours</Label>
+                  <Switch
+                    checked={settings.quietHoursAllowVips}
+                    onCheckedChange={(checked) => updateSetting("quietHoursAllowVips", checked)}
+                  />
+                </div>
+              </>
+            )}
+
+            {accounts.filter((account) => account.data.id).map((account) => {
+              const accountId = account.data.id!;
+              const accountOn = accountSwitches[accountId] ?? true;
+              return (
+                <div key={accountId} className="space-y-2 rounded-lg border p-3">
+                  <div className="flex items-center justify-between">
+                    <Label>{account.data.email}</Label>
+                    <Switch checked={accountOn} onCheckedChange={(checked) => toggleAccount(accountId, checked)} />
+                  </div>
+                  {accountOn &&
+                    (folders[accountId] || [])
+                      .filter((folder) => !folder.is_local && !["sent", "drafts", "trash", "spam"].includes(folder.role || ""))
+                      .map((folder) => (
+                        <div key={folder.id} className="flex items-center justify-between pl-4 text-sm">
+                          <span className="text-muted-foreground">{folder.name}</span>
+                          <Switch
+                            checked={folder.notifications_enabled ?? true}
+                            onCheckedChange={(checked) => toggleFolder(accountId, folder.id, checked)}
+                          />
+                        </div>
+                      ))}
+                </div>
+              );
+            })}
+          </>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
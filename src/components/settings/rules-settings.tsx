@@ -0,0 +1,411 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
+import { Filter, Loader2, Play, Plus, Trash2 } from "lucide-react";
+import { useEmailStore } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import { Switch } from "@/components/ui/switch";
+import { Checkbox } from "@/components/ui/checkbox";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type TextOp = "contains" | "equals" | "starts_with" | "ends_with";
+
+export type RuleCondition =
+  | { type: "sender" | "recipient" | "subject" | "list_id"; op: TextOp; value: string }
+  | { type: "header"; name: string; op: TextOp; value: string }
+  | { type: "has_attachment"; value: boolean }
+  | { type: "ai_category"; category: "personal" | "automated" };
+
+export type RuleAction =
+  | { type: "move"; folder_id: number }
+  | { type: "label"; label: string }
+  | { type: "mark_read" }
+  | { type: "star" }
+  | { type: "skip_notification" }
+  | { type: "forward"; to: string };
+
+export type Rule = {
+  id: number | null;
+  account_id: number | null;
+  name: string;
+  enabled: boolean;
+  match_all: boolean;
+  conditions: RuleCondition[];
+  actions: RuleAction[];
+  stop_processing: boolean;
+  position: number;
+};
+
+const CONDITION_LABELS: Record<RuleCondition["type"], string> = {
+  sender: "Sender",
+  recipient: "Recipient",
+  subject: "Subject",
+  header: "Header",
+  list_id: "List-Id",
+  has_attachment: "Has attachment",
+  ai_category: "Sender category",
+};
+
+const describeCondition = (condition: RuleCondition) => {
+  switch (condition.type) {
+    case "has_attachment":
+      return condition.value ? "has an attachment" : "has no attachment";
+    case "ai_category":
+      return `sender is ${condition.category}`;
+    case "header":
+      return `${condition.name} ${condition.op.replace("_", " ")} "${condition.value}"`;
+    default:
+      return `${CONDITION_LABELS[condition.type].toLowerCase()} ${condition.op.replace("_", " ")} "${condition.value}"`;
+  }
+};
+
+const emptyCondition = (): RuleCondition => ({ type: "sender", op: "contains", value: "" });
+
+export function RulesSettings() {
+  const accounts = useEmailStore((state) => state.accounts);
+  const accountFolders = useEmailStore((state) => state.accountFolders);
+  const [rules, setRules] = useState<Rule[]>([]);
+  const [name, setName] = useState("");
+  const [condition, setCondition] = useState<RuleCondition>(emptyCondition());
+  const [moveTo, setMoveTo] = useState("");
+  const [label, setLabel] = useState("");
+  const [forwardTo, setForwardTo] = useState("");
+  const [markRead, setMarkRead] = useState(false);
+  const [star, setStar] = useState(false);
+  const [skipNotification, setSkipNotification] = useState(false);
+  const [applyingId, setApplyingId] = useState<number | null>(null);
+
+  const loadRules = async () => {
+    try {
+      setRules(await invoke<Rule[]>("get_rules"));
+    } catch (err) {
+      console.error("Failed to load rules:", err);
+    }
+  };
+
+  useEffect(() => {
+    loadRules();
+  }, []);
+
+  const folderName = (folderId: number) => {
+    for (const account of accounts) {
+      const folder = (accountFolders[account.data.id!] || []).find((f) => f.id === folderId);
+      if (folder) return `${account.data.email} / ${folder.name}`;
+    }
+    return `folder ${folderId}`;
+  };
+
+  const describeAction = (action: RuleAction) => {
+    switch (action.type) {
+      case "move":
+        return `move to ${folderName(action.folder_id)}`;
+      case "label":
+        return `label "${action.label}"`;
+      case "mark_read":
+        return "mark read";
+      case "star":
+        return "star";
+      case "skip_notification":
+        return "no notification";
+      case "forward":
+        return `forward to ${action.to}`;
+    }
+  };
+
+  const draftActions = (): RuleAction[] => {
+    const actions: RuleAction[] = [];
+    if (label.trim()) actions.push({ type: "label", label: label.trim() });
+    if (markRead) actions.push({ type: "mark_read" });
+    if (star) actions.push({ type: "star" });
+    if (skipNotification) actions.push({ type: "skip_notification" });
+    if (forwardTo.trim()) actions.push({ type: "forward", to: forwardTo.trim() });
+    if (moveTo) actions.push({ type: "move", folder_id: parseInt(moveTo) });
+    return actions;
+  };
+
+  // Moves only stay within an account, so a rule with a move is scoped to that account
+  const draftAccountId = () => {
+    if (!moveTo) return null;
+    const folderId = parseInt(moveTo);
+    const account = accounts.find((a) =>
+      (accountFolders[a.data.id!] || []).some((f) => f.id === folderId)
+    );
+    return account?.data.id ?? null;
+  };
+
+  const saveRule = async (rule: Rule) => {
+    try {
+      await invoke("save_rule", { rule });
+      await loadRules();
+      return true;
+    } catch (err) {
+      console.error("Failed to save rule:", err);
+      toast.error(typeof err === "string" ? err : "Failed to save rule");
+      return false;
+    }
+  };
+
+  const handleAdd = async () => {
+    const saved = await saveRule({
+      id: null,
+      account_id: draftAccountId(),
+      name,
+      enabled: true,
+      match_all: true,
+      conditions: [condition],
+      actions: draftActions(),
+      stop_processing: false,
+      position: 0,
+    });
+    if (!saved) return;
+
+    setName("");
+    setCondition(emptyCondition());
+    setMoveTo("");
+    setLabel("");
+    setForwardTo("");
+    setMarkRead(false);
+    setStar(false);
+    setSkipNotification(false);
+  };
+
+  const handleDelete = async (id: number) => {
+    try {
+      await invoke("delete_rule", { id });
+      await loadRules();
+    } catch (err) {
+      console.error("Failed to delete rule:", err);
+      toast.error("Failed to delete rule");
+    }
+  };
+
+  const handleApply = async (id: number) => {
+    setApplyingId(id);
+    try {
+      const count = await invoke<number>("apply_rule_to_existing", { ruleId: id, folderId: null });
+      toast.success(`Rule matched ${count} messages`);
+    } catch (err) {
+      console.error("Failed to apply rule:", err);
+      toast.error(typeof err === "string" ? err : "Failed to apply rule");
+    } finally {
+      setApplyingId(null);
+    }
+  };
+
+  const conditionHasText = condition.type !== "has_attachment" && condition.type !== "ai_category";
+  const canAdd =
+    name.trim() !== "" &&
+    draftActions().length > 0 &&
+    (!conditionHasText || ("value" in condition && String(condition.value).trim() !== ""));
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Filter className="h-5 w-5" /> Rules
+        </CardTitle>
+        <CardDescription>
+          Sort new mail as it arrives. Rules run in order while the app is syncing.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        {rules.map((rule) => (
+          <div key={rule.id} className="flex items-center justify-between gap-4 rounded-md border p-3">
+            <div className="min-w-0">
+              <div className="font-medium">{rule.name}</div>
+              <p className="text-sm text-muted-foreground truncate">
+                If {rule.conditions.map(describeCondition).join(rule.match_all ? " and " : " or ") || "any message"}
+                : {rule.actions.map(describeAction).join(", ")}
+              </p>
+            </div>
+            <div className="flex items-center gap-2">
+              <Switch
+                checked={rule.enabled}
+                onCheckedChange={(enabled) => saveRule({ ...rule, enabled })}
+              />
+              <Button
+                variant="ghost"
+                size="icon"
+                title="Apply to the inbox"
+                disabled={applyingId !== null}
+                onClick={() => handleApply(rule.id!)}
+              >
+                {applyingId === rule.id ? (
+                  <Loader2 className="h-4 w-4 animate-spin" />
+                ) : (
+                  <Play className="h-4 w-4" />
+                )}
+              </Button>
+              <Button
+                variant="ghost"
+                size="icon"
+                className="text-destructive"
+                onClick={() => handleDelete(rule.id!)}
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          </div>
+        ))}
+
+        <div className="space-y-3 rounded-md border p-3">
+          <Input placeholder="Rule name" value={name} onChange={(e) => setName(e.target.value)} />
+
+          <div className="flex gap-2">
+            <Select
+              value={condition.type}
+              onValueChange={(type) => {
+                const kind = type as RuleCondition["type"];
+                if (kind === "has_attachment") setCondition({ type: kind, value: true });
+                else if (kind === "ai_category") setCondition({ type: kind, category: "automated" });
+                else if (kind === "header") setCondition({ type: kind, name: "", op: "contains", value: "" });
+                else setCondition({ type: kind, op: "contains", value: "" });
+              }}
+            >
+              <SelectTrigger className="w-[160px]">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {Object.entries(CONDITION_LABELS).map(([value, text]) => (
+                  <SelectItem key={value} value={value}>
+                    {text}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+
+            {condition.type === "header" && (
+              <Input
+                className="w-[140px]"
+                placeholder="Header name"
+                value={condition.name}
+                onChange={(e) => setCondition({ ...condition, name: e.target.value })}
+              />
+            )}
+
+            {"op" in condition && (
+              <>
+                <Select
+                  value={condition.op}
+                  onValueChange={(op) => setCondition({ ...condition, op: op as TextOp })}
+                >
+                  <SelectTrigger className="w-[140px]">
+                    <SelectValue />
+                  </SelectTrigger>
+                  <SelectContent>
+                    <SelectItem value="contains">contains</SelectItem>
+                    <SelectItem value="equals">equals</SelectItem>
+                    <SelectItem value="starts_with">starts with</SelectItem>
+                    <SelectItem value="ends_with">ends with</SelectItem>
+                  </SelectContent>
+                </Select>
+                <Input
+                  placeholder="Value"
+                  value={condition.value}
+                  onChange={(e) => setCondition({ ...condition, value: e.target.value })}
+                />
+              </>
+            )}
+
+            {condition.type === "has_attachment" && (
+              <Select
+                value={condition.value ? "yes" : "no"}
+                onValueChange={(v) => setCondition({ type: "has_attachment", value: v === "yes" })}
+              >
+                <SelectTrigger className="w-[140px]">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value="yes">yes</SelectItem>
+                  <SelectItem value="no">no</SelectItem>
+                </SelectContent>
+              </Select>
+            )}
+
+            {condition.type === "ai_category" && (
+              <Select
+                value={condition.category}
+                onValueChange={(v) =>
+                  setCondition({ type: "ai_category", category: v as "personal" | "automated" })
+                }
+              >
+                <SelectTrigger className="w-[140px]">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value="automated">automated</SelectItem>
+                  <SelectItem value="personal">personal</SelectItem>
+                </SelectContent>
+              </Select>
+            )}
+          </div>
+
+          <Select value={moveTo} onValueChange={setMoveTo}>
+            <SelectTrigger className="w-full">
+              <SelectValue placeholder="Move to folder (optional)" />
+            </SelectTrigger>
+            <SelectContent>
+              {accounts.flatMap((account) =>
+                (accountFolders[account.data.id!] || [])
+                  .filter((folder) => !folder.is_local)
+                  .map((folder) => (
+                    <SelectItem key={folder.id} value={folder.id.toString()}>
+                      {account.data.email} / {folder.name}
+                    </SelectItem>
+                  ))
+              )}
+            </SelectContent>
+          </Select>
+
+          <div className="grid grid-cols-2 gap-2">
+            <Input placeholder="Add label" value={label} onChange={(e) => setLabel(e.target.value)} />
+            <Input
+              placeholder="Forward to"
+              value={forwardTo}
+              onChange={(e) => setForwardTo(e.target.value)}
+            />
+          </div>
+
+          <div className="flex flex-wrap gap-4">
+            <Label className="flex items-center gap-2 font-normal">
+              <Checkbox checked={markRead} onCheckedChange={(c) => setMarkRead(c === true)} />
+              Mark read
+            </Label>
+            <Label className="flex items-center gap-2 font-normal">
+              <Checkbox checked={star} onCheckedChange={(c) => setStar(c === true)} />
+              Star
+            </Label>
+            <Label className="flex items-center gap-2 font-normal">
+              <Checkbox
+                checked={skipNotification}
+                onCheckedChange={(c) => setSkipNotification(c === true)}
+              />
+              No notification
+            </Label>
+          </div>
+
+          <div className="flex justify-end">
+            <Button onClick={handleAdd} disabled={!canAdd}>
+              <Plus className="mr-2 h-4 w-4" /> Add rule
+            </Button>
+          </div>
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
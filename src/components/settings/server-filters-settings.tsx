@@ -0,0 +1,200 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
+import { Loader2, Server, Upload } from "lucide-react";
+import { useEmailStore } from "@/lib/store";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import { Switch } from "@/components/ui/switch";
+import { Textarea } from "@/components/ui/textarea";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type SieveScript = { name: string; active: boolean };
+type ScriptCheck = { valid: boolean; message: string | null };
+
+const SCRIPT_NAME = "dueam";
+
+export function ServerFiltersSettings() {
+  const accounts = useEmailStore((state) => state.accounts).filter((a) => a.type === "imap_smtp");
+  const [accountId, setAccountId] = useState("");
+  const [scripts, setScripts] = useState<SieveScript[] | null>(null);
+  const [vacationOn, setVacationOn] = useState(false);
+  const [subject, setSubject] = useState("Out of office");
+  const [body, setBody] = useState("");
+  const [days, setDays] = useState("7");
+  const [startDate, setStartDate] = useState("");
+  const [endDate, setEndDate] = useState("");
+  const [script, setScript] = useState("");
+  const [busy, setBusy] = useState(false);
+
+  const selectAccount = async (value: string) => {
+    setAccountId(value);
+    setScripts(null);
+    setScript("");
+    try {
+      setScripts(await invoke<SieveScript[]>("list_sieve_scripts", { accountId: parseInt(value) }));
+    } catch (err) {
+      console.error("Failed to list Sieve scripts:", err);
+      toast.error(typeof err === "string" ? err : "Server filters are not available");
+    }
+  };
+
+  const handleGenerate = async () => {
+    const vacation = vacationOn
+      ? {
+          subject,
+          body,
+          days: parseInt(days) || null,
+          addresses: [],
+          start_date: startDate || null,
+          end_date: endDate || null,
+        }
+      : null;
+    try {
+      setScript(await invoke<string>("generate_sieve_script", { accountId: parseInt(accountId), vacation }));
+    } catch (err) {
+      console.error("Failed to generate Sieve script:", err);
+      toast.error("Failed to generate the script");
+    }
+  };
+
+  const handleUpload = async () => {
+    setBusy(true);
+    try {
+      const check = await invoke<ScriptCheck>("check_sieve_script", { accountId: parseInt(accountId), content: script });
+      if (!check.valid) {
+        toast.error(check.message || "The server rejected the script");
+        return;
+      }
+      await invoke("put_sieve_script", { accountId: parseInt(accountId), name: SCRIPT_NAME, content: script, activate: true });
+      setScripts(await invoke<SieveScript[]>("list_sieve_scripts", { accountId: parseInt(accountId) }));
+      toast.success("Server filters updated");
+    } catch (err) {
+      console.error("Failed to upload Sieve script:", err);
+      toast.error(typeof err === "string" ? err : "Failed to upload the script");
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const handleDeactivate = async () => {
+    try {
+      await invoke("activate_sieve_script", { accountId: parseInt(accountId), name: null });
+      setScripts(await invoke<SieveScript[]>("list_sieve_scripts", { accountId: parseInt(accountId) }));
+    } catch (err) {
+      console.error("Failed to deactivate Sieve script:", err);
+      toast.error("Failed to switch server filters off");
+    }
+  };
+
+  if (accounts.length === 0) return null;
+
+  const active = scripts?.find((s) => s.active);
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Server className="h-5 w-5" /> Server filters
+        </CardTitle>
+        <CardDescription>
+          Run your rules and an away reply on the mail server with Sieve, even while Dueam is closed.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="space-y-2">
+          <Label>Account</Label>
+          <Select value={accountId} onValueChange={selectAccount}>
+            <SelectTrigger>
+              <SelectValue placeholder="Choose an account" />
+            </SelectTrigger>
+            <SelectContent>
+              {accounts.map((account) => (
+                <SelectItem key={account.data.id} value={String(account.data.id)}>
+                  {account.data.email}
+                </SelectItem>
+              ))}
+            </SelectContent>
+          </Select>
+          {scripts && (
+            <div className="flex items-center justify-between text-sm text-muted-foreground">
+              <span>{active ? `Active script: ${active.name}` : "No script is active"}</span>
+              {active && (
+                <Button variant="ghost" size="sm" onClick={handleDeactivate}>
+                  Switch off
+                </Button>
+              )}
+            </div>
+          )}
+        </div>
+
+        {scripts && (
+          <>
+            <div className="flex items-center justify-between">
+              <Label htmlFor="vacation">Away reply</Label>
+              <Switch id="vacation" checked={vacationOn} onCheckedChange={setVacationOn} />
+            </div>
+            {vacationOn && (
+              <div className="space-y-2">
+                <Input placeholder="Subject" value={subject} onChange={(e) => setSubject(e.target.value)} />
+                <Textarea placeholder="Message" value={body} onChange={(e) => setBody(e.target.value)} />
+                <div className="grid grid-cols-3 gap-2">
+                  <Input type="date" value={startDate} onChange={(e) => setStartDate(e.target.value)} />
+                  <Input type="date" value={endDate} onChange={(e) => setEndDate(e.target.value)} />
+                  <Input
+                    type="number"
+                    min={1}
+                    placeholder="Days between replies"
+                    value={days}
+                    onChange={(e) => setDays(e.target.value)}
+                  />
+                </div>
+              </div>
+            )}
+
+            <div className="flex justify-end">
+              <Button variant="outline" onClick={handleGenerate}>
+                Generate from rules
+              </Button>
+            </div>
+
+            {script && (
+              <>
+                <Textarea
+                  className="font-mono text-xs"
+                  rows={12}
+                  value={script}
+                  onChange={(e) => setScript(e.target.value)}
+                />
+                <div className="flex justify-end">
+                  <Button onClick={handleUpload} disabled={busy}>
+                    {busy ? (
+                      <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+                    ) : (
+                      <Upload className="mr-2 h-4 w-4" />
+                    )}
+                    Upload and activate
+                  </Button>
+                </div>
+              </>
+            )}
+          </>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,161 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { formatDistanceToNow } from "date-fns";
+import { toast } from "sonner";
+import { Archive, Newspaper } from "lucide-react";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Button } from "@/components/ui/button";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+import { Switch } from "@/components/ui/switch";
+
+type Subscription = {
+  account_id: number;
+  key: string;
+  list_id: string | null;
+  sender_name: string | null;
+  sender_address: string;
+  total: number;
+  per_week: number;
+  last_received: string;
+  read_rate: number;
+  unsubscribe_status: string | null;
+  auto_archive: boolean | null;
+  mode: string | null;
+};
+
+const INBOX = "inbox";
+
+export function SubscriptionsSettings() {
+  const [subscriptions, setSubscriptions] = useState<Subscription[]>([]);
+
+  const load = async () => {
+    try {
+      setSubscriptions(await invoke<Subscription[]>("get_subscriptions", { accountId: null }));
+    } catch (err) {
+      console.error("Failed to load subscriptions:", err);
+    }
+  };
+
+  useEffect(() => {
+    load();
+  }, []);
+
+  const handleMode = async (subscription: Subscription, mode: string) => {
+    try {
+      await invoke("set_subscription_mode", {
+        accountId: subscription.account_id,
+        key: subscription.key,
+        mode: mode === INBOX ? null : mode,
+      });
+      await load();
+    } catch (err) {
+      console.error("Failed to change subscription mode:", err);
+      toast.error("Failed to update the subscription");
+    }
+  };
+
+  const handleAutoArchive = async (subscription: Subscription, enabled: boolean) => {
+    try {
+      await invoke("set_unsubscribe_auto_archive", {
+        accountId: subscription.account_id,
+        key: subscription.key,
+        enabled,
+      });
+      await load();
+    } catch (err) {
+      console.error("Failed to change auto-archive:", err);
+      toast.error("Failed to update the subscription");
+    }
+  };
+
+  const handleArchive = async (subscription: Subscription) => {
+    try {
+      const count = await invoke<number>("archive_subscription", {
+        accountId: subscription.account_id,
+        key: subscription.key,
+      });
+      toast.success(`Archived ${count} message${count === 1 ? "" : "s"}`);
+    } catch (err) {
+      console.error("Failed to archive subscription:", err);
+      toast.error(typeof err === "string" ? err : "Failed to archive");
+    }
+  };
+
+  if (subscriptions.length === 0) return null;
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <Newspaper className="h-5 w-5" /> Subscriptions
+        </CardTitle>
+        <CardDescription>
+          Newsletters, mailing lists and automated senders. Mute them, collect them in the daily digest or clear them out.
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-2">
+        {subscriptions.map((subscription) => (
+          <div
+            key={`${subscription.account_id}-${subscription.key}`}
+            className="flex items-center justify-between gap-4 rounded-lg border p-3"
+          >
+            <div className="min-w-0">
+              <p className="font-medium truncate">
+                {subscription.sender_name || subscription.sender_address}
+              </p>
+              <p className="text-xs text-muted-foreground">
+                {subscription.per_week.toFixed(1)} a week · {Math.round(subscription.read_rate * 100)}% read · last{" "}
+                {formatDistanceToNow(new Date(subscription.last_received), { addSuffix: true })}
+                {subscription.unsubscribe_status && " · unsubscribed"}
+              </p>
+            </div>
+            <div className="flex items-center gap-2 shrink-0">
+              {subscription.unsubscribe_status && (
+                <label className="flex items-center gap-2 text-xs text-muted-foreground">
+                  Auto-archive
+                  <Switch
+                    checked={!!subscription.auto_archive}
+                    onCheckedChange={(checked) => handleAutoArchive(subscription, checked)}
+                  />
+                </label>
+              )}
+              <Select
+                value={subscription.mode || INBOX}
+                onValueChange={(mode) => handleMode(subscription, mode)}
+              >
+                <SelectTrigger className="w-[130px]">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value={INBOX}>Inbox</SelectItem>
+                  <SelectItem value="muted">Muted</SelectItem>
+                  <SelectItem value="digest">Digest</SelectItem>
+                </SelectContent>
+              </Select>
+              <Button
+                variant="ghost"
+                size="icon"
+                aria-label="Archive all"
+                onClick={() => handleArchive(subscription)}
+              >
+                <Archive className="h-4 w-4" />
+              </Button>
+            </div>
+          </div>
+        ))}
+      </CardContent>
+    </Card>
+  );
+}
@@ -39,7 +39,7 @@ export function useEmails(params: EmailSearchParams) {
     getNextPageParam: (lastPage) => {
       if (lastPage.length < PAGE_SIZE) return undefined;
       const lastEmail = lastPage[lastPage.length - 1];
-      return { date: lastEmail.date, id: lastEmail.id };
+      return { date: lastEmail.sort_date || lastEmail.date, id: lastEmail.id };
     },
   });
 }
\ No newline at end of file
@@ -2,6 +2,13 @@ import { useEffect } from "react";
 import { listen } from "@tauri-apps/api/event";
 import { useQueryClient } from "@tanstack/react-query";
 import { useEmailStore } from "@/lib/store";
+import { toast } from "sonner";
+
+interface ReauthRequiredEvent {
+  accountId: number;
+  email: string;
+  reason: string;
+}
 
 export function useGlobalEvents() {
   const queryClient = useQueryClient();
@@ -27,9 +34,17 @@ export function useGlobalEvents() {
       queryClient.invalidateQueries({ queryKey: ["sender", address] });
     });
 
+    const unlistenReauth = listen<ReauthRequiredEvent>("account-needs-reauth", (event) => {
+      toast.error(`Sign in to ${event.payload.email} again`, {
+        description: "Access to this account was revoked or has expired. Sign in again from Add Account to resume syncing.",
+        id: `reauth-${event.payload.accountId}`,
+      });
+    });
+
     return () => {
       unlistenEmails.then(u => u());
       unlistenSenders.then(u => u());
+      unlistenReauth.then(u => u());
       if (timeout) clearTimeout(timeout);
     };
   }, [queryClient, fetchAccountsAndFolders]);
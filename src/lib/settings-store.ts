@@ -24,6 +24,14 @@ export interface Settings {
   aiSummarizationEnabled: boolean;
   notificationsEnabled: boolean;
   syncMonths: number;
+  digestEnabled: boolean;
+  digestHour: number;
+  digestAutoArchive: boolean;
+  notifyVipsOnly: boolean;
+  quietHoursEnabled: boolean;
+  quietHoursStart: number;
+  quietHoursEnd: number;
+  quietHoursAllowVips: boolean;
 }
 
 interface SettingsState {
@@ -51,6 +59,14 @@ const defaultSettings: Settings = {
   aiSummarizationEnabled: false,
   notificationsEnabled: true,
   syncMonths: 3,
+  digestEnabled: false,
+  digestHour: 8,
+  digestAutoArchive: false,
+  notifyVipsOnly: false,
+  quietHoursEnabled: false,
+  quietHoursStart: 22,
+  quietHoursEnd: 7,
+  quietHoursAllowVips: true,
 };
 
 export const useSettingsStore = create<SettingsState>((set, get) => ({
@@ -25,6 +25,8 @@ export type Folder = {
   path: string;
   role?: string;
   unread_count: number;
+  is_local?: boolean;
+  notifications_enabled?: boolean;
 };
 
 export type Email = {
@@ -46,6 +48,7 @@ export type Email = {
   has_attachments: boolean;
   is_reply: boolean;
   is_forward: boolean;
+  sort_date?: string | null;
 };
 
 export type Sender = {
@@ -82,6 +85,29 @@ export type Domain = {
 export type EmailContent = {
   body_text: string | null;
   body_html: string | null;
+  list_unsubscribe: string | null;
+  invite: CalendarEvent | null;
+};
+
+export type CalendarEvent = {
+  id: number;
+  email_id: number;
+  uid: string;
+  sequence: number;
+  method: string;
+  status: string | null;
+  summary: string | null;
+  location: string | null;
+  description: string | null;
+  organizer: string | null;
+  organizer_name: string | null;
+  starts_at: string | null;
+  ends_at: string | null;
+  all_day: boolean;
+  timezone: string | null;
+  attendees: string;
+  change: "new" | "updated" | "outdated" | "cancelled" | "reply";
+  response: "accepted" | "tentative" | "declined" | null;
 };
 
 export type Attachment = {
@@ -126,9 +152,15 @@ interface EmailState {
 
   // Actions
   markAsRead: (ids: number[]) => Promise<void>;
+  setFlags: (ids: number[], flags: string[]) => Promise<void>;
+  removeFlags: (ids: number[], flags: string[]) => Promise<void>;
+  snoozeEmails: (ids: number[], until: Date) => Promise<void>;
+  remindIfNoReply: (ids: number[], by: Date) => Promise<void>;
+  muteThreads: (ids: number[]) => Promise<void>;
   moveToTrash: (ids: number[]) => Promise<void>;
   archiveEmails: (ids: number[]) => Promise<void>;
   moveToInbox: (ids: number[]) => Promise<void>;
+  keepLocally: (ids: number[]) => Promise<void>;
 
   // Composer
   composer: {
@@ -333,6 +365,50 @@ export const useEmailStore = create<EmailState>((set, get) => ({
     }
   },
 
+  setFlags: async (ids, flags) => {
+    try {
+      await invoke("set_flags", { emailIds: ids.filter((id) => id >= 0), flags });
+    } catch (error) {
+      console.error("Failed to set flags:", error);
+    }
+  },
+
+  removeFlags: async (ids, flags) => {
+    try {
+      await invoke("remove_flags", { emailIds: ids.filter((id) => id >= 0), flags });
+    } catch (error) {
+      console.error("Failed to remove flags:", error);
+    }
+  },
+
+  snoozeEmails: async (ids, until) => {
+    set({ selectedIds: new Set() });
+    try {
+      await invoke("snooze_emails", { emailIds: ids, until: until.toISOString() });
+    } catch (error) {
+      console.error("Failed to snooze:", error);
+    }
+  },
+
+  remindIfNoReply: async (ids, by) => {
+    try {
+      for (const id of ids.filter((id) => id >= 0)) {
+        await invoke("remind_if_no_reply", { emailId: id, by: by.toISOString() });
+      }
+    } catch (error) {
+      console.error("Failed to set reminder:", error);
+    }
+  },
+
+  muteThreads: async (ids) => {
+    set({ selectedIds: new Set() });
+    try {
+      await invoke("mute_threads", { emailIds: ids });
+    } catch (error) {
+      console.error("Failed to mute conversations:", error);
+    }
+  },
+
   moveToTrash: async (ids) => {
     set((state) => ({
       selectedIds: new Set(
@@ -414,6 +490,30 @@ export const useEmailStore = create<EmailState>((set, get) => ({
     }
   },
 
+  keepLocally: async (ids) => {
+    set((state) => ({
+      selectedIds: new Set(
+        Array.from(state.selectedIds).filter((id) => !ids.includes(id)),
+      ),
+      selectedEmailId:
+        state.selectedEmailId && ids.includes(state.selectedEmailId)
+          ? null
+          : state.selectedEmailId,
+    }));
+
+    try {
+      const emailIds = ids.filter(id => id >= 0);
+      if (emailIds.length > 0) {
+        await invoke("move_to_local_archive", { emailIds, folderName: null });
+      }
+
+      get().fetchUnifiedCounts();
+      get().fetchAccountsAndFolders();
+    } catch (error) {
+      console.error("Failed to move to local archive:", error);
+    }
+  },
+
   init: () => {
     get()
       .fetchAccountsAndFolders()
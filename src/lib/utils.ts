@@ -4,3 +4,26 @@ import { twMerge } from "tailwind-merge"
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+// Flags every backend sets, plus tags notmuch manages on its own
+const SYSTEM_FLAGS = new Set([
+  "seen", "answered", "flagged", "deleted", "draft",
+  "inbox", "unread", "attachment", "signed", "encrypted", "new", "replied",
+])
+
+// User labels among an email's flags, e.g. notmuch tags
+export function getLabels(flags: string): string[] {
+  try {
+    const parsed = JSON.parse(flags)
+    if (!Array.isArray(parsed)) return []
+    return parsed.filter(
+      (f): f is string =>
+        typeof f === "string" &&
+        !SYSTEM_FLAGS.has(f.toLowerCase()) &&
+        !f.startsWith("$") &&
+        !f.startsWith("\\")
+    )
+  } catch {
+    return []
+  }
+}
@@ -13,6 +13,9 @@ import { Route as SettingsRouteImport } from './routes/settings'
 import { Route as OnboardingRouteImport } from './routes/onboarding'
 import { Route as InboxRouteImport } from './routes/_inbox'
 import { Route as InboxIndexRouteImport } from './routes/_inbox/index'
+import { Route as AccountsNewOauth2RouteImport } from './routes/accounts/new-oauth2'
+import { Route as AccountsNewNotmuchRouteImport } from './routes/accounts/new-notmuch'
+import { Route as AccountsNewMaildirRouteImport } from './routes/accounts/new-maildir'
 import { Route as AccountsNewImapRouteImport } from './routes/accounts/new-imap'
 import { Route as AccountsNewRouteImport } from './routes/accounts/new'
 import { Route as InboxEmailEmailIdRouteImport } from './routes/_inbox/email.$emailId'
@@ -36,6 +39,21 @@ const InboxIndexRoute = InboxIndexRouteImport.update({
   path: '/',
   getParentRoute: () => InboxRoute,
 } as any)
+const AccountsNewOauth2Route = AccountsNewOauth2RouteImport.update({
+  id: '/accounts/new-oauth2',
+  path: '/accounts/new-oauth2',
+  getParentRoute: () => rootRouteImport,
+} as any)
+const AccountsNewNotmuchRoute = AccountsNewNotmuchRouteImport.update({
+  id: '/accounts/new-notmuch',
+  path: '/accounts/new-notmuch',
+  getParentRoute: () => rootRouteImport,
+} as any)
+const AccountsNewMaildirRoute = AccountsNewMaildirRouteImport.update({
+  id: '/accounts/new-maildir',
+  path: '/accounts/new-maildir',
+  getParentRoute: () => rootRouteImport,
+} as any)
 const AccountsNewImapRoute = AccountsNewImapRouteImport.update({
   id: '/accounts/new-imap',
   path: '/accounts/new-imap',
@@ -57,6 +75,9 @@ export interface FileRoutesByFullPath {
   '/settings': typeof SettingsRoute
   '/accounts/new': typeof AccountsNewRoute
   '/accounts/new-imap': typeof AccountsNewImapRoute
+  '/accounts/new-maildir': typeof AccountsNewMaildirRoute
+  '/accounts/new-notmuch': typeof AccountsNewNotmuchRoute
+  '/accounts/new-oauth2': typeof AccountsNewOauth2Route
   '/': typeof InboxIndexRoute
   '/email/$emailId': typeof InboxEmailEmailIdRoute
 }
@@ -65,6 +86,9 @@ export interface FileRoutesByTo {
   '/settings': typeof SettingsRoute
   '/accounts/new': typeof AccountsNewRoute
   '/accounts/new-imap': typeof AccountsNewImapRoute
+  '/accounts/new-maildir': typeof AccountsNewMaildirRoute
+  '/accounts/new-notmuch': typeof AccountsNewNotmuchRoute
+  '/accounts/new-oauth2': typeof AccountsNewOauth2Route
   '/': typeof InboxIndexRoute
   '/email/$emailId': typeof InboxEmailEmailIdRoute
 }
@@ -75,6 +99,9 @@ export interface FileRoutesById {
   '/settings': typeof SettingsRoute
   '/accounts/new': typeof AccountsNewRoute
   '/accounts/new-imap': typeof AccountsNewImapRoute
+  '/accounts/new-maildir': typeof AccountsNewMaildirRoute
+  '/accounts/new-notmuch': typeof AccountsNewNotmuchRoute
+  '/accounts/new-oauth2': typeof AccountsNewOauth2Route
   '/_inbox/': typeof InboxIndexRoute
   '/_inbox/email/$emailId': typeof InboxEmailEmailIdRoute
 }
@@ -85,6 +112,9 @@ export interface FileRouteTypes {
     | '/settings'
     | '/accounts/new'
     | '/accounts/new-imap'
+    | '/accounts/new-maildir'
+    | '/accounts/new-notmuch'
+    | '/accounts/new-oauth2'
     | '/'
     | '/email/$emailId'
   fileRoutesByTo: FileRoutesByTo
@@ -93,6 +123,9 @@ export interface FileRouteTypes {
     | '/settings'
     | '/accounts/new'
     | '/accounts/new-imap'
+    | '/accounts/new-maildir'
+    | '/accounts/new-notmuch'
+    | '/accounts/new-oauth2'
     | '/'
     | '/email/$emailId'
   id:
@@ -102,6 +135,9 @@ export interface FileRouteTypes {
     | '/settings'
     | '/accounts/new'
     | '/accounts/new-imap'
+    | '/accounts/new-maildir'
+    | '/accounts/new-notmuch'
+    | '/accounts/new-oauth2'
     | '/_inbox/'
     | '/_inbox/email/$emailId'
   fileRoutesById: FileRoutesById
@@ -112,6 +148,9 @@ export interface RootRouteChildren {
   SettingsRoute: typeof SettingsRoute
   AccountsNewRoute: typeof AccountsNewRoute
   AccountsNewImapRoute: typeof AccountsNewImapRoute
+  AccountsNewMaildirRoute: typeof AccountsNewMaildirRoute
+  AccountsNewNotmuchRoute: typeof AccountsNewNotmuchRoute
+  AccountsNewOauth2Route: typeof AccountsNewOauth2Route
 }
 
 declare module '@tanstack/react-router' {
@@ -144,6 +183,27 @@ declare module '@tanstack/react-router' {
       preLoaderRoute: typeof InboxIndexRouteImport
       parentRoute: typeof InboxRoute
     }
+    '/accounts/new-oauth2': {
+      id: '/accounts/new-oauth2'
+      path: '/accounts/new-oauth2'
+      fullPath: '/accounts/new-oauth2'
+      preLoaderRoute: typeof AccountsNewOauth2RouteImport
+      parentRoute: typeof rootRouteImport
+    }
+    '/accounts/new-notmuch': {
+      id: '/accounts/new-notmuch'
+      path: '/accounts/new-notmuch'
+      fullPath: '/accounts/new-notmuch'
+      preLoaderRoute: typeof AccountsNewNotmuchRouteImport
+      parentRoute: typeof rootRouteImport
+    }
+    '/accounts/new-maildir': {
+      id: '/accounts/new-maildir'
+      path: '/accounts/new-maildir'
+      fullPath: '/accounts/new-maildir'
+      preLoaderRoute: typeof AccountsNewMaildirRouteImport
+      parentRoute: typeof rootRouteImport
+    }
     '/accounts/new-imap': {
       id: '/accounts/new-imap'
       path: '/accounts/new-imap'
@@ -186,6 +246,9 @@ const rootRouteChildren: RootRouteChildren = {
   SettingsRoute: SettingsRoute,
   AccountsNewRoute: AccountsNewRoute,
   AccountsNewImapRoute: AccountsNewImapRoute,
+  AccountsNewMaildirRoute: AccountsNewMaildirRoute,
+  AccountsNewNotmuchRoute: AccountsNewNotmuchRoute,
+  AccountsNewOauth2Route: AccountsNewOauth2Route,
 }
 export const routeTree = rootRouteImport
   ._addFileChildren(rootRouteChildren)
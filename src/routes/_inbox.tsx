@@ -46,8 +46,14 @@ export function InboxLayout() {
   const selectRange = useEmailStore((state) => state.selectRange);
   const toggleSelectAll = useEmailStore((state) => state.toggleSelectAll);
   const markAsRead = useEmailStore((state) => state.markAsRead);
+  const setFlags = useEmailStore((state) => state.setFlags);
+  const removeFlags = useEmailStore((state) => state.removeFlags);
+  const snoozeEmails = useEmailStore((state) => state.snoozeEmails);
+  const remindIfNoReply = useEmailStore((state) => state.remindIfNoReply);
+  const muteThreads = useEmailStore((state) => state.muteThreads);
   const moveToTrash = useEmailStore((state) => state.moveToTrash);
   const archiveEmails = useEmailStore((state) => state.archiveEmails);
+  const keepLocally = useEmailStore((state) => state.keepLocally);
   const moveToInbox = useEmailStore((state) => state.moveToInbox);
 
   // Navigate away if the currently viewed email is deleted/removed
@@ -85,6 +91,10 @@ export function InboxLayout() {
     if (view === "drafts") return "Drafts";
     if (view === "trash") return "Trash";
     if (view === "archive") return "Archive";
+    if (view === "starred") return "Starred";
+    if (view === "snoozed") return "Snoozed";
+    if (view === "digest") return "Digest";
+    if (view === "local") return "On This Device";
     return "Inbox";
   }, [view, filter, search]);
 
@@ -108,8 +118,18 @@ export function InboxLayout() {
             onArchive={() => archiveEmails(Array.from(selectedIds))}
             onDelete={() => moveToTrash(Array.from(selectedIds))}
             onMarkAsRead={() => markAsRead(Array.from(selectedIds))}
+            onMarkAsUnread={() => removeFlags(Array.from(selectedIds), ["seen"])}
+            onStar={() =>
+              view === "starred"
+                ? removeFlags(Array.from(selectedIds), ["flagged"])
+                : setFlags(Array.from(selectedIds), ["flagged"])
+            }
             onLabel={() => console.log("Label", Array.from(selectedIds))}
             onMoveToInbox={() => moveToInbox(Array.from(selectedIds))}
+            onSnooze={view === "snoozed" ? undefined : (until) => snoozeEmails(Array.from(selectedIds), until)}
+            onRemind={view === "sent" ? (by) => remindIfNoReply(Array.from(selectedIds), by) : undefined}
+            onMute={() => muteThreads(Array.from(selectedIds))}
+            onKeepLocally={view === "local" ? undefined : () => keepLocally(Array.from(selectedIds))}
             showMoveToInbox={view === "spam" || view === "trash"}
           />
         )}
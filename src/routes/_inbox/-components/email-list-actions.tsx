@@ -5,8 +5,14 @@ interface EmailListActionsProps {
   onArchive: () => void;
   onDelete: () => void;
   onMarkAsRead: () => void;
+  onMarkAsUnread: () => void;
+  onStar: () => void;
+  onSnooze?: (until: Date) => void;
+  onRemind?: (by: Date) => void;
+  onMute?: () => void;
   onLabel: () => void;
   onMoveToInbox?: () => void;
+  onKeepLocally?: () => void;
   showMoveToInbox?: boolean;
 }
 
@@ -15,8 +21,14 @@ export function EmailListActions({
   onArchive,
   onDelete,
   onMarkAsRead,
+  onMarkAsUnread,
+  onStar,
+  onSnooze,
+  onRemind,
+  onMute,
   onLabel,
   onMoveToInbox,
+  onKeepLocally,
   showMoveToInbox,
 }: EmailListActionsProps) {
   return (
@@ -26,8 +38,14 @@ export function EmailListActions({
         onArchive={onArchive}
         onDelete={onDelete}
         onMarkAsRead={onMarkAsRead}
+        onMarkAsUnread={onMarkAsUnread}
+        onStar={onStar}
+        onSnooze={onSnooze}
+        onRemind={onRemind}
+        onMute={onMute}
         onLabel={onLabel}
         onMoveToInbox={onMoveToInbox}
+        onKeepLocally={onKeepLocally}
         showMoveToInbox={showMoveToInbox}
       />
     </div>
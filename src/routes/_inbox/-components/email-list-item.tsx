@@ -2,7 +2,7 @@ import { Link, useSearch } from "@tanstack/react-router";
 import { format, isToday, isYesterday, isThisYear } from "date-fns";
 import { Paperclip, Check, Reply, Forward, Sparkles } from "lucide-react";
 import { useMemo, memo, useCallback } from "react";
-import { cn } from "@/lib/utils";
+import { cn, getLabels } from "@/lib/utils";
 import { Email, useEmailStore } from "@/lib/store";
 import { useSettingsStore } from "@/lib/settings-store";
 import { SenderAvatar } from "@/components/sender-avatar";
@@ -50,6 +50,8 @@ export const EmailListItem = memo(function EmailListItem({
     return format(d, "MM/dd/yy");
   }, [email.date]);
 
+  const labels = useMemo(() => getLabels(email.flags), [email.flags]);
+
   const handleClick = useCallback((e: React.MouseEvent) => {
     if (isDraft) {
       e.preventDefault();
@@ -174,6 +176,15 @@ export const EmailListItem = memo(function EmailListItem({
           )}>
             {email.subject || "(No Subject)"}
           </div>
+          {labels.map(label => (
+            <Badge
+              key={label}
+              variant="secondary"
+              className="text-[9px] px-1.5 py-0 h-4 font-medium shrink-0"
+            >
+              {label}
+            </Badge>
+          ))}
         </div>
 
         {email.summary && aiEnabled && aiSummarizationEnabled ? (
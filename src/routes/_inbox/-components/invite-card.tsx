@@ -0,0 +1,108 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { format } from "date-fns";
+import { toast } from "sonner";
+import { CalendarDays, Check, HelpCircle, MapPin, X } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { CalendarEvent } from "@/lib/store";
+import { cn } from "@/lib/utils";
+
+const RESPONSES = [
+  { value: "accepted", label: "Accept", icon: Check },
+  { value: "tentative", label: "Maybe", icon: HelpCircle },
+  { value: "declined", label: "Decline", icon: X },
+] as const;
+
+function formatTime(value: string, allDay: boolean) {
+  if (allDay) return format(new Date(`${value}T00:00:00`), "PPP");
+  return format(new Date(value), "PPP p");
+}
+
+function formatWhen(invite: CalendarEvent) {
+  if (!invite.starts_at) return null;
+  const start = formatTime(invite.starts_at, invite.all_day);
+  const zone = invite.timezone && invite.timezone !== "UTC" ? ` (${invite.timezone})` : "";
+  if (!invite.ends_at || invite.all_day) return `${start}${zone}`;
+  return `${start} – ${formatTime(invite.ends_at, false)}${zone}`;
+}
+
+function describeChange(invite: CalendarEvent) {
+  if (invite.status === "CANCELLED") return "This event was cancelled";
+  switch (invite.change) {
+    case "updated":
+      return "This event was updated";
+    case "outdated":
+      return "A newer version of this invitation arrived";
+    case "reply":
+      return "Response to your invitation";
+    default:
+      return "Invitation";
+  }
+}
+
+export function InviteCard({ invite, onChange }: { invite: CalendarEvent; onChange: (invite: CalendarEvent) => void }) {
+  const [responding, setResponding] = useState<string | null>(null);
+  const cancelled = invite.status === "CANCELLED";
+  const canRespond = invite.method === "REQUEST" && !cancelled && invite.change !== "outdated";
+  const when = formatWhen(invite);
+
+  const respond = async (response: string) => {
+    setResponding(response);
+    try {
+      const updated = await invoke<CalendarEvent>("respond_to_invite", { emailId: invite.email_id, response });
+      onChange(updated);
+      toast.success("Response sent");
+    } catch (err) {
+      console.error("Failed to respond to invitation:", err);
+      toast.error(typeof err === "string" ? err : "Failed to send the response");
+    } finally {
+      setResponding(null);
+    }
+  };
+
+  return (
+    <div className={cn("p-5 rounded-2xl border shadow-sm space-y-3", cancelled ? "bg-destructive/5 border-destructive/20" : "bg-muted/40")}>
+      <div className="flex gap-4 items-start">
+        <div className="mt-1 p-1.5 rounded-lg bg-primary/10">
+          <CalendarDays className="w-4 h-4 text-primary" />
+        </div>
+        <div className="flex-1 min-w-0 space-y-1">
+          <p className="text-[10px] uppercase tracking-[0.12em] font-bold opacity-60">{describeChange(invite)}</p>
+          <p className={cn("text-[15px] font-semibold", cancelled && "line-through opacity-70")}>
+            {invite.summary || "Untitled event"}
+          </p>
+          {when && <p className="text-sm text-muted-foreground">{when}</p>}
+          {invite.location && (
+            <p className="text-sm text-muted-foreground flex items-center gap-1.5">
+              <MapPin className="w-3.5 h-3.5" />
+              {invite.location}
+            </p>
+          )}
+          {invite.organizer && (
+            <p className="text-sm text-muted-foreground">
+              Organized by {invite.organizer_name || invite.organizer}
+            </p>
+          )}
+        </div>
+      </div>
+
+      {canRespond && (
+        <div className="flex items-center gap-2 pl-11">
+          {RESPONSES.map(({ value, label, icon: Icon }) => (
+            <Button
+              key={value}
+              variant={invite.response === value ? "default" : "outline"}
+              size="sm"
+              className="h-8 gap-1.5"
+              disabled={responding !== null}
+              onClick={() => respond(value)}
+            >
+              <Icon className="w-3.5 h-3.5" />
+              {label}
+            </Button>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
@@ -2,6 +2,7 @@ import { useState, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { openUrl } from "@tauri-apps/plugin-opener";
+import { save } from "@tauri-apps/plugin-dialog";
 import { format } from "date-fns";
 import { toast } from "sonner";
 import {
@@ -11,6 +12,7 @@ import {
   ChevronUp,
   Sparkles,
   RotateCcw,
+  MailX,
 } from "lucide-react";
 import { Skeleton } from "@/components/ui/skeleton";
 import { Button } from "@/components/ui/button";
@@ -21,6 +23,7 @@ import { cn } from "@/lib/utils";
 import { AttachmentsList } from "./attachments-list";
 import { EmailBody } from "./email-body";
 import { ToolbarActions } from "./toolbar-actions";
+import { InviteCard } from "./invite-card";
 
 export function ThreadMessage({
   email: initialEmail,
@@ -75,6 +78,37 @@ export function ThreadMessage({
     }
   };
 
+  const handleExport = async () => {
+    const path = await save({
+      defaultPath: `${(email.subject || "message").replace(/[\\/:*?"<>|]/g, "-")}.eml`,
+      filters: [{ name: "Email", extensions: ["eml"] }],
+    });
+    if (!path) return;
+
+    try {
+      await invoke("export_email_eml", { emailId: email.id, path });
+      toast.success("Message saved");
+    } catch (err) {
+      console.error("Failed to export email:", err);
+      toast.error(typeof err === "string" ? err : "Failed to save message");
+    }
+  };
+
+  const handleUnsubscribe = async () => {
+    try {
+      const result = await invoke<{ method: string; status: string; url: string | null }>("unsubscribe", { emailId: email.id });
+      if (result.url) {
+        await openUrl(result.url);
+        toast.info("Finish unsubscribing on the list's page");
+      } else {
+        toast.success(`Unsubscribed from ${email.sender_name || email.sender_address}`);
+      }
+    } catch (err) {
+      console.error("Failed to unsubscribe:", err);
+      toast.error(typeof err === "string" ? err : "Failed to unsubscribe");
+    }
+  };
+
   useEffect(() => {
     // Listen for updates to this specific email (e.g. summary generated)
     const unlistenPromise = listen("emails-updated", async () => {
@@ -229,8 +263,21 @@ export function ThreadMessage({
                   onDelete={onDelete}
                   onMarkAsRead={onMarkAsRead}
                   onMoveToInbox={onMoveToInbox}
+                  onExport={handleExport}
                   showMoveToInbox={showMoveToInbox}
                 />
+                {content?.list_unsubscribe && (
+                  <Button
+                    variant="ghost"
+                    size="sm"
+                    className="h-8 px-3 text-foreground/70 hover:text-primary hover:bg-primary/10 transition-colors gap-2"
+                    aria-label="Unsubscribe"
+                    onClick={handleUnsubscribe}
+                  >
+                    <MailX className="w-4 h-4" />
+                    <span>Unsubscribe</span>
+                  </Button>
+                )}
                 <div className="h-4 w-px bg-border mx-1" />
                 <Button
                   variant="ghost"
@@ -328,6 +375,13 @@ export function ThreadMessage({
                   </div>
                 )}
                 
+                {content?.invite && (
+                  <InviteCard
+                    invite={content.invite}
+                    onChange={(invite) => setContent({ ...content, invite })}
+                  />
+                )}
+
                 <div className="prose-email-container">
                   <EmailBody
                     content={content}
@@ -1,14 +1,84 @@
-import { Archive, Trash2, MailOpen, Tag, Inbox } from "lucide-react";
+import { Archive, Trash2, Mail, MailOpen, Star, Tag, Inbox, Download, HardDriveDownload, Clock, BellRing, BellOff } from "lucide-react";
+import type { ReactNode } from "react";
 import { Button } from "@/components/ui/button";
 import { Separator } from "@/components/ui/separator";
 import { Tooltip, TooltipContent, TooltipTrigger, TooltipProvider } from "@/components/ui/tooltip";
+import {
+  DropdownMenu,
+  DropdownMenuContent,
+  DropdownMenuItem,
+  DropdownMenuLabel,
+  DropdownMenuTrigger,
+} from "@/components/ui/dropdown-menu";
+
+// Times offered for snoozing and reminders, computed when the menu opens
+const timePresets = (): { label: string; time: Date }[] => {
+  const now = new Date();
+  const at = (days: number, hour: number) => {
+    const time = new Date(now);
+    time.setDate(time.getDate() + days);
+    time.setHours(hour, 0, 0, 0);
+    return time;
+  };
+  return [
+    { label: "Later today", time: new Date(now.getTime() + 3 * 60 * 60 * 1000) },
+    { label: "Tomorrow morning", time: at(1, 8) },
+    { label: "In three days", time: at(3, 8) },
+    { label: "Next week", time: at((8 - now.getDay()) % 7 || 7, 8) },
+  ];
+};
+
+function TimeMenu({
+  icon,
+  tooltip,
+  label,
+  onPick,
+}: {
+  icon: ReactNode;
+  tooltip: string;
+  label: string;
+  onPick: (time: Date) => void;
+}) {
+  return (
+    <DropdownMenu>
+      <Tooltip>
+        <TooltipTrigger asChild>
+          <DropdownMenuTrigger asChild>
+            <Button variant="ghost" size="icon" className="h-8 w-8">
+              {icon}
+            </Button>
+          </DropdownMenuTrigger>
+        </TooltipTrigger>
+        <TooltipContent>{tooltip}</TooltipContent>
+      </Tooltip>
+      <DropdownMenuContent align="end">
+        <DropdownMenuLabel>{label}</DropdownMenuLabel>
+        {timePresets().map((preset) => (
+          <DropdownMenuItem key={preset.label} onSelect={() => onPick(preset.time)}>
+            <span>{preset.label}</span>
+            <span className="ml-auto pl-4 text-xs text-muted-foreground">
+              {preset.time.toLocaleString(undefined, { weekday: "short", hour: "numeric", minute: "2-digit" })}
+            </span>
+          </DropdownMenuItem>
+        ))}
+      </DropdownMenuContent>
+    </DropdownMenu>
+  );
+}
 
 interface ToolbarActionsProps {
   onArchive?: () => void;
   onDelete?: () => void;
   onMarkAsRead?: () => void;
+  onMarkAsUnread?: () => void;
+  onStar?: () => void;
+  onSnooze?: (until: Date) => void;
+  onRemind?: (by: Date) => void;
+  onMute?: () => void;
   onLabel?: () => void;
   onMoveToInbox?: () => void;
+  onExport?: () => void;
+  onKeepLocally?: () => void;
   showMoveToInbox?: boolean;
 }
 
@@ -16,8 +86,15 @@ export function ToolbarActions({
   onArchive,
   onDelete,
   onMarkAsRead,
+  onMarkAsUnread,
+  onStar,
+  onSnooze,
+  onRemind,
+  onMute,
   onLabel,
   onMoveToInbox,
+  onExport,
+  onKeepLocally,
   showMoveToInbox = false,
 }: ToolbarActionsProps) {
   return (
@@ -44,6 +121,17 @@ export function ToolbarActions({
             <TooltipContent>Archive</TooltipContent>
           </Tooltip>
         )}
+
+        {onKeepLocally && (
+          <Tooltip>
+            <TooltipTrigger asChild>
+              <Button variant="ghost" size="icon" className="h-8 w-8" onClick={onKeepLocally}>
+                <HardDriveDownload className="w-4 h-4" />
+              </Button>
+            </TooltipTrigger>
+            <TooltipContent>Keep on this device only</TooltipContent>
+          </Tooltip>
+        )}
         
         {onDelete && (
            <Tooltip>
@@ -73,6 +161,57 @@ export function ToolbarActions({
             <TooltipContent>Mark as read</TooltipContent>
           </Tooltip>
         )}
+
+        {onMarkAsUnread && (
+          <Tooltip>
+            <TooltipTrigger asChild>
+              <Button variant="ghost" size="icon" className="h-8 w-8" onClick={onMarkAsUnread}>
+                <Mail className="w-4 h-4" />
+              </Button>
+            </TooltipTrigger>
+            <TooltipContent>Mark as unread</TooltipContent>
+          </Tooltip>
+        )}
+
+        {onStar && (
+          <Tooltip>
+            <TooltipTrigger asChild>
+              <Button variant="ghost" size="icon" className="h-8 w-8" onClick={onStar}>
+                <Star className="w-4 h-4" />
+              </Button>
+            </TooltipTrigger>
+            <TooltipContent>Star</TooltipContent>
+          </Tooltip>
+        )}
+
+        {onSnooze && (
+          <TimeMenu
+            icon={<Clock className="w-4 h-4" />}
+            tooltip="Snooze"
+            label="Snooze until"
+            onPick={onSnooze}
+          />
+        )}
+
+        {onRemind && (
+          <TimeMenu
+            icon={<BellRing className="w-4 h-4" />}
+            tooltip="Remind me if nobody replies"
+            label="Remind me if no reply by"
+            onPick={onRemind}
+          />
+        )}
+
+        {onMute && (
+          <Tooltip>
+            <TooltipTrigger asChild>
+              <Button variant="ghost" size="icon" className="h-8 w-8" onClick={onMute}>
+                <BellOff className="w-4 h-4" />
+              </Button>
+            </TooltipTrigger>
+            <TooltipContent>Mute conversation</TooltipContent>
+          </Tooltip>
+        )}
         
         {onLabel && (
           <Tooltip>
@@ -84,6 +223,17 @@ export function ToolbarActions({
             <TooltipContent>Label</TooltipContent>
           </Tooltip>
         )}
+
+        {onExport && (
+          <Tooltip>
+            <TooltipTrigger asChild>
+              <Button variant="ghost" size="icon" className="h-8 w-8" onClick={onExport}>
+                <Download className="w-4 h-4" />
+              </Button>
+            </TooltipTrigger>
+            <TooltipContent>Save as .eml</TooltipContent>
+          </Tooltip>
+        )}
       </div>
     </TooltipProvider>
   );
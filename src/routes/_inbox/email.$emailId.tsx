@@ -1,6 +1,9 @@
 import { createFileRoute, useSearch } from "@tanstack/react-router";
 import { useMemo } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { open } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { Download } from "lucide-react";
 import { useInfiniteQuery } from "@tanstack/react-query";
 import { ScrollArea } from "@/components/ui/scroll-area";
 import { Button } from "@/components/ui/button";
@@ -67,6 +70,19 @@ export function ThreadView() {
     [email.subject],
   );
 
+  const handleExportThread = async () => {
+    const dir = await open({ directory: true, multiple: false });
+    if (typeof dir !== "string") return;
+
+    try {
+      const count = await invoke<number>("export_thread_eml", { emailId: email.id, dir });
+      toast.success(`Saved ${count} messages`);
+    } catch (err) {
+      console.error("Failed to export thread:", err);
+      toast.error(typeof err === "string" ? err : "Failed to export thread");
+    }
+  };
+
   return (
     <div className="flex h-full w-full min-w-0 overflow-hidden">
       <div className="flex-1 flex flex-col min-w-0 overflow-hidden relative">
@@ -76,6 +92,9 @@ export function ThreadView() {
               {displaySubject}
             </h2>
           </div>
+          <Button variant="ghost" size="sm" className="shrink-0 gap-2" onClick={handleExportThread}>
+            <Download className="w-4 h-4" /> Export thread
+          </Button>
         </div>
 
         <ScrollArea className="flex-1 min-h-0 bg-email-view">
@@ -1,5 +1,5 @@
 import { createFileRoute, useNavigate, Link } from "@tanstack/react-router";
-import { CheckCircle2, ChevronLeft, Info, Loader2, Mail, Server, ShieldCheck } from "lucide-react";
+import { CheckCircle2, ChevronLeft, Info, Loader2, Mail, Server, ShieldCheck, Wand2 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
@@ -11,6 +11,7 @@ import * as z from "zod";
 import {
   Form,
   FormControl,
+  FormDescription,
   FormField,
   FormItem,
   FormLabel,
@@ -35,6 +36,7 @@ const imapFormSchema = z.object({
   smtp_password: z.string(),
   smtp_encryption: z.enum(["tls", "starttls", "none"]),
   smtp_use_imap_credentials: z.boolean(),
+  sendmail_command: z.string(),
 });
 
 type ImapFormValues = z.infer<typeof imapFormSchema>;
@@ -49,6 +51,7 @@ function NewImapComponent() {
   const [isSubmitting, setIsSubmitting] = useState(false);
   const [isVerifying, setIsVerifying] = useState(false);
   const [isVerified, setIsVerified] = useState(false);
+  const [isDiscovering, setIsDiscovering] = useState(false);
 
   const form = useForm<ImapFormValues>({
     resolver: zodResolver(imapFormSchema) as any,
@@ -66,6 +69,7 @@ function NewImapComponent() {
       smtp_password: "",
       smtp_encryption: "starttls",
       smtp_use_imap_credentials: true,
+      sendmail_command: "",
     },
   });
 
@@ -80,6 +84,37 @@ function NewImapComponent() {
     }
   };
 
+  const onDiscover = async () => {
+    const isValid = await form.trigger("email");
+    if (!isValid) return;
+
+    try {
+      setError(null);
+      setIsVerified(false);
+      setIsDiscovering(true);
+      const candidates = await invoke<Omit<ImapFormValues, "name" | "password" | "smtp_password">[]>(
+        "discover_account_settings",
+        { email: form.getValues("email") }
+      );
+      const best = candidates[0];
+      if (!best) return;
+      form.setValue("imap_host", best.imap_host);
+      form.setValue("imap_port", best.imap_port);
+      form.setValue("imap_username", best.imap_username);
+      form.setValue("imap_encryption", best.imap_encryption);
+      form.setValue("smtp_host", best.smtp_host);
+      form.setValue("smtp_port", best.smtp_port);
+      form.setValue("smtp_username", best.smtp_username);
+      form.setValue("smtp_encryption", best.smtp_encryption);
+      form.setValue("smtp_use_imap_credentials", best.smtp_use_imap_credentials);
+    } catch (err: any) {
+      console.error("Discovery failed:", err);
+      setError(err.toString() || "Could not detect the server settings for this address.");
+    } finally {
+      setIsDiscovering(false);
+    }
+  };
+
   const onVerify = async () => {
     const isValid = await form.trigger();
     if (!isValid) return;
@@ -201,6 +236,22 @@ function NewImapComponent() {
                     )}
                   />
                 </div>
+                <Button
+                  type="button"
+                  variant="secondary"
+                  disabled={isDiscovering || isVerifying || isSubmitting}
+                  onClick={onDiscover}
+                >
+                  {isDiscovering ? (
+                    <>
+                      <Loader2 className="mr-2 h-4 w-4 animate-spin" /> Detecting...
+                    </>
+                  ) : (
+                    <>
+                      <Wand2 className="mr-2 h-4 w-4" /> Detect Server Settings
+                    </>
+                  )}
+                </Button>
               </CardContent>
             </Card>
 
@@ -245,7 +296,7 @@ function NewImapComponent() {
                       render={({ field }) => (
                         <FormItem>
                           <FormLabel>Security</FormLabel>
-                          <Select onValueChange={field.onChange} defaultValue={field.value}>
+                          <Select onValueChange={field.onChange} value={field.value}>
                             <FormControl>
                               <SelectTrigger>
                                 <SelectValue placeholder="Encryption" />
@@ -331,7 +382,7 @@ function NewImapComponent() {
                       render={({ field }) => (
                         <FormItem>
                           <FormLabel>Security</FormLabel>
-                          <Select onValueChange={field.onChange} defaultValue={field.value}>
+                          <Select onValueChange={field.onChange} value={field.value}>
                             <FormControl>
                               <SelectTrigger>
                                 <SelectValue placeholder="Encryption" />
@@ -397,6 +448,23 @@ function NewImapComponent() {
                       />
                     </div>
                   )}
+
+                  <FormField
+                    control={form.control}
+                    name="sendmail_command"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Sendmail Command</FormLabel>
+                        <FormControl>
+                          <Input placeholder="msmtp -t" {...field} />
+                        </FormControl>
+                        <FormDescription>
+                          Leave empty to send through SMTP. Otherwise messages are piped to this local command.
+                        </FormDescription>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
                 </CardContent>
               </Card>
             </div>
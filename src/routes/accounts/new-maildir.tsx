@@ -0,0 +1,222 @@
+import { createFileRoute, useNavigate, Link } from "@tanstack/react-router";
+import { ChevronLeft, FolderOpen, HardDrive, Info, Loader2, ShieldCheck } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Switch } from "@/components/ui/switch";
+import { useState } from "react";
+import { useForm } from "react-hook-form";
+import { zodResolver } from "@hookform/resolvers/zod";
+import * as z from "zod";
+import {
+  Form,
+  FormControl,
+  FormDescription,
+  FormField,
+  FormItem,
+  FormLabel,
+  FormMessage,
+} from "@/components/ui/form";
+import { invoke } from "@tauri-apps/api/core";
+import { open } from "@tauri-apps/plugin-dialog";
+import { useEmailStore } from "@/lib/store";
+import { Alert, AlertDescription, AlertTitle } from "@/components/ui/alert";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+
+const maildirFormSchema = z.object({
+  email: z.string().email("Invalid email address"),
+  name: z.string().min(1, "Name is required"),
+  root_dir: z.string().min(1, "Maildir folder is required"),
+  maildirpp: z.boolean(),
+  sendmail_command: z.string(),
+});
+
+type MaildirFormValues = z.infer<typeof maildirFormSchema>;
+
+export const Route = createFileRoute("/accounts/new-maildir")({
+  component: NewMaildirComponent,
+});
+
+function NewMaildirComponent() {
+  const navigate = useNavigate();
+  const [error, setError] = useState<string | null>(null);
+  const [isSubmitting, setIsSubmitting] = useState(false);
+
+  const form = useForm<MaildirFormValues>({
+    resolver: zodResolver(maildirFormSchema),
+    defaultValues: {
+      email: "",
+      name: "",
+      root_dir: "",
+      maildirpp: true,
+      sendmail_command: "",
+    },
+  });
+
+  const onBrowse = async () => {
+    const selected = await open({ directory: true, multiple: false });
+    if (typeof selected === "string") {
+      form.setValue("root_dir", selected, { shouldValidate: true });
+    }
+  };
+
+  const onSubmit = async (values: MaildirFormValues) => {
+    try {
+      setError(null);
+      setIsSubmitting(true);
+      await invoke("add_maildir_account", { account: values });
+      await useEmailStore.getState().fetchAccountsAndFolders();
+      navigate({ to: "/" });
+    } catch (err: any) {
+      console.error("Failed to add Maildir account:", err);
+      setError(err.toString() || "Failed to open the Maildir folder.");
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  return (
+    <div className="min-h-screen bg-background flex flex-col">
+      <div className="container max-w-2xl mx-auto py-12 px-6 flex-1">
+        <Button
+          variant="ghost"
+          asChild
+          className="mb-8 -ml-4 text-muted-foreground hover:text-foreground"
+        >
+          <Link to="/accounts/new">
+            <ChevronLeft className="mr-2 h-4 w-4" /> Back to Providers
+          </Link>
+        </Button>
+
+        <div className="space-y-2 mb-8">
+          <h1 className="text-4xl font-extrabold tracking-tight">
+            Local Maildir
+          </h1>
+          <p className="text-xl text-muted-foreground">
+            Read mail that another tool, like mbsync or offlineimap, keeps on this computer.
+          </p>
+        </div>
+
+        {error && (
+          <Alert variant="destructive" className="mb-8">
+            <Info className="h-4 w-4" />
+            <AlertTitle>Could Not Add Account</AlertTitle>
+            <AlertDescription>{error}</AlertDescription>
+          </Alert>
+        )}
+
+        <Form {...form}>
+          <form onSubmit={form.handleSubmit(onSubmit)} className="space-y-8">
+            <Card>
+              <CardHeader>
+                <CardTitle className="flex items-center gap-2">
+                  <HardDrive className="h-5 w-5" /> Maildir Details
+                </CardTitle>
+                <CardDescription>
+                  The address is used to tell your own messages apart.
+                </CardDescription>
+              </CardHeader>
+              <CardContent className="space-y-4">
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                  <FormField
+                    control={form.control}
+                    name="name"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Full Name</FormLabel>
+                        <FormControl>
+                          <Input placeholder="John Doe" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="email"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Email Address</FormLabel>
+                        <FormControl>
+                          <Input placeholder="john@example.com" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+                <FormField
+                  control={form.control}
+                  name="root_dir"
+                  render={({ field }) => (
+                    <FormItem>
+                      <FormLabel>Maildir Folder</FormLabel>
+                      <div className="flex gap-2">
+                        <FormControl>
+                          <Input placeholder="~/Mail/personal" {...field} />
+                        </FormControl>
+                        <Button type="button" variant="secondary" onClick={onBrowse}>
+                          <FolderOpen className="mr-2 h-4 w-4" /> Browse
+                        </Button>
+                      </div>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+                <FormField
+                  control={form.control}
+                  name="maildirpp"
+                  render={({ field }) => (
+                    <FormItem className="flex flex-row items-center justify-between rounded-lg border p-3 shadow-sm">
+                      <div className="space-y-0.5">
+                        <FormLabel>Maildir++ Layout</FormLabel>
+                        <FormDescription>
+                          The folder itself is the inbox and subfolders start with a dot.
+                        </FormDescription>
+                      </div>
+                      <FormControl>
+                        <Switch
+                          checked={field.value}
+                          onCheckedChange={field.onChange}
+                        />
+                      </FormControl>
+                    </FormItem>
+                  )}
+                />
+                <FormField
+                  control={form.control}
+                  name="sendmail_command"
+                  render={({ field }) => (
+                    <FormItem>
+                      <FormLabel>Sendmail Command</FormLabel>
+                      <FormControl>
+                        <Input placeholder="msmtp -t" {...field} />
+                      </FormControl>
+                      <FormDescription>
+                        Messages you send are piped to this command. Leave empty to only read mail.
+                      </FormDescription>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+              </CardContent>
+            </Card>
+
+            <div className="flex justify-end gap-3 pt-4">
+              <Button type="submit" size="lg" disabled={isSubmitting} className="min-w-[150px]">
+                {isSubmitting ? (
+                  <>
+                    <Loader2 className="mr-2 h-4 w-4 animate-spin" /> Adding...
+                  </>
+                ) : (
+                  <>
+                    Add Account <ShieldCheck className="ml-2 h-4 w-4" />
+                  </>
+                )}
+              </Button>
+            </div>
+          </form>
+        </Form>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,267 @@
+import { createFileRoute, useNavigate, Link } from "@tanstack/react-router";
+import { ChevronLeft, Database, FolderOpen, Info, Loader2, ShieldCheck } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Switch } from "@/components/ui/switch";
+import { useState } from "react";
+import { useForm } from "react-hook-form";
+import { zodResolver } from "@hookform/resolvers/zod";
+import * as z from "zod";
+import {
+  Form,
+  FormControl,
+  FormDescription,
+  FormField,
+  FormItem,
+  FormLabel,
+  FormMessage,
+} from "@/components/ui/form";
+import { invoke } from "@tauri-apps/api/core";
+import { open } from "@tauri-apps/plugin-dialog";
+import { useEmailStore } from "@/lib/store";
+import { Alert, AlertDescription, AlertTitle } from "@/components/ui/alert";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+
+const notmuchFormSchema = z.object({
+  email: z.string().email("Invalid email address"),
+  name: z.string().min(1, "Name is required"),
+  database_path: z.string(),
+  config_path: z.string(),
+  profile: z.string(),
+  maildirpp: z.boolean(),
+  sendmail_command: z.string(),
+});
+
+type NotmuchFormValues = z.infer<typeof notmuchFormSchema>;
+
+export const Route = createFileRoute("/accounts/new-notmuch")({
+  component: NewNotmuchComponent,
+});
+
+function NewNotmuchComponent() {
+  const navigate = useNavigate();
+  const [error, setError] = useState<string | null>(null);
+  const [isSubmitting, setIsSubmitting] = useState(false);
+
+  const form = useForm<NotmuchFormValues>({
+    resolver: zodResolver(notmuchFormSchema),
+    defaultValues: {
+      email: "",
+      name: "",
+      database_path: "",
+      config_path: "",
+      profile: "",
+      maildirpp: false,
+      sendmail_command: "",
+    },
+  });
+
+  const onBrowse = async () => {
+    const selected = await open({ directory: true, multiple: false });
+    if (typeof selected === "string") {
+      form.setValue("database_path", selected, { shouldValidate: true });
+    }
+  };
+
+  const onSubmit = async (values: NotmuchFormValues) => {
+    try {
+      setError(null);
+      setIsSubmitting(true);
+      // Empty fields fall back to the notmuch defaults
+      const account = {
+        email: values.email,
+        name: values.name,
+        database_path: values.database_path || null,
+        config_path: values.config_path || null,
+        profile: values.profile || null,
+        maildirpp: values.maildirpp,
+        sendmail_command: values.sendmail_command || null,
+      };
+      await invoke("add_notmuch_account", { account });
+      await useEmailStore.getState().fetchAccountsAndFolders();
+      navigate({ to: "/" });
+    } catch (err: any) {
+      console.error("Failed to add Notmuch account:", err);
+      setError(err.toString() || "Failed to open the notmuch database.");
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  return (
+    <div className="min-h-screen bg-background flex flex-col">
+      <div className="container max-w-2xl mx-auto py-12 px-6 flex-1">
+        <Button
+          variant="ghost"
+          asChild
+          className="mb-8 -ml-4 text-muted-foreground hover:text-foreground"
+        >
+          <Link to="/accounts/new">
+            <ChevronLeft className="mr-2 h-4 w-4" /> Back to Providers
+          </Link>
+        </Button>
+
+        <div className="space-y-2 mb-8">
+          <h1 className="text-4xl font-extrabold tracking-tight">
+            Notmuch
+          </h1>
+          <p className="text-xl text-muted-foreground">
+            Read a notmuch database. Its tags show up as labels, and changes you make here are saved back as tags.
+          </p>
+        </div>
+
+        {error && (
+          <Alert variant="destructive" className="mb-8">
+            <Info className="h-4 w-4" />
+            <AlertTitle>Could Not Add Account</AlertTitle>
+            <AlertDescription>{error}</AlertDescription>
+          </Alert>
+        )}
+
+        <Form {...form}>
+          <form onSubmit={form.handleSubmit(onSubmit)} className="space-y-8">
+            <Card>
+              <CardHeader>
+                <CardTitle className="flex items-center gap-2">
+                  <Database className="h-5 w-5" /> Notmuch Details
+                </CardTitle>
+                <CardDescription>
+                  The address is used to tell your own messages apart.
+                </CardDescription>
+              </CardHeader>
+              <CardContent className="space-y-4">
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                  <FormField
+                    control={form.control}
+                    name="name"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Full Name</FormLabel>
+                        <FormControl>
+                          <Input placeholder="John Doe" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="email"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Email Address</FormLabel>
+                        <FormControl>
+                          <Input placeholder="john@example.com" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+                <FormField
+                  control={form.control}
+                  name="database_path"
+                  render={({ field }) => (
+                    <FormItem>
+                      <FormLabel>Database Folder</FormLabel>
+                      <div className="flex gap-2">
+                        <FormControl>
+                          <Input placeholder="~/Mail" {...field} />
+                        </FormControl>
+                        <Button type="button" variant="secondary" onClick={onBrowse}>
+                          <FolderOpen className="mr-2 h-4 w-4" /> Browse
+                        </Button>
+                      </div>
+                      <FormDescription>
+                        Leave empty to use the database from your notmuch config.
+                      </FormDescription>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                  <FormField
+                    control={form.control}
+                    name="config_path"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Config File</FormLabel>
+                        <FormControl>
+                          <Input placeholder="~/.notmuch-config" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="profile"
+                    render={({ field }) => (
+                      <FormItem>
+                        <FormLabel>Profile</FormLabel>
+                        <FormControl>
+                          <Input placeholder="default" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+                <FormField
+                  control={form.control}
+                  name="maildirpp"
+                  render={({ field }) => (
+                    <FormItem className="flex flex-row items-center justify-between rounded-lg border p-3 shadow-sm">
+                      <div className="space-y-0.5">
+                        <FormLabel>Maildir++ Layout</FormLabel>
+                        <FormDescription>
+                          The folder itself is the inbox and subfolders start with a dot.
+                        </FormDescription>
+                      </div>
+                      <FormControl>
+                        <Switch
+                          checked={field.value}
+                          onCheckedChange={field.onChange}
+                        />
+                      </FormControl>
+                    </FormItem>
+                  )}
+                />
+                <FormField
+                  control={form.control}
+                  name="sendmail_command"
+                  render={({ field }) => (
+                    <FormItem>
+                      <FormLabel>Sendmail Command</FormLabel>
+                      <FormControl>
+                        <Input placeholder="msmtp -t" {...field} />
+                      </FormControl>
+                      <FormDescription>
+                        Messages you send are piped to this command. Leave empty to only read mail.
+                      </FormDescription>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+              </CardContent>
+            </Card>
+
+            <div className="flex justify-end gap-3 pt-4">
+              <Button type="submit" size="lg" disabled={isSubmitting} className="min-w-[150px]">
+                {isSubmitting ? (
+                  <>
+                    <Loader2 className="mr-2 h-4 w-4 animate-spin" /> Adding...
+                  </>
+                ) : (
+                  <>
+                    Add Account <ShieldCheck className="ml-2 h-4 w-4" />
+                  </>
+                )}
+              </Button>
+            </div>
+          </form>
+        </Form>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,376 @@
+import { createFileRoute, useNavigate, Link } from "@tanstack/react-router";
+import { ChevronLeft, Info, KeyRound, Loader2, Server, ShieldCheck } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
+import { Switch } from "@/components/ui/switch";
+import { useEffect, useState } from "react";
+import { useForm } from "react-hook-form";
+import { zodResolver } from "@hookform/resolvers/zod";
+import * as z from "zod";
+import {
+  Form,
+  FormControl,
+  FormField,
+  FormItem,
+  FormLabel,
+  FormMessage,
+} from "@/components/ui/form";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { useEmailStore } from "@/lib/store";
+import { Alert, AlertDescription, AlertTitle } from "@/components/ui/alert";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+
+const CUSTOM_PROVIDER = "custom";
+
+interface OAuth2Provider {
+  id: string;
+  name: string;
+  auth_url: string;
+  token_url: string;
+  scopes: string[];
+  pkce: boolean;
+  client_id: string;
+  client_secret?: string;
+  redirect_host?: string | null;
+  redirect_port?: number | null;
+  userinfo_url?: string | null;
+  imap_host: string;
+  imap_port: number;
+  imap_encryption: string;
+  smtp_host: string;
+  smtp_port: number;
+  smtp_encryption: string;
+}
+
+const oauth2FormSchema = z.object({
+  email: z.string().email("Invalid email address").or(z.literal("")),
+  name: z.string().min(1, "Name is required"),
+  auth_url: z.string().url("Invalid URL"),
+  token_url: z.string().url("Invalid URL"),
+  scopes: z.string(),
+  client_id: z.string().min(1, "Client ID is required"),
+  client_secret: z.string(),
+  userinfo_url: z.string().url("Invalid URL").or(z.literal("")),
+  pkce: z.boolean(),
+  imap_host: z.string().min(1, "IMAP host is required"),
+  imap_port: z.coerce.number().int().positive(),
+  imap_encryption: z.enum(["tls", "starttls", "none"]),
+  smtp_host: z.string().min(1, "SMTP host is required"),
+  smtp_port: z.coerce.number().int().positive(),
+  smtp_encryption: z.enum(["tls", "starttls", "none"]),
+});
+
+type OAuth2FormValues = z.infer<typeof oauth2FormSchema>;
+
+export const Route = createFileRoute("/accounts/new-oauth2")({
+  component: NewOAuth2Component,
+});
+
+function NewOAuth2Component() {
+  const navigate = useNavigate();
+  const [error, setError] = useState<string | null>(null);
+  const [isConnecting, setIsConnecting] = useState(false);
+  const [providers, setProviders] = useState<OAuth2Provider[]>([]);
+  const [providerId, setProviderId] = useState(CUSTOM_PROVIDER);
+  const [email, setEmail] = useState("");
+
+  const form = useForm<OAuth2FormValues>({
+    resolver: zodResolver(oauth2FormSchema) as any,
+    defaultValues: {
+      email: "",
+      name: "",
+      auth_url: "",
+      token_url: "",
+      scopes: "openid email profile",
+      client_id: "",
+      client_secret: "",
+      userinfo_url: "",
+      pkce: true,
+      imap_host: "",
+      imap_port: 993,
+      imap_encryption: "tls",
+      smtp_host: "",
+      smtp_port: 587,
+      smtp_encryption: "starttls",
+    },
+  });
+
+  useEffect(() => {
+    invoke<OAuth2Provider[]>("get_oauth2_providers")
+      .then((list) => {
+        setProviders(list);
+        if (list.length > 0) setProviderId(list[0].id);
+      })
+      .catch((err) => console.error("Failed to load OAuth2 providers:", err));
+  }, []);
+
+  useEffect(() => {
+    const unlistenAdded = listen("oauth2-account-added", async () => {
+      await useEmailStore.getState().fetchAccountsAndFolders();
+      setIsConnecting(false);
+      navigate({ to: "/" });
+    });
+
+    const unlistenError = listen("oauth2-account-error", (event) => {
+      console.error("OAuth2 account error:", event.payload);
+      setError(event.payload as string);
+      setIsConnecting(false);
+    });
+
+    return () => {
+      unlistenAdded.then((f) => f());
+      unlistenError.then((f) => f());
+    };
+  }, [navigate]);
+
+  const login = async (provider: OAuth2Provider, address: string) => {
+    try {
+      setError(null);
+      setIsConnecting(true);
+      await invoke("login_with_oauth2", { provider, email: address || null });
+    } catch (err: any) {
+      console.error("Failed to start OAuth2 login:", err);
+      setError(err.toString() || "Failed to start the login. Please try again.");
+      setIsConnecting(false);
+    }
+  };
+
+  const onBuiltinLogin = () => {
+    const provider = providers.find((p) => p.id === providerId);
+    if (provider) login(provider, email);
+  };
+
+  const onCustomSubmit = (values: OAuth2FormValues) => {
+    login(
+      {
+        id: CUSTOM_PROVIDER,
+        name: values.name,
+        auth_url: values.auth_url,
+        token_url: values.token_url,
+        scopes: values.scopes.split(/\s+/).filter(Boolean),
+        pkce: values.pkce,
+        client_id: values.client_id,
+        client_secret: values.client_secret || undefined,
+        userinfo_url: values.userinfo_url || null,
+        imap_host: values.imap_host,
+        imap_port: values.imap_port,
+        imap_encryption: values.imap_encryption,
+        smtp_host: values.smtp_host,
+        smtp_port: values.smtp_port,
+        smtp_encryption: values.smtp_encryption,
+      },
+      values.email
+    );
+  };
+
+  const textField = (name: keyof OAuth2FormValues, label: string, placeholder?: string, type?: string) => (
+    <FormField
+      control={form.control}
+      name={name}
+      render={({ field }) => (
+        <FormItem>
+          <FormLabel>{label}</FormLabel>
+          <FormControl>
+            <Input type={type} placeholder={placeholder} {...field} value={field.value as string | number} />
+          </FormControl>
+          <FormMessage />
+        </FormItem>
+      )}
+    />
+  );
+
+  const encryptionField = (name: "imap_encryption" | "smtp_encryption") => (
+    <FormField
+      control={form.control}
+      name={name}
+      render={({ field }) => (
+        <FormItem>
+          <FormLabel>Security</FormLabel>
+          <Select onValueChange={field.onChange} value={field.value}>
+            <FormControl>
+              <SelectTrigger>
+                <SelectValue placeholder="Encryption" />
+              </SelectTrigger>
+            </FormControl>
+            <SelectContent>
+              <SelectItem value="tls">SSL / TLS</SelectItem>
+              <SelectItem value="starttls">STARTTLS</SelectItem>
+              <SelectItem value="none">None</SelectItem>
+            </SelectContent>
+          </Select>
+          <FormMessage />
+        </FormItem>
+      )}
+    />
+  );
+
+  return (
+    <div className="min-h-screen bg-background flex flex-col">
+      <div className="container max-w-2xl mx-auto py-12 px-6 flex-1">
+        <Button
+          variant="ghost"
+          asChild
+          className="mb-8 -ml-4 text-muted-foreground hover:text-foreground"
+        >
+          <Link to="/accounts/new">
+            <ChevronLeft className="mr-2 h-4 w-4" /> Back to Providers
+          </Link>
+        </Button>
+
+        <div className="space-y-2 mb-8">
+          <h1 className="text-4xl font-extrabold tracking-tight">
+            OAuth2 Sign-in
+          </h1>
+          <p className="text-xl text-muted-foreground">
+            Sign in with Fastmail, Yahoo, Zoho or your own identity provider.
+          </p>
+        </div>
+
+        {error && (
+          <Alert variant="destructive" className="mb-8">
+            <Info className="h-4 w-4" />
+            <AlertTitle>Connection Failed</AlertTitle>
+            <AlertDescription>{error}</AlertDescription>
+          </Alert>
+        )}
+
+        <Card className="mb-8">
+          <CardHeader>
+            <CardTitle className="flex items-center gap-2">
+              <KeyRound className="h-5 w-5" /> Provider
+            </CardTitle>
+            <CardDescription>
+              Custom providers use XOAUTH2 against your own IMAP/SMTP servers.
+            </CardDescription>
+          </CardHeader>
+          <CardContent className="space-y-4">
+            <Select onValueChange={setProviderId} value={providerId}>
+              <SelectTrigger>
+                <SelectValue placeholder="Provider" />
+              </SelectTrigger>
+              <SelectContent>
+                {providers.map((provider) => (
+                  <SelectItem key={provider.id} value={provider.id}>
+                    {provider.name}
+                  </SelectItem>
+                ))}
+                <SelectItem value={CUSTOM_PROVIDER}>Custom (Keycloak, Dex, ...)</SelectItem>
+              </SelectContent>
+            </Select>
+
+            {providerId !== CUSTOM_PROVIDER && (
+              <>
+                <Input
+                  placeholder="john@example.com"
+                  value={email}
+                  onChange={(e) => setEmail(e.target.value)}
+                />
+                <div className="flex justify-end">
+                  <Button size="lg" disabled={isConnecting} onClick={onBuiltinLogin} className="min-w-[150px]">
+                    {isConnecting ? (
+                      <>
+                        <Loader2 className="mr-2 h-4 w-4 animate-spin" /> Waiting for browser...
+                      </>
+                    ) : (
+                      <>
+                        Sign in <ShieldCheck className="ml-2 h-4 w-4" />
+                      </>
+                    )}
+                  </Button>
+                </div>
+              </>
+            )}
+          </CardContent>
+        </Card>
+
+        {providerId === CUSTOM_PROVIDER && (
+          <Form {...form}>
+            <form onSubmit={form.handleSubmit(onCustomSubmit)} className="space-y-8">
+              <Card>
+                <CardHeader>
+                  <CardTitle className="flex items-center gap-2 text-lg">
+                    <KeyRound className="h-4 w-4 text-primary" /> Authorization Server
+                  </CardTitle>
+                </CardHeader>
+                <CardContent className="space-y-4">
+                  <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                    {textField("name", "Provider Name", "Company Mail")}
+                    {textField("email", "Email Address", "john@example.com")}
+                  </div>
+                  {textField("auth_url", "Authorization URL", "https://sso.example.com/realms/mail/protocol/openid-connect/auth")}
+                  {textField("token_url", "Token URL", "https://sso.example.com/realms/mail/protocol/openid-connect/token")}
+                  {textField("userinfo_url", "Userinfo URL (optional)", "https://sso.example.com/realms/mail/protocol/openid-connect/userinfo")}
+                  {textField("scopes", "Scopes", "openid email profile")}
+                  <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                    {textField("client_id", "Client ID")}
+                    {textField("client_secret", "Client Secret (optional)", "", "password")}
+                  </div>
+                  <FormField
+                    control={form.control}
+                    name="pkce"
+                    render={({ field }) => (
+                      <FormItem className="flex flex-row items-center justify-between rounded-lg border p-3">
+                        <FormLabel>Use PKCE</FormLabel>
+                        <FormControl>
+                          <Switch checked={field.value} onCheckedChange={field.onChange} />
+                        </FormControl>
+                      </FormItem>
+                    )}
+                  />
+                </CardContent>
+              </Card>
+
+              <div className="grid grid-cols-1 md:grid-cols-2 gap-8">
+                <Card>
+                  <CardHeader>
+                    <CardTitle className="flex items-center gap-2 text-lg">
+                      <Server className="h-4 w-4 text-primary" /> Incoming (IMAP)
+                    </CardTitle>
+                  </CardHeader>
+                  <CardContent className="space-y-4">
+                    {textField("imap_host", "IMAP Host", "imap.example.com")}
+                    <div className="grid grid-cols-2 gap-4">
+                      {textField("imap_port", "Port", "", "number")}
+                      {encryptionField("imap_encryption")}
+                    </div>
+                  </CardContent>
+                </Card>
+
+                <Card>
+                  <CardHeader>
+                    <CardTitle className="flex items-center gap-2 text-lg">
+                      <Server className="h-4 w-4 text-primary" /> Outgoing (SMTP)
+                    </CardTitle>
+                  </CardHeader>
+                  <CardContent className="space-y-4">
+                    {textField("smtp_host", "SMTP Host", "smtp.example.com")}
+                    <div className="grid grid-cols-2 gap-4">
+                      {textField("smtp_port", "Port", "", "number")}
+                      {encryptionField("smtp_encryption")}
+                    </div>
+                  </CardContent>
+                </Card>
+              </div>
+
+              <div className="flex justify-end gap-3 pt-4">
+                <Button type="submit" size="lg" disabled={isConnecting} className="min-w-[150px]">
+                  {isConnecting ? (
+                    <>
+                      <Loader2 className="mr-2 h-4 w-4 animate-spin" /> Waiting for browser...
+                    </>
+                  ) : (
+                    <>
+                      Sign in <ShieldCheck className="ml-2 h-4 w-4" />
+                    </>
+                  )}
+                </Button>
+              </div>
+            </form>
+          </Form>
+        )}
+      </div>
+    </div>
+  );
+}
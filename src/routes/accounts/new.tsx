@@ -5,7 +5,7 @@ import { AccountTypeCard } from "@/routes/accounts/-components/account-type-card
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { useEffect, useState } from "react";
-import { ChevronLeft, Info, Lock, Mail } from "lucide-react";
+import { ChevronLeft, Database, HardDrive, Info, KeyRound, Lock, Mail } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Alert, AlertDescription, AlertTitle } from "@/components/ui/alert";
 import { useEmailStore } from "@/lib/store";
@@ -120,6 +120,14 @@ function RouteComponent() {
             disabled={isConnecting}
           />
 
+          <AccountTypeCard
+            title="Other OAuth2 Providers"
+            description="Fastmail, Yahoo, Zoho or a self-hosted identity provider."
+            icon={KeyRound}
+            onClick={() => navigate({ to: "/accounts/new-oauth2" })}
+            disabled={isConnecting}
+          />
+
           <AccountTypeCard
             title="Other (IMAP)"
             description="Custom server settings."
@@ -128,6 +136,22 @@ function RouteComponent() {
             disabled={isConnecting}
           />
 
+          <AccountTypeCard
+            title="Local Maildir"
+            description="Mail kept on this computer by mbsync or offlineimap."
+            icon={HardDrive}
+            onClick={() => navigate({ to: "/accounts/new-maildir" })}
+            disabled={isConnecting}
+          />
+
+          <AccountTypeCard
+            title="Notmuch"
+            description="A local notmuch database, with its tags as labels."
+            icon={Database}
+            onClick={() => navigate({ to: "/accounts/new-notmuch" })}
+            disabled={isConnecting}
+          />
+
           <div className="relative mt-4">
             <div className="absolute inset-0 flex items-center">
               <span className="w-full border-t" />
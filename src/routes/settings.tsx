@@ -18,13 +18,20 @@ import {
 } from "@/components/ui/select";
 import { Button } from "@/components/ui/button";
 import { Avatar, AvatarFallback, AvatarImage } from "@/components/ui/avatar";
-import { Trash2, Plus, ArrowLeft } from "lucide-react";
+import { Trash2, Plus, ArrowLeft, ArrowUp, ArrowDown } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
 import { AiSettings } from "@/components/settings/ai-settings";
 import { ThemeSettings } from "@/components/settings/theme-settings";
-import { Switch } from "@/components/ui/switch";
-import { useSettingsStore } from "@/lib/settings-store";
 import { SyncSettings } from "@/components/settings/sync-settings";
+import { ExportSettings } from "@/components/settings/export-settings";
+import { ImportSettings } from "@/components/settings/import-settings";
+import { ContactsSettings } from "@/components/settings/contacts-settings";
+import { BackupSettings } from "@/components/settings/backup-settings";
+import { RulesSettings } from "@/components/settings/rules-settings";
+import { ServerFiltersSettings } from "@/components/settings/server-filters-settings";
+import { SubscriptionsSettings } from "@/components/settings/subscriptions-settings";
+import { DigestSettings } from "@/components/settings/digest-settings";
+import { NotificationSettings } from "@/components/settings/notification-settings";
 
 export const Route = createFileRoute("/settings")({
   validateSearch: (search: Record<string, unknown>) => {
@@ -39,17 +46,27 @@ function SettingsPage() {
   const { tab } = useSearch({ from: "/settings" });
   const navigate = useNavigate();
   const { accounts, fetchAccountsAndFolders } = useEmailStore();
-  const { settings, updateSetting } = useSettingsStore();
 
-  const handleRemoveAccount = async (index: number) => {
+  const handleRemoveAccount = async (accountId: number) => {
     try {
-      await invoke("remove_account", { index });
+      await invoke("remove_account", { accountId });
       await fetchAccountsAndFolders();
     } catch (error) {
       console.error("Failed to remove account:", error);
     }
   };
 
+  const handleMoveAccount = async (index: number, offset: number) => {
+    const ids = accounts.map((account) => account.data.id!);
+    [ids[index], ids[index + offset]] = [ids[index + offset], ids[index]];
+    try {
+      await invoke("reorder_accounts", { accountIds: ids });
+      await fetchAccountsAndFolders();
+    } catch (error) {
+      console.error("Failed to reorder accounts:", error);
+    }
+  };
+
   return (
     <div className="flex flex-col h-full bg-background">
       <header className="flex items-center gap-4 p-4 border-b">
@@ -104,32 +121,17 @@ function SettingsPage() {
               </CardContent>
             </Card>
 
-            <Card>
-              <CardHeader>
-                <CardTitle>Notifications</CardTitle>
-                <CardDescription>
-                  Manage how you receive notifications.
-                </CardDescription>
-              </CardHeader>
-              <CardContent className="space-y-4">
-                <div className="flex items-center justify-between">
-                  <div className="space-y-0.5">
-                    <Label>Desktop Notifications</Label>
-                    <p className="text-sm text-muted-foreground">
-                      Show notifications for new emails.
-                    </p>
-                  </div>
-                  <Switch
-                    checked={settings.notificationsEnabled}
-                    onCheckedChange={(checked) =>
-                      updateSetting("notificationsEnabled", checked)
-                    }
-                  />
-                </div>
-              </CardContent>
-            </Card>
+            <NotificationSettings />
 
             <SyncSettings />
+            <RulesSettings />
+            <ServerFiltersSettings />
+            <SubscriptionsSettings />
+            <DigestSettings />
+            <ExportSettings />
+            <ImportSettings />
+            <ContactsSettings />
+            <BackupSettings />
           </TabsContent>
 
           <TabsContent value="appearance" className="space-y-6">
@@ -170,11 +172,28 @@ function SettingsPage() {
                       </div>
                     </div>
                     <div className="flex items-center gap-2">
+                      <Button
+                        variant="ghost"
+                        size="icon"
+                        disabled={index === 0}
+                        onClick={() => handleMoveAccount(index, -1)}
+                      >
+                        <ArrowUp className="h-4 w-4" />
+                      </Button>
+                      <Button
+                        variant="ghost"
+                        size="icon"
+                        disabled={index === accounts.length - 1}
+                        onClick={() => handleMoveAccount(index, 1)}
+                      >
+                        <ArrowDown className="h-4 w-4" />
+                      </Button>
                       <Button
                         variant="ghost"
                         size="icon"
                         className="text-destructive"
-                        onClick={() => handleRemoveAccount(index)}
+                        disabled={account.data.id === undefined}
+                        onClick={() => handleRemoveAccount(account.data.id!)}
                       >
                         <Trash2 className="h-4 w-4" />
                       </Button>